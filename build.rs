@@ -0,0 +1,99 @@
+//! Build script.
+//!
+//! With the `bundled-pages` feature, the English `common` pages of the pages
+//! archive at `TEALDEER_BUNDLED_ARCHIVE` (e.g. a downloaded
+//! `https://github.com/tldr-pages/tldr/archive/master.tar.gz`) are packed into
+//! a compressed snapshot, which is embedded into the binary and installed by
+//! `tldr --seed-cache`.
+
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tar::{Archive, Builder, Header};
+
+/// The environment variable with the path to the archive to bundle pages of.
+const ARCHIVE_ENV_VAR: &str = "TEALDEER_BUNDLED_ARCHIVE";
+
+/// Return the name of the page at `path`, if it is an English `common` page
+/// (e.g. `tldr-master/pages/common/tar.md` or `pages/common/tar.md`).
+fn common_page_name(path: &Path) -> Option<String> {
+    let components: Vec<String> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    if components.len() < 3 || components.len() > 4 {
+        return None;
+    }
+    let tail = &components[components.len() - 3..];
+    if tail[0] == "pages" && tail[1] == "common" && tail[2].ends_with(".md") {
+        Some(tail[2].clone())
+    } else {
+        None
+    }
+}
+
+/// Pack the English `common` pages of the archive at `source` into a new
+/// archive at `target`, with the pages at `pages/common/<name>.md`.
+fn pack_common_pages(source: &Path, target: &Path) -> io::Result<usize> {
+    let mut archive = Archive::new(GzDecoder::new(File::open(source)?));
+    let encoder = GzEncoder::new(File::create(target)?, Compression::best());
+    let mut builder = Builder::new(encoder);
+    let mut count = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = match common_page_name(&entry.path()?) {
+            Some(name) => name,
+            None => continue,
+        };
+        let mut header = Header::new_gnu();
+        header.set_size(entry.header().size()?);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, format!("pages/common/{}", name), &mut entry)?;
+        count += 1;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(count)
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed={}", ARCHIVE_ENV_VAR);
+    if env::var_os("CARGO_FEATURE_BUNDLED_PAGES").is_none() {
+        return;
+    }
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    let target = out_dir.join("bundled-pages.tar.gz");
+    match env::var_os(ARCHIVE_ENV_VAR).filter(|value| !value.is_empty()) {
+        Some(source) => {
+            let source = PathBuf::from(source);
+            println!("cargo:rerun-if-changed={}", source.display());
+            match pack_common_pages(&source, &target) {
+                Ok(0) => panic!("{} contains no English common pages", source.display()),
+                Ok(_) => {}
+                Err(e) => panic!("Could not bundle pages of {}: {}", source.display(), e),
+            }
+        }
+        None => {
+            // Without an archive, the build succeeds, but `--seed-cache`
+            // reports that no pages are bundled
+            println!(
+                "cargo:warning={} is not set, no pages are bundled",
+                ARCHIVE_ENV_VAR
+            );
+            fs::write(&target, &[]).expect("Could not write empty snapshot");
+        }
+    }
+}
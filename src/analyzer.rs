@@ -0,0 +1,149 @@
+//! Normalizing text for searching.
+//!
+//! Search terms and page descriptions are compared after folding them:
+//! Letters are lowercased and stripped of their diacritics, so that
+//! `comprimé` matches `Comprime` and `Größe` matches `grosse`. On top of that,
+//! words are reduced to their stems with the Snowball stemmer of the page's
+//! language (if there is one), so that `compressing` finds `Compress files`.
+//! The stemmers are only available with the `stemming` feature, without it
+//! words are folded only.
+
+use std::collections::HashMap;
+
+#[cfg(feature = "stemming")]
+use rust_stemmers::{Algorithm, Stemmer};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Lowercase `text` and remove diacritics.
+pub fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.nfd().filter(|c| !is_combining_mark(*c)) {
+        match c {
+            'ß' => folded.push_str("ss"),
+            c => folded.extend(c.to_lowercase()),
+        }
+    }
+    folded
+}
+
+/// Return the stemming algorithm for a language code (e.g. `fr` or `pt_BR`).
+#[cfg(feature = "stemming")]
+fn algorithm(language: &str) -> Option<Algorithm> {
+    let base_language = language.split('_').next().unwrap_or(language);
+    Some(match base_language {
+        "ar" => Algorithm::Arabic,
+        "da" => Algorithm::Danish,
+        "de" => Algorithm::German,
+        "el" => Algorithm::Greek,
+        "en" => Algorithm::English,
+        "es" => Algorithm::Spanish,
+        "fi" => Algorithm::Finnish,
+        "fr" => Algorithm::French,
+        "hu" => Algorithm::Hungarian,
+        "it" => Algorithm::Italian,
+        "nb" | "nn" | "no" => Algorithm::Norwegian,
+        "nl" => Algorithm::Dutch,
+        "pt" => Algorithm::Portuguese,
+        "ro" => Algorithm::Romanian,
+        "ru" => Algorithm::Russian,
+        "sv" => Algorithm::Swedish,
+        "ta" => Algorithm::Tamil,
+        "tr" => Algorithm::Turkish,
+        _ => return None,
+    })
+}
+
+/// Splits text into folded and stemmed words.
+pub struct Analyzer {
+    #[cfg(feature = "stemming")]
+    stemmer: Option<Stemmer>,
+}
+
+impl Analyzer {
+    /// Create an analyzer for text in `language`. Languages without a
+    /// stemmer are only folded.
+    #[cfg(feature = "stemming")]
+    pub fn for_language(language: &str) -> Self {
+        Self {
+            stemmer: algorithm(language).map(Stemmer::create),
+        }
+    }
+
+    #[cfg(not(feature = "stemming"))]
+    pub fn for_language(_language: &str) -> Self {
+        Self {}
+    }
+
+    /// Return the folded stems of the words in `text`.
+    pub fn terms(&self, text: &str) -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| self.term(&word.to_lowercase()))
+            .collect()
+    }
+
+    #[cfg(feature = "stemming")]
+    fn term(&self, word: &str) -> String {
+        match self.stemmer {
+            Some(ref stemmer) => fold(&stemmer.stem(word)),
+            None => fold(word),
+        }
+    }
+
+    #[cfg(not(feature = "stemming"))]
+    #[allow(clippy::unused_self)]
+    fn term(&self, word: &str) -> String {
+        fold(word)
+    }
+}
+
+/// The analyzers for all languages that were needed so far.
+#[derive(Default)]
+pub struct Analyzers {
+    analyzers: HashMap<String, Analyzer>,
+}
+
+impl Analyzers {
+    pub fn get(&mut self, language: &str) -> &Analyzer {
+        self.analyzers
+            .entry(language.to_string())
+            .or_insert_with(|| Analyzer::for_language(language))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fold, Analyzer};
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("Comprimé"), "comprime");
+        assert_eq!(fold("Größe ÄNDERN"), "grosse andern");
+        assert_eq!(fold("tar"), "tar");
+        assert_eq!(fold("日本語"), "日本語");
+    }
+
+    #[cfg(feature = "stemming")]
+    #[test]
+    fn test_terms() {
+        let en = Analyzer::for_language("en");
+        assert_eq!(en.terms("Compressing files."), vec!["compress", "file"]);
+        assert_eq!(en.terms("compress"), vec!["compress"]);
+
+        let fr = Analyzer::for_language("fr_FR");
+        assert_eq!(fr.terms("comprimé"), fr.terms("Comprimer"));
+        assert_eq!(fr.terms("comprime"), fr.terms("comprimé"));
+
+        // Languages without a stemmer are folded only
+        let ja = Analyzer::for_language("ja");
+        assert_eq!(ja.terms("Ärger, tar"), vec!["arger", "tar"]);
+    }
+
+    #[cfg(not(feature = "stemming"))]
+    #[test]
+    fn test_terms_without_stemming() {
+        let en = Analyzer::for_language("en");
+        assert_eq!(en.terms("Compressing Files."), vec!["compressing", "files"]);
+    }
+}
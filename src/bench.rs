@@ -0,0 +1,239 @@
+//! Benchmarking the rendering of pages.
+//!
+//! `tldr --bench-render <dir>` renders every page below a directory and
+//! reports how many pages are rendered per second and how many allocations
+//! rendering a page takes. The pages are read into memory first, so that only
+//! rendering is measured. To detect regressions, save the report of a
+//! previous run with `--output json` and pass it with `--baseline`: The
+//! benchmark fails if rendering got noticeably slower or allocates more.
+//!
+//! Allocations are counted by `CountingAllocator`, the global allocator of
+//! tealdeer, which forwards to the system allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::walk::{walk, IgnoreRules};
+
+/// Pages are rendered repeatedly for at least this long.
+const MIN_DURATION: Duration = Duration::from_secs(1);
+
+/// The drop of throughput (as a fraction) compared to the baseline that is
+/// reported as a regression. Throughput varies between runs, so small drops
+/// are tolerated.
+const MAX_SLOWDOWN: f64 = 0.1;
+
+/// The increase of allocations (as a fraction) compared to the baseline that
+/// is reported as a regression.
+const MAX_ALLOCATION_INCREASE: f64 = 0.01;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// The system allocator, counting allocations.
+pub struct CountingAllocator;
+
+fn count_allocation(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count_allocation(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+/// Return the number of allocations and the allocated bytes so far.
+fn allocations() -> (u64, u64) {
+    (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        ALLOCATED_BYTES.load(Ordering::Relaxed),
+    )
+}
+
+/// The result of a benchmark, as printed with `--output json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    /// The number of pages rendered in every pass.
+    pub pages: usize,
+    /// The number of times all pages were rendered.
+    pub passes: usize,
+    pub seconds: f64,
+    pub pages_per_second: f64,
+    pub allocations_per_page: f64,
+    pub bytes_per_page: f64,
+}
+
+/// Return the paths of all pages (`*.md` files) below `dir`, sorted.
+pub fn find_pages(dir: &Path) -> Vec<PathBuf> {
+    let rules = IgnoreRules::default();
+    let mut pages: Vec<PathBuf> = walk(dir, usize::MAX, &rules, |_| true)
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry.file_type().is_file() && entry.path().extension() == Some(OsStr::new("md"))
+        })
+        .map(walkdir::DirEntry::into_path)
+        .collect();
+    pages.sort();
+    pages
+}
+
+/// Read the pages at `paths` into memory.
+pub fn read_pages(paths: &[PathBuf]) -> Result<Vec<Vec<u8>>, String> {
+    paths
+        .iter()
+        .map(|path| fs::read(path).map_err(|e| format!("Could not read {}: {}", path.display(), e)))
+        .collect()
+}
+
+/// Render all `pages` with `render`, repeatedly for at least `MIN_DURATION`.
+#[allow(clippy::cast_precision_loss)]
+pub fn run<F: FnMut(&[u8])>(pages: &[Vec<u8>], mut render: F) -> Report {
+    let (allocations_before, bytes_before) = allocations();
+    let start = Instant::now();
+    let mut passes = 0;
+    while passes == 0 || start.elapsed() < MIN_DURATION {
+        for page in pages {
+            render(page);
+        }
+        passes += 1;
+    }
+    let seconds = start.elapsed().as_secs_f64();
+    let (allocations_after, bytes_after) = allocations();
+
+    let rendered = (pages.len() * passes) as f64;
+    let per_page = |total: u64| {
+        if rendered > 0.0 {
+            total as f64 / rendered
+        } else {
+            0.0
+        }
+    };
+    Report {
+        pages: pages.len(),
+        passes,
+        seconds,
+        pages_per_second: if seconds > 0.0 {
+            rendered / seconds
+        } else {
+            0.0
+        },
+        allocations_per_page: per_page(allocations_after - allocations_before),
+        bytes_per_page: per_page(bytes_after - bytes_before),
+    }
+}
+
+/// Return the relative change from `baseline` to `value`, e.g. `0.1` for 10%
+/// more.
+pub fn change(value: f64, baseline: f64) -> f64 {
+    if baseline > 0.0 {
+        value / baseline - 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Return descriptions of the regressions of `report` compared to
+/// `baseline`.
+pub fn regressions(report: &Report, baseline: &Report) -> Vec<String> {
+    let mut regressions = vec![];
+    let throughput = change(report.pages_per_second, baseline.pages_per_second);
+    if -throughput > MAX_SLOWDOWN {
+        regressions.push(format!(
+            "Throughput dropped by {:.1}% (more than {:.0}%)",
+            -throughput * 100.0,
+            MAX_SLOWDOWN * 100.0
+        ));
+    }
+    let allocations = change(report.allocations_per_page, baseline.allocations_per_page);
+    if allocations > MAX_ALLOCATION_INCREASE {
+        regressions.push(format!(
+            "Allocations per page increased by {:.1}% (more than {:.0}%)",
+            allocations * 100.0,
+            MAX_ALLOCATION_INCREASE * 100.0
+        ));
+    }
+    regressions
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_pages, regressions, run, Report};
+
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    fn report(pages_per_second: f64, allocations_per_page: f64) -> Report {
+        Report {
+            pages: 10,
+            passes: 1,
+            seconds: 1.0,
+            pages_per_second,
+            allocations_per_page,
+            bytes_per_page: 1000.0,
+        }
+    }
+
+    #[test]
+    fn test_find_pages() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("pages").join("common")).unwrap();
+        for name in &["pages/common/tar.md", "pages/common/tar.md~", "README"] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        assert_eq!(
+            find_pages(dir.path()),
+            vec![dir.path().join("pages").join("common").join("tar.md")]
+        );
+    }
+
+    #[test]
+    fn test_run_counts_allocations() {
+        let pages = vec![b"a".to_vec(), b"b".to_vec()];
+        let report = run(&pages, |page| {
+            let copy = page.to_vec();
+            assert_eq!(copy.len(), 1);
+        });
+        assert_eq!(report.pages, 2);
+        assert!(report.passes >= 1);
+        assert!(report.allocations_per_page >= 1.0);
+        assert!(report.pages_per_second > 0.0);
+    }
+
+    #[test]
+    fn test_regressions() {
+        let baseline = report(1000.0, 100.0);
+        assert!(regressions(&report(950.0, 100.0), &baseline).is_empty());
+        assert!(regressions(&report(2000.0, 50.0), &baseline).is_empty());
+        assert_eq!(
+            regressions(&report(800.0, 110.0), &baseline),
+            vec![
+                "Throughput dropped by 20.0% (more than 10%)",
+                "Allocations per page increased by 10.0% (more than 1%)",
+            ]
+        );
+    }
+}
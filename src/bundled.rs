@@ -0,0 +1,36 @@
+//! Pages bundled with the binary.
+//!
+//! With the `bundled-pages` feature, the build script embeds a compressed
+//! snapshot of the English `common` pages (taken from the archive at
+//! `TEALDEER_BUNDLED_ARCHIVE` at build time). `tldr --seed-cache` installs
+//! them into an empty cache without network access, e.g. on air-gapped
+//! machines or when the first download fails.
+
+use crate::error::TealdeerError::{self, CacheError};
+
+/// Return the bundled pages archive.
+#[cfg(feature = "bundled-pages")]
+pub fn archive() -> Result<&'static [u8], TealdeerError> {
+    let archive: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/bundled-pages.tar.gz"));
+    if archive.is_empty() {
+        return Err(CacheError(
+            "This build of tealdeer contains no bundled pages \
+             (TEALDEER_BUNDLED_ARCHIVE was not set at build time)."
+                .into(),
+        ));
+    }
+    Ok(archive)
+}
+
+/// Return the bundled pages archive.
+#[cfg(not(feature = "bundled-pages"))]
+pub fn archive() -> Result<&'static [u8], TealdeerError> {
+    Err(CacheError(
+        "tealdeer was built without bundled pages (the `bundled-pages` feature).".into(),
+    ))
+}
+
+/// Whether pages are bundled with this build.
+pub fn available() -> bool {
+    archive().is_ok()
+}
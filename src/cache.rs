@@ -1,38 +1,419 @@
+use std::collections::BTreeMap;
 use std::env;
 use std::ffi::OsStr;
+use std::fmt;
 use std::fs;
-use std::io::Read;
+use std::io::{self, Read};
+use std::iter;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
 
 use app_dirs::{get_app_root, AppDataType};
 use flate2::read::GzDecoder;
-use log::debug;
-use reqwest::{blocking::Client, Proxy};
+use log::{debug, warn};
+use reqwest::Url;
+use serde_derive::Serialize;
 use std::time::{Duration, SystemTime};
 use tar::Archive;
-use walkdir::{DirEntry, WalkDir};
+use zip::ZipArchive;
 
+use crate::bundled;
+use crate::cache_info::{CacheInfo, CACHE_INFO_FILE_NAME, MANIFEST_FILE_NAME};
 use crate::error::TealdeerError::{self, CacheError, UpdateError};
+use crate::fetch::{
+    fetch_from_mirrors, read_archive_file, Checksum, HttpSettings, PARTIAL_DOWNLOAD_FILE_NAME,
+    PARTIAL_DOWNLOAD_INFO_FILE_NAME,
+};
+use crate::generation;
+use crate::layout::{ArchiveLayout, LayoutSetting, TLDR_PAGES_DIR};
+use crate::lock::CacheLock;
+use crate::pins::{self, PINS_DIR};
+use crate::quarantine::{self, QuarantinedPage, QUARANTINE_DIR};
+use crate::repos::{self, REPOS_DIR};
+use crate::signals::InterruptHandler;
+use crate::sources::SOURCES_DIR;
+use crate::state::{self, Eviction};
+use crate::store::{self, Backend, DirectoryStore, PageStore, UNPACKED_DIR};
 use crate::types::{OsType, PathSource};
+use crate::update_log::UPDATE_LOG_FILE_NAME;
+use crate::walk::{walk, IgnoreRules};
+
+/// The directory inside the cache directory that the archive is extracted to
+/// during an update.
+const STAGING_DIR: &str = "tldr-staging";
+
+/// The directory inside the cache directory that the pages repository is
+/// cloned into by updates with a git source.
+pub const GIT_CLONE_DIR: &str = "tldr-git";
+
+/// The files and directories that tealdeer creates in the cache directory,
+/// except for the lock file.
+///
+/// Every file written to the cache directory must be named after one of
+/// them, optionally with a suffix starting with a dot (like the rotated
+/// `update_history.jsonl.1` or the temporary `tldr-master.new`), so that `clear` can
+/// remove all of them from symlinked cache directories.
+const CACHE_DIR_ENTRIES: &[&str] = &[
+    TLDR_PAGES_DIR,
+    generation::GENERATIONS_DIR,
+    STAGING_DIR,
+    GIT_CLONE_DIR,
+    CACHE_INFO_FILE_NAME,
+    MANIFEST_FILE_NAME,
+    PARTIAL_DOWNLOAD_FILE_NAME,
+    PARTIAL_DOWNLOAD_INFO_FILE_NAME,
+    REPOS_DIR,
+    PINS_DIR,
+    QUARANTINE_DIR,
+    SOURCES_DIR,
+    UPDATE_LOG_FILE_NAME,
+];
+
+/// The first bytes of a zip archive.
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// The file name extension of pages in the custom pages directory.
+const CUSTOM_PAGE_EXTENSION: &str = "page.md";
+
+/// Statistics about the contents of the cache.
+#[derive(Debug, PartialEq)]
+pub struct CacheStats {
+    /// Time of the last update in seconds since the Unix epoch, if known.
+    pub last_update: Option<u64>,
+    /// The URL the pages were downloaded from, if known.
+    pub source_url: Option<String>,
+    /// The upstream release the pages belong to, if known.
+    pub release_tag: Option<String>,
+    /// The number of pages in all languages and platforms.
+    pub pages: u64,
+    /// The number of pages per language, for languages with at least one
+    /// page.
+    pub languages: BTreeMap<String, u64>,
+    /// The number of pages per platform (e.g. `linux`) in all languages.
+    pub platforms: BTreeMap<String, u64>,
+    /// The total size of all files in the cache directory in bytes.
+    pub size: u64,
+}
+
+/// What happened to the cache directory when it was cleared.
+#[derive(Debug, PartialEq)]
+pub enum ClearOutcome {
+    Deleted,
+    #[cfg(feature = "trash")]
+    Trashed,
+    BackedUp(PathBuf),
+}
+
+/// Where an update downloaded the pages from.
+pub struct UpdateSummary {
+    pub url: String,
+    /// The size of the downloaded archive in bytes, unless the pages were
+    /// cloned with git.
+    pub bytes: Option<u64>,
+    /// The mirrors that were tried before, with the errors they failed with.
+    pub failed: Vec<(String, TealdeerError)>,
+    /// The invalid pages of the archive, which were not installed.
+    pub quarantined: Vec<QuarantinedPage>,
+}
+
+/// A page in the cache, in one language and for one platform.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct PageEntry {
+    pub name: String,
+    pub language: String,
+    pub platform: String,
+}
+
+/// Where a page was found.
+enum PageLocation {
+    /// A custom page.
+    Custom(PathBuf),
+    /// A pinned page, with the path of the pinned copy.
+    Pinned(PathBuf),
+    /// A page in the store of an extra pages source (with its pages
+    /// directory) or of the cache (`None`), with its path in the store.
+    Store(Arc<dyn PageStore>, String, Option<PathBuf>),
+}
+
+/// Where a found page comes from, in the order of precedence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageKind {
+    Custom,
+    Pinned,
+    /// A page of an extra pages source, with its pages directory.
+    Extra(PathBuf),
+    Cache,
+}
+
+impl fmt::Display for PageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Custom => write!(f, "custom page"),
+            Self::Pinned => write!(f, "pinned page"),
+            Self::Extra(dir) => write!(f, "extra pages source {}", dir.display()),
+            Self::Cache => write!(f, "cache"),
+        }
+    }
+}
+
+/// A page found by `find_page`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundPage {
+    /// The page file. Pages of packed caches are unpacked into it.
+    pub path: PathBuf,
+    pub kind: PageKind,
+    /// The language of the page, `None` for custom pages.
+    pub language: Option<String>,
+    /// The platform directory of the page (e.g. `linux`), `None` for custom
+    /// pages.
+    pub platform: Option<String>,
+}
+
+/// A page listed by `list_page_locations`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ListedPage {
+    pub name: String,
+    /// The platform directory of the page, "custom" for custom pages.
+    pub platform: String,
+    /// The language of the page, `None` for custom pages.
+    pub language: Option<String>,
+    /// The page file, `None` for pages packed into a zip or `SQLite` cache.
+    pub path: Option<PathBuf>,
+}
+
+/// A step of a page lookup, reported to the tracer set with
+/// `with_lookup_tracer`.
+#[derive(Debug, PartialEq)]
+pub enum LookupEvent<'a> {
+    /// A custom page was checked.
+    CustomPage { path: &'a Path, found: bool },
+    /// A platform directory was skipped, because it is not in the cache.
+    MissingDir { dir: &'a str },
+    /// A page in the cache was checked.
+    Page { path: &'a str, found: bool },
+    /// A pinned page was found.
+    PinnedPage { path: &'a str },
+    /// The page at this location was chosen.
+    Chosen { location: &'a str },
+    /// No page was found.
+    NotFound { name: &'a str },
+}
+
+impl fmt::Display for LookupEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = |found: bool| if found { "found" } else { "not found" };
+        match *self {
+            LookupEvent::CustomPage { path, found } => {
+                write!(f, "custom page {}: {}", path.display(), outcome(found))
+            }
+            LookupEvent::MissingDir { dir } => write!(f, "{}: not in the cache, skipped", dir),
+            LookupEvent::Page { path, found } => write!(f, "{}: {}", path, outcome(found)),
+            LookupEvent::PinnedPage { path } => write!(f, "pinned {}: found", path),
+            LookupEvent::Chosen { location } => write!(f, "using {}", location),
+            LookupEvent::NotFound { name } => write!(f, "no page found for {}", name),
+        }
+    }
+}
+
+/// A callback receiving the steps of every page lookup.
+pub type LookupTracer = Box<dyn Fn(&LookupEvent<'_>) + Send + Sync>;
 
-#[derive(Debug)]
 pub struct Cache {
-    url: String,
-    os: OsType,
+    /// The URLs of the pages archive, tried in order until one works.
+    urls: Vec<String>,
+    http: HttpSettings,
+    /// How the fetched archive is verified.
+    checksum: Checksum,
+    /// The minisign public key that the fetched archive must be signed for.
+    minisign_key: Option<String>,
+    /// The platform directories to look up pages in before `common`.
+    platform_chain: Vec<String>,
+    /// The directories containing custom pages, in order of precedence.
+    custom_pages_dirs: Vec<PathBuf>,
+    ignore_rules: IgnoreRules,
+    /// How updates store the pages.
+    backend: Backend,
+    /// How updates determine the layout of the pages archive.
+    archive_layout: LayoutSetting,
+    /// The store of the active pages directory, opened on first use.
+    store: Mutex<Option<(PathBuf, Arc<dyn PageStore>)>>,
+    /// Receives the steps of page lookups, if set.
+    tracer: Option<LookupTracer>,
+    /// The maximum size of the auxiliary caches (unpacked pages), if limited.
+    aux_cache_max_bytes: Option<u64>,
+    /// The directory that pages are read from instead of the cache, if set.
+    pages_dir: Option<PathBuf>,
+    /// The URL of the git repository that updates clone instead of
+    /// downloading the archive, if set.
+    git_url: Option<String>,
+    /// The directories of the extra pages sources, searched in order before
+    /// the pages of the cache.
+    extra_pages_dirs: Vec<PathBuf>,
 }
 
 impl Cache {
-    pub fn new<S>(url: S, os: OsType) -> Self
+    pub fn new<S>(
+        url: S,
+        os: OsType,
+        custom_pages_dirs: Vec<PathBuf>,
+        ignore_rules: IgnoreRules,
+    ) -> Self
     where
         S: Into<String>,
     {
         Self {
-            url: url.into(),
-            os,
+            urls: vec![url.into()],
+            http: HttpSettings::default(),
+            checksum: Checksum::default(),
+            minisign_key: None,
+            platform_chain: os
+                .platform_chain()
+                .iter()
+                .map(|platform| (*platform).to_string())
+                .collect(),
+            custom_pages_dirs,
+            ignore_rules,
+            backend: Backend::default(),
+            archive_layout: LayoutSetting::default(),
+            store: Mutex::new(None),
+            tracer: None,
+            aux_cache_max_bytes: None,
+            pages_dir: None,
+            git_url: None,
+            extra_pages_dirs: vec![],
         }
     }
 
+    /// Download the pages archive from the first of `urls` that works
+    /// instead of the default URL.
+    pub fn with_archive_urls(mut self, urls: Vec<String>) -> Self {
+        if !urls.is_empty() {
+            self.urls = urls;
+        }
+        self
+    }
+
+    /// Send the requests of updates with `http`.
+    pub fn with_http_settings(mut self, http: HttpSettings) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Verify the fetched archive with `checksum`.
+    pub fn with_checksum(mut self, checksum: Checksum) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Verify the fetched archive with its minisign signature, made with the
+    /// secret key of `key`.
+    pub fn with_minisign_key(mut self, key: Option<String>) -> Self {
+        self.minisign_key = key;
+        self
+    }
+
+    /// Store the pages with `backend` on updates.
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Determine the layout of the pages archive according to `setting` on
+    /// updates.
+    pub fn with_archive_layout(mut self, setting: LayoutSetting) -> Self {
+        self.archive_layout = setting;
+        self
+    }
+
+    /// Keep the auxiliary caches (unpacked pages) within `max_bytes`.
+    pub fn with_aux_cache_limit(mut self, max_bytes: u64) -> Self {
+        self.aux_cache_max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Read the pages from `dir` (e.g. a checkout of the tldr-pages
+    /// repository) instead of the cache, so that edits show up right away.
+    pub fn with_pages_dir(mut self, dir: PathBuf) -> Self {
+        self.pages_dir = Some(dir);
+        self
+    }
+
+    /// Clone (or fetch) the pages repository at `url` on updates instead of
+    /// downloading the archive.
+    pub fn with_git_source(mut self, url: String) -> Self {
+        self.git_url = Some(url);
+        self
+    }
+
+    /// Look up pages in `dirs` (in order) before the pages of the cache.
+    pub fn with_extra_pages_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.extra_pages_dirs = dirs;
+        self
+    }
+
+    /// Report every candidate checked by page lookups to `tracer`.
+    pub fn with_lookup_tracer(mut self, tracer: LookupTracer) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
+
+    fn trace(&self, event: &LookupEvent<'_>) {
+        if let Some(ref tracer) = self.tracer {
+            tracer(event);
+        }
+    }
+
+    /// Return the store of the active pages directory, or of the directory
+    /// set with `with_pages_dir`.
+    ///
+    /// The store is only opened on the first call for every pages directory
+    /// (a new one is activated by each update).
+    fn store(&self) -> Result<Arc<dyn PageStore>, TealdeerError> {
+        let pages_dir = match self.pages_dir {
+            Some(ref dir) => dir.clone(),
+            None => Self::get_active_pages_dir()?,
+        };
+        let mut cached = self.store.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((ref dir, ref store)) = *cached {
+            if *dir == pages_dir {
+                return Ok(Arc::clone(store));
+            }
+        }
+        let store: Arc<dyn PageStore> = Arc::from(store::open(&pages_dir)?);
+        *cached = Some((pages_dir, Arc::clone(&store)));
+        Ok(store)
+    }
+
+    /// Return the stores of the extra pages directories that exist, in
+    /// order. They are opened on every call, since they may be updated while
+    /// tealdeer runs.
+    fn extra_stores(&self) -> Vec<(&Path, Arc<dyn PageStore>)> {
+        self.extra_pages_dirs
+            .iter()
+            .filter(|dir| dir.is_dir())
+            .map(|dir| {
+                let store = Arc::new(DirectoryStore::open(dir)) as Arc<dyn PageStore>;
+                (dir.as_path(), store)
+            })
+            .collect()
+    }
+
+    /// Return the path of the custom page called `name`, if it exists.
+    fn find_custom_page(&self, name: &str) -> Option<PathBuf> {
+        self.custom_pages_dirs
+            .iter()
+            .map(|dir| dir.join(format!("{}.{}", name, CUSTOM_PAGE_EXTENSION)))
+            .find(|path| {
+                let found = path.is_file();
+                self.trace(&LookupEvent::CustomPage { path, found });
+                found
+            })
+    }
+
     /// Return the path to the cache directory.
+    ///
+    /// The path is not canonicalized. If it is a symlink, it is followed
+    /// when reading and writing, but never removed or replaced.
     pub fn get_cache_dir() -> Result<(PathBuf, PathSource), TealdeerError> {
         // Allow overriding the cache directory by setting the
         // $TEALDEER_CACHE_DIR env variable.
@@ -46,38 +427,34 @@ impl Cache {
                     "Path specified by $TEALDEER_CACHE_DIR \
                      does not exist or is not a directory."
                         .into(),
-                ));
+                )
+                .with_hint(format!("Create it with `mkdir -p {}`.", path.display())));
             }
         };
 
         // Otherwise, fall back to user cache directory.
         match get_app_root(AppDataType::UserCache, &crate::APP_INFO) {
             Ok(dirs) => Ok((dirs, PathSource::OsConvention)),
-            Err(_) => Err(CacheError(
-                "Could not determine user cache directory.".into(),
-            )),
+            Err(_) => Err(
+                CacheError("Could not determine user cache directory.".into())
+                    .with_hint("Set $TEALDEER_CACHE_DIR to the directory to use."),
+            ),
         }
     }
 
-    /// Download the archive
-    fn download(&self) -> Result<Vec<u8>, TealdeerError> {
-        let mut builder = Client::builder();
-        if let Ok(ref host) = env::var("HTTP_PROXY") {
-            if let Ok(proxy) = Proxy::http(host) {
-                builder = builder.proxy(proxy);
-            }
-        }
-        if let Ok(ref host) = env::var("HTTPS_PROXY") {
-            if let Ok(proxy) = Proxy::https(host) {
-                builder = builder.proxy(proxy);
-            }
-        }
-        let client = builder.build().unwrap_or_else(|_| Client::new());
-        let mut resp = client.get(&self.url).send()?;
-        let mut buf: Vec<u8> = vec![];
-        let bytes_downloaded = resp.copy_to(&mut buf)?;
-        debug!("{} bytes downloaded", bytes_downloaded);
-        Ok(buf)
+    /// Return the path to the directory containing the pages directories.
+    pub fn get_pages_dir() -> Result<PathBuf, TealdeerError> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        Ok(cache_dir.join(TLDR_PAGES_DIR))
+    }
+
+    /// Return the pages directory of the active cache generation.
+    ///
+    /// Use this instead of `get_pages_dir` when reading pages, so that a
+    /// concurrent update can not switch generations halfway through.
+    pub fn get_active_pages_dir() -> Result<PathBuf, TealdeerError> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        Ok(generation::resolve_active(&cache_dir))
     }
 
     /// Decompress and open the archive
@@ -85,42 +462,245 @@ impl Cache {
         Archive::new(GzDecoder::new(reader))
     }
 
+    /// Unpack a gzipped tar archive or a zip archive (like the `tldr.zip`
+    /// of tldr-pages releases) into `dir`.
+    pub fn unpack(bytes: &[u8], dir: &Path) -> io::Result<()> {
+        if bytes.starts_with(ZIP_MAGIC) {
+            ZipArchive::new(io::Cursor::new(bytes))?.extract(dir)?;
+            Ok(())
+        } else {
+            Self::decompress(bytes).unpack(dir)
+        }
+    }
+
     /// Update the pages cache.
-    pub fn update(&self) -> Result<(), TealdeerError> {
-        // First, download the compressed data
-        let bytes: Vec<u8> = self.download()?;
+    ///
+    /// The cache is locked while the pages are installed, so that parallel
+    /// updates wait for each other.
+    ///
+    /// If the user interrupts the update with Ctrl+C, it is stopped as soon as
+    /// possible and the files of the incomplete update are removed. The
+    /// manifest of the new pages is built by up to `jobs` threads.
+    ///
+    /// Returns the URL that the pages were downloaded from and the mirrors
+    /// that failed before.
+    pub fn update(&self, jobs: usize) -> Result<UpdateSummary, TealdeerError> {
+        let interrupts = InterruptHandler::new();
+        if let Some(ref url) = self.git_url {
+            return self.update_from_git(url, jobs, &interrupts);
+        }
+
+        // First, download the compressed data. Interrupted downloads are kept
+        // in the cache directory to be resumed.
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let partial_dir = fs::create_dir_all(directory_to_create(&cache_dir))
+            .ok()
+            .map(|()| cache_dir);
+        let fetched = fetch_from_mirrors(
+            &self.urls,
+            &self.http,
+            &self.checksum,
+            self.minisign_key.as_deref(),
+            partial_dir.as_deref(),
+            &interrupts,
+        )?;
+
+        let quarantined =
+            self.install(&fetched.bytes, Some(fetched.url.clone()), jobs, &interrupts)?;
+        Ok(UpdateSummary {
+            url: fetched.url,
+            bytes: Some(fetched.bytes.len() as u64),
+            failed: fetched.failed,
+            quarantined,
+        })
+    }
+
+    /// Update the clone of the pages repository at `url` in the cache
+    /// directory and install its pages.
+    ///
+    /// The clone is kept between updates, so only new commits are fetched.
+    fn update_from_git(
+        &self,
+        url: &str,
+        jobs: usize,
+        interrupts: &InterruptHandler,
+    ) -> Result<UpdateSummary, TealdeerError> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let clone_dir = cache_dir.join(GIT_CLONE_DIR);
+        let quarantined = self.install_with(Some(url.into()), jobs, interrupts, |staging_dir| {
+            repos::sync_clone(url, &clone_dir)?;
+            // Hidden files (like `.git`) are not copied
+            store::export(
+                &DirectoryStore::open(&clone_dir),
+                staging_dir,
+                Backend::Directory,
+            )
+        })?;
+        Ok(UpdateSummary {
+            url: url.into(),
+            bytes: None,
+            failed: vec![],
+            quarantined,
+        })
+    }
+
+    /// Update the pages cache from the archive at `path` (`-` for stdin)
+    /// instead of downloading it, e.g. on air-gapped networks. The archive
+    /// is verified and installed like a downloaded one.
+    ///
+    /// Returns the invalid pages of the archive, which were not installed.
+    pub fn update_from_file(
+        &self,
+        path: &Path,
+        jobs: usize,
+    ) -> Result<Vec<QuarantinedPage>, TealdeerError> {
+        let bytes = read_archive_file(path, &self.checksum, self.minisign_key.as_deref())?;
+        let source_url = if path == Path::new("-") {
+            None
+        } else {
+            fs::canonicalize(path)
+                .ok()
+                .and_then(|path| Url::from_file_path(path).ok())
+                .map(|url| url.to_string())
+        };
+        self.install(&bytes, source_url, jobs, &InterruptHandler::new())
+    }
+
+    /// Populate an empty cache with the pages bundled with the binary.
+    pub fn seed(&self, jobs: usize) -> Result<(), TealdeerError> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        if CacheInfo::load(&cache_dir)?.is_some() {
+            return Err(CacheError("The cache already contains pages.".into())
+                .with_hint("Use `tldr --update` to update them."));
+        }
+        let archive = bundled::archive()?;
+        self.install(archive, None, jobs, &InterruptHandler::new())
+            .map(|_| ())
+    }
 
-        // Decompress the response body into an `Archive`
-        let mut archive = Self::decompress(&bytes[..]);
+    /// Install the pages of a compressed archive as a new generation.
+    ///
+    /// Invalid pages are moved to the quarantine instead and returned.
+    fn install(
+        &self,
+        bytes: &[u8],
+        source_url: Option<String>,
+        jobs: usize,
+        interrupts: &InterruptHandler,
+    ) -> Result<Vec<QuarantinedPage>, TealdeerError> {
+        self.install_with(source_url, jobs, interrupts, |staging_dir| {
+            Self::unpack(bytes, staging_dir)
+                .map_err(|e| UpdateError(format!("Could not unpack compressed data: {}", e)))
+        })
+    }
 
+    /// Install the pages that `extract` writes into the staging directory as
+    /// a new generation. The cache is locked while `extract` runs.
+    fn install_with<F>(
+        &self,
+        source_url: Option<String>,
+        jobs: usize,
+        interrupts: &InterruptHandler,
+        extract: F,
+    ) -> Result<Vec<QuarantinedPage>, TealdeerError>
+    where
+        F: FnOnce(&Path) -> Result<(), TealdeerError>,
+    {
         // Determine paths
         let (cache_dir, _) = Self::get_cache_dir()?;
 
         // Make sure that cache directory exists
         debug!("Ensure cache directory {:?} exists", &cache_dir);
-        fs::create_dir_all(&cache_dir)
-            .map_err(|e| UpdateError(format!("Could not create cache directory: {}", e)))?;
+        fs::create_dir_all(directory_to_create(&cache_dir)).map_err(|e| {
+            UpdateError(format!("Could not create cache directory: {}", e)).with_io_hint(&e)
+        })?;
+        let _lock = lock_cache_dir(&cache_dir)?;
 
-        // Clear cache directory
-        // Note: This is not the best solution. Ideally we would download the
-        // archive to a temporary directory and then swap the two directories.
-        // But renaming a directory doesn't work across filesystems and Rust
-        // does not yet offer a recursive directory copying function. So for
-        // now, we'll use this approach.
-        Self::clear()?;
+        // Extract archive into a staging directory, removing leftovers of
+        // previously failed updates first
+        let staging_dir = cache_dir.join(STAGING_DIR);
+        if staging_dir.exists() {
+            fs::remove_dir_all(&staging_dir)
+                .map_err(|e| UpdateError(format!("Could not remove staging directory: {}", e)))?;
+        }
+        let generation_dir = generation::next_generation_dir(&cache_dir)?;
+        let installed = (|| {
+            extract(&staging_dir)?;
+            if interrupts.interrupted() {
+                return Err(interrupted_error());
+            }
+
+            // Move the pages into a new generation, independent of the archive
+            // layout
+            let layout =
+                ArchiveLayout::resolve(self.archive_layout, &staging_dir, source_url.as_deref())
+                    .ok_or_else(|| UpdateError("Unknown layout of the pages archive.".into()))?;
+            debug!("Detected archive layout {:?}", layout);
+            layout.normalize(&staging_dir, &generation_dir)?;
+            let quarantined = quarantine::quarantine_invalid_pages(
+                &generation_dir,
+                &cache_dir.join(QUARANTINE_DIR),
+            )?;
+            store::convert(&generation_dir, self.backend)?;
+            if interrupts.interrupted() {
+                return Err(interrupted_error());
+            }
+            Ok(quarantined)
+        })();
+        let quarantined = match installed {
+            Ok(quarantined) => quarantined,
+            Err(e) => {
+                // Don't leave the files of the incomplete update behind
+                for dir in &[&staging_dir, &generation_dir] {
+                    if let Err(e) = remove_path(dir) {
+                        debug!("Could not remove {}: {}", dir.display(), e);
+                    }
+                }
+                return Err(e);
+            }
+        };
+
+        // Make the new generation the active one. From here on, the update is
+        // completed even if the user interrupts it.
+        generation::activate(&cache_dir, &generation_dir)?;
+        if staging_dir.exists() {
+            if let Err(e) = fs::remove_dir_all(&staging_dir) {
+                debug!("Could not remove {}: {}", staging_dir.display(), e);
+            }
+        }
 
-        // Extract archive
-        archive
-            .unpack(&cache_dir)
-            .map_err(|e| UpdateError(format!("Could not unpack compressed data: {}", e)))?;
+        // Record metadata about the new cache contents
+        CacheInfo::create(&cache_dir, source_url, jobs)?.save(&cache_dir)?;
 
-        Ok(())
+        Ok(quarantined)
+    }
+
+    /// Convert the pages in the cache to the backend of this cache, without
+    /// downloading them again.
+    ///
+    /// The converted pages become a new generation, so readers are never
+    /// affected by the conversion.
+    pub fn migrate(&self) -> Result<(), TealdeerError> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        if CacheInfo::load(&cache_dir)?.is_none() {
+            return Err(cache_not_found());
+        }
+        let _lock = lock_cache_dir(&cache_dir)?;
+        let store = self.store()?;
+        let generation_dir = generation::next_generation_dir(&cache_dir)?;
+        if let Err(e) = store::export(&*store, &generation_dir, self.backend) {
+            if let Err(e) = remove_path(&generation_dir) {
+                debug!("Could not remove {}: {}", generation_dir.display(), e);
+            }
+            return Err(e);
+        }
+        generation::activate(&cache_dir, &generation_dir)
     }
 
     /// Return the duration since the cache directory was last modified.
     pub fn last_update() -> Option<Duration> {
-        if let Ok((cache_dir, _)) = Self::get_cache_dir() {
-            if let Ok(metadata) = fs::metadata(cache_dir.join("tldr-master")) {
+        if let Ok(pages_dir) = Self::get_pages_dir() {
+            if let Ok(metadata) = fs::metadata(pages_dir) {
                 if let Ok(mtime) = metadata.modified() {
                     let now = SystemTime::now();
                     return now.duration_since(mtime).ok();
@@ -130,138 +710,1110 @@ impl Cache {
         None
     }
 
-    /// Return the platform directory.
-    #[allow(clippy::match_same_arms)]
-    fn get_platform_dir(&self) -> Option<&'static str> {
-        match self.os {
-            OsType::Linux => Some("linux"),
-            OsType::OsX => Some("osx"),
-            OsType::SunOs => None, // TODO: Does Rust support SunOS?
-            OsType::Windows => Some("windows"),
-            OsType::Other => None,
+    /// Pack the active pages into a gzipped tar archive, which updates can
+    /// install like the upstream archive.
+    #[cfg(feature = "share")]
+    pub fn pack_pages(&self) -> Result<Vec<u8>, TealdeerError> {
+        let store = self.store()?;
+        store::pack_tar_gz(&*store)
+            .map_err(|e| CacheError(format!("Could not pack pages into an archive: {}", e)))
+    }
+
+    /// Collect statistics about the contents of the cache.
+    pub fn stats() -> Result<CacheStats, TealdeerError> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let info = CacheInfo::load(&cache_dir)?.ok_or_else(cache_not_found)?;
+
+        let rules = IgnoreRules::default();
+        let store = store::open(&generation::resolve_active(&cache_dir))?;
+        let mut pages = 0;
+        let mut languages = BTreeMap::new();
+        let mut platforms = BTreeMap::new();
+        for platform_dir in store.platform_dirs() {
+            if platform_dir.split('/').any(|name| rules.is_ignored(name)) {
+                continue;
+            }
+            let page_count = store
+                .list(platform_dir)
+                .iter()
+                .filter(|entry| !entry.is_dir && entry.name.ends_with(".md"))
+                .filter(|entry| !rules.is_ignored(&entry.name))
+                .count() as u64;
+            if page_count > 0 {
+                pages += page_count;
+                let mut names = platform_dir.split('/');
+                if let Some(language) = names.next().and_then(dir_language) {
+                    *languages.entry(language).or_insert(0) += page_count;
+                }
+                if let Some(platform) = names.next() {
+                    *platforms.entry(platform.to_string()).or_insert(0) += page_count;
+                }
+            }
         }
+
+        let size = walk(&cache_dir, usize::MAX, &rules, |_| true)
+            .filter_map(Result::ok)
+            .filter_map(|entry| entry.metadata().ok())
+            .filter(fs::Metadata::is_file)
+            .map(|metadata| metadata.len())
+            .sum();
+
+        Ok(CacheStats {
+            last_update: Some(info.last_update).filter(|&timestamp| timestamp > 0),
+            source_url: info.source_url,
+            release_tag: info.release_tag,
+            pages,
+            languages,
+            platforms,
+            size,
+        })
     }
 
-    /// Check for pages for a given platform in one of the given languages.
+    /// Look up pages in the platform directories of `chain` (in order)
+    /// instead of the default ones for the operating system.
+    pub fn with_platform_chain(mut self, chain: Vec<String>) -> Self {
+        self.platform_chain = chain;
+        self
+    }
+
+    /// Return the platform directories to look up pages in, in order.
+    fn lookup_platforms(&self) -> impl Iterator<Item = &str> {
+        self.platform_chain
+            .iter()
+            .map(String::as_str)
+            .chain(iter::once("common"))
+    }
+
+    /// Check for pages for a given platform in one of the given languages
+    /// and return the path in the store.
+    ///
+    /// Only the platform directories of the store are probed.
     fn find_page_for_platform(
+        &self,
         name: &str,
-        cache_dir: &Path,
+        store: &dyn PageStore,
         platform: &str,
         language_dirs: &[String],
-    ) -> Option<PathBuf> {
+    ) -> Option<String> {
         language_dirs
             .iter()
-            .map(|lang_dir| cache_dir.join(lang_dir).join(platform).join(name))
-            .find(|path| path.exists() && path.is_file())
+            .map(|lang_dir| store::join(lang_dir, platform))
+            .filter(|dir| {
+                let exists = store.platform_dirs().contains(dir);
+                if !exists {
+                    self.trace(&LookupEvent::MissingDir { dir });
+                }
+                exists
+            })
+            .map(|dir| store::join(&dir, name))
+            .find(|path| {
+                let found = store.is_file(path);
+                self.trace(&LookupEvent::Page { path, found });
+                found
+            })
     }
 
-    /// Search for a page and return the path to it.
-    pub fn find_page(&self, name: &str, languages: &[String]) -> Option<PathBuf> {
-        let page_filename = format!("{}.md", name);
+    /// Search for a page and return where it was found.
+    fn locate_page(&self, name: &str, languages: &[String]) -> Option<PageLocation> {
+        if let Err(e) = validate_page_name(name) {
+            log::error!("{}", e);
+            return None;
+        }
+
+        let location = self.locate_valid_page(name, languages);
+        match location {
+            Some(PageLocation::Custom(ref path)) => self.trace(&LookupEvent::Chosen {
+                location: &path.display().to_string(),
+            }),
+            Some(PageLocation::Pinned(ref path)) => self.trace(&LookupEvent::Chosen {
+                location: &path.display().to_string(),
+            }),
+            Some(PageLocation::Store(_, ref path, _)) => {
+                self.trace(&LookupEvent::Chosen { location: path });
+            }
+            None => self.trace(&LookupEvent::NotFound { name }),
+        }
+        location
+    }
 
-        // Get cache dir
-        let cache_dir = match Self::get_cache_dir() {
-            Ok((cache_dir, _)) => cache_dir.join("tldr-master"),
+    fn locate_valid_page(&self, name: &str, languages: &[String]) -> Option<PageLocation> {
+        // Custom pages take precedence over the pages in the cache. Their
+        // names may contain uppercase letters, so the lowercase name is only
+        // tried second.
+        let lowercase_name = name.to_lowercase();
+        let custom_page = self.find_custom_page(name).or_else(|| {
+            if lowercase_name == name {
+                None
+            } else {
+                self.find_custom_page(&lowercase_name)
+            }
+        });
+        if let Some(path) = custom_page {
+            return Some(PageLocation::Custom(path));
+        }
+
+        // Upstream page names are always lowercase
+        let page_filename = format!("{}.md", lowercase_name);
+        let lang_dirs: Vec<String> = languages.iter().map(|lang| language_dir(lang)).collect();
+
+        // Pinned pages take precedence over the updated ones
+        if let Some(path) = self.find_pinned_page(&page_filename, &lang_dirs) {
+            return Some(PageLocation::Pinned(path));
+        }
+
+        // The extra sources take precedence over the pages of the cache
+        for (dir, store) in self.extra_stores() {
+            if let Some(path) = self.find_page_in_store(&page_filename, &*store, &lang_dirs) {
+                return Some(PageLocation::Store(store, path, Some(dir.to_path_buf())));
+            }
+        }
+
+        let store = match self.store() {
+            Ok(store) => store,
             Err(e) => {
-                log::error!("Could not get cache directory: {}", e);
+                log::error!("Could not open cache: {}", e);
                 return None;
             }
         };
+        let path = self.find_page_in_store(&page_filename, &*store, &lang_dirs)?;
+        Some(PageLocation::Store(store, path, None))
+    }
 
-        let lang_dirs: Vec<String> = languages
-            .iter()
-            .map(|lang| {
-                if lang == "en" {
-                    String::from("pages")
+    /// Search for a page in `store` and return its path in the store.
+    fn find_page_in_store(
+        &self,
+        page_filename: &str,
+        store: &dyn PageStore,
+        lang_dirs: &[String],
+    ) -> Option<String> {
+        // Try the platform specific directories first, then "common"
+        self.lookup_platforms().find_map(|platform| {
+            self.find_page_for_platform(page_filename, store, platform, lang_dirs)
+        })
+    }
+
+    /// Return the path of the pinned copy of a page, if there is one.
+    ///
+    /// Unlike in the cache, only found pages are traced, since there usually
+    /// are only a few pinned pages.
+    fn find_pinned_page(&self, page_filename: &str, lang_dirs: &[String]) -> Option<PathBuf> {
+        let pins = pins::open()?;
+        let path = self
+            .lookup_platforms()
+            .flat_map(|platform| {
+                lang_dirs.iter().map(move |lang_dir| {
+                    store::join(&store::join(lang_dir, platform), page_filename)
+                })
+            })
+            .find(|path| pins.is_file(path))?;
+        self.trace(&LookupEvent::PinnedPage { path: &path });
+        pins.local_path(&path).ok()
+    }
+
+    /// Pin the page `name` as it is in the cache now, so that updates don't
+    /// change it. Return its path in the cache.
+    pub fn pin(&self, name: &str, languages: &[String]) -> Result<String, TealdeerError> {
+        validate_page_name(name)?;
+        let page_filename = format!("{}.md", name.to_lowercase());
+        let lang_dirs: Vec<String> = languages.iter().map(|lang| language_dir(lang)).collect();
+        if self.find_pinned_page(&page_filename, &lang_dirs).is_some() {
+            return Err(
+                CacheError(format!("Page {} is already pinned.", name)).with_hint(format!(
+                    "Use `tldr --unpin {}` first to pin the current version.",
+                    name
+                )),
+            );
+        }
+
+        let store = self.store()?;
+        let path = self
+            .find_page_in_store(&page_filename, &*store, &lang_dirs)
+            .ok_or_else(|| CacheError(format!("Page {} not found in cache", name)))?;
+        let contents = store
+            .read(&path)
+            .map_err(|e| CacheError(format!("Could not read page {}: {}", path, e)))?;
+        pins::pin(&path, &contents)?;
+        Ok(path)
+    }
+
+    /// Search for a page and return where it was found.
+    pub fn find_page(&self, name: &str, languages: &[String]) -> Option<FoundPage> {
+        match self.locate_page(name, languages)? {
+            PageLocation::Custom(path) => Some(FoundPage {
+                path,
+                kind: PageKind::Custom,
+                language: None,
+                platform: None,
+            }),
+            PageLocation::Pinned(path) => Some(FoundPage {
+                language: page_language(&path),
+                platform: page_platform(&path),
+                path,
+                kind: PageKind::Pinned,
+            }),
+            PageLocation::Store(store, path, extra_dir) => match store.local_path(&path) {
+                Ok(local_path) => {
+                    if let Some(max_bytes) = self.aux_cache_max_bytes {
+                        Self::enforce_aux_cache_limit(max_bytes, &local_path);
+                    }
+                    let mut dirs = path.split('/');
+                    Some(FoundPage {
+                        path: local_path,
+                        kind: extra_dir.map_or(PageKind::Cache, PageKind::Extra),
+                        language: dirs.next().and_then(dir_language),
+                        platform: dirs.next().map(str::to_string),
+                    })
+                }
+                Err(e) => {
+                    log::error!("Could not read page {}: {}", path, e);
+                    None
+                }
+            },
+        }
+    }
+
+    /// Return the directories of the auxiliary caches of all generations.
+    fn aux_cache_dirs() -> Result<Vec<PathBuf>, TealdeerError> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let mut dirs = generation::generation_dirs(&cache_dir);
+        // Caches without generations have a single pages directory
+        let active = generation::resolve_active(&cache_dir);
+        if !dirs.contains(&active) {
+            dirs.push(active);
+        }
+        Ok(dirs.into_iter().map(|dir| dir.join(UNPACKED_DIR)).collect())
+    }
+
+    /// Evict the least recently used files of the auxiliary caches if they
+    /// exceed `max_bytes`, except for the file at `keep`.
+    fn enforce_aux_cache_limit(max_bytes: u64, keep: &Path) {
+        let evicted = Self::aux_cache_dirs().and_then(|dirs| {
+            state::evict_least_recently_used(&dirs, max_bytes, Some(keep))
+                .map_err(|e| CacheError(e.to_string()))
+        });
+        match evicted {
+            Ok(eviction) if eviction.files > 0 => debug!(
+                "Evicted {} files ({} bytes) from auxiliary caches",
+                eviction.files, eviction.bytes
+            ),
+            Ok(_) => {}
+            Err(e) => warn!("Could not evict files from auxiliary caches: {}", e),
+        }
+    }
+
+    /// Remove all files of the auxiliary caches, which are recreated when
+    /// needed.
+    pub fn clean_aux() -> Result<Eviction, TealdeerError> {
+        let dirs = Self::aux_cache_dirs()?;
+        let eviction = state::evict_least_recently_used(&dirs, 0, None)
+            .map_err(|e| CacheError(format!("Could not clean auxiliary caches: {}", e)))?;
+        for dir in dirs.iter().filter(|dir| dir.is_dir()) {
+            fs::remove_dir_all(dir)
+                .map_err(|e| CacheError(format!("Could not remove {}: {}", dir.display(), e)))?;
+        }
+        Ok(eviction)
+    }
+
+    /// Search for a page and return its contents and language (`None` for
+    /// custom pages).
+    ///
+    /// Unlike `find_page`, this never unpacks the page into a file.
+    pub fn read_page(&self, name: &str, languages: &[String]) -> Option<(Vec<u8>, Option<String>)> {
+        match self.locate_page(name, languages)? {
+            PageLocation::Custom(path) | PageLocation::Pinned(path) => {
+                Some((fs::read(&path).ok()?, page_language(&path)))
+            }
+            PageLocation::Store(store, path, _) => {
+                let language = path.split('/').next().and_then(dir_language);
+                Some((store.read(&path).ok()?, language))
+            }
+        }
+    }
+
+    /// Return the languages in which a page is available for the platforms
+    /// to look up pages in (or "common"), sorted alphabetically.
+    pub fn find_page_languages(&self, name: &str) -> Vec<String> {
+        if validate_page_name(name).is_err() {
+            return vec![];
+        }
+        let page_filename = format!("{}.md", name.to_lowercase());
+
+        let store = match self.store() {
+            Ok(store) => store,
+            Err(e) => {
+                log::error!("Could not open cache: {}", e);
+                return vec![];
+            }
+        };
+
+        let platforms: Vec<&str> = self.lookup_platforms().collect();
+
+        let mut languages: Vec<String> = store
+            .list("")
+            .into_iter()
+            .filter(|entry| entry.is_dir)
+            .filter_map(|entry| {
+                let language = dir_language(&entry.name)?;
+                let found = platforms.iter().any(|platform| {
+                    let path = store::join(&store::join(&entry.name, platform), &page_filename);
+                    store.is_file(&path)
+                });
+                if found {
+                    Some(language)
                 } else {
-                    format!("pages.{}", lang)
+                    None
                 }
             })
             .collect();
+        languages.sort();
+        languages
+    }
 
-        // Try to find a platform specific path first.
-        if let Some(pf) = self.get_platform_dir() {
-            let pf_path = Self::find_page_for_platform(&page_filename, &cache_dir, pf, &lang_dirs);
-            if pf_path.is_some() {
-                return pf_path;
+    /// Return the platforms for which the page `name` exists in one of the
+    /// given languages, sorted alphabetically. A custom page is reported as
+    /// platform "custom".
+    pub fn page_platforms(&self, name: &str, languages: &[String]) -> Vec<String> {
+        if validate_page_name(name).is_err() {
+            return vec![];
+        }
+        let mut platforms = Vec::new();
+        if self.find_custom_page(name).is_some() {
+            platforms.push(String::from("custom"));
+        }
+
+        let store = match self.store() {
+            Ok(store) => store,
+            Err(e) => {
+                log::error!("Could not open cache: {}", e);
+                return platforms;
             }
+        };
+        let page_filename = format!("{}.md", name);
+        for lang_dir in languages.iter().map(|lang| language_dir(lang)) {
+            platforms.extend(store.list(&lang_dir).into_iter().filter_map(|entry| {
+                let path = store::join(&store::join(&lang_dir, &entry.name), &page_filename);
+                if entry.is_dir
+                    && !self.ignore_rules.is_ignored(&entry.name)
+                    && store.is_file(&path)
+                {
+                    Some(entry.name)
+                } else {
+                    None
+                }
+            }));
         }
 
-        // Did not find platform specific results, fall back to "common"
-        Self::find_page_for_platform(&page_filename, &cache_dir, "common", &lang_dirs)
+        platforms.sort();
+        platforms.dedup();
+        platforms
     }
 
-    /// Return the available pages.
-    pub fn list_pages(&self) -> Result<Vec<String>, TealdeerError> {
-        // Determine platforms directory and platform
-        let (cache_dir, _) = Self::get_cache_dir()?;
-        let platforms_dir = cache_dir.join("tldr-master").join("pages");
-        let platform_dir = self.get_platform_dir();
-
-        // Closure that allows the WalkDir instance to traverse platform
-        // specific and common page directories, but not others.
-        let should_walk = |entry: &DirEntry| -> bool {
-            let file_type = entry.file_type();
-            let file_name = match entry.file_name().to_str() {
-                Some(name) => name,
-                None => return false,
+    /// Return the pages in the cache in `language` for all platforms, as
+    /// paths relative to the pages directory (e.g. `linux/tar.md`), sorted.
+    pub fn language_pages(&self, language: &str) -> Result<Vec<String>, TealdeerError> {
+        let store = self.store()?;
+        let dir = language_dir(language);
+        let mut pages: Vec<String> = store::walk_files(&*store, &dir, &self.ignore_rules)
+            .into_iter()
+            .filter(|path| Path::new(path).extension() == Some(OsStr::new("md")))
+            .map(|path| path[dir.len() + 1..].to_string())
+            .collect();
+        pages.sort();
+        Ok(pages)
+    }
+
+    /// Read the page at `path` (e.g. `linux/tar.md`) in `language` from the
+    /// cache.
+    pub fn read_language_page(&self, language: &str, path: &str) -> Result<Vec<u8>, TealdeerError> {
+        let path = store::join(&language_dir(language), path);
+        self.store()?
+            .read(&path)
+            .map_err(|e| CacheError(format!("Could not read {}: {}", path, e)))
+    }
+
+    /// Return the pages in the cache in all languages and for all platforms
+    /// (without custom and pinned pages), sorted by name, language and
+    /// platform.
+    pub fn all_pages(&self) -> Result<Vec<PageEntry>, TealdeerError> {
+        let store = self.store()?;
+        let mut pages = Vec::new();
+        for platform_dir in store.platform_dirs() {
+            let mut parts = platform_dir.splitn(2, '/');
+            let (language, platform) = match (parts.next().and_then(dir_language), parts.next()) {
+                (Some(language), Some(platform)) => (language, platform),
+                _ => continue,
             };
-            if file_type.is_dir() {
-                if file_name == "common" {
-                    return true;
-                }
-                if let Some(platform) = platform_dir {
-                    return file_name == platform;
+            if self.ignore_rules.is_ignored(platform) {
+                continue;
+            }
+            for entry in store.list(platform_dir) {
+                if entry.is_dir
+                    || !entry.name.ends_with(".md")
+                    || self.ignore_rules.is_ignored(&entry.name)
+                {
+                    continue;
                 }
-            } else if file_type.is_file() {
-                return true;
+                pages.push(PageEntry {
+                    name: entry.name[..entry.name.len() - ".md".len()].to_string(),
+                    language: language.clone(),
+                    platform: platform.to_string(),
+                });
             }
-            false
-        };
+        }
+        pages.sort();
+        Ok(pages)
+    }
 
-        // Recursively walk through common and (if applicable) platform specific directory
-        let mut pages = WalkDir::new(platforms_dir)
-            .min_depth(1) // Skip root directory
+    /// Return the available pages.
+    pub fn list_pages(&self) -> Result<Vec<String>, TealdeerError> {
+        let mut stores: Vec<_> = self
+            .extra_stores()
             .into_iter()
-            .filter_entry(|e| should_walk(e)) // Filter out pages for other architectures
-            .filter_map(Result::ok) // Convert results to options, filter out errors
-            .filter_map(|e| {
-                let path = e.path();
-                let extension = &path.extension().and_then(OsStr::to_str).unwrap_or("");
-                if e.file_type().is_file() && extension == &"md" {
-                    path.file_stem()
-                        .and_then(|stem| stem.to_str().map(|s| s.into()))
+            .map(|(_, store)| store)
+            .collect();
+        stores.push(self.store()?);
+
+        // Walk through the platform specific and common directories
+        let mut pages: Vec<String> = stores
+            .iter()
+            .flat_map(|store| {
+                self.lookup_platforms().flat_map(move |platform| {
+                    store::walk_files(
+                        &**store,
+                        &store::join("pages", platform),
+                        &self.ignore_rules,
+                    )
+                })
+            })
+            .filter_map(|path| {
+                let file_name = path.rsplit('/').next()?;
+                if file_name.ends_with(".md") {
+                    Some(file_name[..file_name.len() - ".md".len()].to_string())
                 } else {
                     None
                 }
             })
-            .collect::<Vec<String>>();
+            .collect();
+
+        for custom_pages_dir in &self.custom_pages_dirs {
+            let suffix = format!(".{}", CUSTOM_PAGE_EXTENSION);
+            let entries = walk(custom_pages_dir, 1, &self.ignore_rules, |entry| {
+                entry.file_type().is_file()
+            });
+            pages.extend(entries.filter_map(Result::ok).filter_map(|entry| {
+                let file_name = entry.file_name().to_str()?;
+                if file_name.ends_with(&suffix) {
+                    Some(file_name[..file_name.len() - suffix.len()].to_string())
+                } else {
+                    None
+                }
+            }));
+        }
+
+        pages.extend(pins::pinned_names());
+
         pages.sort();
         pages.dedup();
         Ok(pages)
     }
 
+    /// Return every page in `platform` (or the lookup platforms) and
+    /// `language` (or English) with its platform and language, sorted by
+    /// name. A page that exists in several platforms is listed once for every
+    /// platform, from the source that takes precedence in lookups.
+    ///
+    /// Custom pages are listed as platform "custom", in every language.
+    pub fn list_page_locations(
+        &self,
+        platform: Option<&str>,
+        language: Option<&str>,
+    ) -> Result<Vec<ListedPage>, TealdeerError> {
+        let language = language.unwrap_or("en");
+        let platforms: Vec<&str> = match platform {
+            Some(platform) => vec![platform],
+            None => self.lookup_platforms().collect(),
+        };
+
+        // In the order of precedence, the first page of every location is
+        // kept
+        let mut stores: Vec<Arc<dyn PageStore>> = Vec::new();
+        if let Some(pins) = pins::open() {
+            stores.push(Arc::new(pins));
+        }
+        stores.extend(self.extra_stores().into_iter().map(|(_, store)| store));
+        stores.push(self.store()?);
+
+        let mut pages = Vec::new();
+        for store in &stores {
+            for platform in &platforms {
+                let dir = store::join(&language_dir(language), platform);
+                pages.extend(
+                    store::walk_files(&**store, &dir, &self.ignore_rules)
+                        .iter()
+                        .filter_map(|path| {
+                            let file_name = path.rsplit('/').next()?;
+                            if file_name.ends_with(".md") {
+                                Some(ListedPage {
+                                    name: file_name[..file_name.len() - ".md".len()].to_string(),
+                                    platform: (*platform).to_string(),
+                                    language: Some(language.to_string()),
+                                    path: store.file_path(path),
+                                })
+                            } else {
+                                None
+                            }
+                        }),
+                );
+            }
+        }
+
+        if platform.is_none() || platform == Some("custom") {
+            let suffix = format!(".{}", CUSTOM_PAGE_EXTENSION);
+            for custom_pages_dir in &self.custom_pages_dirs {
+                let entries = walk(custom_pages_dir, 1, &self.ignore_rules, |entry| {
+                    entry.file_type().is_file()
+                });
+                pages.extend(entries.filter_map(Result::ok).filter_map(|entry| {
+                    let file_name = entry.file_name().to_str()?;
+                    if file_name.ends_with(&suffix) {
+                        Some(ListedPage {
+                            name: file_name[..file_name.len() - suffix.len()].to_string(),
+                            platform: String::from("custom"),
+                            language: None,
+                            path: Some(entry.path().to_path_buf()),
+                        })
+                    } else {
+                        None
+                    }
+                }));
+            }
+        }
+
+        // The sort is stable, so the page of the first source is kept
+        pages.sort_by(|a, b| {
+            (&a.name, &a.platform, &a.language).cmp(&(&b.name, &b.platform, &b.language))
+        });
+        pages.dedup_by(|a, b| {
+            a.name == b.name && a.platform == b.platform && a.language == b.language
+        });
+        Ok(pages)
+    }
+
     /// Delete the cache directory.
-    pub fn clear() -> Result<(), TealdeerError> {
+    ///
+    /// If the cache directory is a symlink (e.g. managed by a dotfile
+    /// manager), the symlink and the directory it points to are kept and only
+    /// the files created by tealdeer are removed.
+    ///
+    /// The cache is locked while it is cleared, like during updates.
+    ///
+    /// If `to_trash` is set, the cache is moved to the system trash (if
+    /// tealdeer was built with the `trash` feature) or to a timestamped
+    /// backup directory next to the cache directory instead of deleting it.
+    pub fn clear(to_trash: bool) -> Result<ClearOutcome, TealdeerError> {
         let (path, _) = Self::get_cache_dir()?;
-        if path.exists() && path.is_dir() {
-            fs::remove_dir_all(&path).map_err(|_| {
-                CacheError(format!(
-                    "Could not remove cache directory ({}).",
-                    path.display()
-                ))
-            })?;
-        } else if path.exists() {
+        if !path.exists() {
             return Err(CacheError(format!(
-                "Cache path ({}) is not a directory.",
+                "Cache path ({}) does not exist.",
                 path.display()
             )));
-        } else {
+        } else if !path.is_dir() {
             return Err(CacheError(format!(
-                "Cache path ({}) does not exist.",
+                "Cache path ({}) is not a directory.",
                 path.display()
             )));
+        }
+        let _lock = lock_cache_dir(&path)?;
+
+        // Symlinked cache directories are kept, only the files created by
+        // tealdeer are removed from them.
+        let targets: Vec<PathBuf> = if is_symlink(&path) {
+            let entries = fs::read_dir(&path)
+                .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+                .map_err(|e| {
+                    CacheError(format!(
+                        "Could not read cache directory ({}): {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+            entries
+                .into_iter()
+                .filter(|entry| is_cache_dir_entry(&entry.file_name().to_string_lossy()))
+                .map(|entry| entry.path())
+                .collect()
+        } else {
+            vec![path.clone()]
         };
-        Ok(())
+
+        if to_trash {
+            #[cfg(feature = "trash")]
+            match trash::delete_all(&targets) {
+                Ok(()) => return Ok(ClearOutcome::Trashed),
+                Err(e) => debug!("Could not move cache to the trash: {}", e),
+            }
+            let backup_dir = backup_dir(&path)?;
+            move_to_backup(&path, &targets, &backup_dir).map_err(|e| {
+                CacheError(format!(
+                    "Could not move cache to backup directory ({}): {}",
+                    backup_dir.display(),
+                    e
+                ))
+            })?;
+            return Ok(ClearOutcome::BackedUp(backup_dir));
+        }
+
+        for target in &targets {
+            remove_path(target).map_err(|e| {
+                CacheError(format!(
+                    "Could not remove {} from cache directory: {}",
+                    target.display(),
+                    e
+                ))
+            })?;
+        }
+        Ok(ClearOutcome::Deleted)
+    }
+}
+
+/// The error for operations that need pages before the first update.
+pub fn cache_not_found() -> TealdeerError {
+    CacheError("Cache not found.".into()).with_hint("Please run `tldr --update`.")
+}
+
+/// Lock the cache directory for writing, waiting for other processes that
+/// write to it.
+fn lock_cache_dir(cache_dir: &Path) -> Result<CacheLock, TealdeerError> {
+    CacheLock::lock(cache_dir)
+        .map_err(|e| CacheError(format!("Could not lock the cache: {}", e)).with_io_hint(&e))
+}
+
+/// Return a new backup directory next to the (resolved) cache directory.
+fn backup_dir(cache_dir: &Path) -> Result<PathBuf, TealdeerError> {
+    // Backups are created next to the target of a symlinked cache directory,
+    // so that the files can be moved instead of copied
+    let resolved = fs::canonicalize(cache_dir).map_err(|e| {
+        CacheError(format!(
+            "Could not resolve cache directory ({}): {}",
+            cache_dir.display(),
+            e
+        ))
+    })?;
+    let name = resolved
+        .file_name()
+        .map_or_else(|| "tealdeer".into(), |name| name.to_string_lossy());
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    Ok(resolved.with_file_name(format!("{}-backup-{}", name, timestamp)))
+}
+
+/// Move the `targets` inside of `cache_dir` to `backup_dir`.
+///
+/// If the cache directory itself is the only target, it is renamed.
+fn move_to_backup(cache_dir: &Path, targets: &[PathBuf], backup_dir: &Path) -> io::Result<()> {
+    if let [target] = targets {
+        if target == cache_dir {
+            return fs::rename(cache_dir, backup_dir);
+        }
+    }
+    fs::create_dir_all(backup_dir)?;
+    for target in targets {
+        if let Some(name) = target.file_name() {
+            fs::rename(target, backup_dir.join(name))?;
+        }
+    }
+    Ok(())
+}
+
+/// The error returned when the user interrupts an update.
+pub fn interrupted_error() -> TealdeerError {
+    UpdateError("The update was interrupted.".into())
+}
+
+/// Return whether the file `name` in the cache directory was created by
+/// tealdeer (see `CACHE_DIR_ENTRIES`).
+fn is_cache_dir_entry(name: &str) -> bool {
+    CACHE_DIR_ENTRIES.iter().any(|entry| {
+        name == *entry || (name.starts_with(entry) && name[entry.len()..].starts_with('.'))
+    })
+}
+
+/// Return whether `path` is a symlink, without following it.
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .map(|metadata| metadata.file_type().is_symlink())
+        .unwrap_or(false)
+}
+
+/// Return the path to create the missing directory `dir` at.
+///
+/// Dotfile managers often create symlinks to directories that do not exist
+/// yet. In that case, the target of the symlink is returned, so that the
+/// symlink is kept.
+pub fn directory_to_create(dir: &Path) -> PathBuf {
+    match fs::read_link(dir) {
+        Ok(target) => dir.parent().unwrap_or_else(|| Path::new("")).join(target),
+        Err(_) => dir.to_path_buf(),
+    }
+}
+
+/// Remove the file, symlink or directory at `path`, if it exists.
+///
+/// Symlinks are removed themselves, their targets are never touched.
+fn remove_path(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Turn the words of a lookup into a page name.
+///
+/// Words are joined by dashes (`git checkout` becomes `git-checkout`), also
+/// if they were passed as a single argument. The case is kept, since custom
+/// pages may have uppercase names; lookups in the cache lowercase the name,
+/// because all upstream page names are lowercase. Other characters (like the
+/// `+` in `g++` or the `.` in `clang-format-17.0`) are kept as they are.
+pub fn page_name_from_words(words: &[String]) -> String {
+    words
+        .iter()
+        .flat_map(|word| word.split_whitespace())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Return the name of the page of the parent command of a subcommand, e.g.
+/// `git-remote` for `git-remote-add`.
+pub fn parent_page_name(name: &str) -> Option<&str> {
+    let parent = name.rsplitn(2, '-').nth(1)?;
+    if parent.is_empty() {
+        None
+    } else {
+        Some(parent)
+    }
+}
+
+/// Make sure that a user supplied page name can be safely used as a file name
+/// inside the pages directories, i.e. that it can not be used to escape them.
+pub fn validate_page_name(name: &str) -> Result<(), TealdeerError> {
+    let invalid = |reason: &str| {
+        Err(CacheError(format!(
+            "Invalid page name {:?}: {}",
+            name, reason
+        )))
+    };
+    if name.is_empty() {
+        return invalid("The name is empty");
+    }
+    if name == "." || name == ".." {
+        return invalid("The name refers to a directory");
+    }
+    if let Some(c) = name.chars().find(|&c| is_forbidden_char(c)) {
+        return invalid(&format!("The name contains the character {:?}", c));
+    }
+    Ok(())
+}
+
+/// Path separators, NUL and characters with special meaning in Windows paths
+/// (like drive prefixes) are not allowed in page names.
+fn is_forbidden_char(c: char) -> bool {
+    c == '/' || c == '\\' || c == ':' || c == '\0' || c.is_control()
+}
+
+/// Return the language of the page at `path` in the cache, or `None` for
+/// custom pages.
+pub fn page_language(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    if file_name.ends_with(&format!(".{}", CUSTOM_PAGE_EXTENSION)) {
+        return None;
+    }
+    // <pages dir>/<platform>/<page>.md
+    let pages_dir = path.parent()?.parent()?.file_name()?.to_str()?;
+    dir_language(pages_dir)
+}
+
+/// Return the platform of the page at `path` in the cache, or `None` for
+/// custom pages.
+fn page_platform(path: &Path) -> Option<String> {
+    page_language(path)?;
+    Some(path.parent()?.file_name()?.to_str()?.to_string())
+}
+
+/// Return the name of the pages directory for a language.
+fn language_dir(language: &str) -> String {
+    if language == "en" {
+        String::from("pages")
+    } else {
+        format!("pages.{}", language)
+    }
+}
+
+/// Return the language of a pages directory, or `None` if the directory name
+/// does not denote a pages directory.
+fn dir_language(dir_name: &str) -> Option<String> {
+    if dir_name == "pages" {
+        Some(String::from("en"))
+    } else if dir_name.starts_with("pages.") {
+        Some(dir_name["pages.".len()..].to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        dir_language, is_cache_dir_entry, language_dir, page_name_from_words, parent_page_name,
+        validate_page_name, Cache, LookupEvent,
+    };
+
+    use std::fs;
+    use std::path::{Component, Path};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+
+    use tempfile::TempDir;
+
+    use crate::store::{DirectoryStore, PageStore};
+    use crate::types::OsType;
+    use crate::walk::IgnoreRules;
+
+    fn create_cache() -> Cache {
+        Cache::new("", OsType::Linux, vec![], IgnoreRules::default())
+    }
+
+    /// Create a pages directory with 100 pages per language and platform.
+    fn create_pages_dir(lang_dirs: &[&str]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for lang_dir in lang_dirs {
+            for platform in &["common", "linux", "osx", "windows"] {
+                let platform_dir = dir.path().join(lang_dir).join(platform);
+                fs::create_dir_all(&platform_dir).unwrap();
+                for i in 0..100 {
+                    fs::write(platform_dir.join(format!("page-{}.md", i)), "").unwrap();
+                }
+            }
+        }
+        dir
+    }
+
+    #[test]
+    fn test_list_platform_dirs() {
+        let dir = create_pages_dir(&["pages", "pages.de"]);
+        fs::create_dir(dir.path().join("not-pages")).unwrap();
+        let store = DirectoryStore::open(dir.path());
+        let dirs = store.platform_dirs();
+        assert_eq!(dirs.len(), 8);
+        assert!(dirs.contains("pages.de/linux"));
+        assert!(!dirs.iter().any(|dir| dir.starts_with("not-pages")));
+    }
+
+    #[test]
+    fn test_lookup_trace() {
+        let dir = create_pages_dir(&["pages"]);
+        let store = DirectoryStore::open(dir.path());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&events);
+        let cache = create_cache().with_lookup_tracer(Box::new(move |event| {
+            recorded.lock().unwrap().push(event.to_string());
+        }));
+        let lang_dirs = vec![language_dir("de"), language_dir("en")];
+
+        let path = cache.find_page_for_platform("page-1.md", &store, "linux", &lang_dirs);
+        assert_eq!(path.as_deref(), Some("pages/linux/page-1.md"));
+        assert!(cache
+            .find_page_for_platform("page-100.md", &store, "linux", &lang_dirs)
+            .is_none());
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "pages.de/linux: not in the cache, skipped",
+                "pages/linux/page-1.md: found",
+                "pages.de/linux: not in the cache, skipped",
+                "pages/linux/page-100.md: not found",
+            ]
+        );
+        assert_eq!(
+            LookupEvent::Chosen {
+                location: "pages/linux/page-1.md"
+            }
+            .to_string(),
+            "using pages/linux/page-1.md"
+        );
+    }
+
+    /// Compare looking up pages with and without listing the platform
+    /// directories first, with many configured languages that mostly have no
+    /// pages directory. Run with
+    /// `cargo test --release -- --ignored --nocapture bench_find_page`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_find_page_for_platform() {
+        const ROUNDS: usize = 20;
+        let dir = create_pages_dir(&["pages", "pages.de", "pages.fr"]);
+        let lang_dirs: Vec<String> = [
+            "ar", "bn", "ca", "cs", "da", "es", "fa", "fi", "hi", "id", "it", "ja", "ko", "nl",
+            "pl", "pt_BR", "ru", "sv", "th", "tr", "zh", "de", "fr", "en",
+        ]
+        .iter()
+        .map(|lang| language_dir(lang))
+        .collect();
+        // Half of the pages exist
+        let names: Vec<String> = (0..200).map(|i| format!("page-{}.md", i)).collect();
+
+        let start = Instant::now();
+        let mut probed = 0;
+        for _ in 0..ROUNDS {
+            probed = names
+                .iter()
+                .filter(|name| {
+                    lang_dirs
+                        .iter()
+                        .map(|lang_dir| dir.path().join(lang_dir).join("linux").join(name))
+                        .any(|path| path.exists() && path.is_file())
+                })
+                .count();
+        }
+        let probing = start.elapsed();
+
+        let cache = create_cache();
+        let start = Instant::now();
+        let mut prechecked = 0;
+        for _ in 0..ROUNDS {
+            let store = DirectoryStore::open(dir.path());
+            prechecked = names
+                .iter()
+                .filter(|name| {
+                    cache
+                        .find_page_for_platform(name, &store, "linux", &lang_dirs)
+                        .is_some()
+                })
+                .count();
+        }
+        let precheck = start.elapsed();
+
+        assert_eq!(probed, 100);
+        assert_eq!(prechecked, probed);
+        println!(
+            "{} lookups: probing {:?}, with pre-check {:?}",
+            names.len() * ROUNDS,
+            probing,
+            precheck
+        );
+    }
+
+    #[test]
+    fn test_page_name_from_words() {
+        let name = |words: &[&str]| {
+            let words: Vec<String> = words.iter().map(|&w| w.to_string()).collect();
+            page_name_from_words(&words)
+        };
+        assert_eq!(name(&["tar"]), "tar");
+        assert_eq!(name(&["git", "checkout"]), "git-checkout");
+        assert_eq!(name(&["git checkout"]), "git-checkout");
+        assert_eq!(name(&["G++"]), "G++");
+        assert_eq!(name(&["clang-format-17.0"]), "clang-format-17.0");
+        assert_eq!(name(&["["]), "[");
+    }
+
+    #[test]
+    fn test_is_cache_dir_entry() {
+        assert!(is_cache_dir_entry("tldr-master"));
+        assert!(is_cache_dir_entry("tldr-master.new"));
+        assert!(is_cache_dir_entry("update_history.jsonl.1"));
+        assert!(is_cache_dir_entry("cache_info.toml"));
+        assert!(!is_cache_dir_entry("tealdeer.lock"));
+        assert!(!is_cache_dir_entry("tldr-master-backup"));
+        assert!(!is_cache_dir_entry("unrelated.txt"));
+    }
+
+    #[test]
+    fn test_parent_page_name() {
+        fn chain(name: &str) -> Vec<&str> {
+            let mut names = vec![];
+            let mut candidate = parent_page_name(name);
+            while let Some(name) = candidate {
+                names.push(name);
+                candidate = parent_page_name(name);
+            }
+            names
+        }
+        assert_eq!(chain("git-remote-add"), vec!["git-remote", "git"]);
+        assert_eq!(chain("git-log"), vec!["git"]);
+        assert_eq!(chain("clang-format-17.0"), vec!["clang-format", "clang"]);
+        assert!(chain("tar").is_empty());
+        assert!(chain("-v").is_empty());
+    }
+
+    #[test]
+    fn test_validate_page_name() {
+        for name in &["tar", "git-checkout", "g++", "7z", "clang-format-17.0", "["] {
+            assert!(validate_page_name(name).is_ok(), "{} is valid", name);
+        }
+        for name in &[
+            "",
+            ".",
+            "..",
+            "../../etc/passwd",
+            "/etc/passwd",
+            "foo/bar",
+            "..\\..\\windows\\win.ini",
+            "C:secret",
+            "tar\0",
+            "line\nbreak",
+        ] {
+            assert!(validate_page_name(name).is_err(), "{:?} is invalid", name);
+        }
+    }
+
+    /// Generate many hostile names from path-like fragments and make sure
+    /// that no accepted name can escape the pages directory.
+    #[test]
+    fn test_validate_page_name_fuzz() {
+        let fragments = [
+            "..", ".", "/", "\\", "\0", ":", "a", "tar", "%2e", "~", " ", "C:", "\u{2215}",
+        ];
+        // Simple linear congruential generator, to stay deterministic
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = |bound: usize| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (state >> 33) as usize % bound
+        };
+
+        for _ in 0..10_000 {
+            let len = 1 + next(6);
+            let name: String = (0..len).map(|_| fragments[next(fragments.len())]).collect();
+            if validate_page_name(&name).is_err() {
+                continue;
+            }
+            let filename = format!("{}.md", name);
+            let components: Vec<Component> = Path::new(&filename).components().collect();
+            assert_eq!(
+                components.len(),
+                1,
+                "{:?} must be a single path component",
+                name
+            );
+            match components[0] {
+                Component::Normal(_) => {}
+                _ => panic!("{:?} must be a normal path component", name),
+            }
+        }
+    }
+
+    #[test]
+    fn test_language_dir_roundtrip() {
+        for language in &["en", "de", "pt_BR"] {
+            assert_eq!(
+                dir_language(&language_dir(language)).as_deref(),
+                Some(*language)
+            );
+        }
+        assert_eq!(dir_language("LICENSE.md"), None);
     }
 }
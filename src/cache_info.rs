@@ -0,0 +1,440 @@
+//! Versioned metadata about the contents of the cache.
+//!
+//! The metadata is stored in `CACHE_INFO_FILE_NAME` in the cache directory,
+//! next to a manifest listing the SHA-256 checksum of every page (in the
+//! format used by `sha256sum`). Caches created before the metadata was
+//! introduced are treated as format version 0 and migrated on load.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::TealdeerError::{self, CacheError};
+use crate::generation;
+use crate::layout::TLDR_PAGES_DIR;
+use crate::state;
+use crate::store::{self, PageStore};
+use crate::walk::IgnoreRules;
+
+pub const CACHE_INFO_FILE_NAME: &str = "cache_info.toml";
+pub const MANIFEST_FILE_NAME: &str = "manifest.sha256";
+
+/// The current version of the metadata format. Bump this and add a migration
+/// step to `CacheInfo::parse` whenever the format changes.
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheInfo {
+    pub format_version: u32,
+    /// Time of the last update in seconds since the Unix epoch.
+    pub last_update: u64,
+    /// The URL the pages were downloaded from, if known.
+    pub source_url: Option<String>,
+    /// The upstream release the pages belong to, if known.
+    pub release_tag: Option<String>,
+    /// The SHA-256 checksum of the manifest file, if there is one.
+    pub manifest_checksum: Option<String>,
+}
+
+/// Only the format version, used to decide how to parse the rest of the file.
+#[derive(Deserialize)]
+struct Versioned {
+    #[serde(default)]
+    format_version: u32,
+}
+
+/// Return the hex encoded SHA-256 checksum of `data`.
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Return the upstream release tag that `url` refers to, for GitHub release
+/// assets (`…/releases/download/<tag>/…`) and tag archives
+/// (`…/archive/refs/tags/<tag>.tar.gz`). Branch archives have no tag.
+pub fn release_tag_from_url(url: &str) -> Option<String> {
+    let path = url.split(|c| c == '?' || c == '#').next().unwrap_or(url);
+    let segments: Vec<&str> = path.split('/').collect();
+    segments
+        .windows(4)
+        .find_map(|window| match window {
+            ["releases", "download", tag, _] => Some(*tag),
+            ["archive", "refs", "tags", file] => [".tar.gz", ".zip"]
+                .iter()
+                .find(|extension| file.ends_with(*extension))
+                .map(|extension| &file[..file.len() - extension.len()]),
+            _ => None,
+        })
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+}
+
+fn unix_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// The number of threads used to build the manifest by default.
+pub fn default_jobs() -> usize {
+    num_cpus::get()
+}
+
+/// Return the path of the file at `path` in `store` and its checksum.
+fn manifest_entry(store: &dyn PageStore, path: String) -> Result<(String, String), TealdeerError> {
+    let contents = store
+        .read(&path)
+        .map_err(|e| CacheError(format!("Could not read page {}: {}", path, e)))?;
+    Ok((path, sha256_hex(&contents)))
+}
+
+/// Build a manifest of all pages in `store`, sorted by path.
+///
+/// Every line contains the checksum and the path of a page in the store,
+/// separated by two spaces.
+///
+/// The pages are hashed by up to `jobs` threads. The platform directories of
+/// all languages are put into a shared queue, from which every thread takes
+/// the next directory as soon as it is done with its last one, so that a few
+/// large directories (like the English `common` pages) don't hold up the rest.
+pub fn build_manifest(store: &dyn PageStore, jobs: usize) -> Result<String, TealdeerError> {
+    let rules = IgnoreRules::default();
+
+    // Files next to the platform directories are hashed right away
+    let mut entries = Vec::new();
+    let mut platform_dirs = Vec::new();
+    for top_level in store.list("") {
+        if rules.is_ignored(&top_level.name) {
+            continue;
+        }
+        if !top_level.is_dir {
+            entries.push(manifest_entry(store, top_level.name)?);
+            continue;
+        }
+        for entry in store.list(&top_level.name) {
+            let path = store::join(&top_level.name, &entry.name);
+            if rules.is_ignored(&entry.name) {
+                continue;
+            } else if entry.is_dir {
+                platform_dirs.push(path);
+            } else {
+                entries.push(manifest_entry(store, path)?);
+            }
+        }
+    }
+    debug!(
+        "Hashing {} platform directories with {} threads",
+        platform_dirs.len(),
+        jobs
+    );
+
+    let queue = Mutex::new(platform_dirs);
+    let next_dir = || queue.lock().unwrap_or_else(PoisonError::into_inner).pop();
+    let hash_platform_dirs = || -> Result<Vec<(String, String)>, TealdeerError> {
+        let mut entries = Vec::new();
+        while let Some(dir) = next_dir() {
+            for path in store::walk_files(store, &dir, &rules) {
+                entries.push(manifest_entry(store, path)?);
+            }
+        }
+        Ok(entries)
+    };
+    let results = crossbeam_utils::thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.max(1))
+            .map(|_| scope.spawn(|_| hash_platform_dirs()))
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("Manifest worker panicked"))
+            .collect::<Vec<_>>()
+    })
+    .expect("Manifest worker panicked");
+    for result in results {
+        entries.extend(result?);
+    }
+    entries.sort();
+
+    Ok(entries
+        .into_iter()
+        .map(|(path, checksum)| format!("{}  {}\n", checksum, path))
+        .collect())
+}
+
+/// Parse a manifest into a map from page paths to checksums.
+pub fn parse_manifest(contents: &str) -> Result<BTreeMap<String, String>, TealdeerError> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let separator = line
+                .find("  ")
+                .ok_or_else(|| CacheError(format!("Invalid manifest line: {:?}", line)))?;
+            let (checksum, path) = (&line[..separator], &line[separator + 2..]);
+            Ok((path.to_string(), checksum.to_string()))
+        })
+        .collect()
+}
+
+/// The differences between two manifests.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ManifestDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl ManifestDiff {
+    /// Compare the manifests `old` and `new`. All lists are sorted by path.
+    pub fn new(old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> Self {
+        let mut diff = Self::default();
+        for (path, checksum) in new {
+            match old.get(path) {
+                None => diff.added.push(path.clone()),
+                Some(old_checksum) if old_checksum != checksum => diff.changed.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .keys()
+            .filter(|path| !new.contains_key(*path))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl CacheInfo {
+    /// Create metadata for the current contents of the cache at `cache_dir`
+    /// and write the manifest, using up to `jobs` threads.
+    pub fn create(
+        cache_dir: &Path,
+        source_url: Option<String>,
+        jobs: usize,
+    ) -> Result<Self, TealdeerError> {
+        let store = store::open(&generation::resolve_active(cache_dir))?;
+        let manifest = build_manifest(&*store, jobs)?;
+        state::write_atomic(&cache_dir.join(MANIFEST_FILE_NAME), manifest.as_bytes())
+            .map_err(|e| CacheError(format!("Could not write manifest: {}", e)).with_io_hint(&e))?;
+
+        Ok(Self {
+            format_version: FORMAT_VERSION,
+            last_update: unix_timestamp(SystemTime::now()),
+            release_tag: source_url.as_deref().and_then(release_tag_from_url),
+            source_url,
+            manifest_checksum: Some(sha256_hex(manifest.as_bytes())),
+        })
+    }
+
+    /// Write the metadata to the cache at `cache_dir`.
+    pub fn save(&self, cache_dir: &Path) -> Result<(), TealdeerError> {
+        let serialized = toml::to_string(self)
+            .map_err(|e| CacheError(format!("Could not serialize cache info: {}", e)))?;
+        state::save(&cache_dir.join(CACHE_INFO_FILE_NAME), &serialized)
+            .map_err(|e| CacheError(format!("Could not write cache info: {}", e)).with_io_hint(&e))
+    }
+
+    /// Load the metadata of the cache at `cache_dir`, migrating it to the
+    /// current format version if necessary.
+    ///
+    /// Returns `Ok(None)` if there is no cache. If the metadata is corrupt,
+    /// it is created anew.
+    pub fn load(cache_dir: &Path) -> Result<Option<Self>, TealdeerError> {
+        let path = cache_dir.join(CACHE_INFO_FILE_NAME);
+        let contents = state::load(&path)
+            .map_err(|e| CacheError(format!("Could not read cache info: {}", e)))?;
+        match contents {
+            Some(contents) => Self::parse(&contents).map(Some),
+            None if cache_dir.join(TLDR_PAGES_DIR).is_dir() => Self::recreate(cache_dir).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the manifest of the cache at `cache_dir`.
+    pub fn read_manifest(cache_dir: &Path) -> Result<String, TealdeerError> {
+        fs::read_to_string(cache_dir.join(MANIFEST_FILE_NAME))
+            .map_err(|e| CacheError(format!("Could not read manifest: {}", e)))
+    }
+
+    /// Parse serialized metadata of any supported format version.
+    fn parse(contents: &str) -> Result<Self, TealdeerError> {
+        let parse_err = |e: toml::de::Error| CacheError(format!("Invalid cache info: {}", e));
+        let Versioned { format_version } = toml::from_str(contents).map_err(parse_err)?;
+        match format_version {
+            FORMAT_VERSION => toml::from_str(contents).map_err(parse_err),
+            version if version > FORMAT_VERSION => Err(CacheError(format!(
+                "The cache was created by a newer version of tealdeer (cache format version {}).",
+                version
+            ))
+            .with_hint("Run `tldr --clear-cache` or upgrade tealdeer.")),
+            version => Err(CacheError(format!(
+                "Unsupported cache format version {}.",
+                version
+            ))),
+        }
+    }
+
+    /// Create metadata for a cache without (intact) metadata, e.g. one created
+    /// before metadata was introduced.
+    fn recreate(cache_dir: &Path) -> Result<Self, TealdeerError> {
+        let mtime = fs::metadata(cache_dir.join(TLDR_PAGES_DIR))
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| CacheError(format!("Could not read cache directory: {}", e)))?;
+        let mut info = Self::create(cache_dir, None, default_jobs())?;
+        info.last_update = unix_timestamp(mtime);
+        info.save(cache_dir)?;
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        build_manifest, parse_manifest, release_tag_from_url, CacheInfo, ManifestDiff,
+        CACHE_INFO_FILE_NAME, FORMAT_VERSION, MANIFEST_FILE_NAME,
+    };
+
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use crate::layout::TLDR_PAGES_DIR;
+    use crate::store::DirectoryStore;
+
+    fn create_cache() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let common = dir.path().join(TLDR_PAGES_DIR).join("pages").join("common");
+        fs::create_dir_all(&common).unwrap();
+        fs::write(common.join("tar.md"), "# tar\n").unwrap();
+        fs::write(common.join("ls.md"), "# ls\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = create_cache();
+        let info = CacheInfo::create(dir.path(), Some("https://example.com".into()), 2).unwrap();
+        info.save(dir.path()).unwrap();
+        assert_eq!(CacheInfo::load(dir.path()).unwrap(), Some(info));
+
+        let manifest = fs::read_to_string(dir.path().join(MANIFEST_FILE_NAME)).unwrap();
+        // Skip the 64 hex digits of the checksum and the two spaces
+        let paths: Vec<&str> = manifest.lines().map(|line| &line[66..]).collect();
+        assert_eq!(paths, vec!["pages/common/ls.md", "pages/common/tar.md"]);
+    }
+
+    #[test]
+    fn test_parallel_manifest() {
+        let dir = create_cache();
+        let pages_dir = dir.path().join(TLDR_PAGES_DIR);
+        for platform in &["linux", "osx", "windows"] {
+            for lang in &["pages", "pages.de", "pages.fr"] {
+                let platform_dir = pages_dir.join(lang).join(platform);
+                fs::create_dir_all(&platform_dir).unwrap();
+                for page in &["a", "b", "c"] {
+                    let path = platform_dir.join(format!("{}.md", page));
+                    fs::write(path, format!("# {} {} {}\n", page, lang, platform)).unwrap();
+                }
+            }
+        }
+        fs::write(pages_dir.join("README.md"), "# Pages\n").unwrap();
+
+        let store = DirectoryStore::open(&pages_dir);
+        let sequential = build_manifest(&store, 1).unwrap();
+        assert_eq!(sequential.lines().count(), 2 + 3 * 3 * 3 + 1);
+        for jobs in &[2, 4, 64] {
+            assert_eq!(build_manifest(&store, *jobs).unwrap(), sequential);
+        }
+    }
+
+    #[test]
+    fn test_migrate_from_v0() {
+        let dir = create_cache();
+        let info = CacheInfo::load(dir.path()).unwrap().unwrap();
+        assert_eq!(info.format_version, FORMAT_VERSION);
+        assert_eq!(info.source_url, None);
+        assert!(info.manifest_checksum.is_some());
+        assert!(dir.path().join(CACHE_INFO_FILE_NAME).is_file());
+    }
+
+    #[test]
+    fn test_corrupt_cache_info() {
+        let dir = create_cache();
+        let info = CacheInfo::create(dir.path(), Some("https://example.com".into()), 1).unwrap();
+        info.save(dir.path()).unwrap();
+
+        // Simulate a write that was cut short
+        let path = dir.path().join(CACHE_INFO_FILE_NAME);
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::write(&path, &contents[..contents.len() / 2]).unwrap();
+
+        let recreated = CacheInfo::load(dir.path()).unwrap().unwrap();
+        assert_eq!(recreated.source_url, None);
+        assert_eq!(recreated.manifest_checksum, info.manifest_checksum);
+        assert!(path.is_file());
+    }
+
+    #[test]
+    fn test_manifest_diff() {
+        let old = parse_manifest(
+            "aaa  pages/common/a.md\nbbb  pages/common/b.md\nccc  pages/common/c.md\n",
+        )
+        .unwrap();
+        let new = parse_manifest(
+            "aaa  pages/common/a.md\nxxx  pages/common/b.md\nddd  pages/common/d.md\n",
+        )
+        .unwrap();
+        assert_eq!(
+            ManifestDiff::new(&old, &new),
+            ManifestDiff {
+                added: vec!["pages/common/d.md".into()],
+                removed: vec!["pages/common/c.md".into()],
+                changed: vec!["pages/common/b.md".into()],
+            }
+        );
+        assert!(ManifestDiff::new(&old, &old).is_empty());
+        assert!(parse_manifest("invalid\n").is_err());
+    }
+
+    #[test]
+    fn test_no_cache() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(CacheInfo::load(dir.path()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_newer_format_version() {
+        let result = CacheInfo::parse("format_version = 999\nlast_update = 0\n");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_release_tag_from_url() {
+        let tag = |url| release_tag_from_url(url);
+        assert_eq!(
+            tag("https://github.com/tldr-pages/tldr/releases/download/v2.3/tldr.zip"),
+            Some("v2.3".into())
+        );
+        assert_eq!(
+            tag("https://github.com/tldr-pages/tldr/archive/refs/tags/v2.3.tar.gz?raw=1"),
+            Some("v2.3".into())
+        );
+        assert_eq!(
+            tag("https://github.com/tldr-pages/tldr/releases/latest/download/tldr.zip"),
+            None
+        );
+        assert_eq!(
+            tag("https://github.com/tldr-pages/tldr/archive/master.tar.gz"),
+            None
+        );
+        assert_eq!(tag("https://tldr.sh/assets/tldr.zip"), None);
+    }
+}
@@ -0,0 +1,104 @@
+//! Showing pages for commands that are not installed.
+//!
+//! Shells run a hook when a command could not be found (e.g.
+//! `command_not_found_handle` in bash). `tldr --shell-hook` prints a hook
+//! that calls `tldr --command-not-found <name>`, which prints the description
+//! and the first example of the page for the command, if there is one. If
+//! there is no page, nothing is printed, so the hook only adds the time of a
+//! single page lookup.
+
+use crate::parser::Page;
+use crate::quoting::Shell;
+
+/// The hook for bash (`command_not_found_handle`) and zsh
+/// (`command_not_found_handler`).
+const POSIX_HOOK: &str = r#"# Show the page of commands that are not installed, added by `tldr --shell-hook`
+command_not_found_handle() {
+    tldr --command-not-found "$1" >&2
+    printf '%s: command not found\n' "$1" >&2
+    return 127
+}
+command_not_found_handler() {
+    command_not_found_handle "$@"
+}
+"#;
+
+/// The hook for fish, which keeps the default message.
+const FISH_HOOK: &str = r#"# Show the page of commands that are not installed, added by `tldr --shell-hook`
+function fish_command_not_found
+    tldr --command-not-found $argv[1] >&2
+    __fish_default_command_not_found_handler $argv
+end
+"#;
+
+/// The hook for PowerShell, which keeps the default error.
+const POWERSHELL_HOOK: &str = r#"# Show the page of commands that are not installed, added by `tldr --shell-hook`
+$ExecutionContext.InvokeCommand.CommandNotFoundAction = {
+    param($Name, $EventArgs)
+    tldr --command-not-found $Name | Out-Host
+}
+"#;
+
+/// The hook for Nushell, which shows the page as part of the error.
+const NUSHELL_HOOK: &str = r"# Show the page of commands that are not installed, added by `tldr --shell-hook`
+$env.config.hooks.command_not_found = {|name|
+    let page = (^tldr --command-not-found $name | complete)
+    if $page.exit_code == 0 { $page.stdout }
+}
+";
+
+/// Return the command-not-found hook for `shell`, or `None` if the shell has
+/// no such hook (cmd).
+pub fn shell_hook(shell: Shell) -> Option<&'static str> {
+    match shell {
+        Shell::Bash => Some(POSIX_HOOK),
+        Shell::Fish => Some(FISH_HOOK),
+        Shell::PowerShell => Some(POWERSHELL_HOOK),
+        Shell::Nushell => Some(NUSHELL_HOOK),
+        Shell::Cmd => None,
+    }
+}
+
+/// Shorten a page to its title, the first line of its description and its
+/// first example, or return `None` if the page has no examples.
+pub fn brief_page(page: Page) -> Option<Page> {
+    let example = page
+        .examples
+        .into_iter()
+        .find(|example| !example.code.is_empty())?;
+    Some(Page {
+        title: page.title,
+        description: page.description.into_iter().take(1).collect(),
+        more_information: None,
+        examples: vec![example],
+        sections: vec![],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::brief_page;
+    use crate::parser::{Example, Page};
+
+    #[test]
+    fn test_brief_page() {
+        let page = "# tar\n\n> Archiving utility.\n> Often combined with gzip.\n\
+                    > More information: <https://www.gnu.org/software/tar>.\n\n\
+                    - Create an archive:\n\n`tar cf {{target.tar}} {{file}}`\n\n\
+                    - Extract an archive:\n\n`tar xf {{source.tar}}`\n";
+        assert_eq!(
+            brief_page(Page::read(page.as_bytes())),
+            Some(Page {
+                title: "tar".into(),
+                description: vec!["Archiving utility.".into()],
+                more_information: None,
+                examples: vec![Example {
+                    description: "Create an archive:".into(),
+                    code: "tar cf {{target.tar}} {{file}}".into(),
+                }],
+                sections: vec![],
+            })
+        );
+        assert_eq!(brief_page(Page::read(&b"# empty\n"[..])), None);
+    }
+}
@@ -0,0 +1,143 @@
+//! Showing two translations of a page next to each other.
+//!
+//! `tldr <command> --compare-lang en,de` renders the page in both languages
+//! and prints them side by side, so that translators can spot outdated or
+//! missing examples. Lines are paired in order and wrapped to the width of
+//! their column. If the terminal is too narrow for two columns, the
+//! translations are printed one after the other.
+
+use crate::width::{pad, wrap};
+
+/// The separator between the columns.
+const SEPARATOR: &str = " | ";
+
+/// The minimum width of a column for the side by side layout.
+const MIN_COLUMN_WIDTH: usize = 30;
+
+/// A rendered translation of a page.
+pub struct Translation {
+    pub language: String,
+    /// The rendered page, or `None` if the page is not available in this
+    /// language.
+    pub rendered: Option<String>,
+}
+
+impl Translation {
+    fn lines(&self) -> Vec<String> {
+        match self.rendered {
+            Some(ref rendered) => rendered.lines().map(str::to_string).collect(),
+            None => vec![
+                String::new(),
+                format!("  (not available in {})", self.language),
+            ],
+        }
+    }
+}
+
+/// Lay out two translations for a terminal `width` columns wide.
+pub fn layout(left: &Translation, right: &Translation, width: usize) -> String {
+    let column_width = width.saturating_sub(SEPARATOR.len()) / 2;
+    if column_width < MIN_COLUMN_WIDTH {
+        sequential(&[left, right])
+    } else {
+        side_by_side(left, right, column_width)
+    }
+}
+
+fn side_by_side(left: &Translation, right: &Translation, column_width: usize) -> String {
+    let left_lines = left.lines();
+    let right_lines = right.lines();
+    let mut output = format!(
+        "{}{}{}\n",
+        pad(&format!("[{}]", left.language), column_width),
+        SEPARATOR,
+        format!("[{}]", right.language)
+    );
+    for i in 0..left_lines.len().max(right_lines.len()) {
+        let left_parts = wrap(left_lines.get(i).map_or("", String::as_str), column_width);
+        let right_parts = wrap(right_lines.get(i).map_or("", String::as_str), column_width);
+        for j in 0..left_parts.len().max(right_parts.len()) {
+            let left_part = left_parts.get(j).map_or("", String::as_str);
+            let right_part = right_parts.get(j).map_or("", String::as_str);
+            let row = format!(
+                "{}{}{}",
+                pad(left_part, column_width),
+                SEPARATOR,
+                right_part
+            );
+            output.push_str(row.trim_end());
+            output.push('\n');
+        }
+    }
+    output
+}
+
+fn sequential(translations: &[&Translation]) -> String {
+    let mut output = String::new();
+    for (i, translation) in translations.iter().enumerate() {
+        if i > 0 {
+            output.push('\n');
+        }
+        output.push_str(&format!("[{}]\n", translation.language));
+        for line in translation.lines() {
+            output.push_str(&line);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::{layout, Translation};
+
+    fn translation(language: &str, rendered: Option<&str>) -> Translation {
+        Translation {
+            language: language.into(),
+            rendered: rendered.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        let en = translation(
+            "en",
+            Some("\n  Archiving utility.\n\n  - Extract an archive:"),
+        );
+        let de = translation("de", Some("\n  Archivierungsprogramm.\n"));
+        assert_eq!(
+            layout(&en, &de, 69),
+            "[en]                              | [de]\n\
+             \x20                                 |\n\
+             \x20 Archiving utility.              |   Archivierungsprogramm.\n\
+             \x20                                 |\n\
+             \x20 - Extract an archive:           |\n"
+        );
+    }
+
+    #[test]
+    fn test_wrapped_and_missing() {
+        let en = translation(
+            "en",
+            Some("  Extract the archive into the current directory."),
+        );
+        let fr = translation("fr", None);
+        assert_eq!(
+            layout(&en, &fr, 63),
+            "[en]                           | [fr]\n\
+             \x20 Extract the archive into the |\n\
+             \x20current directory.            |\n\
+             \x20                              |   (not available in fr)\n"
+        );
+    }
+
+    #[test]
+    fn test_sequential() {
+        let en = translation("en", Some("  Archiving utility."));
+        let de = translation("de", Some("  Archivierungsprogramm."));
+        assert_eq!(
+            layout(&en, &de, 40),
+            "[en]\n  Archiving utility.\n\n[de]\n  Archivierungsprogramm.\n"
+        );
+    }
+}
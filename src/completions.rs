@@ -0,0 +1,46 @@
+//! Completion scripts for shells.
+//!
+//! `tldr --completions <shell>` prints the completion script for the shell,
+//! which completes the options of tldr and the names of the pages. The page
+//! names are read from `tldr --list` whenever they are completed, so pages
+//! added by an update or custom pages can be completed right away. The
+//! scripts are the ones shipped next to the binary (`bash_tealdeer`,
+//! `fish_tealdeer` and `zsh_tealdeer`).
+
+use serde_derive::Deserialize;
+
+/// A shell with a completion script.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompletionShell {
+    Bash,
+    Fish,
+    Zsh,
+}
+
+impl CompletionShell {
+    /// Return the completion script for the shell.
+    pub fn script(self) -> &'static str {
+        match self {
+            Self::Bash => include_str!("../bash_tealdeer"),
+            Self::Fish => include_str!("../fish_tealdeer"),
+            Self::Zsh => include_str!("../zsh_tealdeer"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CompletionShell;
+
+    #[test]
+    fn test_page_names_from_list() {
+        for shell in &[
+            CompletionShell::Bash,
+            CompletionShell::Fish,
+            CompletionShell::Zsh,
+        ] {
+            assert!(shell.script().contains("tldr --list 2>/dev/null"));
+        }
+    }
+}
@@ -1,16 +1,31 @@
+use std::collections::BTreeMap;
 use std::env;
+use std::fmt;
 use std::fs;
 use std::io::{Error as IoError, Read, Write};
-use std::path::PathBuf;
+use std::iter;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use ansi_term::{Color, Style};
 use app_dirs::{get_app_root, AppDataType};
 use log::debug;
 use serde_derive::{Deserialize, Serialize};
+use toml::Value;
 
+use crate::cache::directory_to_create;
+use crate::config_schema;
+use crate::encoding::OutputEncoding;
 use crate::error::TealdeerError::{self, ConfigError};
+use crate::fetch::{is_minisign_key, is_proxy_url, is_sha256_hex, Checksum, HttpSettings};
+use crate::layout::LayoutSetting;
+use crate::repos::TLDR_PAGES_GIT_URL;
+use crate::rewrite::RewriteRules;
+use crate::store::Backend;
+use crate::theme;
 use crate::types::PathSource;
+use crate::upstream::DEFAULT_UPSTREAM_PAGES_URL;
+use crate::walk::IgnoreRules;
 
 pub const CONFIG_FILE_NAME: &str = "config.toml";
 pub const MAX_CACHE_AGE: Duration = Duration::from_secs(2_592_000); // 30 days
@@ -56,6 +71,69 @@ impl From<RawColor> for Color {
     }
 }
 
+/// The styles that the terminal can show. All styles are resolved for the
+/// profile before they are used.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StyleProfile {
+    /// Any color and attribute.
+    Full,
+    /// The 8 basic colors, bold and underline, for serial consoles and old
+    /// terminal emulators (`minimal_ansi`).
+    Basic,
+}
+
+impl StyleProfile {
+    /// Downgrade `style` to what the profile supports.
+    pub fn resolve(self, style: Style) -> Style {
+        match self {
+            Self::Full => style,
+            Self::Basic => Style {
+                foreground: style.foreground.map(basic_color),
+                background: style.background.map(basic_color),
+                is_bold: style.is_bold,
+                is_underline: style.is_underline,
+                ..Style::default()
+            },
+        }
+    }
+}
+
+/// Return the basic color closest to `color`.
+fn basic_color(color: Color) -> Color {
+    const BASIC_COLORS: [Color; 8] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Purple,
+        Color::Cyan,
+        Color::White,
+    ];
+    // The basic colors are ordered by their red, green and blue bits
+    let from_rgb = |r: u8, g: u8, b: u8| {
+        BASIC_COLORS
+            [usize::from(r >= 128) | usize::from(g >= 128) << 1 | usize::from(b >= 128) << 2]
+    };
+    match color {
+        Color::Fixed(num @ 0..=7) => BASIC_COLORS[usize::from(num)],
+        Color::Fixed(num @ 8..=15) => BASIC_COLORS[usize::from(num - 8)],
+        Color::Fixed(num @ 16..=231) => {
+            // The 6x6x6 color cube
+            let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+            let num = num - 16;
+            from_rgb(level(num / 36), level(num / 6 % 6), level(num % 6))
+        }
+        Color::Fixed(num) => {
+            // The grayscale ramp
+            let gray = 8 + (num - 232) * 10;
+            from_rgb(gray, gray, gray)
+        }
+        Color::RGB(r, g, b) => from_rgb(r, g, b),
+        basic => basic,
+    }
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 struct RawStyle {
     pub foreground: Option<RawColor>,
@@ -113,14 +191,56 @@ struct RawStyleConfig {
     pub example_code: RawStyle,
     #[serde(default)]
     pub example_variable: RawStyle,
+    #[serde(default)]
+    pub placeholder: RawStyle,
+    #[serde(default)]
+    pub url: RawStyle,
+    #[serde(default)]
+    pub section: RawStyle,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 struct RawDisplayConfig {
     #[serde(default)]
     pub compact: bool,
     #[serde(default)]
     pub use_pager: bool,
+    #[serde(default)]
+    pub theme: Option<String>,
+    #[serde(default = "default_indent")]
+    pub indent: usize,
+    #[serde(default = "default_example_indent")]
+    pub example_indent: usize,
+    #[serde(default)]
+    pub render_filter_command: Option<String>,
+    #[serde(default)]
+    pub output_encoding: OutputEncoding,
+    #[serde(default)]
+    pub accessible: bool,
+    #[serde(default = "default_placeholder_markers")]
+    pub placeholder_markers: (String, String),
+    #[serde(default)]
+    pub minimal_ansi: bool,
+    #[serde(default = "default_page_header")]
+    pub page_header: String,
+}
+
+impl Default for RawDisplayConfig {
+    fn default() -> Self {
+        Self {
+            compact: false,
+            use_pager: false,
+            theme: None,
+            indent: default_indent(),
+            example_indent: default_example_indent(),
+            render_filter_command: None,
+            output_encoding: OutputEncoding::default(),
+            accessible: false,
+            placeholder_markers: default_placeholder_markers(),
+            minimal_ansi: false,
+            page_header: default_page_header(),
+        }
+    }
 }
 
 /// Serde doesn't support default values yet (tracking issue:
@@ -131,12 +251,225 @@ const fn default_auto_update_interval_hours() -> u64 {
     DEFAULT_UPDATE_INTERVAL_HOURS
 }
 
+const fn default_indent() -> usize {
+    2
+}
+
+const fn default_example_indent() -> usize {
+    6
+}
+
+const fn default_timeout_seconds() -> u64 {
+    30
+}
+
+const fn default_retry_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_page_header() -> String {
+    "=== {name} ===".into()
+}
+
+const fn default_typing_delay_ms() -> u64 {
+    60
+}
+
+const fn default_pause_ms() -> u64 {
+    1500
+}
+
+fn default_git_url() -> String {
+    TLDR_PAGES_GIT_URL.into()
+}
+
+fn default_upstream_pages_url() -> String {
+    DEFAULT_UPSTREAM_PAGES_URL.into()
+}
+
+fn default_placeholder_markers() -> (String, String) {
+    ("<".into(), ">".into())
+}
+
+const fn default_suggestions() -> usize {
+    5
+}
+
+const fn default_aux_cache_max_mb() -> u64 {
+    10
+}
+
+/// What to do when a page could not be found.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OnMiss {
+    /// Print an error message
+    Error,
+    /// Print an error message followed by the names of similar pages
+    Suggest,
+    /// Print an error message followed by the search results for the command
+    Search,
+}
+
+impl Default for OnMiss {
+    fn default() -> Self {
+        Self::Error
+    }
+}
+
+/// Where the pages are read from.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PagesSource {
+    /// The pages archive at `url` (or the `archive_urls`), downloaded into
+    /// the cache by updates
+    Archive {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        url: Option<String>,
+    },
+    /// A directory containing the pages directories, e.g. a checkout of the
+    /// tldr-pages repository. The pages are read from it directly, so they
+    /// never need to be updated.
+    Directory { path: PathBuf },
+    /// A git repository with the layout of tldr-pages, cloned (shallow) into
+    /// the cache by updates
+    Git {
+        #[serde(default = "default_git_url")]
+        url: String,
+    },
+}
+
+// `#[default]` on enum variants needs a newer Rust than the MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for PagesSource {
+    fn default() -> Self {
+        Self::Archive { url: None }
+    }
+}
+
+impl fmt::Display for PagesSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Archive { url: Some(url) } => write!(f, "the archive at {}", url),
+            Self::Archive { url: None } => write!(f, "the pages archive"),
+            Self::Directory { path } => write!(f, "the directory {}", path.display()),
+            Self::Git { url } => write!(f, "the git repository {}", url),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct RawSearchConfig {
+    #[serde(default)]
+    pub on_miss: OnMiss,
+    #[serde(default = "default_suggestions")]
+    pub suggestions: usize,
+    #[serde(default)]
+    pub fallback_to_other_languages: bool,
+    #[serde(default)]
+    pub rewrite: RewriteRules,
+    #[serde(default)]
+    pub platform_chain: Option<Vec<String>>,
+}
+
+impl Default for RawSearchConfig {
+    fn default() -> Self {
+        Self {
+            on_miss: OnMiss::default(),
+            suggestions: default_suggestions(),
+            fallback_to_other_languages: false,
+            rewrite: RewriteRules::default(),
+            platform_chain: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct RawRunConfig {
+    #[serde(default)]
+    pub allow_run: bool,
+    #[serde(default)]
+    pub history_defaults: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct RawDemoConfig {
+    #[serde(default = "default_typing_delay_ms")]
+    pub typing_delay_ms: u64,
+    #[serde(default = "default_pause_ms")]
+    pub pause_ms: u64,
+}
+
+impl Default for RawDemoConfig {
+    fn default() -> Self {
+        Self {
+            typing_delay_ms: default_typing_delay_ms(),
+            pause_ms: default_pause_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct RawDirectoriesConfig {
+    #[serde(default)]
+    pub custom_pages_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub create_custom_pages_dir: bool,
+    #[serde(default)]
+    pub ignore: IgnoreRules,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 struct RawUpdatesConfig {
     #[serde(default)]
     pub auto_update: bool,
     #[serde(default = "default_auto_update_interval_hours")]
     pub auto_update_interval_hours: u64,
+    #[serde(default)]
+    pub auto_update_in_background: bool,
+    #[serde(default)]
+    pub auto_update_after_lookup: bool,
+    #[serde(default)]
+    pub clear_to_trash: bool,
+    #[serde(default)]
+    pub cache_format: Backend,
+    #[serde(default)]
+    pub archive_urls: Vec<String>,
+    #[serde(default)]
+    pub archive_layout: LayoutSetting,
+    #[serde(default)]
+    pub archive_sha256: Option<String>,
+    #[serde(default)]
+    pub verify_archive_checksum: bool,
+    #[serde(default)]
+    pub archive_minisign_key: Option<String>,
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: u64,
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    #[serde(default)]
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub ca_file: Option<PathBuf>,
+    #[serde(default)]
+    pub client_cert: Option<PathBuf>,
+    #[serde(default)]
+    pub client_cert_password: String,
+    #[serde(default = "default_upstream_pages_url")]
+    pub upstream_pages_url: String,
+    #[serde(default = "default_aux_cache_max_mb")]
+    pub aux_cache_max_mb: u64,
+    #[serde(default)]
+    pub share_token: Option<String>,
+    #[serde(default)]
+    pub record_update_history: bool,
+    // Tables must be serialized after all other values
+    #[serde(default)]
+    pub extra_pages_sources: Vec<PagesSource>,
+    #[serde(default)]
+    pub pages_source: Option<PagesSource>,
 }
 
 impl Default for RawUpdatesConfig {
@@ -144,6 +477,28 @@ impl Default for RawUpdatesConfig {
         Self {
             auto_update: false,
             auto_update_interval_hours: DEFAULT_UPDATE_INTERVAL_HOURS,
+            auto_update_in_background: false,
+            auto_update_after_lookup: false,
+            clear_to_trash: false,
+            cache_format: Backend::default(),
+            archive_urls: Vec::new(),
+            archive_layout: LayoutSetting::default(),
+            archive_sha256: None,
+            verify_archive_checksum: false,
+            archive_minisign_key: None,
+            timeout_seconds: default_timeout_seconds(),
+            retries: 0,
+            retry_backoff_ms: default_retry_backoff_ms(),
+            proxy: None,
+            ca_file: None,
+            client_cert: None,
+            client_cert_password: String::new(),
+            upstream_pages_url: default_upstream_pages_url(),
+            aux_cache_max_mb: default_aux_cache_max_mb(),
+            share_token: None,
+            record_update_history: false,
+            extra_pages_sources: Vec::new(),
+            pages_source: None,
         }
     }
 }
@@ -156,6 +511,14 @@ struct RawConfig {
     display: RawDisplayConfig,
     #[serde(default)]
     updates: RawUpdatesConfig,
+    #[serde(default)]
+    search: RawSearchConfig,
+    #[serde(default)]
+    directories: RawDirectoriesConfig,
+    #[serde(default)]
+    run: RawRunConfig,
+    #[serde(default)]
+    demo: RawDemoConfig,
 }
 
 impl RawConfig {
@@ -168,6 +531,10 @@ impl RawConfig {
         raw_config.style.example_code.foreground = Some(RawColor::Cyan);
         raw_config.style.example_variable.foreground = Some(RawColor::Cyan);
         raw_config.style.example_variable.underline = true;
+        raw_config.style.placeholder.foreground = Some(RawColor::Cyan);
+        raw_config.style.placeholder.bold = true;
+        raw_config.style.url.underline = true;
+        raw_config.style.section.bold = true;
 
         raw_config
     }
@@ -180,29 +547,139 @@ pub struct StyleConfig {
     pub example_text: Style,
     pub example_code: Style,
     pub example_variable: Style,
+    /// The values substituted for variables with `--fill`.
+    pub placeholder: Style,
+    pub url: Style,
+    pub section: Style,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct DisplayConfig {
     pub compact: bool,
     pub use_pager: bool,
+    pub indent: usize,
+    pub example_indent: usize,
+    /// The shell command that rendered pages are piped through.
+    pub render_filter_command: Option<String>,
+    /// The encoding that pages are printed in.
+    pub output_encoding: OutputEncoding,
+    /// Whether pages are rendered for screen readers: without styles, and
+    /// with placeholders between `placeholder_markers`.
+    pub accessible: bool,
+    pub placeholder_markers: (String, String),
+    /// The profile that all styles are resolved for.
+    pub style_profile: StyleProfile,
+    /// The line printed before every page if several pages are shown, with
+    /// `{name}` replaced by the name of the page.
+    pub page_header: String,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct UpdatesConfig {
     pub auto_update: bool,
     pub auto_update_interval: Duration,
+    /// Whether automatic updates run in a background process, while the
+    /// page is shown from the outdated cache.
+    pub auto_update_in_background: bool,
+    /// Whether automatic updates are deferred until a page was shown, and
+    /// then run in a background process.
+    pub auto_update_after_lookup: bool,
+    pub clear_to_trash: bool,
+    pub cache_format: Backend,
+    /// The URLs of the pages archive, tried in order until one works.
+    pub archive_urls: Vec<String>,
+    /// How the layout of the pages archive is determined.
+    pub archive_layout: LayoutSetting,
+    /// How the fetched archive is verified.
+    pub checksum: Checksum,
+    /// The minisign public key that the fetched archive must be signed for.
+    pub archive_minisign_key: Option<String>,
+    /// The timeouts and retries of requests.
+    pub http: HttpSettings,
+    /// The URL that single pages are downloaded from by `--check-upstream`.
+    pub upstream_pages_url: String,
+    /// The size budget of the auxiliary caches (unpacked pages) in MiB.
+    pub aux_cache_max_mb: u64,
+    /// The token that clients of the API of `--share-cache` must send.
+    pub share_token: Option<String>,
+    /// Whether the outcome of every update is logged in the cache directory.
+    pub record_update_history: bool,
+    pub pages_source: PagesSource,
+    /// More sources of pages, searched in order before `pages_source`.
+    pub extra_pages_sources: Vec<PagesSource>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchConfig {
+    pub on_miss: OnMiss,
+    /// The maximum number of similar pages suggested for a missing page.
+    pub suggestions: usize,
+    pub fallback_to_other_languages: bool,
+    pub rewrite: RewriteRules,
+    /// The platform directories to look up pages in before `common`, instead
+    /// of the default ones for the operating system.
+    pub platform_chain: Option<Vec<String>>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RunConfig {
+    pub allow_run: bool,
+    pub history_defaults: bool,
+}
+
+/// The timing of demos recorded with `--demo`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DemoConfig {
+    /// The delay between two typed characters.
+    pub typing_delay: Duration,
+    /// The pause after every example, to read it.
+    pub pause: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DirectoriesConfig {
+    pub custom_pages_dir: Option<PathBuf>,
+    pub create_custom_pages_dir: bool,
+    pub ignore: IgnoreRules,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Config {
     pub style: StyleConfig,
     pub display: DisplayConfig,
     pub updates: UpdatesConfig,
+    pub search: SearchConfig,
+    pub directories: DirectoriesConfig,
+    pub run: RunConfig,
+    pub demo: DemoConfig,
+    /// The configs for rendering pages in certain languages, from the
+    /// `[language.<lang>]` tables.
+    pub languages: BTreeMap<String, Config>,
+}
+
+impl StyleConfig {
+    /// Resolve all styles for `profile`.
+    fn resolve(self, profile: StyleProfile) -> Self {
+        Self {
+            command_name: profile.resolve(self.command_name),
+            description: profile.resolve(self.description),
+            example_text: profile.resolve(self.example_text),
+            example_code: profile.resolve(self.example_code),
+            example_variable: profile.resolve(self.example_variable),
+            placeholder: profile.resolve(self.placeholder),
+            url: profile.resolve(self.url),
+            section: profile.resolve(self.section),
+        }
+    }
 }
 
 impl From<RawConfig> for Config {
     fn from(raw_config: RawConfig) -> Self {
+        let style_profile = if raw_config.display.minimal_ansi {
+            StyleProfile::Basic
+        } else {
+            StyleProfile::Full
+        };
         Self {
             style: StyleConfig {
                 command_name: raw_config.style.command_name.into(),
@@ -210,28 +687,93 @@ impl From<RawConfig> for Config {
                 example_text: raw_config.style.example_text.into(),
                 example_code: raw_config.style.example_code.into(),
                 example_variable: raw_config.style.example_variable.into(),
-            },
+                placeholder: raw_config.style.placeholder.into(),
+                url: raw_config.style.url.into(),
+                section: raw_config.style.section.into(),
+            }
+            .resolve(style_profile),
             display: DisplayConfig {
                 compact: raw_config.display.compact,
                 use_pager: raw_config.display.use_pager,
+                indent: raw_config.display.indent,
+                example_indent: raw_config.display.example_indent,
+                render_filter_command: raw_config.display.render_filter_command,
+                output_encoding: raw_config.display.output_encoding,
+                accessible: raw_config.display.accessible,
+                placeholder_markers: raw_config.display.placeholder_markers,
+                style_profile,
+                page_header: raw_config.display.page_header,
             },
             updates: UpdatesConfig {
                 auto_update: raw_config.updates.auto_update,
                 auto_update_interval: Duration::from_secs(
                     raw_config.updates.auto_update_interval_hours * 3600,
                 ),
+                auto_update_in_background: raw_config.updates.auto_update_in_background,
+                auto_update_after_lookup: raw_config.updates.auto_update_after_lookup,
+                clear_to_trash: raw_config.updates.clear_to_trash,
+                cache_format: raw_config.updates.cache_format,
+                archive_urls: raw_config.updates.archive_urls,
+                archive_layout: raw_config.updates.archive_layout,
+                checksum: match raw_config.updates.archive_sha256 {
+                    Some(checksum) => Checksum::Pinned(checksum.to_lowercase()),
+                    None if raw_config.updates.verify_archive_checksum => Checksum::Sibling,
+                    None => Checksum::Unverified,
+                },
+                archive_minisign_key: raw_config.updates.archive_minisign_key,
+                http: HttpSettings {
+                    timeout: match raw_config.updates.timeout_seconds {
+                        0 => None,
+                        seconds => Some(Duration::from_secs(seconds)),
+                    },
+                    retries: raw_config.updates.retries,
+                    retry_backoff: Duration::from_millis(raw_config.updates.retry_backoff_ms),
+                    proxy: raw_config.updates.proxy,
+                    ca_file: raw_config.updates.ca_file,
+                    client_cert: raw_config.updates.client_cert,
+                    client_cert_password: raw_config.updates.client_cert_password,
+                },
+                upstream_pages_url: raw_config.updates.upstream_pages_url,
+                aux_cache_max_mb: raw_config.updates.aux_cache_max_mb,
+                share_token: raw_config.updates.share_token,
+                record_update_history: raw_config.updates.record_update_history,
+                pages_source: raw_config.updates.pages_source.unwrap_or_default(),
+                extra_pages_sources: raw_config.updates.extra_pages_sources,
+            },
+            search: SearchConfig {
+                on_miss: raw_config.search.on_miss,
+                suggestions: raw_config.search.suggestions,
+                fallback_to_other_languages: raw_config.search.fallback_to_other_languages,
+                rewrite: raw_config.search.rewrite,
+                platform_chain: raw_config.search.platform_chain,
+            },
+            directories: DirectoriesConfig {
+                custom_pages_dir: raw_config.directories.custom_pages_dir,
+                create_custom_pages_dir: raw_config.directories.create_custom_pages_dir,
+                ignore: raw_config.directories.ignore,
+            },
+            run: RunConfig {
+                allow_run: raw_config.run.allow_run,
+                history_defaults: raw_config.run.history_defaults,
             },
+            demo: DemoConfig {
+                typing_delay: Duration::from_millis(raw_config.demo.typing_delay_ms),
+                pause: Duration::from_millis(raw_config.demo.pause_ms),
+            },
+            languages: BTreeMap::new(),
         }
     }
 }
 
 #[allow(clippy::needless_pass_by_value)]
 fn map_io_err_to_config_err(e: IoError) -> TealdeerError {
-    ConfigError(format!("Io Error: {}", e))
+    ConfigError(format!("Io Error: {}", e)).with_io_hint(&e)
 }
 
 impl Config {
-    pub fn load(enable_styles: bool) -> Result<Self, TealdeerError> {
+    /// Load the config file, with the settings of `profile` (if any) applied
+    /// on top of the base settings.
+    pub fn load(enable_styles: bool, profile: Option<&str>) -> Result<Self, TealdeerError> {
         debug!("Loading config");
 
         // Determine path
@@ -239,35 +781,403 @@ impl Config {
             .map_err(|e| ConfigError(format!("Could not determine config path: {}", e)))?;
 
         // Load raw config
+        let mut language_configs = BTreeMap::new();
         let raw_config: RawConfig = if config_file_path.exists() && config_file_path.is_file() {
-            let mut config_file =
-                fs::File::open(config_file_path).map_err(map_io_err_to_config_err)?;
-            let mut contents = String::new();
-            let _ = config_file
-                .read_to_string(&mut contents)
-                .map_err(map_io_err_to_config_err)?;
-            toml::from_str(&contents)
+            let mut value = load_config_value(&config_file_path, &mut Vec::new())?;
+            apply_profile(&mut value, profile)?;
+            for (language, overrides) in take_language_overrides(&mut value)? {
+                let mut language_value = value.clone();
+                merge_values(&mut language_value, overrides);
+                apply_theme(&mut language_value)?;
+                let raw_config: RawConfig = language_value.try_into().map_err(|err| {
+                    ConfigError(format!(
+                        "Failed to parse settings for language {}: {}",
+                        language, err
+                    ))
+                })?;
+                language_configs.insert(language, Self::from(raw_config));
+            }
+            apply_theme(&mut value)?;
+            value
+                .try_into()
                 .map_err(|err| ConfigError(format!("Failed to parse config file: {}", err)))?
+        } else if let Some(profile) = profile {
+            return Err(ConfigError(format!(
+                "Profile {} not found, there is no config file at {}",
+                profile,
+                config_file_path.display()
+            )));
         } else {
             RawConfig::new()
         };
 
         // Convert to config
         let mut config = Self::from(raw_config);
+        config.validate()?;
+        config.languages = language_configs;
 
-        // Potentially override styles
-        if !enable_styles {
-            config.style = StyleConfig {
-                command_name: Style::default(),
-                description: Style::default(),
-                example_text: Style::default(),
-                example_code: Style::default(),
-                example_variable: Style::default(),
-            };
+        // Potentially override styles. Accessible pages never depend on
+        // colors.
+        if !enable_styles || config.display.accessible {
+            config.disable_styles();
+        }
+        for language_config in config.languages.values_mut() {
+            if !enable_styles || language_config.display.accessible {
+                language_config.disable_styles();
+            }
         }
 
         Ok(config)
     }
+
+    fn disable_styles(&mut self) {
+        self.style = StyleConfig {
+            command_name: Style::default(),
+            description: Style::default(),
+            example_text: Style::default(),
+            example_code: Style::default(),
+            example_variable: Style::default(),
+            placeholder: Style::default(),
+            url: Style::default(),
+            section: Style::default(),
+        };
+    }
+
+    /// Don't pipe pages through the render filter, in any language.
+    pub fn disable_render_filter(&mut self) {
+        self.display.render_filter_command = None;
+        for config in self.languages.values_mut() {
+            config.disable_render_filter();
+        }
+    }
+
+    /// Return the config for rendering pages in `language`.
+    ///
+    /// Falls back to the settings for the base language (e.g. `zh` for
+    /// `zh_TW`) and then to the general settings.
+    pub fn for_language(&self, language: &str) -> &Self {
+        let base_language = language.split('_').next().unwrap_or(language);
+        self.languages
+            .get(language)
+            .or_else(|| self.languages.get(base_language))
+            .unwrap_or(self)
+    }
+
+    /// Check the config for values that can never work.
+    ///
+    /// A custom pages directory that does not exist yet is created if
+    /// `create_custom_pages_dir` is enabled. Otherwise it is not an error,
+    /// see `custom_pages_dir_missing`.
+    fn validate(&self) -> Result<(), TealdeerError> {
+        for platform in self.search.platform_chain.iter().flatten() {
+            if platform.is_empty() || platform.contains(&['/', '\\', '.'][..]) {
+                return Err(ConfigError(format!(
+                    "Invalid platform {:?} in `platform_chain`.",
+                    platform
+                )));
+            }
+        }
+        if let Checksum::Pinned(ref checksum) = self.updates.checksum {
+            if !is_sha256_hex(checksum) {
+                return Err(ConfigError(format!(
+                    "Invalid SHA-256 checksum {:?} in `archive_sha256`.",
+                    checksum
+                )));
+            }
+        }
+        if let Some(ref url) = self.updates.http.proxy {
+            if !is_proxy_url(url) {
+                return Err(
+                    ConfigError(format!("Invalid proxy URL {:?} in `proxy`.", url))
+                        .with_hint("Use an http://, https://, socks5:// or socks5h:// URL."),
+                );
+            }
+        }
+        if let Some(ref key) = self.updates.archive_minisign_key {
+            if !is_minisign_key(key) {
+                return Err(ConfigError(format!(
+                    "Invalid minisign public key {:?} in `archive_minisign_key`.",
+                    key
+                ))
+                .with_hint(
+                    "Use the second line of `minisign.pub`, e.g. \"RWQf6LRCGA9i53ml...\".",
+                ));
+            }
+        }
+        if let Some(ref token) = self.updates.share_token {
+            if token.trim().is_empty() || token.chars().any(char::is_whitespace) {
+                return Err(ConfigError(
+                    "Invalid `share_token`, it must not be empty or contain whitespace.".into(),
+                ));
+            }
+        }
+        match self.updates.pages_source {
+            PagesSource::Directory { ref path } => validate_pages_dir(path)?,
+            PagesSource::Archive { url: Some(_) } if !self.updates.archive_urls.is_empty() => {
+                return Err(ConfigError(
+                    "The archive of the pages source is set by both `url` and `archive_urls`."
+                        .into(),
+                )
+                .with_hint("List all mirrors in `archive_urls`."));
+            }
+            _ => {}
+        }
+        for source in &self.updates.extra_pages_sources {
+            match source {
+                PagesSource::Directory { path } => validate_pages_dir(path)?,
+                PagesSource::Archive { url: None } => {
+                    return Err(ConfigError(
+                        "An archive in `extra_pages_sources` has no `url`.".into(),
+                    ))
+                }
+                _ => {}
+            }
+        }
+        if let Some(ref dir) = self.directories.custom_pages_dir {
+            if dir.exists() && !dir.is_dir() {
+                return Err(ConfigError(format!(
+                    "The custom pages directory {} is not a directory.",
+                    dir.display()
+                )));
+            }
+            if !dir.exists() && self.directories.create_custom_pages_dir {
+                fs::create_dir_all(directory_to_create(dir)).map_err(|e| {
+                    ConfigError(format!(
+                        "Could not create custom pages directory {}: {}",
+                        dir.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the configured custom pages directory if it does not exist.
+    pub fn custom_pages_dir_missing(&self) -> Option<&Path> {
+        self.directories
+            .custom_pages_dir
+            .as_deref()
+            .filter(|dir| !dir.exists())
+    }
+}
+
+/// Merge `overlay` into `base`. Tables are merged recursively, all other
+/// values (including arrays) in `overlay` replace the ones in `base`.
+fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Table(base), Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Apply the theme selected in `display.theme` (if any) below the `style`
+/// tables of the config `value`.
+fn apply_theme(value: &mut Value) -> Result<(), TealdeerError> {
+    let name = match config_schema::lookup(value, "display.theme") {
+        Some(Value::String(name)) => name.clone(),
+        _ => return Ok(()),
+    };
+    let mut style = theme::load_theme(&name)?;
+    let table = value
+        .as_table_mut()
+        .expect("The config file is always a table");
+    if let Some(overlay) = table.remove("style") {
+        merge_values(&mut style, overlay);
+    }
+    table.insert("style".into(), style);
+    Ok(())
+}
+
+/// Check that `path` is a directory with the layout of tldr-pages, which a
+/// pages source can read pages from.
+fn validate_pages_dir(path: &Path) -> Result<(), TealdeerError> {
+    if !path.is_dir() {
+        return Err(ConfigError(format!(
+            "The pages source {} is not a directory.",
+            path.display()
+        )));
+    }
+    if !path.join("pages").is_dir() {
+        return Err(ConfigError(format!(
+            "The pages source {} does not contain a `pages` directory.",
+            path.display()
+        ))
+        .with_hint("Use the root directory of a checkout of the tldr-pages repository."));
+    }
+    Ok(())
+}
+
+/// Check that `styles` are valid `style.*` tables.
+pub fn validate_styles(styles: Value) -> Result<(), String> {
+    styles
+        .try_into::<RawStyleConfig>()
+        .map(drop)
+        .map_err(|e| e.to_string())
+}
+
+/// Load the config file at `path` and the files it includes.
+///
+/// The files listed in the top-level `include` array are loaded first, in
+/// order, with later files overriding earlier ones. The including file
+/// overrides all of them. Relative paths are resolved against the directory
+/// of the including file. `stack` contains the files that are currently being
+/// loaded and is used to detect include cycles.
+fn load_config_value(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Value, TealdeerError> {
+    let canonical_path = path.canonicalize().map_err(|e| {
+        ConfigError(format!(
+            "Could not open config file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    if let Some(start) = stack.iter().position(|p| p == &canonical_path) {
+        let cycle: Vec<String> = stack[start..]
+            .iter()
+            .chain(iter::once(&canonical_path))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(ConfigError(format!(
+            "Config files include each other: {}",
+            cycle.join(" -> ")
+        )));
+    }
+
+    let mut contents = String::new();
+    fs::File::open(&canonical_path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .map_err(map_io_err_to_config_err)?;
+    let mut value: Value = toml::from_str(&contents).map_err(|err| {
+        ConfigError(format!(
+            "Failed to parse config file {}: {}",
+            path.display(),
+            err
+        ))
+    })?;
+
+    let includes = match value
+        .as_table_mut()
+        .and_then(|table| table.remove("include"))
+    {
+        None => return Ok(value),
+        Some(Value::Array(includes)) => includes,
+        Some(_) => {
+            return Err(ConfigError(format!(
+                "`include` in {} must be an array of paths",
+                path.display()
+            )))
+        }
+    };
+
+    stack.push(canonical_path);
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut merged = Value::Table(toml::value::Table::new());
+    for include in includes {
+        let include = include.as_str().ok_or_else(|| {
+            ConfigError(format!(
+                "`include` in {} must be an array of paths",
+                path.display()
+            ))
+        })?;
+        let included = load_config_value(&base_dir.join(include), stack)?;
+        merge_values(&mut merged, included);
+    }
+    stack.pop();
+
+    merge_values(&mut merged, value);
+    Ok(merged)
+}
+
+/// Remove all profiles from the config and merge the settings of `profile`
+/// (if any) into the base settings.
+fn apply_profile(value: &mut Value, profile: Option<&str>) -> Result<(), TealdeerError> {
+    let mut profiles = match value
+        .as_table_mut()
+        .and_then(|table| table.remove("profile"))
+    {
+        Some(Value::Table(profiles)) => profiles,
+        Some(_) => return Err(ConfigError("`profile` must be a table of profiles".into())),
+        None => toml::value::Table::new(),
+    };
+
+    if let Some(profile) = profile {
+        debug!("Applying profile {}", profile);
+        let settings = profiles.remove(profile).ok_or_else(|| {
+            let mut names: Vec<&String> = profiles.keys().collect();
+            names.sort();
+            let available = names
+                .iter()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            ConfigError(format!(
+                "Profile {} not found in config file (available profiles: {})",
+                profile,
+                if available.is_empty() {
+                    "none"
+                } else {
+                    &available
+                }
+            ))
+        })?;
+        if !settings.is_table() {
+            return Err(ConfigError(format!(
+                "Profile {} must be a table of settings",
+                profile
+            )));
+        }
+        merge_values(value, settings);
+    }
+    Ok(())
+}
+
+/// The sections that can be overridden for pages in a certain language.
+const LANGUAGE_SECTIONS: &[&str] = &["style", "display"];
+
+/// Remove the `[language.<lang>]` tables from the config `value` and return
+/// them by language.
+fn take_language_overrides(value: &mut Value) -> Result<Vec<(String, Value)>, TealdeerError> {
+    let languages = match value
+        .as_table_mut()
+        .and_then(|table| table.remove("language"))
+    {
+        Some(Value::Table(languages)) => languages,
+        Some(_) => {
+            return Err(ConfigError(
+                "`language` must be a table of languages".into(),
+            ))
+        }
+        None => return Ok(vec![]),
+    };
+
+    languages
+        .into_iter()
+        .map(|(language, overrides)| {
+            let valid = overrides.as_table().map_or(false, |sections| {
+                sections
+                    .keys()
+                    .all(|key| LANGUAGE_SECTIONS.contains(&key.as_str()))
+            });
+            if valid {
+                Ok((language, overrides))
+            } else {
+                Err(ConfigError(format!(
+                    "The settings for language {} may only contain the sections {}",
+                    language,
+                    LANGUAGE_SECTIONS.join(", ")
+                )))
+            }
+        })
+        .collect()
 }
 
 /// Return the path to the config directory.
@@ -303,8 +1213,31 @@ pub fn get_config_path() -> Result<(PathBuf, PathSource), TealdeerError> {
     Ok((config_file_path, source))
 }
 
+/// Return the default config as a TOML value.
+pub fn default_config_value() -> Value {
+    Value::try_from(RawConfig::new()).expect("The default config can always be serialized")
+}
+
+/// Return the default config as a JSON value. Unlike in TOML, unset options
+/// are kept (as `null`), so every option of the config is included.
+#[cfg(test)]
+pub fn default_config_json() -> serde_json::Value {
+    serde_json::to_value(RawConfig::new()).expect("The default config can always be serialized")
+}
+
+/// Header of generated config files.
+const SEED_CONFIG_HEADER: &str = "\
+# tealdeer config file, see https://dbrgn.github.io/tealdeer/config.html
+#
+# Every option is documented with its type and default value. Options that
+# are not set by default are commented out.
+
+";
+
 /// Create default config file.
-pub fn make_default_config() -> Result<PathBuf, TealdeerError> {
+///
+/// Unless `minimal` is set, every option is documented in a comment.
+pub fn make_default_config(minimal: bool) -> Result<PathBuf, TealdeerError> {
     let (config_dir, _) = get_config_dir()?;
 
     // Ensure that config directory exists
@@ -328,12 +1261,20 @@ pub fn make_default_config() -> Result<PathBuf, TealdeerError> {
         return Err(ConfigError(format!(
             "A configuration file already exists at {}, no action was taken.",
             config_file_path.to_str().unwrap()
-        )));
+        ))
+        .with_hint("Use `tldr --seed-config --diff` to show the options missing from it."));
     }
 
     // Create default config
-    let serialized_config = toml::to_string(&RawConfig::new())
-        .map_err(|err| ConfigError(format!("Failed to serialize default config: {}", err)))?;
+    let serialized_config = if minimal {
+        config_schema::render(&config_schema::sections(), false, |_, _| true)
+    } else {
+        format!(
+            "{}{}",
+            SEED_CONFIG_HEADER,
+            config_schema::render(&config_schema::sections(), true, |_, _| true)
+        )
+    };
 
     // Write default config
     let mut config_file = fs::File::create(&config_file_path).map_err(map_io_err_to_config_err)?;
@@ -344,6 +1285,104 @@ pub fn make_default_config() -> Result<PathBuf, TealdeerError> {
     Ok(config_file_path)
 }
 
+/// Return the documented options that are not set in the config file
+/// (including the files it includes), rendered as a config file.
+///
+/// Returns an empty string if all options are set.
+pub fn missing_options(minimal: bool) -> Result<String, TealdeerError> {
+    let (config_file_path, _) = get_config_path()?;
+    let value = if config_file_path.is_file() {
+        load_config_value(&config_file_path, &mut Vec::new())?
+    } else {
+        Value::Table(toml::value::Table::new())
+    };
+    Ok(config_schema::render(
+        &config_schema::sections(),
+        !minimal,
+        |section, option| {
+            config_schema::lookup(&value, &format!("{}.{}", section.name, option.key)).is_none()
+        },
+    ))
+}
+
+#[test]
+fn test_seed_config_roundtrip() {
+    let commented = config_schema::render(&config_schema::sections(), true, |_, _| true);
+    let deserialized: RawConfig = toml::from_str(&commented).unwrap();
+    assert_eq!(deserialized, RawConfig::new());
+}
+
+/// Every commented out example must be valid when it is uncommented.
+#[test]
+fn test_seed_config_examples() {
+    for section in config_schema::sections() {
+        for option in section.options {
+            let snippet = format!(
+                "[{}]\n{} = {}\n",
+                section.name,
+                option.key,
+                config_schema::format_value(&option.option_type.example())
+            );
+            if let Err(e) = toml::from_str::<RawConfig>(&snippet) {
+                panic!("Invalid example {:?}: {}", snippet, e);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_merge_values() {
+    let mut base: Value = toml::from_str(
+        "[style.command_name]\nforeground = \"red\"\nbold = true\n[display]\ncompact = true\n",
+    )
+    .unwrap();
+    let overlay: Value = toml::from_str(
+        "[style.command_name]\nforeground = \"blue\"\n[updates]\nauto_update = true\n",
+    )
+    .unwrap();
+    merge_values(&mut base, overlay);
+    let expected: Value = toml::from_str(
+        "[style.command_name]\nforeground = \"blue\"\nbold = true\n\
+         [display]\ncompact = true\n[updates]\nauto_update = true\n",
+    )
+    .unwrap();
+    assert_eq!(base, expected);
+}
+
+#[test]
+fn test_apply_profile() {
+    let mut value: Value = toml::from_str(
+        "[display]\ncompact = false\nuse_pager = true\n\
+         [profile.work.display]\ncompact = true\n\
+         [profile.home.updates]\nauto_update = true\n",
+    )
+    .unwrap();
+    let mut base = value.clone();
+
+    apply_profile(&mut value, Some("work")).unwrap();
+    let expected: Value = toml::from_str("[display]\ncompact = true\nuse_pager = true\n").unwrap();
+    assert_eq!(value, expected);
+
+    apply_profile(&mut base, None).unwrap();
+    let expected: Value = toml::from_str("[display]\ncompact = false\nuse_pager = true\n").unwrap();
+    assert_eq!(base, expected);
+
+    let mut value: Value = toml::from_str("[profile.work]\n").unwrap();
+    assert!(apply_profile(&mut value, Some("client")).is_err());
+}
+
+#[test]
+fn test_include_cycle() {
+    let dir = tempfile::TempDir::new().unwrap();
+    fs::write(dir.path().join("a.toml"), "include = [\"b.toml\"]\n").unwrap();
+    fs::write(dir.path().join("b.toml"), "include = [\"a.toml\"]\n").unwrap();
+    let result = load_config_value(&dir.path().join("a.toml"), &mut Vec::new());
+    match result {
+        Err(ConfigError(msg)) => assert!(msg.contains("include each other"), "{}", msg),
+        other => panic!("Expected an include cycle error, got {:?}", other),
+    }
+}
+
 #[test]
 fn test_serialize_deserialize() {
     let raw_config = RawConfig::new();
@@ -351,3 +1390,53 @@ fn test_serialize_deserialize() {
     let deserialized: RawConfig = toml::from_str(&serialized).unwrap();
     assert_eq!(raw_config, deserialized);
 }
+
+#[test]
+fn test_language_overrides() {
+    let mut value: Value = toml::from_str(
+        "[display]\nindent = 2\n\
+         [language.ja.display]\nindent = 4\n\
+         [language.zh.style.example_text]\nbold = true\n",
+    )
+    .unwrap();
+    let overrides = take_language_overrides(&mut value).unwrap();
+    assert_eq!(value.get("language"), None);
+    assert_eq!(
+        overrides
+            .iter()
+            .map(|(l, _)| l.as_str())
+            .collect::<Vec<_>>(),
+        vec!["ja", "zh"]
+    );
+
+    let mut config = Config::from(RawConfig::new());
+    let mut ja = config.clone();
+    ja.display.indent = 4;
+    config.languages.insert("ja".into(), ja);
+    assert_eq!(config.for_language("ja").display.indent, 4);
+    assert_eq!(config.for_language("ja_JP").display.indent, 4);
+    assert_eq!(config.for_language("de").display.indent, 2);
+
+    let mut invalid: Value = toml::from_str("[language.ja.updates]\nauto_update = true\n").unwrap();
+    assert!(take_language_overrides(&mut invalid).is_err());
+}
+
+#[test]
+fn test_basic_style_profile() {
+    let style = Style::new()
+        .fg(Color::Fixed(208))
+        .on(Color::RGB(10, 20, 200))
+        .bold()
+        .italic()
+        .dimmed();
+    assert_eq!(StyleProfile::Full.resolve(style), style);
+    assert_eq!(
+        StyleProfile::Basic.resolve(style),
+        Style::new().fg(Color::Yellow).on(Color::Blue).bold()
+    );
+
+    assert_eq!(basic_color(Color::Fixed(9)), Color::Red);
+    assert_eq!(basic_color(Color::Fixed(232)), Color::Black);
+    assert_eq!(basic_color(Color::Fixed(255)), Color::White);
+    assert_eq!(basic_color(Color::Cyan), Color::Cyan);
+}
@@ -0,0 +1,91 @@
+//! Checking which commands of a list have pages.
+//!
+//! Teams can use this to audit whether the tools they use are documented
+//! before rolling out tealdeer. The command list contains one command per
+//! line, multi-word commands like `git checkout` are allowed. Empty lines and
+//! lines starting with `#` are ignored.
+
+use serde_derive::Serialize;
+
+use crate::cache::{page_name_from_words, Cache};
+use crate::rewrite::RewriteRules;
+
+/// A command that has a page.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct CoveredCommand {
+    pub command: String,
+    /// The platforms with a page, e.g. `common` or `linux`.
+    pub platforms: Vec<String>,
+}
+
+/// The commands of a list that have pages and those that don't.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Coverage {
+    pub covered: Vec<CoveredCommand>,
+    pub missing: Vec<String>,
+}
+
+impl Coverage {
+    /// Return the number of commands that were checked.
+    pub fn len(&self) -> usize {
+        self.covered.len() + self.missing.len()
+    }
+}
+
+/// Parse a line of a command list into a page name. Returns `None` for
+/// empty lines and comments.
+///
+/// Command lists (e.g. from the shell history) name upstream commands, so
+/// the names are lowercase like the upstream pages.
+pub fn parse_command_line(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        None
+    } else {
+        Some(page_name_from_words(&[line.to_string()]).to_lowercase())
+    }
+}
+
+/// Parse a list of commands into page names.
+pub fn parse_command_list(contents: &str) -> Vec<String> {
+    contents.lines().filter_map(parse_command_line).collect()
+}
+
+/// Check which of the `commands` have pages in one of the `languages`.
+///
+/// The commands are rewritten with `rewrite` like for a normal lookup, but
+/// reported with their original name.
+pub fn check_coverage(
+    cache: &Cache,
+    commands: &[String],
+    languages: &[String],
+    rewrite: &RewriteRules,
+) -> Coverage {
+    let mut coverage = Coverage::default();
+    for command in commands {
+        let platforms = cache.page_platforms(&rewrite.apply(command), languages);
+        if platforms.is_empty() {
+            coverage.missing.push(command.clone());
+        } else {
+            coverage.covered.push(CoveredCommand {
+                command: command.clone(),
+                platforms,
+            });
+        }
+    }
+    coverage
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_command_list;
+
+    #[test]
+    fn test_parse_command_list() {
+        let list = "# Build tools\ncargo\n\n  git checkout \nDocker\n";
+        assert_eq!(
+            parse_command_list(list),
+            vec!["cargo", "git-checkout", "docker"]
+        );
+    }
+}
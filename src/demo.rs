@@ -0,0 +1,275 @@
+//! Recording pages as terminal demos.
+//!
+//! `tldr --demo <command>` steps through the examples of a page: The
+//! description of every example is shown as a comment, then its command is
+//! typed at a prompt, one character at a time. The commands are never run.
+//!
+//! The demo is either an [asciinema](https://asciinema.org) cast (version 2),
+//! which can be played with `asciinema play` or embedded into slides, or a
+//! shell script that replays the typing in a terminal. The timing comes from
+//! the `[demo]` section of the config.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use serde_derive::Deserialize;
+use serde_json::json;
+
+use crate::config::DemoConfig;
+use crate::fill::{next_placeholder, Fill};
+use crate::parser::Page;
+use crate::quoting::{quote, Shell};
+
+/// The width of the terminal a cast is recorded for, unless a line of the
+/// demo is longer.
+const CAST_WIDTH: usize = 80;
+const CAST_HEIGHT: usize = 24;
+
+/// The prompt that commands are typed at.
+const PROMPT: &str = "$ ";
+
+/// The format of a demo.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DemoFormat {
+    Cast,
+    Script,
+}
+
+/// A step of a demo.
+#[derive(Debug, PartialEq)]
+enum Step {
+    /// Text that appears at once.
+    Print(String),
+    /// Text that is typed.
+    Type(String),
+    /// A pause, e.g. to read an example.
+    Pause,
+}
+
+/// Return the command of an example with the values in `fill` and the bare
+/// placeholders, e.g. `path/to/file` for `{{path/to/file}}`.
+fn plain_command(code: &str, fill: &Fill) -> String {
+    let filled = fill.apply(code);
+    let mut command = String::with_capacity(filled.len());
+    let mut rest = filled.as_str();
+    while let Some((start, end)) = next_placeholder(rest) {
+        command.push_str(&rest[..start]);
+        command.push_str(&rest[start + 2..end - 2]);
+        rest = &rest[end..];
+    }
+    command.push_str(rest);
+    command
+}
+
+/// Return the steps of the demo of `page`.
+fn steps(page: &Page, fill: &Fill) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let mut header = String::new();
+    for line in &page.description {
+        let _ = writeln!(header, "# {}", line);
+    }
+    if let Some(ref url) = page.more_information {
+        let _ = writeln!(header, "# More information: {}", url);
+    }
+    if !header.is_empty() {
+        steps.push(Step::Print(header));
+        steps.push(Step::Pause);
+    }
+
+    let mut sections = page.sections.iter().peekable();
+    for (i, example) in page.examples.iter().enumerate() {
+        let mut intro = String::from("\n");
+        while let Some(section) = sections.peek() {
+            if section.start > i {
+                break;
+            }
+            let _ = writeln!(intro, "## {}\n", section.name);
+            sections.next();
+        }
+        if !example.description.is_empty() {
+            let _ = writeln!(intro, "# {}", example.description);
+        }
+        intro.push_str(PROMPT);
+        steps.push(Step::Print(intro));
+        steps.push(Step::Type(plain_command(&example.code, fill)));
+        steps.push(Step::Print(String::from("\n")));
+        steps.push(Step::Pause);
+    }
+    steps
+}
+
+/// Format a duration as seconds, e.g. `1.250`.
+fn seconds(duration: Duration) -> String {
+    format!("{}.{:03}", duration.as_secs(), duration.subsec_millis())
+}
+
+/// Return the demo of `page` as an asciinema cast.
+fn to_cast(page: &Page, title: &str, fill: &Fill, timing: &DemoConfig) -> String {
+    let steps = steps(page, fill);
+    let width = steps
+        .iter()
+        .flat_map(|step| match step {
+            Step::Print(text) | Step::Type(text) => text.lines().collect(),
+            Step::Pause => vec![],
+        })
+        .map(|line| line.chars().count() + PROMPT.len())
+        .max()
+        .unwrap_or(0)
+        .max(CAST_WIDTH);
+    let header = json!({
+        "version": 2,
+        "width": width,
+        "height": CAST_HEIGHT,
+        "title": title,
+    });
+
+    let mut cast = format!("{}\n", header);
+    let mut event = |time: Duration, text: &str| {
+        let _ = writeln!(
+            cast,
+            "[{}, \"o\", {}]",
+            seconds(time),
+            json!(text.replace('\n', "\r\n"))
+        );
+    };
+    let mut time = Duration::from_secs(0);
+    for step in &steps {
+        match step {
+            Step::Print(text) => event(time, text),
+            Step::Type(text) => {
+                for c in text.chars() {
+                    time += timing.typing_delay;
+                    event(time, &c.to_string());
+                }
+            }
+            Step::Pause => time += timing.pause,
+        }
+    }
+    cast
+}
+
+/// Return the demo of `page` as a POSIX shell script.
+fn to_script(page: &Page, title: &str, fill: &Fill, timing: &DemoConfig) -> String {
+    let typing_delay = seconds(timing.typing_delay);
+    let mut script = format!(
+        "#!/bin/sh\n\
+         # Demo of {}, generated by tealdeer. The commands are only typed, never run.\n\
+         \n\
+         type_slowly() {{\n    \
+             text=$1\n    \
+             while [ -n \"$text\" ]; do\n        \
+                 rest=${{text#?}}\n        \
+                 printf '%s' \"${{text%\"$rest\"}}\"\n        \
+                 text=$rest\n        \
+                 sleep {}\n    \
+             done\n\
+         }}\n\
+         \n",
+        title, typing_delay
+    );
+    for step in steps(page, fill) {
+        let _ = match step {
+            Step::Print(text) => writeln!(script, "printf '%s' {}", quote(&text, Shell::Bash)),
+            Step::Type(text) => writeln!(script, "type_slowly {}", quote(&text, Shell::Bash)),
+            Step::Pause => writeln!(script, "sleep {}", seconds(timing.pause)),
+        };
+    }
+    script
+}
+
+/// Return the demo of `page` in `format`, titled `title`.
+pub fn record(
+    page: &Page,
+    title: &str,
+    fill: &Fill,
+    timing: &DemoConfig,
+    format: DemoFormat,
+) -> String {
+    match format {
+        DemoFormat::Cast => to_cast(page, title, fill, timing),
+        DemoFormat::Script => to_script(page, title, fill, timing),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{steps, to_cast, to_script, Step};
+
+    use std::time::Duration;
+
+    use crate::config::DemoConfig;
+    use crate::fill::Fill;
+    use crate::parser::{Example, Page, Section};
+    use crate::quoting::Shell;
+
+    fn page() -> Page {
+        Page {
+            title: "tar".into(),
+            description: vec!["Archiving utility.".into()],
+            more_information: None,
+            examples: vec![Example {
+                description: "Extract an archive:".into(),
+                code: "tar xf {{source.tar}}".into(),
+            }],
+            sections: vec![Section {
+                name: "Basics".into(),
+                start: 0,
+            }],
+        }
+    }
+
+    fn timing() -> DemoConfig {
+        DemoConfig {
+            typing_delay: Duration::from_millis(50),
+            pause: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn test_steps() {
+        let mut fill = Fill::new(Shell::Bash);
+        assert_eq!(
+            steps(&page(), &fill),
+            vec![
+                Step::Print("# Archiving utility.\n".into()),
+                Step::Pause,
+                Step::Print("\n## Basics\n\n# Extract an archive:\n$ ".into()),
+                Step::Type("tar xf source.tar".into()),
+                Step::Print("\n".into()),
+                Step::Pause,
+            ]
+        );
+
+        fill.set("source.tar", "my archive.tar");
+        assert_eq!(
+            steps(&page(), &fill)[3],
+            Step::Type("tar xf 'my archive.tar'".into())
+        );
+    }
+
+    #[test]
+    fn test_cast() {
+        let cast = to_cast(&page(), "tldr tar", &Fill::new(Shell::Bash), &timing());
+        let mut lines = cast.lines();
+        assert_eq!(
+            lines.next(),
+            Some(r#"{"height":24,"title":"tldr tar","version":2,"width":80}"#)
+        );
+        assert_eq!(
+            lines.next(),
+            Some(r##"[0.000, "o", "# Archiving utility.\r\n"]"##)
+        );
+        assert_eq!(lines.nth(1), Some(r#"[1.050, "o", "t"]"#));
+        assert_eq!(lines.last(), Some(r#"[1.850, "o", "\r\n"]"#));
+    }
+
+    #[test]
+    fn test_script() {
+        let script = to_script(&page(), "tldr tar", &Fill::new(Shell::Bash), &timing());
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("        sleep 0.050\n"));
+        assert!(script.contains("type_slowly 'tar xf source.tar'\n"));
+        assert!(script.ends_with("printf '%s' '\n'\nsleep 1.000\n"));
+    }
+}
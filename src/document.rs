@@ -0,0 +1,191 @@
+//! Pages as structured documents.
+//!
+//! `tldr --output json <command>` prints the page as a document with its
+//! name, description and examples, so that editors, launchers and scripts
+//! don't have to parse the rendered page. Examples of long pages with
+//! `## section` headings name their section. Placeholders with alternatives or
+//! ranges are listed as choices, so that they can be enumerated.
+
+use serde_derive::Serialize;
+
+use crate::fill::placeholders;
+use crate::parser::{Page, Placeholder};
+
+/// A placeholder with a fixed set of values.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Choice {
+    /// One of `values`, e.g. `{{start|stop}}`.
+    Alternatives {
+        placeholder: String,
+        values: Vec<String>,
+    },
+    /// A value from `start` to `end`, e.g. `{{1..10}}`.
+    Range {
+        placeholder: String,
+        start: String,
+        end: String,
+    },
+}
+
+impl Choice {
+    /// Return the choice of the placeholder `placeholder`, if it has one.
+    fn from_placeholder(placeholder: &str) -> Option<Self> {
+        match Placeholder::parse(placeholder) {
+            Placeholder::Alternatives(values) => Some(Choice::Alternatives {
+                placeholder: placeholder.to_string(),
+                values: values.into_iter().map(str::to_string).collect(),
+            }),
+            Placeholder::Range(start, end) => Some(Choice::Range {
+                placeholder: placeholder.to_string(),
+                start: start.to_string(),
+                end: end.to_string(),
+            }),
+            Placeholder::Value(_) => None,
+        }
+    }
+}
+
+/// An example of a page.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Example {
+    pub description: String,
+    pub command: String,
+    /// The `## section` heading that the example is in. Left out for pages
+    /// without sections.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    /// The distinct placeholders of the command, in order of appearance.
+    pub placeholders: Vec<String>,
+    /// The placeholders with alternatives or ranges.
+    pub choices: Vec<Choice>,
+}
+
+/// A parsed page.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Document {
+    pub name: String,
+    /// The language of the page, or `None` for pages outside of the cache.
+    pub language: Option<String>,
+    /// The lines of the description, joined with spaces.
+    pub description: String,
+    /// The link to more information about the command.
+    pub more_information: Option<String>,
+    pub examples: Vec<Example>,
+}
+
+impl Document {
+    /// Create a document from a parsed page. The name is taken from the title
+    /// of the page, or `name` if the page has no title.
+    pub fn from_page(page: Page, name: &str, language: Option<String>) -> Self {
+        let sections: Vec<Option<String>> = (0..page.examples.len())
+            .map(|i| page.section_of(i).map(str::to_string))
+            .collect();
+        Self {
+            name: if page.title.is_empty() {
+                name.to_string()
+            } else {
+                page.title
+            },
+            language,
+            description: page.description.join(" "),
+            more_information: page.more_information,
+            examples: page
+                .examples
+                .into_iter()
+                .zip(sections)
+                .filter(|(example, _)| !example.code.is_empty())
+                .map(|(example, section)| {
+                    let placeholders = placeholders(&example.code);
+                    Example {
+                        description: example.description,
+                        section,
+                        choices: placeholders
+                            .iter()
+                            .filter_map(|placeholder| Choice::from_placeholder(placeholder))
+                            .collect(),
+                        placeholders: placeholders.into_iter().map(str::to_string).collect(),
+                        command: example.code,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Choice, Document, Example};
+    use crate::parser::Page;
+
+    #[test]
+    fn test_read_document() {
+        let page = "# tar\n\n> Archiving utility.\n> Often combined with gzip.\n\
+                    > More information: <https://www.gnu.org/software/tar>.\n\n\
+                    - Create an archive:\n\n`tar cf {{target.tar}} {{file}}`\n\n\
+                    - Extract an archive:\n\n`tar xf {{source.tar}}`\n";
+        assert_eq!(
+            Document::from_page(Page::read(page.as_bytes()), "tar", Some("en".into())),
+            Document {
+                name: "tar".into(),
+                language: Some("en".into()),
+                description: "Archiving utility. Often combined with gzip.".into(),
+                more_information: Some("https://www.gnu.org/software/tar".into()),
+                examples: vec![
+                    Example {
+                        description: "Create an archive:".into(),
+                        command: "tar cf {{target.tar}} {{file}}".into(),
+                        placeholders: vec!["target.tar".into(), "file".into()],
+                        section: None,
+                        choices: vec![],
+                    },
+                    Example {
+                        description: "Extract an archive:".into(),
+                        command: "tar xf {{source.tar}}".into(),
+                        placeholders: vec!["source.tar".into()],
+                        section: None,
+                        choices: vec![],
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_sections() {
+        let page = "# deploy\n\n- Show the status:\n\n`deploy status`\n\n\
+                    ## Backups\n\n- Only a description\n\n- Create a backup:\n\n`deploy backup`\n";
+        let document = Document::from_page(Page::read(page.as_bytes()), "deploy", None);
+        let sections: Vec<_> = document
+            .examples
+            .iter()
+            .map(|example| example.section.as_deref())
+            .collect();
+        assert_eq!(sections, vec![None, Some("Backups")]);
+    }
+
+    #[test]
+    fn test_choices() {
+        let page = "# systemctl\n\n- Control a unit:\n\n\
+                    `systemctl {{start|stop}} {{unit}} --lines {{1..100}}`\n";
+        let document = Document::from_page(Page::read(page.as_bytes()), "systemctl", None);
+        assert_eq!(
+            document.examples[0].choices,
+            vec![
+                Choice::Alternatives {
+                    placeholder: "start|stop".into(),
+                    values: vec!["start".into(), "stop".into()],
+                },
+                Choice::Range {
+                    placeholder: "1..100".into(),
+                    start: "1".into(),
+                    end: "100".into(),
+                },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&document.examples[0].choices[1]).unwrap(),
+            serde_json::json!({"kind": "range", "placeholder": "1..100", "start": "1", "end": "100"})
+        );
+    }
+}
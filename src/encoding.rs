@@ -0,0 +1,159 @@
+//! Printing pages on terminals that don't use UTF-8.
+//!
+//! Pages are UTF-8, but some terminals use a different encoding (e.g. with
+//! the `C` locale on minimal BSD or Solaris installations), where non-ASCII
+//! characters turn into mojibake. On such terminals, rendered pages are
+//! transliterated to ASCII: Diacritics are removed (`é` becomes `e`), common
+//! punctuation is replaced (`“` becomes `"`) and all other characters are
+//! escaped (`<U+65E5>`).
+//!
+//! The encoding is detected from the locale environment variables (`LC_ALL`,
+//! `LC_CTYPE` and `LANG`). It can be set with `output_encoding` in the
+//! `[display]` section of the config.
+
+use std::borrow::Cow;
+use std::env;
+use std::fmt::Write;
+
+use serde_derive::{Deserialize, Serialize};
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+
+/// The encoding that pages are printed in.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputEncoding {
+    /// Detect the encoding from the locale.
+    Auto,
+    /// Print pages as they are.
+    #[serde(rename = "utf-8")]
+    Utf8,
+    /// Transliterate pages to ASCII.
+    Ascii,
+}
+
+impl Default for OutputEncoding {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+impl OutputEncoding {
+    /// Resolve `Auto` to the encoding of the terminal.
+    ///
+    /// If no locale is set or the platform has no locale environment
+    /// variables (Windows), UTF-8 is assumed.
+    pub fn resolve(self) -> Self {
+        match self {
+            Self::Auto if cfg!(unix) && locale_codeset_is_utf8() == Some(false) => Self::Ascii,
+            Self::Auto => Self::Utf8,
+            encoding => encoding,
+        }
+    }
+}
+
+/// Return whether the locale of the environment uses UTF-8, or `None` if no
+/// locale is set.
+fn locale_codeset_is_utf8() -> Option<bool> {
+    // The first variable that is set determines the character set
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())?;
+    Some(is_utf8_locale(&locale))
+}
+
+/// Return whether `locale` (e.g. `de_DE.UTF-8@euro`) uses UTF-8.
+///
+/// Locales without a codeset (e.g. `C` or `en_US`) use a legacy encoding.
+fn is_utf8_locale(locale: &str) -> bool {
+    let codeset = match locale.find('.') {
+        Some(dot) => &locale[dot + 1..],
+        None => return false,
+    };
+    let codeset = codeset.split('@').next().unwrap_or(codeset);
+    codeset.replace('-', "").eq_ignore_ascii_case("utf8")
+}
+
+/// Return an ASCII replacement for a punctuation character or a letter that
+/// does not decompose into a base letter.
+fn replacement(c: char) -> Option<&'static str> {
+    Some(match c {
+        '\u{a0}' | '\u{2002}'..='\u{200a}' | '\u{202f}' => " ",
+        '‘' | '’' | '‚' | '′' => "'",
+        '“' | '”' | '„' | '″' | '«' | '»' => "\"",
+        '‐' | '‑' | '‒' | '–' | '−' => "-",
+        '—' | '―' => "--",
+        '…' => "...",
+        '•' | '·' => "*",
+        '→' => "->",
+        '←' => "<-",
+        '×' => "x",
+        '©' => "(c)",
+        'ß' => "ss",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'ø' => "o",
+        'Ø' => "O",
+        'đ' => "d",
+        'Đ' => "D",
+        'ł' => "l",
+        'Ł' => "L",
+        'œ' => "oe",
+        'Œ' => "OE",
+        _ => return None,
+    })
+}
+
+/// Transliterate `text` to ASCII. Returns `text` unchanged if it only
+/// contains ASCII characters.
+pub fn to_ascii(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let mut ascii = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii() {
+            ascii.push(c);
+        } else if let Some(replacement) = replacement(c) {
+            ascii.push_str(replacement);
+        } else {
+            let mut base = String::new();
+            decompose_canonical(c, |c| {
+                if !is_combining_mark(c) {
+                    base.push(c);
+                }
+            });
+            if !base.is_empty() && base.is_ascii() {
+                ascii.push_str(&base);
+            } else {
+                let _ = write!(ascii, "<U+{:04X}>", u32::from(c));
+            }
+        }
+    }
+    Cow::Owned(ascii)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_utf8_locale, to_ascii};
+
+    #[test]
+    fn test_is_utf8_locale() {
+        assert!(is_utf8_locale("en_US.UTF-8"));
+        assert!(is_utf8_locale("de_DE.utf8@euro"));
+        assert!(is_utf8_locale("C.UTF-8"));
+        assert!(!is_utf8_locale("C"));
+        assert!(!is_utf8_locale("POSIX"));
+        assert!(!is_utf8_locale("en_US"));
+        assert!(!is_utf8_locale("ja_JP.eucJP"));
+        assert!(!is_utf8_locale("fr_FR.ISO8859-15@euro"));
+    }
+
+    #[test]
+    fn test_to_ascii() {
+        assert_eq!(to_ascii("tar -xf file.tar"), "tar -xf file.tar");
+        assert_eq!(to_ascii("Comprimé – “Größe”…"), "Comprime - \"Grosse\"...");
+        assert_eq!(to_ascii("Łódź"), "Lodz");
+        assert_eq!(to_ascii("日本"), "<U+65E5><U+672C>");
+    }
+}
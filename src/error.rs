@@ -1,5 +1,10 @@
-use reqwest::Error as ReqwestError;
 use std::fmt;
+use std::io;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use reqwest::Error as ReqwestError;
+use serde_derive::Serialize;
 
 #[derive(Debug)]
 #[allow(clippy::pub_enum_variant_names)]
@@ -7,6 +12,54 @@ pub enum TealdeerError {
     CacheError(String),
     ConfigError(String),
     UpdateError(String),
+    /// An error with a more specific hint than the one of its kind, see
+    /// `with_hint`.
+    Hinted(Box<TealdeerError>, String),
+}
+
+impl TealdeerError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::CacheError(_) => ErrorKind::Cache,
+            Self::ConfigError(_) => ErrorKind::Config,
+            Self::UpdateError(_) => ErrorKind::Update,
+            Self::Hinted(error, _) => error.kind(),
+        }
+    }
+
+    /// The message of the error, without the kind.
+    pub fn message(&self) -> &str {
+        match self {
+            Self::CacheError(msg) | Self::ConfigError(msg) | Self::UpdateError(msg) => msg,
+            Self::Hinted(error, _) => error.message(),
+        }
+    }
+
+    /// Attach a hint on what the user can do about the error, instead of the
+    /// generic hint of its kind.
+    pub fn with_hint<S: Into<String>>(self, hint: S) -> Self {
+        match self {
+            Self::Hinted(error, _) => Self::Hinted(error, hint.into()),
+            error => Self::Hinted(Box::new(error), hint.into()),
+        }
+    }
+
+    /// Attach the hint for `error` if there is one for its cause, see
+    /// `io_hint`.
+    pub fn with_io_hint(self, error: &io::Error) -> Self {
+        match io_hint(error) {
+            Some(hint) => self.with_hint(hint),
+            None => self,
+        }
+    }
+
+    /// What the user can do about the error.
+    pub fn hint(&self) -> &str {
+        match self {
+            Self::Hinted(_, hint) => hint,
+            error => error.kind().hint(),
+        }
+    }
 }
 
 impl From<ReqwestError> for TealdeerError {
@@ -21,6 +74,158 @@ impl fmt::Display for TealdeerError {
             Self::CacheError(e) => write!(f, "CacheError: {}", e),
             Self::ConfigError(e) => write!(f, "ConfigError: {}", e),
             Self::UpdateError(e) => write!(f, "UpdateError: {}", e),
+            Self::Hinted(e, _) => e.fmt(f),
+        }
+    }
+}
+
+/// The kind of an error, as reported with `--output json`.
+///
+/// The names are part of the documented output format, don't rename them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorKind {
+    /// Invalid arguments.
+    Usage,
+    Config,
+    Cache,
+    Update,
+    /// A file given on the command line could not be read.
+    Io,
+    PageNotFound,
+    /// A search or lookup did not find anything.
+    NoResults,
+    /// A page could not be rendered or printed.
+    Render,
+    /// A page is irregular (with `--strict`).
+    InvalidPage,
+    /// An example could not be run.
+    Run,
+}
+
+impl ErrorKind {
+    /// The next step for errors of this kind, if there is no more specific
+    /// hint.
+    pub fn hint(self) -> &'static str {
+        match self {
+            Self::Usage => "See `tldr --help`.",
+            Self::Config => "Run `tldr --show-paths` to find the config file and fix it.",
+            Self::Cache => "Run `tldr --update` to rebuild the cache.",
+            Self::Update => "Check your network connection and run `tldr --update` again.",
+            Self::Io => "Check that the file exists and is readable with `ls -l <file>`.",
+            Self::PageNotFound => "Try updating with `tldr --update`.",
+            Self::NoResults => "Run `tldr --list` to list all pages.",
+            Self::Render => "Run `tldr --markdown <command>` to show the raw page.",
+            Self::InvalidPage => "Render the page without `--strict` to show it anyway.",
+            Self::Run => "Run `tldr <command>` to show the page and copy the example instead.",
         }
     }
 }
+
+/// Return a hint for errors caused by `error` that is more specific than the
+/// one of the error kind.
+pub fn io_hint(error: &io::Error) -> Option<&'static str> {
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => Some(
+            "Check the permissions of the directories listed by `tldr --show-paths`, \
+             or choose others with TEALDEER_CACHE_DIR and TEALDEER_CONFIG_DIR.",
+        ),
+        _ => None,
+    }
+}
+
+/// Whether errors are reported as JSON, see `enable_json_errors`.
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Report all following errors as JSON objects instead of text.
+pub fn enable_json_errors() {
+    JSON_ERRORS.store(true, Ordering::Relaxed);
+}
+
+/// An error as reported with `--output json`.
+#[derive(Serialize)]
+struct ErrorReport<'a> {
+    kind: ErrorKind,
+    message: &'a str,
+    /// What the user can do about the error.
+    hint: &'a str,
+}
+
+/// Format an error for stderr, as JSON or as text.
+fn format_error(kind: ErrorKind, message: &str, hint: &str, json: bool) -> String {
+    if json {
+        let report = ErrorReport {
+            kind,
+            message,
+            hint,
+        };
+        serde_json::json!({ "error": report }).to_string()
+    } else if message.contains('\n') {
+        // Keep the hint apart from a list of problems
+        format!("{}\n{}", message, hint)
+    } else {
+        format!("{} {}", message, hint)
+    }
+}
+
+/// Print an error to stderr and exit with status 1.
+///
+/// Every error comes with a hint on what to do about it: `hint` if given,
+/// otherwise the hint of `kind`. With `--output json`, the error is printed
+/// as a single line `{"error": {"kind": ..., "message": ..., "hint": ...}}`,
+/// which is always the last line written to stderr.
+pub fn exit_with_error(kind: ErrorKind, message: &str, hint: Option<&str>) -> ! {
+    let json = JSON_ERRORS.load(Ordering::Relaxed);
+    let hint = hint.unwrap_or_else(|| kind.hint());
+    eprintln!("{}", format_error(kind, message, hint, json));
+    process::exit(1);
+}
+
+/// Report a `TealdeerError` with a prefix describing what failed and exit.
+pub fn exit_with(prefix: &str, error: &TealdeerError) -> ! {
+    let message = if prefix.is_empty() {
+        error.message().to_string()
+    } else {
+        format!("{}: {}", prefix, error.message())
+    };
+    exit_with_error(error.kind(), &message, Some(error.hint()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::{format_error, ErrorKind, TealdeerError};
+
+    #[test]
+    fn test_format_error() {
+        let hint = "Please run `tldr --update`.";
+        assert_eq!(
+            format_error(ErrorKind::Cache, "Cache not found.", hint, false),
+            "Cache not found. Please run `tldr --update`."
+        );
+        assert_eq!(
+            format_error(ErrorKind::NoResults, "No pages found.", hint, true),
+            r#"{"error":{"hint":"Please run `tldr --update`.","kind":"no-results","message":"No pages found."}}"#
+        );
+    }
+
+    #[test]
+    fn test_hints() {
+        let error = TealdeerError::CacheError("Cache not found.".into());
+        assert_eq!(error.hint(), ErrorKind::Cache.hint());
+
+        let error = error.with_hint("Please run `tldr --update`.");
+        assert_eq!(error.kind(), ErrorKind::Cache);
+        assert_eq!(error.message(), "Cache not found.");
+        assert_eq!(error.hint(), "Please run `tldr --update`.");
+
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        let error = TealdeerError::UpdateError("Could not create cache directory".into())
+            .with_io_hint(&denied);
+        assert!(error.hint().contains("permissions"), "{}", error.hint());
+        let missing = io::Error::from(io::ErrorKind::NotFound);
+        let error = TealdeerError::UpdateError("x".into()).with_io_hint(&missing);
+        assert_eq!(error.hint(), ErrorKind::Update.hint());
+    }
+}
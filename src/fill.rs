@@ -0,0 +1,150 @@
+//! Substitution of placeholder values into example commands.
+
+use std::borrow::Cow;
+
+use crate::quoting::{quote, Shell};
+
+/// Values for the placeholders of a page, e.g. `path/to/file` in
+/// `{{path/to/file}}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fill {
+    values: Vec<(String, String)>,
+    shell: Shell,
+}
+
+impl Fill {
+    /// Create a fill without any values.
+    pub fn new(shell: Shell) -> Self {
+        Self {
+            values: vec![],
+            shell,
+        }
+    }
+
+    /// Parse assignments of the form `placeholder=value`.
+    pub fn parse(assignments: &[String], shell: Shell) -> Result<Self, String> {
+        let mut fill = Self::new(shell);
+        for assignment in assignments {
+            let separator = assignment.find('=').ok_or_else(|| {
+                format!(
+                    "Invalid placeholder value {:?}, expected `placeholder=value`",
+                    assignment
+                )
+            })?;
+            fill.set(&assignment[..separator], &assignment[separator + 1..]);
+        }
+        Ok(fill)
+    }
+
+    /// Set the value of a placeholder, replacing any previous value.
+    pub fn set(&mut self, placeholder: &str, value: &str) {
+        let placeholder = placeholder.trim();
+        self.values.retain(|(p, _)| p != placeholder);
+        self.values
+            .push((placeholder.to_string(), value.to_string()));
+    }
+
+    /// Return the shell that values are quoted for.
+    pub fn shell(&self) -> Shell {
+        self.shell
+    }
+
+    /// Return whether there is a value for `placeholder`.
+    pub fn contains(&self, placeholder: &str) -> bool {
+        let placeholder = placeholder.trim();
+        self.values.iter().any(|(p, _)| p == placeholder)
+    }
+
+    /// Replace all placeholders in the example command `code` that have a
+    /// value.
+    pub fn apply(&self, code: &str) -> String {
+        let mut filled = String::with_capacity(code.len());
+        let mut rest = code;
+        while let Some((start, end)) = next_placeholder(rest) {
+            filled.push_str(&rest[..start]);
+            let placeholder = &rest[start + 2..end - 2];
+            match self.value(placeholder) {
+                Some(value) => filled.push_str(&value),
+                None => filled.push_str(&rest[start..end]),
+            }
+            rest = &rest[end..];
+        }
+        filled.push_str(rest);
+        filled
+    }
+
+    /// Return the value of `placeholder`, quoted for the shell.
+    pub fn value(&self, placeholder: &str) -> Option<Cow<'_, str>> {
+        let placeholder = placeholder.trim();
+        self.values
+            .iter()
+            .find(|(p, _)| p == placeholder)
+            .map(|(_, value)| quote(value, self.shell))
+    }
+}
+
+/// Return the start and end of the next `{{placeholder}}` in `code`,
+/// including the braces.
+pub fn next_placeholder(code: &str) -> Option<(usize, usize)> {
+    let start = code.find("{{")?;
+    let end = start + 2 + code[start + 2..].find("}}")? + 2;
+    Some((start, end))
+}
+
+/// Return the distinct placeholders of the example command `code`, in order
+/// of appearance.
+pub fn placeholders(code: &str) -> Vec<&str> {
+    let mut placeholders: Vec<&str> = Vec::new();
+    let mut rest = code;
+    while let Some((start, end)) = next_placeholder(rest) {
+        let placeholder = rest[start + 2..end - 2].trim();
+        if !placeholders.contains(&placeholder) {
+            placeholders.push(placeholder);
+        }
+        rest = &rest[end..];
+    }
+    placeholders
+}
+
+#[cfg(test)]
+mod test {
+    use super::{placeholders, Fill};
+    use crate::quoting::Shell;
+
+    #[test]
+    fn test_parse() {
+        let assignments = vec!["path/to/file=my file.txt".into(), "n=a=b".into()];
+        let fill = Fill::parse(&assignments, Shell::Bash).unwrap();
+        assert_eq!(fill.value("path/to/file").unwrap(), "'my file.txt'");
+        assert_eq!(fill.value(" n ").unwrap(), "'a=b'");
+        assert_eq!(fill.value("other"), None);
+
+        assert!(Fill::parse(&["novalue".into()], Shell::Bash).is_err());
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(
+            placeholders("cp {{source}} {{target}} {{source}}"),
+            vec!["source", "target"]
+        );
+        assert!(placeholders("ls -la").is_empty());
+        assert!(placeholders("echo {{unterminated").is_empty());
+    }
+
+    #[test]
+    fn test_apply() {
+        let fill = Fill::parse(&["source=a b".into()], Shell::Bash).unwrap();
+        assert_eq!(
+            fill.apply("cp {{source}} {{target}}"),
+            "cp 'a b' {{target}}"
+        );
+    }
+
+    #[test]
+    fn test_last_value_wins() {
+        let assignments = vec!["x=1".into(), "x=2".into()];
+        let fill = Fill::parse(&assignments, Shell::Fish).unwrap();
+        assert_eq!(fill.value("x").unwrap(), "2");
+    }
+}
@@ -0,0 +1,81 @@
+//! Post-processing rendered pages with an external command.
+//!
+//! If `render_filter_command` is set in the `[display]` section, every
+//! rendered page is piped through this command (run by the system shell) and
+//! its output is printed instead. The filter may be used to redact
+//! information or to recolor pages. If it can not be run or fails, nothing is
+//! printed, since printing the unfiltered page could defeat its purpose.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use log::debug;
+
+use crate::signals::SigpipeIgnored;
+
+/// Return the command to run `command` with the system shell.
+fn shell_command(command: &str) -> Command {
+    let (program, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut cmd = Command::new(program);
+    cmd.arg(flag).arg(command);
+    cmd
+}
+
+/// Pipe `input` through the shell command `command` and return its output.
+pub fn apply_filter(command: &str, input: Vec<u8>) -> Result<Vec<u8>, String> {
+    debug!("Piping page through {:?}", command);
+    let mut child = shell_command(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Could not run render filter {:?}: {}", command, e))?;
+
+    // A filter is free to stop reading early (e.g. `head`)
+    let _sigpipe_ignored = SigpipeIgnored::new();
+
+    // Write in a separate thread, so that a filter that produces output
+    // before reading all of its input can not deadlock us
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = thread::spawn(move || {
+        let _ = stdin.write_all(&input);
+    });
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Could not run render filter {:?}: {}", command, e))?;
+    let _ = writer.join();
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(format!(
+            "The render filter {:?} failed ({}). Use --no-filter to show the page without it.",
+            command, output.status
+        ))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::apply_filter;
+
+    #[test]
+    fn test_apply_filter() {
+        let filtered = apply_filter("tr a-z A-Z", b"tar\n".to_vec()).unwrap();
+        assert_eq!(filtered, b"TAR\n");
+
+        // Large inputs don't deadlock
+        let input = vec![b'x'; 1 << 20];
+        assert_eq!(apply_filter("cat", input.clone()).unwrap(), input);
+    }
+
+    #[test]
+    fn test_failing_filter() {
+        let error = apply_filter("exit 3", b"tar\n".to_vec()).unwrap_err();
+        assert!(error.contains("--no-filter"), "{}", error);
+    }
+}
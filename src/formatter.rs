@@ -1,13 +1,12 @@
-//! Functions related to formatting and printing lines from a `Tokenizer`.
+//! Functions related to formatting and printing a parsed `Page`.
 
-use std::io::BufRead;
+use std::io::{self, Write};
 
-use ansi_term::{ANSIString, ANSIStrings};
-use log::debug;
+use ansi_term::{ANSIString, ANSIStrings, Style};
 
 use crate::config::Config;
-use crate::tokenizer::Tokenizer;
-use crate::types::LineType;
+use crate::fill::Fill;
+use crate::parser::{Page, Placeholder};
 
 fn highlight_command<'a>(
     command: &'a str,
@@ -23,9 +22,9 @@ fn highlight_command<'a>(
             // Only highlight command names at the start of the line ...
             parts.push(config.style.command_name.paint(command));
         } else {
-            let char_before_command = example_code
+            let char_before_command = example_code[..code_part_end_pos + command_start]
                 .chars()
-                .nth(code_part_end_pos + command_start - 1);
+                .next_back();
             if char_before_command.filter(|c| c.is_whitespace()).is_some() {
                 // ... or when preceded by a whitespace character.
                 parts.push(config.style.command_name.paint(command));
@@ -44,8 +43,55 @@ fn highlight_command<'a>(
     );
 }
 
+/// Return `style`, dimmed if the style profile supports it. Unstyled text
+/// (e.g. without colors) stays unstyled.
+fn dimmed(style: Style, config: &Config) -> Style {
+    if style == Style::default() {
+        style
+    } else {
+        config.display.style_profile.resolve(style.dimmed())
+    }
+}
+
+/// Highlight a placeholder: Alternatives are highlighted separately, with
+/// dimmed separators, and ranges are dimmed.
+fn highlight_placeholder<'a>(
+    placeholder: &'a str,
+    config: &Config,
+    parts: &mut Vec<ANSIString<'a>>,
+) {
+    let style = config.style.example_variable;
+    match Placeholder::parse(placeholder) {
+        Placeholder::Alternatives(alternatives) => {
+            for (i, alternative) in alternatives.into_iter().enumerate() {
+                if i > 0 {
+                    parts.push(dimmed(style, config).paint("|"));
+                }
+                parts.push(style.paint(alternative));
+            }
+        }
+        Placeholder::Range(..) => parts.push(dimmed(style, config).paint(placeholder)),
+        Placeholder::Value(_) => parts.push(style.paint(placeholder)),
+    }
+}
+
+/// Put a placeholder between the placeholder markers, so that it can be told
+/// apart without colors. Alternatives are read as `a or b` and ranges as
+/// `1 to 10`.
+fn mark_placeholder(placeholder: &str, config: &Config) -> String {
+    let (ref before, ref after) = config.display.placeholder_markers;
+    let text = match Placeholder::parse(placeholder) {
+        Placeholder::Alternatives(alternatives) => alternatives.join(" or "),
+        Placeholder::Range(start, end) => format!("{} to {}", start, end),
+        Placeholder::Value(value) => value.to_string(),
+    };
+    format!("{}{}{}", before, text, after)
+}
+
 /// Format and highlight code examples including variables in {{ curly braces }}.
-fn format_code(command: &str, text: &str, config: &Config) -> String {
+///
+/// Variables with a value in `fill` are replaced by the value.
+fn format_code(command: &str, text: &str, config: &Config, fill: &Fill) -> String {
     let mut parts = Vec::new();
     for between_variables in text.split("}}") {
         if let Some(variable_start) = between_variables.find("{{") {
@@ -53,7 +99,16 @@ fn format_code(command: &str, text: &str, config: &Config) -> String {
             let example_variable = &between_variables[variable_start + 2..];
 
             highlight_command(&command, &example_code, &config, &mut parts);
-            parts.push(config.style.example_variable.paint(example_variable));
+            match fill.value(example_variable) {
+                Some(value) => parts.push(config.style.placeholder.paint(value)),
+                None if config.display.accessible => parts.push(
+                    config
+                        .style
+                        .example_code
+                        .paint(mark_placeholder(example_variable, config)),
+                ),
+                None => highlight_placeholder(example_variable, config, &mut parts),
+            }
         } else {
             highlight_command(&command, &between_variables, &config, &mut parts);
         }
@@ -62,34 +117,166 @@ fn format_code(command: &str, text: &str, config: &Config) -> String {
     ANSIStrings(&parts).to_string()
 }
 
-/// Print a token stream to an ANSI terminal.
-pub fn print_lines<R>(tokenizer: &mut Tokenizer<R>, config: &Config)
-where
-    R: BufRead,
-{
-    let mut command = String::new();
-    while let Some(token) = tokenizer.next_token() {
-        match token {
-            LineType::Empty => {
-                if !config.display.compact {
-                    println!()
+/// Return the code of an example as plain text, with the values in `fill`
+/// (or the bare placeholders) instead of `{{ placeholders }}`.
+fn plain_code(text: &str, config: &Config, fill: &Fill) -> String {
+    let mut code = String::with_capacity(text.len());
+    for between_variables in text.split("}}") {
+        if let Some(variable_start) = between_variables.find("{{") {
+            code.push_str(&between_variables[..variable_start]);
+            let example_variable = &between_variables[variable_start + 2..];
+            match fill.value(example_variable) {
+                Some(value) => code.push_str(&value),
+                None if config.display.accessible => {
+                    code.push_str(&mark_placeholder(example_variable, config));
                 }
+                None => code.push_str(example_variable),
             }
-            LineType::Title(title) => {
-                debug!("Ignoring title");
+        } else {
+            code.push_str(between_variables);
+        }
+    }
+    code
+}
 
-                // This is safe as long as the parsed title is only the command,
-                // and tokenizer yields values in order of appearance.
-                command = title;
-                debug!("Detected command name: {}", &command);
+/// Wrap `text` in an OSC 8 escape sequence, which makes it a clickable link
+/// to `url` in terminals that support it.
+fn hyperlink(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// Print a page to an ANSI terminal (or any other `out`).
+///
+/// If `hyperlinks` is set, links are written as OSC 8 hyperlinks.
+pub fn print_page<W: Write>(
+    page: &Page,
+    config: &Config,
+    fill: &Fill,
+    hyperlinks: bool,
+    out: &mut W,
+) -> io::Result<()> {
+    let indent = " ".repeat(config.display.indent);
+    let example_indent = " ".repeat(config.display.example_indent);
+    let compact = config.display.compact;
+    if !compact {
+        writeln!(out)?;
+    }
+    for line in &page.description {
+        writeln!(out, "{}{}", indent, config.style.description.paint(line))?;
+    }
+    if let Some(ref url) = page.more_information {
+        let link = if hyperlinks {
+            hyperlink(url, url)
+        } else {
+            url.clone()
+        };
+        writeln!(
+            out,
+            "{}{} {}",
+            indent,
+            config.style.description.paint("More information:"),
+            config.style.url.paint(link)
+        )?;
+    }
+    let mut sections = page.sections.iter().peekable();
+    for (i, example) in page.examples.iter().enumerate() {
+        while let Some(section) = sections.peek() {
+            if section.start > i {
+                break;
+            }
+            print_section(&section.name, &indent, config, out)?;
+            sections.next();
+        }
+        if !compact {
+            writeln!(out)?;
+        }
+        if !example.description.is_empty() {
+            writeln!(
+                out,
+                "{}{}",
+                indent,
+                config.style.example_text.paint(&example.description)
+            )?;
+            if !compact && !example.code.is_empty() {
+                writeln!(out)?;
+            }
+        }
+        if !example.code.is_empty() {
+            writeln!(
+                out,
+                "{}{}",
+                example_indent,
+                // Highlight the command name, which is the title of the page
+                &format_code(&page.title, &example.code, config, fill)
+            )?;
+        }
+    }
+    for section in sections {
+        print_section(&section.name, &indent, config, out)?;
+    }
+    writeln!(out)
+}
+
+/// Print the heading of a section as a separator.
+fn print_section<W: Write>(
+    name: &str,
+    indent: &str,
+    config: &Config,
+    out: &mut W,
+) -> io::Result<()> {
+    if !config.display.compact {
+        writeln!(out)?;
+    }
+    writeln!(
+        out,
+        "{}{}",
+        indent,
+        config.style.section.paint(format!("== {} ==", name))
+    )
+}
+
+/// Print a page for copying commands: Every command is printed on a single
+/// line, without indentation, styles or line breaks, after its description
+/// as a shell comment. Terminals only soft-wrap such lines, so a triple-click
+/// always selects the complete command.
+pub fn print_page_copyable<W: Write>(
+    page: &Page,
+    config: &Config,
+    fill: &Fill,
+    out: &mut W,
+) -> io::Result<()> {
+    let compact = config.display.compact;
+    for line in &page.description {
+        writeln!(out, "# {}", line)?;
+    }
+    if let Some(ref url) = page.more_information {
+        writeln!(out, "# More information: {}", url)?;
+    }
+    let mut sections = page.sections.iter().peekable();
+    for (i, example) in page.examples.iter().enumerate() {
+        while let Some(section) = sections.peek() {
+            if section.start > i {
+                break;
             }
-            LineType::Description(text) => println!("  {}", config.style.description.paint(text)),
-            LineType::ExampleText(text) => println!("  {}", config.style.example_text.paint(text)),
-            LineType::ExampleCode(text) => {
-                println!("      {}", &format_code(&command, &text, &config))
+            if !compact {
+                writeln!(out)?;
             }
-            LineType::Other(text) => debug!("Unknown line type: {:?}", text),
+            writeln!(out, "## {}", section.name)?;
+            sections.next();
+        }
+        if !compact {
+            writeln!(out)?;
+        }
+        if !example.description.is_empty() {
+            writeln!(out, "# {}", example.description)?;
+        }
+        if !example.code.is_empty() {
+            let code = plain_code(&example.code, config, fill);
+            // Pages never break commands, but be sure that a copied line is
+            // the complete command
+            let code: Vec<&str> = code.lines().map(str::trim).collect();
+            writeln!(out, "{}", code.join(" "))?;
         }
     }
-    println!();
+    Ok(())
 }
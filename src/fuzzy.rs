@@ -0,0 +1,101 @@
+//! Suggesting pages for misspelled commands.
+//!
+//! When a page could not be found, the names of all pages are compared with
+//! the command by their edit distance (the number of inserted, removed,
+//! replaced or swapped adjacent characters). Names that are close enough are
+//! suggested, as well as names that the command starts with (`docker` for `docker-compse`) or
+//! that start with the command (`docker-compose` for `dock`).
+
+/// Return the edit distance between `a` and `b`, counting a swap of two
+/// adjacent characters as a single edit (`gti` for `git`).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // distances[i][j] is the distance between the first i characters of `a`
+    // and the first j characters of `b`
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    distances[0] = (0..=b.len()).collect();
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// Return whether `name` is the first part of the hyphenated `command`.
+fn is_prefix_part(name: &str, command: &str) -> bool {
+    command.len() > name.len()
+        && command.starts_with(name)
+        && command[name.len()..].starts_with('-')
+}
+
+/// Return up to `count` names of pages similar to `command`, the most
+/// similar first.
+pub fn suggest<'a>(command: &str, names: &'a [String], count: usize) -> Vec<&'a str> {
+    let max_distance = (command.chars().count() / 3).max(1);
+    let mut candidates: Vec<(usize, &str)> = names
+        .iter()
+        .filter(|name| name.as_str() != command)
+        .filter_map(|name| {
+            let distance = edit_distance(command, name);
+            if distance <= max_distance
+                || name.starts_with(command)
+                || is_prefix_part(name, command)
+            {
+                Some((distance, name.as_str()))
+            } else {
+                None
+            }
+        })
+        .collect();
+    candidates.sort_unstable();
+    candidates
+        .into_iter()
+        .take(count)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{edit_distance, suggest};
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("tar", "tar"), 0);
+        assert_eq!(edit_distance("tar", "tra"), 1);
+        assert_eq!(edit_distance("gti", "git"), 1);
+        assert_eq!(edit_distance("docker-compse", "docker-compose"), 1);
+        assert_eq!(edit_distance("", "ls"), 2);
+        assert_eq!(edit_distance("größe", "grösse"), 2);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let names: Vec<String> = ["docker", "docker-compose", "git", "gitk", "tar", "zip"]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            suggest("docker-compse", &names, 5),
+            vec!["docker-compose", "docker"]
+        );
+        assert_eq!(suggest("dock", &names, 5), vec!["docker", "docker-compose"]);
+        assert_eq!(suggest("gti", &names, 5), vec!["git"]);
+        assert_eq!(suggest("gitt", &names, 5), vec!["git", "gitk"]);
+        assert_eq!(suggest("gitt", &names, 1), vec!["git"]);
+        assert!(suggest("kubectl", &names, 5).is_empty());
+        assert!(suggest("tar", &names, 0).is_empty());
+    }
+}
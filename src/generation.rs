@@ -0,0 +1,281 @@
+//! Generations of the pages directory.
+//!
+//! Every update extracts the pages into a new generation directory
+//! (`GENERATIONS_DIR/<n>`) instead of overwriting the pages in place. Once
+//! the new generation is complete, the pointer `TLDR_PAGES_DIR` is switched
+//! over to it. On Unix, the pointer is a relative symlink that is replaced
+//! atomically by renaming a new symlink over it, so readers that resolve the
+//! pointer when opening the cache always observe a complete generation.
+//!
+//! The previous generation is kept around, so readers that resolved the
+//! pointer right before the switch can still finish reading their page.
+//!
+//! Where the pointer is a directory (caches of older versions, and platforms
+//! without symlinks), it is moved aside before the new pages are moved into
+//! place, and only removed once they are. If the new pages can not be moved
+//! into place, the old directory is moved back, so a failed update never
+//! leaves the cache without pages.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+use crate::error::TealdeerError::{self, UpdateError};
+use crate::layout::TLDR_PAGES_DIR;
+
+/// The directory inside the cache directory that contains all generations.
+pub const GENERATIONS_DIR: &str = "generations";
+
+/// Return the numbers of all existing generations, in ascending order.
+fn generations(cache_dir: &Path) -> Vec<u64> {
+    let mut generations: Vec<u64> = fs::read_dir(cache_dir.join(GENERATIONS_DIR))
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    generations.sort_unstable();
+    generations
+}
+
+/// Return the directories of all existing generations.
+pub fn generation_dirs(cache_dir: &Path) -> Vec<PathBuf> {
+    generations(cache_dir)
+        .into_iter()
+        .map(|generation| cache_dir.join(GENERATIONS_DIR).join(generation.to_string()))
+        .collect()
+}
+
+/// Return the path of a new, not yet existing generation directory.
+pub fn next_generation_dir(cache_dir: &Path) -> Result<PathBuf, TealdeerError> {
+    let generations_dir = cache_dir.join(GENERATIONS_DIR);
+    fs::create_dir_all(&generations_dir).map_err(|e| {
+        UpdateError(format!("Could not create generations directory: {}", e)).with_io_hint(&e)
+    })?;
+    let next = generations(cache_dir).last().map_or(1, |last| last + 1);
+    Ok(generations_dir.join(next.to_string()))
+}
+
+/// Return the pages directory of the active generation.
+///
+/// The pointer is resolved once, so that all paths derived from the result
+/// refer to the same generation, even if an update switches the pointer in
+/// the meantime. Caches without generations (e.g. created by older versions)
+/// are returned as is.
+pub fn resolve_active(cache_dir: &Path) -> PathBuf {
+    let pointer = cache_dir.join(TLDR_PAGES_DIR);
+    match fs::read_link(&pointer) {
+        Ok(target) => cache_dir.join(target),
+        Err(_) => pointer,
+    }
+}
+
+/// Replace the directory `dir` by `new` (a directory or symlink). `dir` is
+/// moved aside until `new` is in place, and moved back if that fails.
+fn replace_dir(new: &Path, dir: &Path) -> io::Result<()> {
+    let mut old_name = dir.file_name().unwrap_or_default().to_os_string();
+    old_name.push(".old");
+    let old = dir.with_file_name(old_name);
+    // Left behind by a crash in the middle of a previous replacement
+    if old.exists() {
+        fs::remove_dir_all(&old)?;
+    }
+
+    fs::rename(dir, &old)?;
+    if let Err(e) = fs::rename(new, dir) {
+        if let Err(e) = fs::rename(&old, dir) {
+            debug!("Could not restore {}: {}", dir.display(), e);
+        }
+        return Err(e);
+    }
+    if let Err(e) = fs::remove_dir_all(&old) {
+        debug!("Could not remove {}: {}", old.display(), e);
+    }
+    Ok(())
+}
+
+/// Point `TLDR_PAGES_DIR` to the generation at `generation_dir`.
+#[cfg(unix)]
+fn switch_pointer(cache_dir: &Path, generation_dir: &Path) -> Result<(), TealdeerError> {
+    use std::os::unix::fs::symlink;
+
+    let pointer = cache_dir.join(TLDR_PAGES_DIR);
+    let target = generation_dir
+        .strip_prefix(cache_dir)
+        .unwrap_or(generation_dir);
+    let new_pointer = cache_dir.join(format!("{}.new", TLDR_PAGES_DIR));
+    let map_err = |e| UpdateError(format!("Could not activate new pages: {}", e));
+
+    if fs::symlink_metadata(&new_pointer).is_ok() {
+        fs::remove_file(&new_pointer).map_err(map_err)?;
+    }
+    symlink(target, &new_pointer).map_err(map_err)?;
+
+    // Pages directories of caches created by older versions are replaced once
+    if pointer.is_dir() && fs::read_link(&pointer).is_err() {
+        replace_dir(&new_pointer, &pointer).map_err(map_err)
+    } else {
+        fs::rename(&new_pointer, &pointer).map_err(map_err)
+    }
+}
+
+/// Point `TLDR_PAGES_DIR` to the generation at `generation_dir`.
+///
+/// Symlinks require special privileges on some platforms, so the generation
+/// is moved into place instead.
+#[cfg(not(unix))]
+fn switch_pointer(cache_dir: &Path, generation_dir: &Path) -> Result<(), TealdeerError> {
+    let pointer = cache_dir.join(TLDR_PAGES_DIR);
+    let map_err = |e| UpdateError(format!("Could not activate new pages: {}", e));
+    if pointer.exists() {
+        replace_dir(generation_dir, &pointer).map_err(map_err)
+    } else {
+        fs::rename(generation_dir, &pointer).map_err(map_err)
+    }
+}
+
+/// Make the generation at `generation_dir` the active one and remove all
+/// generations except for the new and the previous one.
+///
+/// Once the new generation is active, the activation has succeeded, so
+/// stale generations that can't be removed are left for the next one.
+pub fn activate(cache_dir: &Path, generation_dir: &Path) -> Result<(), TealdeerError> {
+    let previous = resolve_active(cache_dir);
+    switch_pointer(cache_dir, generation_dir)?;
+
+    for generation in generations(cache_dir) {
+        let dir = cache_dir.join(GENERATIONS_DIR).join(generation.to_string());
+        if dir != generation_dir && dir != previous {
+            debug!("Removing stale generation {:?}", dir);
+            if let Err(e) = fs::remove_dir_all(&dir) {
+                debug!("Could not remove {}: {}", dir.display(), e);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{activate, generations, next_generation_dir, replace_dir, resolve_active};
+
+    use std::fs;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use tempfile::TempDir;
+
+    /// Create and activate a new generation containing a single page.
+    fn install_generation(cache_dir: &Path, contents: &str) {
+        let dir = next_generation_dir(cache_dir).unwrap();
+        let common = dir.join("pages").join("common");
+        fs::create_dir_all(&common).unwrap();
+        fs::write(common.join("tar.md"), contents).unwrap();
+        activate(cache_dir, &dir).unwrap();
+    }
+
+    fn read_page(cache_dir: &Path) -> std::io::Result<String> {
+        let pages_dir = resolve_active(cache_dir);
+        fs::read_to_string(pages_dir.join("pages").join("common").join("tar.md"))
+    }
+
+    #[test]
+    fn test_activate() {
+        let dir = TempDir::new().unwrap();
+        install_generation(dir.path(), "one");
+        assert_eq!(read_page(dir.path()).unwrap(), "one");
+        install_generation(dir.path(), "two");
+        assert_eq!(read_page(dir.path()).unwrap(), "two");
+        install_generation(dir.path(), "three");
+        assert_eq!(read_page(dir.path()).unwrap(), "three");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_keep_previous_generation() {
+        let dir = TempDir::new().unwrap();
+        for _ in 0..5 {
+            install_generation(dir.path(), "page");
+        }
+        assert_eq!(generations(dir.path()), vec![4, 5]);
+    }
+
+    #[test]
+    fn test_replace_legacy_pages_dir() {
+        let dir = TempDir::new().unwrap();
+        let legacy = dir.path().join(crate::layout::TLDR_PAGES_DIR);
+        fs::create_dir_all(legacy.join("pages").join("common")).unwrap();
+        fs::write(legacy.join("pages").join("common").join("tar.md"), "old").unwrap();
+        install_generation(dir.path(), "new");
+        assert_eq!(read_page(dir.path()).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_replace_dir() {
+        let dir = TempDir::new().unwrap();
+        let pages = dir.path().join("pages");
+        let new = dir.path().join("new");
+        fs::create_dir(&pages).unwrap();
+        fs::write(pages.join("tar.md"), "old").unwrap();
+        // Left behind by an earlier crash
+        fs::create_dir(dir.path().join("pages.old")).unwrap();
+
+        // If the new directory can not be moved into place, the old one is
+        // kept
+        assert!(replace_dir(&new, &pages).is_err());
+        assert_eq!(fs::read_to_string(pages.join("tar.md")).unwrap(), "old");
+
+        fs::create_dir(&new).unwrap();
+        fs::write(new.join("tar.md"), "new").unwrap();
+        replace_dir(&new, &pages).unwrap();
+        assert_eq!(fs::read_to_string(pages.join("tar.md")).unwrap(), "new");
+        assert!(!new.exists());
+        assert!(!dir.path().join("pages.old").exists());
+    }
+
+    /// Readers running concurrently to updates must always see a complete
+    /// generation.
+    #[test]
+    #[cfg(unix)]
+    fn test_concurrent_readers_and_updater() {
+        let dir = Arc::new(TempDir::new().unwrap());
+        install_generation(dir.path(), "initial");
+
+        let done = Arc::new(AtomicBool::new(false));
+        let readers: Vec<_> = (0..2)
+            .map(|_| {
+                let dir = Arc::clone(&dir);
+                let done = Arc::clone(&done);
+                thread::spawn(move || {
+                    let mut reads = 0;
+                    while !done.load(Ordering::SeqCst) {
+                        let contents = read_page(dir.path()).expect("Page must always be readable");
+                        assert!(!contents.is_empty());
+                        reads += 1;
+                    }
+                    reads
+                })
+            })
+            .collect();
+
+        // Unlike real updates, the updater is not slowed down by a download.
+        // Pause between updates, so that readers are not expected to finish
+        // reading while two complete updates happen.
+        for i in 0..20 {
+            install_generation(dir.path(), &format!("generation {}", i));
+            thread::sleep(Duration::from_millis(25));
+        }
+        done.store(true, Ordering::SeqCst);
+
+        for reader in readers {
+            assert!(reader.join().unwrap() > 0);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Translations of the help text.
+//!
+//! `tldr --help` is shown in the first language of the same language chain
+//! that pages are looked up with, if there is a catalog for it. A catalog
+//! (`src/help/<lang>.toml`) translates the headings and the descriptions of
+//! the options of the usage text. Options without a translation, e.g. new
+//! ones, keep their English description, so a catalog never has to be
+//! complete.
+
+use std::collections::HashMap;
+
+use log::warn;
+use serde_derive::Deserialize;
+
+use crate::width::display_width;
+
+/// The catalogs of all translations of the help text.
+const CATALOGS: &[(&str, &str)] = &[("de", include_str!("help/de.toml"))];
+
+/// The column that option descriptions start at.
+const DESCRIPTION_COLUMN: usize = 26;
+
+/// The maximum width of the help text.
+const WIDTH: usize = 80;
+
+/// The translations of the help text into a language.
+#[derive(Debug, Default, Deserialize)]
+struct Catalog {
+    /// Translated headings (e.g. `Options:`), by their English text.
+    #[serde(default)]
+    headings: HashMap<String, String>,
+    /// Translated option descriptions, by the long name of the option.
+    #[serde(default)]
+    options: HashMap<String, String>,
+}
+
+/// Return the catalog for the first language in `languages` that has one.
+/// English ends the search, as the usage text is English.
+fn find_catalog(languages: &[String]) -> Option<Catalog> {
+    for language in languages {
+        if language == "en" {
+            return None;
+        }
+        if let Some(&(_, source)) = CATALOGS.iter().find(|(name, _)| name == language) {
+            return toml::from_str(source)
+                .map_err(|e| warn!("Could not parse help catalog {}: {}", language, e))
+                .ok();
+        }
+    }
+    None
+}
+
+/// Return the long name of the option described by an option line like
+/// `    -l --list             List all commands`, or `None` for other lines.
+fn option_name(line: &str) -> Option<&str> {
+    if !line.starts_with("    -") {
+        return None;
+    }
+    line.split_whitespace().find(|word| word.starts_with("--"))
+}
+
+/// Split `text` into lines of at most `width` columns at spaces.
+fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && display_width(&line) + 1 + display_width(word) > width {
+            lines.push(line);
+            line = String::new();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    lines
+}
+
+/// Translate the usage text `usage` with `catalog`.
+fn translate(usage: &str, catalog: &Catalog) -> String {
+    let indent = " ".repeat(DESCRIPTION_COLUMN);
+    let mut translated = String::with_capacity(usage.len());
+    let mut lines = usage.lines().peekable();
+    while let Some(line) = lines.next() {
+        let description = option_name(line).and_then(|name| catalog.options.get(name));
+        if let Some(description) = description {
+            // The option is followed by its description, after at least two
+            // spaces, or on the next line
+            let spec = line[4..].find("  ").map_or(line, |end| &line[..end + 4]);
+            while lines.peek().map_or(false, |next| {
+                next.starts_with(&indent) && !next.trim().is_empty()
+            }) {
+                lines.next();
+            }
+            let mut description_lines =
+                wrap_words(description, WIDTH - DESCRIPTION_COLUMN).into_iter();
+            translated.push_str(spec);
+            if display_width(spec) + 2 <= DESCRIPTION_COLUMN {
+                translated.push_str(&" ".repeat(DESCRIPTION_COLUMN - display_width(spec)));
+                translated.push_str(&description_lines.next().unwrap_or_default());
+            }
+            translated.push('\n');
+            for description_line in description_lines {
+                translated.push_str(&indent);
+                translated.push_str(&description_line);
+                translated.push('\n');
+            }
+        } else {
+            translated.push_str(catalog.headings.get(line).map_or(line, String::as_str));
+            translated.push('\n');
+        }
+    }
+    translated
+}
+
+/// Return the usage text `usage`, translated into the first language in
+/// `languages` that the help text is available in.
+pub fn localized_usage(usage: &str, languages: &[String]) -> String {
+    match find_catalog(languages) {
+        Some(catalog) => translate(usage, &catalog),
+        None => usage.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{localized_usage, option_name, wrap_words, Catalog, CATALOGS};
+    use crate::USAGE;
+
+    const USAGE_EXCERPT: &str = "Options:\n\
+                                 \n    -l --list             List all commands in the cache\
+                                 \n    --jobs <n>            Number of threads used to index the pages after an\
+                                 \n                          update (default: number of CPUs)\
+                                 \n    --add-pages-repo <git-url>\
+                                 \n                          Clone a git repository of custom pages and use its\
+                                 \n                          pages, it is pulled on every update\
+                                 \n    --stats               Show statistics about the cache\n";
+
+    #[test]
+    fn test_localized_usage() {
+        let languages = vec!["de_DE".to_string(), "de".to_string(), "en".to_string()];
+        assert_eq!(
+            localized_usage(USAGE_EXCERPT, &languages),
+            "Optionen:\n\
+             \n    -l --list             Alle Befehle im Cache auflisten\
+             \n    --jobs <n>            Anzahl der Threads, die nach einer Aktualisierung die\
+             \n                          Seiten indizieren (Standard: Anzahl der CPUs)\
+             \n    --add-pages-repo <git-url>\
+             \n                          Ein Git-Repository mit eigenen Seiten klonen und seine\
+             \n                          Seiten verwenden, es wird bei jeder Aktualisierung\
+             \n                          abgeglichen\
+             \n    --stats               Statistiken über den Cache anzeigen\n"
+        );
+    }
+
+    #[test]
+    fn test_english_and_unknown_languages() {
+        let english = vec!["en".to_string(), "de".to_string()];
+        assert_eq!(localized_usage(USAGE_EXCERPT, &english), USAGE_EXCERPT);
+        let unknown = vec!["xx".to_string(), "en".to_string()];
+        assert_eq!(localized_usage(USAGE_EXCERPT, &unknown), USAGE_EXCERPT);
+    }
+
+    #[test]
+    fn test_wrap_words() {
+        assert_eq!(wrap_words("a bb ccc dddd", 6), vec!["a bb", "ccc", "dddd"]);
+        assert_eq!(wrap_words("", 6), vec![""]);
+    }
+
+    #[test]
+    fn test_catalogs_match_usage() {
+        let options: Vec<&str> = USAGE.lines().filter_map(option_name).collect();
+        for (language, source) in CATALOGS {
+            let catalog: Catalog = toml::from_str(source).unwrap();
+            for name in catalog.options.keys() {
+                assert!(
+                    options.contains(&name.as_str()),
+                    "{} translates unknown option {}",
+                    language,
+                    name
+                );
+            }
+            for heading in catalog.headings.keys() {
+                assert!(
+                    USAGE.lines().any(|line| line == heading),
+                    "{} translates unknown heading {}",
+                    language,
+                    heading
+                );
+            }
+        }
+    }
+}
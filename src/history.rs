@@ -0,0 +1,204 @@
+//! Reading the shell history to suggest placeholder values.
+//!
+//! Suggestions are based on a simple heuristic: A history entry matches an
+//! example if it has the same literal words in front of the placeholder, and
+//! the word at the position of the placeholder is suggested. For example,
+//! `git push origin main` suggests `origin` for `git push {{remote}}`.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Maximum number of history entries that are considered.
+const MAX_ENTRIES: usize = 1000;
+
+/// Return the path of the history file of the user's shell, if known.
+fn history_file() -> Option<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let shell = env::var("SHELL").unwrap_or_default();
+    let shell_name = shell.rsplit('/').next().unwrap_or("");
+
+    if shell_name == "fish" {
+        let data_dir = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.map(|home| home.join(".local").join("share")))?;
+        return Some(data_dir.join("fish").join("fish_history"));
+    }
+    if let Some(histfile) = env::var_os("HISTFILE") {
+        return Some(PathBuf::from(histfile));
+    }
+    if cfg!(windows) {
+        return env::var_os("APPDATA").map(|appdata| {
+            PathBuf::from(appdata)
+                .join("Microsoft")
+                .join("Windows")
+                .join("PowerShell")
+                .join("PSReadLine")
+                .join("ConsoleHost_history.txt")
+        });
+    }
+    let file_name = if shell_name == "zsh" {
+        ".zsh_history"
+    } else {
+        ".bash_history"
+    };
+    home.map(|home| home.join(file_name))
+}
+
+/// Extract the commands from the contents of a history file.
+///
+/// Supports plain histories (bash, PowerShell), the extended zsh format
+/// (`: <timestamp>:<duration>;<command>`) and the fish format
+/// (`- cmd: <command>`).
+fn parse_history(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            if line.starts_with("- cmd: ") {
+                Some(&line["- cmd: ".len()..])
+            } else if line.starts_with("  ") || line.starts_with('#') {
+                // fish metadata and bash timestamps
+                None
+            } else if line.starts_with(": ") {
+                line.find(';').map(|pos| &line[pos + 1..])
+            } else {
+                Some(line)
+            }
+        })
+        .map(str::trim)
+        .filter(|command| !command.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Read the recent commands from the shell history, most recent first.
+pub fn read_history() -> Vec<String> {
+    let contents = match history_file().and_then(|path| fs::read(path).ok()) {
+        Some(contents) => contents,
+        None => return vec![],
+    };
+    let mut commands = parse_history(&String::from_utf8_lossy(&contents));
+    commands.reverse();
+    commands.truncate(MAX_ENTRIES);
+    commands
+}
+
+/// Split a history entry into words, removing quotes and backslash escapes
+/// like a POSIX shell would.
+fn split_words(entry: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = entry.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\'') | (None, '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (Some(q), c) if c == q => quote = None,
+            (None, '\\') | (Some('"'), '\\') => {
+                word.extend(chars.next());
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (_, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// Suggest a value for `placeholder` in the example command `code` from the
+/// `history` (most recent first).
+pub fn suggest(code: &str, placeholder: &str, history: &[String]) -> Option<String> {
+    let words: Vec<&str> = code.split_whitespace().collect();
+    let position = words
+        .iter()
+        .position(|word| *word == format!("{{{{{}}}}}", placeholder))?;
+    let prefix = &words[..position];
+
+    history.iter().find_map(|entry| {
+        let entry_words = split_words(entry);
+        let matches = prefix
+            .iter()
+            .zip(&entry_words)
+            .all(|(word, entry_word)| word.contains("{{") || word == entry_word);
+        let value = entry_words.get(position)?;
+        if matches && !value.is_empty() && !value.starts_with('-') {
+            Some(value.clone())
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_history, split_words, suggest};
+
+    #[test]
+    fn test_parse_history() {
+        let bash = "#1600000000\ngit status\ngit push origin main\n";
+        assert_eq!(
+            parse_history(bash),
+            vec!["git status", "git push origin main"]
+        );
+
+        let zsh = ": 1600000000:0;git status\n: 1600000001:0;ls -la\n";
+        assert_eq!(parse_history(zsh), vec!["git status", "ls -la"]);
+
+        let fish = "- cmd: git status\n  when: 1600000000\n- cmd: ls\n  when: 1600000001\n";
+        assert_eq!(parse_history(fish), vec!["git status", "ls"]);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let history = vec![
+            "git push upstream feature".to_string(),
+            "git pull origin main".to_string(),
+            "git push origin main".to_string(),
+        ];
+        let code = "git push {{remote}} {{branch}}";
+        assert_eq!(
+            suggest(code, "remote", &history),
+            Some("upstream".to_string())
+        );
+        assert_eq!(
+            suggest(code, "branch", &history),
+            Some("feature".to_string())
+        );
+        assert_eq!(suggest("git clone {{url}}", "url", &history), None);
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words(r#"cp 'my file' "it's" a\ b '' x"#),
+            vec!["cp", "my file", "it's", "a b", "", "x"]
+        );
+    }
+
+    #[test]
+    fn test_suggest_skips_options() {
+        let history = vec![
+            "cp -r src dest".to_string(),
+            "cp 'my file' other".to_string(),
+        ];
+        assert_eq!(
+            suggest("cp {{source}} {{target}}", "source", &history),
+            Some("my file".to_string())
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Human readable formatting of numbers, sizes, durations and dates.
+//!
+//! Numbers use the decimal and thousands separators of the user's locale (as
+//! far as they can be derived from `LC_ALL`, `LC_NUMERIC` or `LANG`). Dates are
+//! always formatted according to ISO 8601, which is understood everywhere.
+//! Machine-readable output should not use this module, but raw numbers and
+//! `iso8601` timestamps instead.
+
+use std::env;
+use std::time::Duration;
+
+/// The separators used to format numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub decimal: char,
+    pub thousands: char,
+}
+
+/// Languages using a decimal comma and a dot to group thousands.
+const DOT_GROUPING_LANGUAGES: &[&str] = &[
+    "da", "de", "el", "es", "id", "it", "nl", "pt", "ro", "sl", "sr", "tr", "vi",
+];
+/// Languages using a decimal comma and a space to group thousands.
+const SPACE_GROUPING_LANGUAGES: &[&str] = &[
+    "bg", "cs", "et", "fi", "fr", "hu", "lt", "lv", "nb", "no", "pl", "ru", "sk", "sv", "uk",
+];
+
+impl NumberFormat {
+    /// The format of numbers in the given POSIX locale (e.g. `de_DE.UTF-8`).
+    pub fn for_locale(locale: &str) -> Self {
+        let language = locale.split(&['_', '.', '@', '-'][..]).next().unwrap_or("");
+        if DOT_GROUPING_LANGUAGES.contains(&language) {
+            Self {
+                decimal: ',',
+                thousands: '.',
+            }
+        } else if SPACE_GROUPING_LANGUAGES.contains(&language) {
+            Self {
+                decimal: ',',
+                // Narrow no-break space
+                thousands: '\u{202f}',
+            }
+        } else {
+            Self {
+                decimal: '.',
+                thousands: ',',
+            }
+        }
+    }
+
+    /// The format of numbers in the locale of the user.
+    pub fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        Self::for_locale(&locale)
+    }
+
+    /// Format an integer, grouping thousands.
+    pub fn count(self, n: u64) -> String {
+        let digits = n.to_string();
+        let first_group = match digits.len() % 3 {
+            0 => 3,
+            len => len,
+        };
+        let mut formatted = digits[..first_group].to_string();
+        for group in digits.as_bytes()[first_group..].chunks(3) {
+            formatted.push(self.thousands);
+            formatted.push_str(std::str::from_utf8(group).expect("Digits are ASCII"));
+        }
+        formatted
+    }
+
+    /// Format a size in bytes with a binary unit (e.g. `1.5 MiB`).
+    pub fn size(self, bytes: u64) -> String {
+        const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+        if bytes < 1024 {
+            return format!("{} B", bytes);
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let mut value = bytes as f64 / 1024.0;
+        let mut unit = UNITS[0];
+        for next_unit in &UNITS[1..] {
+            if value < 1024.0 {
+                break;
+            }
+            value /= 1024.0;
+            unit = next_unit;
+        }
+        let formatted = format!("{:.1}", value).replace('.', &self.decimal.to_string());
+        format!("{} {}", formatted, unit)
+    }
+}
+
+/// Format a duration coarsely, e.g. `3 days` or `5 minutes`.
+pub fn duration(duration: Duration) -> String {
+    const UNITS: &[(u64, &str)] = &[
+        (365 * 24 * 3600, "year"),
+        (30 * 24 * 3600, "month"),
+        (7 * 24 * 3600, "week"),
+        (24 * 3600, "day"),
+        (3600, "hour"),
+        (60, "minute"),
+        (1, "second"),
+    ];
+    let secs = duration.as_secs();
+    let (unit_secs, unit) = UNITS
+        .iter()
+        .find(|(unit_secs, _)| secs >= *unit_secs)
+        .unwrap_or(&UNITS[UNITS.len() - 1]);
+    let n = secs / unit_secs;
+    if n == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", n, unit)
+    }
+}
+
+/// Convert days since the Unix epoch to a (year, month, day) date.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Split a Unix timestamp into a date and the seconds since midnight (UTC).
+fn split_timestamp(timestamp: u64) -> ((i64, u32, u32), u64) {
+    #[allow(clippy::cast_possible_wrap)]
+    let days = (timestamp / 86400) as i64;
+    (civil_from_days(days), timestamp % 86400)
+}
+
+/// Format a Unix timestamp as a human readable date, e.g.
+/// `2021-03-04 05:06 UTC`.
+pub fn date(timestamp: u64) -> String {
+    let ((year, month, day), secs) = split_timestamp(timestamp);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60
+    )
+}
+
+/// Format a Unix timestamp according to RFC 3339, e.g.
+/// `2021-03-04T05:06:07Z`.
+pub fn iso8601(timestamp: u64) -> String {
+    let ((year, month, day), secs) = split_timestamp(timestamp);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{date, duration, iso8601, NumberFormat};
+
+    use std::time::Duration;
+
+    #[test]
+    fn test_count() {
+        let en = NumberFormat::for_locale("en_US.UTF-8");
+        assert_eq!(en.count(0), "0");
+        assert_eq!(en.count(999), "999");
+        assert_eq!(en.count(1000), "1,000");
+        assert_eq!(en.count(1_234_567), "1,234,567");
+        assert_eq!(NumberFormat::for_locale("de_DE").count(5432), "5.432");
+        assert_eq!(NumberFormat::for_locale("fr").count(5432), "5\u{202f}432");
+        assert_eq!(NumberFormat::for_locale("").count(5432), "5,432");
+    }
+
+    #[test]
+    fn test_size() {
+        let en = NumberFormat::for_locale("C");
+        assert_eq!(en.size(512), "512 B");
+        assert_eq!(en.size(1536), "1.5 KiB");
+        assert_eq!(en.size(22_437_888), "21.4 MiB");
+        assert_eq!(NumberFormat::for_locale("de_AT").size(1536), "1,5 KiB");
+    }
+
+    #[test]
+    fn test_duration() {
+        assert_eq!(duration(Duration::from_secs(0)), "0 seconds");
+        assert_eq!(duration(Duration::from_secs(61)), "1 minute");
+        assert_eq!(duration(Duration::from_secs(3 * 86400 + 5)), "3 days");
+        assert_eq!(duration(Duration::from_secs(90 * 86400)), "3 months");
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(iso8601(0), "1970-01-01T00:00:00Z");
+        assert_eq!(iso8601(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(iso8601(1_614_834_367), "2021-03-04T05:06:07Z");
+        assert_eq!(date(1_614_834_367), "2021-03-04 05:06 UTC");
+    }
+}
@@ -0,0 +1,18 @@
+//! Detecting whether tealdeer may ask the user for input.
+//!
+//! In non-interactive mode (`--non-interactive`), tealdeer never prompts and
+//! never waits for input: Features that need an answer fail with an error
+//! and the pager is not used. This is needed in Docker build steps and CI
+//! provisioning scripts, where nobody can answer.
+//!
+//! The mode is enabled automatically unless both stdin and stdout are
+//! terminals, e.g. if stdin is `/dev/null` or a pipe, or if the output is
+//! redirected to a file.
+
+use atty::Stream;
+
+/// Return whether to run without prompting, given the `--non-interactive`
+/// flag.
+pub fn is_non_interactive(flag: bool) -> bool {
+    flag || !atty::is(Stream::Stdin) || !atty::is(Stream::Stdout)
+}
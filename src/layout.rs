@@ -0,0 +1,270 @@
+//! Adapters for the different layouts of the pages archive.
+//!
+//! Upstream has changed the structure of the pages archive over time (e.g. the
+//! name of the top-level directory in GitHub source archives). Every known
+//! layout is normalized into the same structure in the cache directory:
+//! `TLDR_PAGES_DIR/pages[.<lang>]/<platform>/<page>.md`.
+//!
+//! The layout is detected from the contents of the archive, unless the
+//! `archive_layout` option names one. The language of archives with the pages
+//! of a single language (e.g. `tldr-pages.de.zip`) is taken from their name.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::TealdeerError::{self, UpdateError};
+
+/// The directory inside the cache directory that contains the pages
+/// directories.
+pub const TLDR_PAGES_DIR: &str = "tldr-master";
+
+#[derive(Debug, PartialEq)]
+pub enum ArchiveLayout {
+    /// The pages directories are nested in a single top-level directory, like
+    /// in GitHub source archives (e.g. `tldr-master/pages/common/tar.md` or
+    /// `tldr-main/pages.de/common/tar.md`).
+    Nested(PathBuf),
+    /// The pages directories are at the root of the archive (e.g.
+    /// `pages/common/tar.md`).
+    Flat,
+    /// The archive only contains the platform directories of a single
+    /// language (e.g. `common/tar.md`).
+    SingleLanguage(String),
+}
+
+/// How the layout of the pages archive is determined.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutSetting {
+    /// Detect the layout from the contents of the archive
+    Auto,
+    /// See `ArchiveLayout::Nested`
+    Nested,
+    /// See `ArchiveLayout::Flat`
+    Flat,
+    /// See `ArchiveLayout::SingleLanguage`
+    SingleLanguage,
+}
+
+impl Default for LayoutSetting {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+/// Return the language of the pages in an archive named like
+/// `tldr-pages.<lang>.zip` that was downloaded from `url`.
+pub fn language_from_url(url: &str) -> Option<String> {
+    let path = url.split(|c| c == '?' || c == '#').next().unwrap_or(url);
+    let file_name = path.rsplit(|c| c == '/' || c == '\\').next()?;
+    let stem = [".zip", ".tar.gz", ".tgz"]
+        .iter()
+        .find(|extension| file_name.ends_with(*extension))
+        .map(|extension| &file_name[..file_name.len() - extension.len()])?;
+    let language = &stem[stem.rfind('.')? + 1..];
+    let is_language = (2..=5).contains(&language.len())
+        && language.starts_with(|c: char| c.is_ascii_lowercase())
+        && language
+            .chars()
+            .all(|c| c.is_ascii_alphabetic() || c == '_');
+    if is_language {
+        Some(language.to_string())
+    } else {
+        None
+    }
+}
+
+/// Return whether `dir` contains at least one pages directory.
+fn contains_pages_dirs(dir: &Path) -> bool {
+    fs::read_dir(dir).map_or(false, |entries| {
+        entries.filter_map(Result::ok).any(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            (name == "pages" || name.starts_with("pages.")) && entry.path().is_dir()
+        })
+    })
+}
+
+/// Return the only directory in `dir`.
+fn single_dir(dir: &Path) -> Option<PathBuf> {
+    let dirs: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    match dirs.as_slice() {
+        [dir] => Some(dir.clone()),
+        _ => None,
+    }
+}
+
+impl ArchiveLayout {
+    /// Determine the layout of an archive downloaded from `url` and extracted
+    /// to `root`, according to `setting`.
+    pub fn resolve(setting: LayoutSetting, root: &Path, url: Option<&str>) -> Option<Self> {
+        let language = || {
+            url.and_then(language_from_url)
+                .unwrap_or_else(|| "en".into())
+        };
+        let nested = || {
+            single_dir(root)?
+                .file_name()
+                .map(|name| Self::Nested(name.into()))
+        };
+        match setting {
+            LayoutSetting::Auto if contains_pages_dirs(root) => Some(Self::Flat),
+            LayoutSetting::Auto if root.join("common").is_dir() => {
+                Some(Self::SingleLanguage(language()))
+            }
+            LayoutSetting::Auto => single_dir(root)
+                .filter(|dir| contains_pages_dirs(dir))
+                .and_then(|_| nested()),
+            LayoutSetting::Nested => nested(),
+            LayoutSetting::Flat => Some(Self::Flat),
+            LayoutSetting::SingleLanguage => Some(Self::SingleLanguage(language())),
+        }
+    }
+
+    /// Move the pages of an archive extracted to `root` to `target`, which
+    /// must not exist yet.
+    pub fn normalize(&self, root: &Path, target: &Path) -> Result<(), TealdeerError> {
+        let map_err = |e| {
+            UpdateError(format!(
+                "Could not move pages to {}: {}",
+                target.display(),
+                e
+            ))
+        };
+        match self {
+            Self::Nested(dir) => fs::rename(root.join(dir), target).map_err(map_err),
+            Self::Flat => fs::rename(root, target).map_err(map_err),
+            Self::SingleLanguage(language) => {
+                let pages_dir = if language == "en" {
+                    "pages".to_string()
+                } else {
+                    format!("pages.{}", language)
+                };
+                fs::create_dir_all(target).map_err(map_err)?;
+                fs::rename(root, target.join(pages_dir)).map_err(map_err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{language_from_url, ArchiveLayout, LayoutSetting};
+
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use tempfile::TempDir;
+
+    fn create_page(root: &Path, path: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "# page\n").unwrap();
+    }
+
+    fn resolve_and_normalize(
+        setting: LayoutSetting,
+        url: Option<&str>,
+        pages: &[&str],
+    ) -> (ArchiveLayout, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("archive");
+        for page in pages {
+            create_page(&root, page);
+        }
+        let layout = ArchiveLayout::resolve(setting, &root, url).unwrap();
+        layout.normalize(&root, &dir.path().join("out")).unwrap();
+        (layout, dir)
+    }
+
+    fn detect_and_normalize(pages: &[&str]) -> (ArchiveLayout, TempDir) {
+        resolve_and_normalize(LayoutSetting::Auto, None, pages)
+    }
+
+    #[test]
+    fn test_nested() {
+        for top_level in &["tldr-master", "tldr-main"] {
+            let (layout, dir) = detect_and_normalize(&[
+                &format!("{}/pages/common/tar.md", top_level),
+                &format!("{}/pages.de/linux/ls.md", top_level),
+            ]);
+            assert_eq!(layout, ArchiveLayout::Nested(PathBuf::from(top_level)));
+            assert!(dir.path().join("out/pages/common/tar.md").is_file());
+            assert!(dir.path().join("out/pages.de/linux/ls.md").is_file());
+        }
+    }
+
+    #[test]
+    fn test_flat() {
+        let (layout, dir) = detect_and_normalize(&["pages/common/tar.md", "LICENSE.md"]);
+        assert_eq!(layout, ArchiveLayout::Flat);
+        assert!(dir.path().join("out/pages/common/tar.md").is_file());
+    }
+
+    #[test]
+    fn test_single_language() {
+        let (layout, dir) = detect_and_normalize(&["common/tar.md", "osx/ls.md"]);
+        assert_eq!(layout, ArchiveLayout::SingleLanguage("en".into()));
+        assert!(dir.path().join("out/pages/common/tar.md").is_file());
+        assert!(dir.path().join("out/pages/osx/ls.md").is_file());
+
+        let (layout, dir) = resolve_and_normalize(
+            LayoutSetting::Auto,
+            Some("https://tldr.sh/assets/tldr-pages.pt_BR.zip"),
+            &["common/tar.md"],
+        );
+        assert_eq!(layout, ArchiveLayout::SingleLanguage("pt_BR".into()));
+        assert!(dir.path().join("out/pages.pt_BR/common/tar.md").is_file());
+    }
+
+    #[test]
+    fn test_configured_layout() {
+        // Without a pages directory, the top-level directory isn't detected
+        let (layout, dir) =
+            resolve_and_normalize(LayoutSetting::Nested, None, &["tldr-2.0/common/tar.md"]);
+        assert_eq!(layout, ArchiveLayout::Nested(PathBuf::from("tldr-2.0")));
+        assert!(dir.path().join("out/common/tar.md").is_file());
+
+        let (layout, dir) = resolve_and_normalize(
+            LayoutSetting::SingleLanguage,
+            Some("https://example.com/pages.de.tar.gz"),
+            &["linux/ls.md"],
+        );
+        assert_eq!(layout, ArchiveLayout::SingleLanguage("de".into()));
+        assert!(dir.path().join("out/pages.de/linux/ls.md").is_file());
+    }
+
+    #[test]
+    fn test_language_from_url() {
+        let language = |url| language_from_url(url);
+        assert_eq!(
+            language("https://tldr.sh/assets/tldr-pages.de.zip"),
+            Some("de".into())
+        );
+        assert_eq!(language("/tmp/pages.zh_TW.tar.gz"), Some("zh_TW".into()));
+        assert_eq!(language("https://tldr.sh/assets/tldr.zip?raw=1"), None);
+        assert_eq!(
+            language("https://github.com/tldr-pages/tldr/archive/master.tar.gz"),
+            None
+        );
+        assert_eq!(language("https://example.com/tldr-1.2.zip"), None);
+    }
+
+    #[test]
+    fn test_unknown() {
+        let dir = TempDir::new().unwrap();
+        create_page(dir.path(), "a/foo.md");
+        create_page(dir.path(), "b/bar.md");
+        assert_eq!(
+            ArchiveLayout::resolve(LayoutSetting::Auto, dir.path(), None),
+            None
+        );
+    }
+}
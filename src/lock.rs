@@ -0,0 +1,155 @@
+//! Advisory locking of the cache directory.
+//!
+//! Two processes must not write to the cache at the same time, e.g. two
+//! `tldr --update` in parallel. Updates, migrations and clearing the cache
+//! hold an exclusive lock on a lock file in the cache directory while they
+//! write. Lookups don't lock the cache: Updates replace the pages atomically,
+//! so readers always see complete pages. The operating system releases the
+//! lock when the process exits, even if it crashes, so a lock is never left
+//! behind.
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::Path;
+
+use fs2::FileExt;
+use log::debug;
+
+/// The name of the lock file in the cache directory.
+const LOCK_FILE: &str = "tealdeer.lock";
+
+/// An exclusive lock on the cache directory, held as long as it is alive.
+#[derive(Debug)]
+pub struct CacheLock {
+    file: File,
+}
+
+/// Open (or create) the lock file in `cache_dir`.
+fn open_lock_file(cache_dir: &Path) -> io::Result<File> {
+    fs::create_dir_all(cache_dir)?;
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(cache_dir.join(LOCK_FILE))
+}
+
+/// Return whether `file` is still the lock file in `cache_dir`. It is not if
+/// the cache was cleared while waiting for the lock.
+#[cfg(unix)]
+fn is_current_lock_file(file: &File, cache_dir: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let locked = file.metadata()?;
+    Ok(match fs::metadata(cache_dir.join(LOCK_FILE)) {
+        Ok(current) => current.dev() == locked.dev() && current.ino() == locked.ino(),
+        Err(_) => false,
+    })
+}
+
+/// Open files can not be removed on other platforms.
+#[cfg(not(unix))]
+fn is_current_lock_file(_file: &File, _cache_dir: &Path) -> io::Result<bool> {
+    Ok(true)
+}
+
+impl CacheLock {
+    /// Lock the cache directory `cache_dir`, waiting for other processes to
+    /// release the lock first.
+    pub fn lock(cache_dir: &Path) -> io::Result<Self> {
+        loop {
+            let file = open_lock_file(cache_dir)?;
+            if let Err(e) = file.try_lock_exclusive() {
+                if e.kind() != fs2::lock_contended_error().kind() {
+                    return Err(e);
+                }
+                debug!("Waiting for another process to unlock the cache");
+                file.lock_exclusive()?;
+            }
+            if is_current_lock_file(&file, cache_dir)? {
+                return Ok(Self { file });
+            }
+        }
+    }
+
+    /// Try to lock the cache directory `cache_dir`. Returns `None` if another
+    /// process holds the lock.
+    pub fn try_lock(cache_dir: &Path) -> io::Result<Option<Self>> {
+        let file = open_lock_file(cache_dir)?;
+        match file.try_lock_exclusive() {
+            Ok(()) => Ok(Some(Self { file })),
+            Err(ref e) if e.kind() == fs2::lock_contended_error().kind() => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use tempfile::TempDir;
+
+    use super::CacheLock;
+
+    #[test]
+    fn test_try_lock() {
+        let dir = TempDir::new().unwrap();
+        let lock = CacheLock::try_lock(dir.path()).unwrap();
+        assert!(lock.is_some());
+        // Locks are exclusive, even within a process
+        assert!(CacheLock::try_lock(dir.path()).unwrap().is_none());
+        drop(lock);
+        assert!(CacheLock::try_lock(dir.path()).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_lock_waits() {
+        let dir = TempDir::new().unwrap();
+        let lock = CacheLock::lock(dir.path()).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let cache_dir = dir.path().to_path_buf();
+        let waiting = thread::spawn(move || {
+            let lock = CacheLock::lock(&cache_dir).unwrap();
+            sender.send(()).unwrap();
+            drop(lock);
+        });
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+        drop(lock);
+        receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        waiting.join().unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_lock_after_clearing() {
+        let dir = TempDir::new().unwrap();
+        let cache_dir = dir.path().join("cache");
+        let lock = CacheLock::lock(&cache_dir).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let waiting_cache_dir = cache_dir.clone();
+        let waiting = thread::spawn(move || {
+            let lock = CacheLock::lock(&waiting_cache_dir).unwrap();
+            sender.send(()).unwrap();
+            // Keep the lock until the main thread checked that the new lock
+            // file is locked
+            thread::sleep(Duration::from_millis(200));
+            drop(lock);
+        });
+        thread::sleep(Duration::from_millis(100));
+        // Clear the cache while the other thread waits for the lock
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+        drop(lock);
+        receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        // The waiting thread locked the new lock file
+        assert!(CacheLock::try_lock(&cache_dir).unwrap().is_none());
+        waiting.join().unwrap();
+    }
+}
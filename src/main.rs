@@ -17,13 +17,18 @@
 #[cfg(feature = "logging")]
 extern crate env_logger;
 
+#[global_allocator]
+static ALLOCATOR: bench::CountingAllocator = bench::CountingAllocator;
+
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::env;
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::iter;
 use std::path::{Path, PathBuf};
-use std::process;
+use std::process::{self, Command, Stdio};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use ansi_term::{Color, Style};
 use app_dirs::AppInfo;
@@ -33,21 +38,99 @@ use docopt::Docopt;
 use pager::Pager;
 use serde_derive::Deserialize;
 
+mod analyzer;
+mod bench;
+mod bundled;
 mod cache;
+mod cache_info;
+mod command_not_found;
+mod compare;
+mod completions;
 mod config;
+mod config_schema;
+mod coverage;
 mod dedup;
+mod demo;
+mod document;
+mod encoding;
 mod error;
+mod fetch;
+mod fill;
+mod filter;
 mod formatter;
+mod fuzzy;
+mod generation;
+mod help;
+mod history;
+mod human;
+mod interactive;
+mod layout;
+mod lock;
+mod man;
+mod nushell;
+mod parser;
+mod pins;
+mod powershell;
+mod profile;
+mod provenance;
+mod quarantine;
+mod quoting;
+mod repos;
+mod rewrite;
+mod run;
+mod search;
+#[cfg(feature = "share")]
+mod share;
+mod signals;
+mod sources;
+mod spinner;
+mod state;
+mod store;
+mod theme;
 mod tokenizer;
+mod translations;
 mod types;
-
-use crate::cache::Cache;
-use crate::config::{get_config_dir, get_config_path, make_default_config, Config, MAX_CACHE_AGE};
+mod update_log;
+mod upstream;
+mod walk;
+mod width;
+mod workspace;
+
+use crate::cache::{
+    cache_not_found, page_language, page_name_from_words, parent_page_name, validate_page_name,
+    Cache, ClearOutcome, FoundPage,
+};
+use crate::cache_info::{parse_manifest, CacheInfo, ManifestDiff};
+use crate::completions::CompletionShell;
+use crate::config::{
+    get_config_dir, get_config_path, make_default_config, missing_options, Config, OnMiss,
+    PagesSource, StyleProfile, UpdatesConfig, MAX_CACHE_AGE,
+};
+use crate::coverage::{check_coverage, parse_command_line, parse_command_list};
 use crate::dedup::Dedup;
-use crate::error::TealdeerError::{CacheError, ConfigError, UpdateError};
-use crate::formatter::print_lines;
-use crate::tokenizer::Tokenizer;
-use crate::types::{ColorOptions, OsType};
+use crate::demo::DemoFormat;
+use crate::document::Document;
+use crate::encoding::OutputEncoding;
+use crate::error::TealdeerError::{self, CacheError};
+use crate::error::{enable_json_errors, exit_with, exit_with_error, ErrorKind};
+use crate::fill::Fill;
+use crate::human::NumberFormat;
+use crate::lock::CacheLock;
+use crate::parser::Page;
+use crate::powershell::PageObject;
+use crate::provenance::{Origin, Provenance};
+use crate::quarantine::{QuarantinedPage, QUARANTINE_DIR};
+use crate::quoting::Shell;
+use crate::repos::AddOutcome;
+use crate::run::run_example;
+use crate::search::{search_pages, SearchResult};
+use crate::sources::SourceUpdate;
+use crate::spinner::Spinner;
+use crate::translations::{translation_status, Staleness};
+use crate::types::{ColorOptions, OsType, OutputFormat, RenderMode};
+use crate::update_log::UpdateAttempt;
+use crate::upstream::{DiffLine, UpstreamStatus};
+use crate::workspace::Workspace;
 
 const NAME: &str = "tealdeer";
 const APP_INFO: AppInfo = AppInfo {
@@ -56,9 +139,26 @@ const APP_INFO: AppInfo = AppInfo {
 };
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const USAGE: &str = include_str!("usage.docopt");
+/// Set for `tldr --update` in the process started by
+/// `spawn_background_update`.
+const BACKGROUND_UPDATE_ENV_VAR: &str = "TEALDEER_BACKGROUND_UPDATE";
 const ARCHIVE_URL: &str = "https://github.com/tldr-pages/tldr/archive/master.tar.gz";
 #[cfg(not(target_os = "windows"))]
 const PAGER_COMMAND: &str = "less -R";
+const MISS_SEARCH_RESULTS: usize = 5;
+/// The optional cargo features and whether they are enabled in this build,
+/// as shown by `--version --verbose`.
+const FEATURES: &[(&str, bool)] = &[
+    ("bundled-pages", cfg!(feature = "bundled-pages")),
+    ("git", cfg!(feature = "git")),
+    ("logging", cfg!(feature = "logging")),
+    ("mmap", cfg!(feature = "mmap")),
+    ("share", cfg!(feature = "share")),
+    ("signatures", cfg!(feature = "signatures")),
+    ("sqlite", cfg!(feature = "sqlite")),
+    ("stemming", cfg!(feature = "stemming")),
+    ("trash", cfg!(feature = "trash")),
+];
 
 #[derive(Debug, Deserialize)]
 struct Args {
@@ -66,38 +166,918 @@ struct Args {
     flag_help: bool,
     flag_version: bool,
     flag_list: bool,
+    flag_platform: Option<String>,
+    flag_long: bool,
+    flag_search: Option<String>,
+    flag_command_not_found: Option<String>,
+    flag_shell_hook: bool,
+    flag_shell_integration: Option<Shell>,
+    flag_completions: Option<CompletionShell>,
+    flag_suggest: Option<usize>,
     flag_render: Option<String>,
     flag_os: Option<OsType>,
     flag_update: bool,
+    flag_jobs: Option<usize>,
+    flag_add_pages_repo: Option<String>,
     flag_clear_cache: bool,
+    flag_clean_aux: bool,
+    flag_migrate_cache: bool,
+    flag_seed_cache: bool,
+    flag_update_from_file: Option<String>,
+    flag_pin: Option<String>,
+    flag_unpin: Option<String>,
+    flag_export_profile: Option<String>,
+    flag_import_profile: Option<String>,
     flag_pager: bool,
     flag_quiet: bool,
+    flag_verbose: bool,
+    flag_non_interactive: bool,
     flag_show_paths: bool,
     flag_config_path: bool,
     flag_seed_config: bool,
+    flag_minimal: bool,
+    flag_diff: bool,
+    flag_config_schema: bool,
     flag_markdown: bool,
+    flag_copyable: bool,
+    flag_demo: bool,
+    flag_demo_format: DemoFormat,
+    flag_section: Option<String>,
+    flag_no_filter: bool,
+    flag_trace_lookup: bool,
+    flag_strict: bool,
+    flag_batch: Option<String>,
+    flag_batch_separator: Option<String>,
+    flag_bench_render: Option<String>,
+    flag_baseline: Option<String>,
+    flag_compare_lang: Option<String>,
     flag_color: ColorOptions,
     flag_language: Option<String>,
+    flag_profile: Option<String>,
+    flag_fill: Vec<String>,
+    flag_shell: Option<Shell>,
+    flag_run: bool,
+    flag_cache_diff: Option<String>,
+    flag_stats: bool,
+    flag_info: bool,
+    flag_update_history: Option<usize>,
+    flag_share_cache: Option<String>,
+    flag_output: OutputFormat,
+    flag_url: bool,
+    flag_attribution: bool,
+    flag_check_upstream: bool,
+    flag_check_coverage: Option<String>,
+    flag_translation_status: Option<String>,
+    flag_dump_theme: Option<String>,
+}
+
+impl Args {
+    /// Return how pages are rendered as text.
+    fn render_mode(&self) -> RenderMode {
+        if self.flag_markdown {
+            RenderMode::Markdown
+        } else if self.flag_copyable {
+            RenderMode::Copyable
+        } else {
+            RenderMode::Formatted
+        }
+    }
+}
+
+/// Render the page read from `reader` into `buffer`, only with the examples
+/// of `section` if it is given.
+fn render_page<R: BufRead>(
+    reader: R,
+    mode: RenderMode,
+    section: Option<&str>,
+    config: &Config,
+    fill: &Fill,
+    hyperlinks: bool,
+    buffer: &mut Vec<u8>,
+) -> Result<(), String> {
+    if mode == RenderMode::Markdown && section.is_none() {
+        // Print the raw markdown of the file.
+        for line in reader.lines() {
+            let line = line.map_err(|msg| format!("Could not read file: {}", msg))?;
+            writeln!(buffer, "{}", line).expect("Writing to a Vec never fails");
+        }
+        return Ok(());
+    }
+
+    let mut page = Page::read(reader);
+    if let Some(section) = section {
+        page.select_section(section);
+    }
+    match mode {
+        RenderMode::Markdown => buffer.extend_from_slice(page.to_markdown().as_bytes()),
+        RenderMode::Formatted => formatter::print_page(&page, config, fill, hyperlinks, buffer)
+            .expect("Writing to a Vec never fails"),
+        RenderMode::Copyable => formatter::print_page_copyable(&page, config, fill, buffer)
+            .expect("Writing to a Vec never fails"),
+    }
+    Ok(())
+}
+
+/// Return whether the page at `path` may be memory-mapped.
+///
+/// Only pages in the active cache generation are mapped: They are never
+/// modified in place (updates create a new generation), while other files
+/// (e.g. custom pages) could be truncated by an editor while they are mapped.
+#[cfg(feature = "mmap")]
+fn is_mappable(path: &Path) -> bool {
+    Cache::get_active_pages_dir().map_or(false, |pages_dir| path.starts_with(pages_dir))
+}
+
+/// Memory mapping is disabled.
+#[cfg(not(feature = "mmap"))]
+fn is_mappable(_path: &Path) -> bool {
+    false
+}
+
+/// Memory-map the page at `path`, so that it can be rendered without copying
+/// it into a read buffer.
+///
+/// Returns `None` if the page should be or can not be mapped (see
+/// `is_mappable`, or e.g. because it is empty), it is read with a buffered
+/// reader then.
+#[cfg(feature = "mmap")]
+fn map_page(path: &Path, file: &File) -> Option<memmap::Mmap> {
+    if !is_mappable(path) {
+        return None;
+    }
+    unsafe { memmap::Mmap::map(file) }.ok()
+}
+
+/// Memory mapping is disabled, pages are always read with a buffered reader.
+#[cfg(not(feature = "mmap"))]
+fn map_page(_path: &Path, _file: &File) -> Option<Vec<u8>> {
+    None
 }
 
 /// Print page by path
-fn print_page(path: &Path, enable_markdown: bool, config: &Config) -> Result<(), String> {
+fn print_page(
+    path: &Path,
+    mode: RenderMode,
+    section: Option<&str>,
+    config: &Config,
+    fill: &Fill,
+    hyperlinks: bool,
+    quietly: bool,
+) -> Result<(), String> {
+    let buffer = render_file(path, mode, section, config, fill, hyperlinks)?;
+    write_output(buffer, config, quietly)
+}
+
+/// Render the page at `path` (and pipe it through the render filter) into a
+/// buffer.
+fn render_file(
+    path: &Path,
+    mode: RenderMode,
+    section: Option<&str>,
+    config: &Config,
+    fill: &Fill,
+    hyperlinks: bool,
+) -> Result<Vec<u8>, String> {
+    let config = page_language(path).map_or(config, |language| config.for_language(&language));
+
     // Open file
     let file = File::open(path).map_err(|msg| format!("Could not open file: {}", msg))?;
-    let reader = BufReader::new(file);
 
-    if enable_markdown {
-        // Print the raw markdown of the file.
-        for line in reader.lines() {
-            println!("{}", line.unwrap());
+    // The page is rendered into a buffer first and written with a single
+    // call, so that it is not interleaved with the output of other programs
+    // and long pages are not sent in many small chunks (e.g. over SSH).
+    let mut buffer = Vec::new();
+    match map_page(path, &file) {
+        Some(mapped) => render_page(
+            &mapped[..],
+            mode,
+            section,
+            config,
+            fill,
+            hyperlinks,
+            &mut buffer,
+        )?,
+        None => render_page(
+            BufReader::new(file),
+            mode,
+            section,
+            config,
+            fill,
+            hyperlinks,
+            &mut buffer,
+        )?,
+    }
+
+    if let Some(ref command) = config.display.render_filter_command {
+        buffer = filter::apply_filter(command, buffer)?;
+    }
+    Ok(buffer)
+}
+
+/// Write rendered pages to stdout, in the encoding of the terminal.
+fn write_output(mut buffer: Vec<u8>, config: &Config, quietly: bool) -> Result<(), String> {
+    let encoding = config.display.output_encoding;
+    if encoding.resolve() == OutputEncoding::Ascii {
+        if let Cow::Owned(ascii) = encoding::to_ascii(&String::from_utf8_lossy(&buffer)) {
+            if encoding == OutputEncoding::Auto && !quietly {
+                eprintln!(
+                    "Note: Your terminal does not use UTF-8, so non-ASCII characters were \
+                     replaced. Set `output_encoding` in the `[display]` config section to \
+                     override this."
+                );
+            }
+            buffer = ascii.into_bytes();
         }
+    }
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    match stdout.write_all(&buffer).and_then(|()| stdout.flush()) {
+        // The reader is not interested in the rest of the page
+        Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.map_err(|msg| format!("Could not write page: {}", msg)),
+    }
+}
+
+/// Print two translations of the page for `command` side by side and exit.
+fn compare_and_exit(
+    cache: &Cache,
+    command: &str,
+    languages: &str,
+    args: &Args,
+    config: &Config,
+    fill: &Fill,
+) -> ! {
+    let languages: Vec<&str> = languages.split(',').map(str::trim).collect();
+    if languages.len() != 2 || languages.iter().any(|language| language.is_empty()) {
+        exit_with_error(
+            ErrorKind::Usage,
+            "--compare-lang expects two languages, e.g. --compare-lang en,de",
+            None,
+        );
+    }
+
+    let translations: Vec<compare::Translation> = languages
+        .iter()
+        .map(|language| {
+            let rendered = cache
+                .find_page(command, &[(*language).to_string()])
+                .map(|found| {
+                    render_file(
+                        &found.path,
+                        args.render_mode(),
+                        args.flag_section.as_deref(),
+                        config,
+                        fill,
+                        false,
+                    )
+                    .unwrap_or_else(|msg| exit_with_error(ErrorKind::Render, &msg, None))
+                });
+            compare::Translation {
+                language: (*language).to_string(),
+                rendered: rendered.map(|buffer| String::from_utf8_lossy(&buffer).into_owned()),
+            }
+        })
+        .collect();
+    if translations
+        .iter()
+        .all(|translation| translation.rendered.is_none())
+    {
+        exit_with_error(
+            ErrorKind::PageNotFound,
+            &format!(
+                "Page {} is available in neither {} nor {}",
+                command, languages[0], languages[1]
+            ),
+            None,
+        );
+    }
+
+    let output = compare::layout(&translations[0], &translations[1], width::terminal_width());
+    if let Err(msg) = write_output(output.into_bytes(), config, args.flag_quiet) {
+        exit_with_error(ErrorKind::Render, &msg, None);
+    }
+    process::exit(0);
+}
+
+/// Print the link to more information about the command of the page at
+/// `path` and exit.
+fn print_url_and_exit(path: &Path, format: OutputFormat) -> ! {
+    let url = File::open(path)
+        .ok()
+        .and_then(|file| Page::read(BufReader::new(file)).more_information);
+    let url = url.unwrap_or_else(|| {
+        exit_with_error(
+            ErrorKind::NoResults,
+            "The page does not link to more information.",
+            None,
+        );
+    });
+    match format {
+        OutputFormat::Json | OutputFormat::PsObjectJson | OutputFormat::Nuon => {
+            println!("{}", serde_json::json!({ "url": url }))
+        }
+        OutputFormat::Text | OutputFormat::Man | OutputFormat::Tsv => println!("{}", url),
+    }
+    process::exit(0);
+}
+
+/// Print a demo of the page for `command` at `path`, only with the examples
+/// of `section` if it is given, and exit.
+fn print_demo_and_exit(
+    path: &Path,
+    command: &str,
+    section: Option<&str>,
+    config: &Config,
+    fill: &Fill,
+    format: DemoFormat,
+) -> ! {
+    let file = File::open(path).unwrap_or_else(|e| {
+        exit_with_error(ErrorKind::Io, &format!("Could not open file: {}", e), None)
+    });
+    let mut page = Page::read(BufReader::new(file));
+    if let Some(section) = section {
+        page.select_section(section);
+    }
+    let title = format!("tldr {}", command);
+    print!(
+        "{}",
+        demo::record(&page, &title, fill, &config.demo, format)
+    );
+    process::exit(0);
+}
+
+/// Print the page for `command` at `path` as a JSON document or a man page
+/// and exit.
+///
+/// With `psobject-json`, the document is printed as an object for PowerShell,
+/// with `nuon` as a record for Nushell.
+fn print_document_and_exit(
+    path: &Path,
+    command: &str,
+    section: Option<&str>,
+    format: OutputFormat,
+) -> ! {
+    let document = format_document(path, command, section, format, false).unwrap_or_else(|e| {
+        exit_with_error(ErrorKind::Io, &format!("Could not open file: {}", e), None)
+    });
+    print!("{}", document);
+    process::exit(0);
+}
+
+/// Format the page at `path` in `format` (other than text), only with the
+/// examples of `section` if it is given, ending with a newline. JSON is
+/// written on a single line if `compact` is set.
+fn format_document(
+    path: &Path,
+    command: &str,
+    section: Option<&str>,
+    format: OutputFormat,
+    compact: bool,
+) -> io::Result<String> {
+    let mut page = Page::read(BufReader::new(File::open(path)?));
+    if let Some(section) = section {
+        page.select_section(section);
+    }
+    if format == OutputFormat::Man {
+        return Ok(man::to_roff(&page));
+    }
+    let document = Document::from_page(page, command, page_language(path));
+    let formatted = match format {
+        OutputFormat::PsObjectJson => serde_json::to_string(&PageObject::from(document)),
+        OutputFormat::Nuon => Ok(nushell::to_nuon(
+            &serde_json::to_value(&document).expect("The page can always be serialized"),
+        )),
+        _ if compact => serde_json::to_string(&document),
+        _ => serde_json::to_string_pretty(&document),
+    };
+    Ok(formatted.expect("The page can always be serialized") + "\n")
+}
+
+/// Show the pages of the commands listed in `list_path` (one per line, `-`
+/// for stdin) and exit.
+///
+/// Pages are printed as soon as their command is read, so that `tldr` can
+/// be used at the end of a pipeline. With `--output json`, every page is
+/// printed as a single line of JSON. Commands without a page are reported
+/// on stderr, the exit status is 1 if there are any.
+fn show_batch_and_exit(
+    cache: &Cache,
+    list_path: &str,
+    languages: &[String],
+    args: &Args,
+    config: &Config,
+    fill: &Fill,
+    enable_hyperlinks: bool,
+) -> ! {
+    let input: Box<dyn BufRead> = if list_path == "-" {
+        Box::new(BufReader::new(io::stdin()))
     } else {
-        // Create tokenizer and print output
-        let mut tokenizer = Tokenizer::new(reader);
-        print_lines(&mut tokenizer, &config);
+        let file = File::open(list_path).unwrap_or_else(|e| {
+            exit_with_error(
+                ErrorKind::Io,
+                &format!("Could not read {}: {}", list_path, e),
+                None,
+            )
+        });
+        Box::new(BufReader::new(file))
     };
 
-    Ok(())
+    let mut seen = HashSet::new();
+    let mut missing = Vec::new();
+    let mut shown = 0;
+    for line in input.lines() {
+        let line = line.unwrap_or_else(|e| {
+            exit_with_error(
+                ErrorKind::Io,
+                &format!("Could not read {}: {}", list_path, e),
+                None,
+            )
+        });
+        let command = match parse_command_line(&line) {
+            Some(command) => config.search.rewrite.apply(&command).into_owned(),
+            None => continue,
+        };
+        // Pipelines like the shell history repeat commands a lot
+        if !seen.insert(command.clone()) {
+            continue;
+        }
+        let path = validate_page_name(&command)
+            .ok()
+            .and_then(|()| cache.find_page(&command, languages));
+        let path = if let Some(found) = path {
+            found.path
+        } else {
+            if !args.flag_quiet && !args.flag_output.is_json() {
+                eprintln!("Page {} not found in cache", command);
+            }
+            missing.push(command);
+            continue;
+        };
+
+        if shown > 0 {
+            if let Some(ref separator) = args.flag_batch_separator {
+                println!("{}", separator);
+            }
+        }
+        shown += 1;
+        let result = if args.flag_output == OutputFormat::Text {
+            render_file(
+                &path,
+                args.render_mode(),
+                args.flag_section.as_deref(),
+                config,
+                fill,
+                enable_hyperlinks,
+            )
+            .and_then(|buffer| write_output(buffer, config, args.flag_quiet))
+        } else {
+            format_document(
+                &path,
+                &command,
+                args.flag_section.as_deref(),
+                args.flag_output,
+                true,
+            )
+            .map_err(|e| format!("Could not open file: {}", e))
+            .and_then(|document| write_output(document.into_bytes(), config, args.flag_quiet))
+        };
+        if let Err(msg) = result {
+            exit_with_error(ErrorKind::Render, &msg, None);
+        }
+    }
+
+    if missing.is_empty() {
+        process::exit(0);
+    }
+    if args.flag_output.is_json() {
+        exit_with_error(
+            ErrorKind::PageNotFound,
+            &format!("Pages not found in cache: {}", missing.join(", ")),
+            None,
+        );
+    }
+    process::exit(1);
+}
+
+/// Show the pages of several commands (e.g. `tldr tar git-log ssh`) and
+/// exit. In text, every page follows the `page_header` line, other formats
+/// are printed like with `--batch`.
+fn show_pages_and_exit(
+    pages: &[(String, PathBuf)],
+    args: &Args,
+    config: &Config,
+    fill: &Fill,
+    enable_hyperlinks: bool,
+) -> ! {
+    for (command, path) in pages {
+        let result = if args.flag_output == OutputFormat::Text {
+            let header = config.display.page_header.replace("{name}", command);
+            let mut buffer = format!("{}\n", config.style.command_name.paint(header)).into_bytes();
+            render_file(
+                path,
+                args.render_mode(),
+                args.flag_section.as_deref(),
+                config,
+                fill,
+                enable_hyperlinks,
+            )
+            .and_then(|page| {
+                buffer.extend(page);
+                write_output(buffer, config, args.flag_quiet)
+            })
+        } else {
+            format_document(
+                path,
+                command,
+                args.flag_section.as_deref(),
+                args.flag_output,
+                true,
+            )
+            .map_err(|e| format!("Could not open file: {}", e))
+            .and_then(|document| write_output(document.into_bytes(), config, args.flag_quiet))
+        };
+        if let Err(msg) = result {
+            exit_with_error(ErrorKind::Render, &msg, None);
+        }
+    }
+    process::exit(0);
+}
+
+/// Return the pages of the words of a lookup as separate commands (as in
+/// `tldr tar git-log ssh`), if there are several words and every word has a
+/// page.
+fn find_separate_pages(
+    cache: &Cache,
+    words: &[String],
+    languages: &[String],
+    config: &Config,
+) -> Option<Vec<(String, PathBuf)>> {
+    if words.len() < 2 {
+        return None;
+    }
+    words
+        .iter()
+        .map(|word| {
+            let command = page_name_from_words(&[word.clone()]);
+            let command = config.search.rewrite.apply(&command).into_owned();
+            validate_page_name(&command).ok()?;
+            let found = cache.find_page(&command, languages)?;
+            Some((command, found.path))
+        })
+        .collect()
+}
+
+/// Return the name and page of the closest parent command of a subcommand
+/// that has no page (as in `tldr git remote frobnicate`).
+///
+/// Only lookups of several words are resolved, and only for commands with
+/// pages for their subcommands, so that typos still end in a miss. If the
+/// page is available in other languages, it is not replaced either.
+fn find_parent_page(
+    cache: &Cache,
+    words: &[String],
+    command: &str,
+    languages: &[String],
+) -> Option<(String, FoundPage)> {
+    let mut words = words.iter().flat_map(|word| word.split_whitespace());
+    let root = words.next()?;
+    words.next()?;
+    let prefix = format!("{}-", root.to_lowercase());
+    if !command.to_lowercase().starts_with(&prefix)
+        || !cache.find_page_languages(command).is_empty()
+    {
+        return None;
+    }
+    let pages = cache.list_pages().ok()?;
+    if !pages
+        .iter()
+        .any(|page| page.to_lowercase().starts_with(&prefix))
+    {
+        return None;
+    }
+
+    let mut parent = parent_page_name(command);
+    while let Some(name) = parent {
+        if name.len() < root.len() {
+            break;
+        }
+        if let Some(found) = cache.find_page(name, languages) {
+            return Some((name.to_string(), found));
+        }
+        parent = parent_page_name(name);
+    }
+    None
+}
+
+/// Exit with an error listing the irregularities of the page at `path`, if
+/// it has any.
+fn check_page_strictly(path: &Path) {
+    let file = File::open(path).unwrap_or_else(|e| {
+        exit_with_error(ErrorKind::Io, &format!("Could not open file: {}", e), None)
+    });
+    let (_, diagnostics) = Page::read_with_diagnostics(BufReader::new(file));
+    if diagnostics.is_empty() {
+        return;
+    }
+    let mut message = format!("Page {} is irregular:", path.display());
+    for diagnostic in &diagnostics {
+        message.push_str("\n  ");
+        message.push_str(&diagnostic.to_string());
+    }
+    exit_with_error(ErrorKind::InvalidPage, &message, None);
+}
+
+/// Print where a page was found to stderr, for `--verbose`.
+fn print_page_origin(found: &FoundPage) {
+    let mut origin = vec![found.kind.to_string()];
+    if let Some(ref language) = found.language {
+        origin.push(format!("language {}", language));
+    }
+    if let Some(ref platform) = found.platform {
+        origin.push(format!("platform {}", platform));
+    }
+    if is_mappable(&found.path) {
+        origin.push("memory-mapped".to_string());
+    }
+    eprintln!("page: {} ({})", found.path.display(), origin.join(", "));
+}
+
+/// Exit with an error if the page at `path` has no section called `section`.
+fn check_section(path: &Path, section: &str) {
+    let file = File::open(path).unwrap_or_else(|e| {
+        exit_with_error(ErrorKind::Io, &format!("Could not open file: {}", e), None)
+    });
+    let page = Page::read(BufReader::new(file));
+    if page.clone().select_section(section) {
+        return;
+    }
+    let hint = if page.sections.is_empty() {
+        "The page has no sections.".to_string()
+    } else {
+        let names: Vec<&str> = page
+            .sections
+            .iter()
+            .map(|section| section.name.as_str())
+            .collect();
+        format!("Its sections are: {}.", names.join(", "))
+    };
+    exit_with_error(
+        ErrorKind::NoResults,
+        &format!("Page {} has no section \"{}\"", page.title, section),
+        Some(&hint),
+    );
+}
+
+/// Print the origin and license of the page for `command` at `path` and exit.
+fn print_attribution_and_exit(
+    path: &Path,
+    command: &str,
+    config: &Config,
+    format: OutputFormat,
+) -> ! {
+    let provenance = Provenance::of_page(path, config.directories.custom_pages_dir.as_deref());
+    let attribution = provenance.attribution(command);
+    match format {
+        OutputFormat::Json | OutputFormat::PsObjectJson | OutputFormat::Nuon => {
+            let mut json =
+                serde_json::to_value(&provenance).expect("The provenance can always be serialized");
+            json["command"] = command.into();
+            json["attribution"] = serde_json::json!(attribution);
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json)
+                    .expect("The provenance can always be serialized")
+            );
+        }
+        OutputFormat::Text | OutputFormat::Man | OutputFormat::Tsv => {
+            let unknown = || "[Unknown]".to_string();
+            let source = match provenance.origin {
+                Origin::TldrPages => format!(
+                    "{} ({})",
+                    provenance.project.unwrap_or_default(),
+                    provenance.project_url.unwrap_or_default()
+                ),
+                Origin::Custom => "Custom pages directory".into(),
+                Origin::Repository => "Pages repository".into(),
+                Origin::Local => "Local file".into(),
+            };
+            let rows = [
+                ("Source:", source),
+                (
+                    "License:",
+                    provenance.license.map_or_else(
+                        || "[Unknown, ask the author of the page]".into(),
+                        |license| format!("{} ({})", license.name, license.url),
+                    ),
+                ),
+                (
+                    "Upstream path:",
+                    provenance.upstream_path.unwrap_or_else(unknown),
+                ),
+                (
+                    "Downloaded from:",
+                    provenance.source_url.unwrap_or_else(unknown),
+                ),
+                ("File:", provenance.path.display().to_string()),
+            ];
+            let label_width = rows
+                .iter()
+                .map(|(label, _)| width::display_width(label))
+                .max()
+                .unwrap_or(0);
+            for (label, value) in &rows {
+                println!("{} {}", width::pad(label, label_width), value);
+            }
+            if let Some(attribution) = attribution {
+                println!("\n{}", attribution);
+            }
+        }
+    }
+    process::exit(0);
+}
+
+/// Compare the cached page for `command` at `path` with its current upstream
+/// version and exit, with exit code 1 if the cached page is behind.
+fn check_upstream_and_exit(
+    path: &Path,
+    command: &str,
+    config: &Config,
+    format: OutputFormat,
+    enable_styles: bool,
+) -> ! {
+    let provenance = Provenance::of_page(path, config.directories.custom_pages_dir.as_deref());
+    let comparison = upstream::compare(
+        command,
+        path,
+        &provenance,
+        &config.updates.upstream_pages_url,
+        &config.updates.http,
+    )
+    .unwrap_or_else(|e| exit_with("Could not check the upstream version", &e));
+    match format {
+        OutputFormat::Json | OutputFormat::PsObjectJson | OutputFormat::Nuon => println!(
+            "{}",
+            serde_json::to_string_pretty(&comparison)
+                .expect("The comparison can always be serialized")
+        ),
+        OutputFormat::Text | OutputFormat::Man | OutputFormat::Tsv => match comparison.status {
+            UpstreamStatus::UpToDate => {
+                println!("The cached page {} is up to date.", command);
+            }
+            UpstreamStatus::RemovedUpstream => {
+                println!(
+                    "The page {} was removed upstream ({} does not exist).",
+                    command, comparison.upstream_url
+                );
+            }
+            UpstreamStatus::Outdated => {
+                println!(
+                    "The cached page {} differs from the upstream version at {}:\n",
+                    command, comparison.upstream_url
+                );
+                let style = |color: Color| {
+                    if enable_styles {
+                        config.display.style_profile.resolve(Style::new().fg(color))
+                    } else {
+                        Style::new()
+                    }
+                };
+                for line in &comparison.diff {
+                    match line {
+                        DiffLine::Unchanged(line) => println!("  {}", line),
+                        DiffLine::Removed(line) => {
+                            println!("{}", style(Color::Red).paint(format!("- {}", line)));
+                        }
+                        DiffLine::Added(line) => {
+                            println!("{}", style(Color::Green).paint(format!("+ {}", line)));
+                        }
+                    }
+                }
+                println!("\nRun `tldr --update` to update the cache.");
+            }
+        },
+    }
+    if comparison.status == UpstreamStatus::UpToDate {
+        process::exit(0);
+    }
+    process::exit(1);
+}
+
+/// Interactively run an example of the page at `path` and exit with its exit
+/// code.
+fn run_example_and_exit(path: &Path, config: &Config, mut fill: Fill, non_interactive: bool) -> ! {
+    if !config.run.allow_run {
+        exit_with_error(
+            ErrorKind::Run,
+            "Running examples is disabled.",
+            Some(
+                "Set `allow_run = true` in the `[run]` section of the config file to \
+                 enable --run.",
+            ),
+        );
+    }
+    if non_interactive {
+        // Choosing an example and confirming it have no sensible default
+        exit_with_error(
+            ErrorKind::Run,
+            "Running examples needs confirmation, which is not possible in \
+             non-interactive mode.",
+            Some("Copy the command from the page instead, e.g. with `tldr --fill ... <command>`."),
+        );
+    }
+    match run_example(path, &mut fill, config.run.history_defaults) {
+        Ok(Some(code)) => process::exit(code),
+        Ok(None) => process::exit(1),
+        Err(msg) => exit_with_error(ErrorKind::Run, &msg, None),
+    }
+}
+
+/// Print search results, one page per line.
+fn print_search_results(results: &[SearchResult]) {
+    for result in results {
+        match result.description {
+            Some(ref description) => println!("{} - {}", result.name, description),
+            None => println!("{}", result.name),
+        }
+    }
+}
+
+/// Search the cache, exit if the search fails.
+fn search_or_exit(cache: &Cache, term: &str, languages: &[String]) -> Vec<SearchResult> {
+    search_pages(cache, term, languages).unwrap_or_else(|e| exit_with("Could not search pages", &e))
+}
+
+/// Print the names of up to `count` pages with names similar to `command`.
+fn print_suggestions(cache: &Cache, command: &str, count: usize) {
+    let pages = cache
+        .list_pages()
+        .unwrap_or_else(|e| exit_with("Could not get list of pages", &e));
+    let names = fuzzy::suggest(command, &pages, count);
+    if !names.is_empty() {
+        println!();
+        println!("Did you mean: {}?", names.join(", "));
+    }
+}
+
+/// Handle a failed page lookup according to the `on_miss` config option.
+///
+/// `other_languages` are the languages in which the page is available even
+/// though it is missing in the requested ones. `suggestions` overrides the
+/// number of suggested pages (`--suggest`), even if `on_miss` is not
+/// `suggest`.
+fn handle_miss(
+    cache: &Cache,
+    command: &str,
+    languages: &[String],
+    other_languages: &[String],
+    config: &Config,
+    suggestions: Option<usize>,
+) {
+    println!("Page {} not found in cache", command);
+    if other_languages.is_empty() {
+        println!("Try updating with `tldr --update`, or submit a pull request to:");
+        println!("https://github.com/tldr-pages/tldr");
+    } else {
+        println!(
+            "The page is available in other languages: {}",
+            other_languages.join(", ")
+        );
+        println!("Use `tldr --language <lang> {}` to show it.", command);
+    }
+
+    if let Some(count) = suggestions {
+        print_suggestions(cache, command, count);
+        return;
+    }
+    match config.search.on_miss {
+        OnMiss::Error => {}
+        OnMiss::Suggest => print_suggestions(cache, command, config.search.suggestions),
+        OnMiss::Search => {
+            let results = search_or_exit(cache, command, languages);
+            if !results.is_empty() {
+                println!();
+                println!("Search results for {}:", command);
+                print_search_results(&results[..results.len().min(MISS_SEARCH_RESULTS)]);
+            }
+        }
+    }
+}
+
+/// Report a failed page lookup as an error and exit.
+fn exit_with_miss(command: &str, other_languages: &[String]) -> ! {
+    let message = format!("Page {} not found in cache", command);
+    let hint = if other_languages.is_empty() {
+        String::from("Try updating with `tldr --update`.")
+    } else {
+        format!(
+            "The page is available in other languages: {}. Use `tldr --language <lang> {}` \
+             to show it.",
+            other_languages.join(", "),
+            command
+        )
+    };
+    exit_with_error(ErrorKind::PageNotFound, &message, Some(&hint))
 }
 
 /// Set up display pager
@@ -117,87 +1097,1239 @@ fn should_update_cache(args: &Args, config: &Config) -> bool {
             && Cache::last_update().map_or(true, |ago| ago >= config.updates.auto_update_interval))
 }
 
+/// Return whether to use styles for the output written to `stream`.
+fn should_use_styles(color: ColorOptions, stream: Stream) -> bool {
+    #[cfg(target_os = "windows")]
+    let ansi_support = ansi_term::enable_ansi_support().is_ok();
+    #[cfg(not(target_os = "windows"))]
+    let ansi_support = true;
+
+    match color {
+        // Attempt to use styling if instructed, even if the output is
+        // redirected to a file (e.g. for `less -R`)
+        ColorOptions::Always => true,
+        // Enable styling if:
+        // * There is `ansi_support`
+        // * NO_COLOR env var isn't set: https://no-color.org/
+        // * The output stream is a terminal (not being piped)
+        ColorOptions::Auto => ansi_support && env::var_os("NO_COLOR").is_none() && atty::is(stream),
+        // Disable styling
+        ColorOptions::Never => false,
+    }
+}
+
+/// Return whether links in pages can be written as OSC 8 hyperlinks.
+///
+/// Pagers and dumb terminals would show the escape sequences verbatim.
+fn should_use_hyperlinks(enable_styles: bool, use_pager: bool) -> bool {
+    enable_styles
+        && !use_pager
+        && atty::is(Stream::Stdout)
+        && env::var("TERM").map_or(true, |term| term != "dumb")
+}
+
+/// List the pages in `platform` and `language`, with the platform and
+/// language of every page if `long` is set, and exit.
+///
+/// The JSON and TSV output always contain the platform, language and path
+/// of every page.
+fn list_page_locations_and_exit(
+    cache: &Cache,
+    platform: Option<&str>,
+    language: Option<&str>,
+    long: bool,
+    format: OutputFormat,
+) -> ! {
+    let pages = cache
+        .list_page_locations(platform, language)
+        .unwrap_or_else(|e| exit_with("Could not get list of pages", &e));
+    if format.is_json() {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&pages).expect("The pages can always be serialized")
+        );
+    } else if format == OutputFormat::Tsv {
+        for page in &pages {
+            println!(
+                "{}\t{}\t{}\t{}",
+                page.name,
+                page.platform,
+                page.language.as_deref().unwrap_or(""),
+                page.path
+                    .as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default()
+            );
+        }
+    } else if long {
+        let width = pages.iter().map(|page| page.name.len()).max().unwrap_or(0);
+        for page in &pages {
+            let line = format!(
+                "{:width$}  {:8}  {}",
+                page.name,
+                page.platform,
+                page.language.as_deref().unwrap_or("-"),
+                width = width
+            );
+            println!("{}", line.trim_end());
+        }
+    } else {
+        let mut names: Vec<&str> = pages.iter().map(|page| page.name.as_str()).collect();
+        names.dedup();
+        for name in names {
+            println!("{}", name);
+        }
+    }
+    process::exit(0);
+}
+
+/// Return the style of warnings. Only use color if enabled.
+fn warning_style(config: &Config, enable_styles: bool) -> Style {
+    if enable_styles {
+        config
+            .display
+            .style_profile
+            .resolve(Style::new().fg(Color::Yellow))
+    } else {
+        Style::default()
+    }
+}
+
 /// Check the cache for freshness
-fn check_cache(args: &Args, enable_styles: bool) {
+fn check_cache(args: &Args, config: &Config, enable_styles: bool) {
     match Cache::last_update() {
         Some(ago) if ago > MAX_CACHE_AGE => {
             if args.flag_quiet {
                 return;
             }
 
-            // Only use color if enabled
-            let warning_style = if enable_styles {
-                Style::new().fg(Color::Yellow)
-            } else {
-                Style::default()
-            };
-
             eprintln!(
                 "{}",
-                warning_style.paint(format!(
-                    "The cache hasn't been updated for more than {} days.\n\
+                warning_style(config, enable_styles).paint(format!(
+                    "The cache hasn't been updated for more than {}.\n\
                          You should probably run `tldr --update` soon.",
-                    MAX_CACHE_AGE.as_secs() / 24 / 3600
+                    human::duration(MAX_CACHE_AGE)
                 ))
             );
         }
         Some(_) => {}
-        None => {
-            eprintln!("Cache not found. Please run `tldr --update`.");
-            process::exit(1);
+        None => exit_with("", &cache_not_found()),
+    };
+}
+
+/// Warn if the configured custom pages directory does not exist, because no
+/// custom page will be found in that case.
+fn check_custom_pages_dir(config: &Config, quietly: bool, enable_styles: bool) {
+    if quietly {
+        return;
+    }
+    if let Some(dir) = config.custom_pages_dir_missing() {
+        eprintln!(
+            "{}",
+            warning_style(config, enable_styles).paint(format!(
+                "Warning: The custom pages directory {} does not exist.\n\
+                 Create it, or set `create_custom_pages_dir = true` in the \
+                 `[directories]` section of the config file.",
+                dir.display()
+            ))
+        );
+    }
+}
+
+/// Serve the cache over HTTP at `address` until the process is stopped.
+#[cfg(feature = "share")]
+fn share_cache_and_exit(cache: &Cache, address: &str, token: Option<String>, quietly: bool) -> ! {
+    use crate::share::{self, Share};
+    use std::net::TcpListener;
+
+    let has_cache = Cache::get_cache_dir()
+        .and_then(|(cache_dir, _)| CacheInfo::load(&cache_dir))
+        .unwrap_or_else(|e| exit_with("Could not share the cache", &e));
+    if has_cache.is_none() {
+        exit_with("Could not share the cache", &cache_not_found());
+    }
+    let listener = TcpListener::bind(address).unwrap_or_else(|e| {
+        exit_with_error(
+            ErrorKind::Io,
+            &format!("Could not listen on {}: {}", address, e),
+            Some("Choose another address, e.g. `tldr --share-cache 0.0.0.0:8080`."),
+        )
+    });
+    let local_address = listener
+        .local_addr()
+        .map_or_else(|_| address.to_string(), |address| address.to_string());
+    println!(
+        "Sharing the cache at http://{}{}, press Ctrl+C to stop.",
+        local_address,
+        share::ARCHIVE_PATH
+    );
+    let _ = io::stdout().flush();
+    Share::new(cache)
+        .with_token(token)
+        .serve(&listener, quietly);
+    process::exit(0);
+}
+
+#[cfg(not(feature = "share"))]
+fn share_cache_and_exit(
+    _cache: &Cache,
+    _address: &str,
+    _token: Option<String>,
+    _quietly: bool,
+) -> ! {
+    exit_with_error(
+        ErrorKind::Usage,
+        "tealdeer was built without support for sharing the cache (the `share` feature).",
+        None,
+    )
+}
+
+/// Show statistics about the cache and exit.
+fn show_stats_and_exit(format: OutputFormat) -> ! {
+    let stats =
+        Cache::stats().unwrap_or_else(|e| exit_with("Could not collect cache statistics", &e));
+
+    match format {
+        OutputFormat::Json | OutputFormat::PsObjectJson | OutputFormat::Nuon => {
+            let stats = serde_json::json!({
+                "last_update": stats.last_update.map(human::iso8601),
+                "source_url": stats.source_url,
+                "release_tag": stats.release_tag,
+                "pages": stats.pages,
+                "languages": stats.languages.len(),
+                "size_bytes": stats.size,
+            });
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&stats).expect("The stats can always be serialized")
+            );
+        }
+        OutputFormat::Text | OutputFormat::Man | OutputFormat::Tsv => {
+            let numbers = NumberFormat::from_env();
+            let mut rows = vec![
+                ("Last update:", format_last_update(stats.last_update)),
+                (
+                    "Source:",
+                    stats.source_url.unwrap_or_else(|| "[Unknown]".into()),
+                ),
+            ];
+            if let Some(release_tag) = stats.release_tag {
+                rows.push(("Release:", release_tag));
+            }
+            rows.push(("Pages:", numbers.count(stats.pages)));
+            rows.push(("Languages:", numbers.count(stats.languages.len() as u64)));
+            rows.push(("Size:", numbers.size(stats.size)));
+            let label_width = rows
+                .iter()
+                .map(|(label, _)| width::display_width(label))
+                .max()
+                .unwrap_or(0);
+            for (label, value) in &rows {
+                println!("{} {}", width::pad(label, label_width), value);
+            }
+        }
+    }
+    process::exit(0);
+}
+
+/// Format the time of the last update with its age, e.g.
+/// `2024-03-01 (3 days ago)`.
+fn format_last_update(last_update: Option<u64>) -> String {
+    last_update.map_or_else(
+        || "[Unknown]".to_string(),
+        |timestamp| {
+            let age = SystemTime::now()
+                .duration_since(UNIX_EPOCH + Duration::from_secs(timestamp))
+                .unwrap_or_default();
+            format!("{} ({} ago)", human::date(timestamp), human::duration(age))
+        },
+    )
+}
+
+/// Print where the cache is, what it contains and where its pages come from,
+/// for bug reports, and exit.
+///
+/// Unlike `--stats`, this also works without a cache.
+fn show_info_and_exit(config: &Config, format: OutputFormat) -> ! {
+    let cache_dir = Cache::get_cache_dir();
+    let stats = Cache::stats();
+    let archive_urls = match config.updates.pages_source {
+        PagesSource::Archive { url: Some(ref url) } => vec![url.clone()],
+        PagesSource::Archive { url: None } if config.updates.archive_urls.is_empty() => {
+            vec![ARCHIVE_URL.to_string()]
         }
+        PagesSource::Archive { url: None } => config.updates.archive_urls.clone(),
+        PagesSource::Directory { .. } | PagesSource::Git { .. } => vec![],
     };
+    let pages_repos: Vec<String> = repos::registered()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|repo| repo.url)
+        .collect();
+
+    match format {
+        OutputFormat::Json | OutputFormat::PsObjectJson | OutputFormat::Nuon => {
+            let (cache_dir, cache_dir_source) = match cache_dir {
+                Ok((ref path, ref source)) => (Some(path.clone()), Some(source.to_string())),
+                Err(_) => (None, None),
+            };
+            let stats = stats.ok();
+            let info = serde_json::json!({
+                "cache_dir": cache_dir,
+                "cache_dir_source": cache_dir_source,
+                "last_update": stats.as_ref().and_then(|stats| stats.last_update).map(human::iso8601),
+                "source_url": stats.as_ref().and_then(|stats| stats.source_url.clone()),
+                "release_tag": stats.as_ref().and_then(|stats| stats.release_tag.clone()),
+                "size_bytes": stats.as_ref().map(|stats| stats.size),
+                "pages": stats.as_ref().map(|stats| stats.pages),
+                "languages": stats.as_ref().map(|stats| &stats.languages),
+                "platforms": stats.as_ref().map(|stats| &stats.platforms),
+                "sources": {
+                    "pages_source": config.updates.pages_source,
+                    "archive_urls": archive_urls,
+                    "extra_pages_sources": config.updates.extra_pages_sources,
+                    "pages_repos": pages_repos,
+                    "custom_pages_dir": config.directories.custom_pages_dir,
+                },
+            });
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&info).expect("The info can always be serialized")
+            );
+        }
+        OutputFormat::Text | OutputFormat::Man | OutputFormat::Tsv => {
+            let numbers = NumberFormat::from_env();
+            let mut rows = vec![(
+                "Cache dir:".to_string(),
+                match cache_dir {
+                    Ok((path, source)) => format!("{} ({})", path.display(), source),
+                    Err(ref e) => format!("[Error: {}]", e.message()),
+                },
+            )];
+            match stats {
+                Ok(stats) => {
+                    rows.push(("Size:".into(), numbers.size(stats.size)));
+                    rows.push(("Last update:".into(), format_last_update(stats.last_update)));
+                    rows.push((
+                        "Updated from:".into(),
+                        stats.source_url.unwrap_or_else(|| "[Unknown]".into()),
+                    ));
+                    if let Some(release_tag) = stats.release_tag {
+                        rows.push(("Release:".into(), release_tag));
+                    }
+                    rows.push(("Pages:".into(), numbers.count(stats.pages)));
+                    rows.push(("Languages:".into(), String::new()));
+                    for (language, count) in &stats.languages {
+                        rows.push((format!("  {}", language), numbers.count(*count)));
+                    }
+                    rows.push(("Platforms:".into(), String::new()));
+                    for (platform, count) in &stats.platforms {
+                        rows.push((format!("  {}", platform), numbers.count(*count)));
+                    }
+                }
+                Err(ref e) => rows.push(("Pages:".into(), format!("[Error: {}]", e.message()))),
+            }
+            rows.push(("Sources:".into(), String::new()));
+            rows.push(("  Pages:".into(), config.updates.pages_source.to_string()));
+            for url in archive_urls {
+                rows.push(("  Mirror:".into(), url));
+            }
+            for source in &config.updates.extra_pages_sources {
+                rows.push(("  Extra:".into(), source.to_string()));
+            }
+            for url in pages_repos {
+                rows.push(("  Repository:".into(), url));
+            }
+            rows.push((
+                "  Custom pages:".into(),
+                config
+                    .directories
+                    .custom_pages_dir
+                    .as_ref()
+                    .map_or_else(|| "[Not set]".into(), |path| path.display().to_string()),
+            ));
+
+            let label_width = rows
+                .iter()
+                .map(|(label, _)| width::display_width(label))
+                .max()
+                .unwrap_or(0);
+            for (label, value) in &rows {
+                if value.is_empty() {
+                    println!("{}", label);
+                } else {
+                    println!("{} {}", width::pad(label, label_width), value);
+                }
+            }
+        }
+    }
+    process::exit(0);
 }
 
-/// Clear the cache
-fn clear_cache(quietly: bool) {
-    Cache::clear().unwrap_or_else(|e| {
-        match e {
-            CacheError(msg) | ConfigError(msg) | UpdateError(msg) => {
-                eprintln!("Could not delete cache: {}", msg)
+/// Show the last `count` recorded update attempts and exit. `recording`
+/// tells whether new attempts are recorded.
+fn show_update_history_and_exit(count: usize, recording: bool, format: OutputFormat) -> ! {
+    let attempts = update_log::recent(count)
+        .unwrap_or_else(|e| exit_with("Could not read the update history", &e));
+
+    match format {
+        OutputFormat::Json | OutputFormat::PsObjectJson | OutputFormat::Nuon => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&attempts)
+                    .expect("Update attempts can always be serialized")
+            );
+        }
+        OutputFormat::Text | OutputFormat::Man | OutputFormat::Tsv => {
+            if attempts.is_empty() {
+                println!("No updates were recorded.");
+                if !recording {
+                    println!("Set `record_update_history = true` in the [updates] section of the config to record them.");
+                }
             }
-        };
+            let numbers = NumberFormat::from_env();
+            for attempt in &attempts {
+                let duration = format!(
+                    "{}.{} s",
+                    attempt.duration_ms / 1000,
+                    attempt.duration_ms % 1000 / 100
+                );
+                let details = match (&attempt.error, &attempt.url) {
+                    (Some(error), _) => error.clone(),
+                    (None, Some(url)) => match attempt.bytes {
+                        Some(bytes) => format!("{} ({})", url, numbers.size(bytes)),
+                        None => url.clone(),
+                    },
+                    (None, None) => String::new(),
+                };
+                println!(
+                    "{}  {:<6}  {:>7}  {}{}",
+                    attempt.started,
+                    if attempt.succeeded() { "ok" } else { "failed" },
+                    duration,
+                    details,
+                    if attempt.automatic {
+                        " (automatic)"
+                    } else {
+                        ""
+                    }
+                );
+                for mirror in &attempt.failed_mirrors {
+                    println!("  {} failed: {}", mirror.url, mirror.error);
+                }
+            }
+        }
+    }
+    process::exit(0);
+}
+
+/// Report which commands of the list at `list_path` have pages and exit.
+///
+/// Exits with 1 if any command has no page.
+fn check_coverage_and_exit(
+    cache: &Cache,
+    list_path: &str,
+    languages: &[String],
+    config: &Config,
+    format: OutputFormat,
+) -> ! {
+    let list = if list_path == "-" {
+        let mut list = String::new();
+        io::stdin().read_to_string(&mut list).map(|_| list)
+    } else {
+        fs::read_to_string(list_path)
+    };
+    let list = list.unwrap_or_else(|e| {
+        exit_with_error(
+            ErrorKind::Io,
+            &format!("Could not read {}: {}", list_path, e),
+            None,
+        )
+    });
+    let commands = parse_command_list(&list);
+    let coverage = check_coverage(cache, &commands, languages, &config.search.rewrite);
+
+    match format {
+        OutputFormat::Json | OutputFormat::PsObjectJson | OutputFormat::Nuon => println!(
+            "{}",
+            serde_json::to_string_pretty(&coverage).expect("The coverage can always be serialized")
+        ),
+        OutputFormat::Text | OutputFormat::Man | OutputFormat::Tsv => {
+            for covered in &coverage.covered {
+                println!("+ {} ({})", covered.command, covered.platforms.join(", "));
+            }
+            for command in &coverage.missing {
+                println!("- {}", command);
+            }
+            println!(
+                "{} of {} commands have pages.",
+                coverage.covered.len(),
+                coverage.len()
+            );
+        }
+    }
+    if !coverage.missing.is_empty() {
         process::exit(1);
+    }
+    process::exit(0);
+}
+
+/// Render all pages below `dir` repeatedly, print the throughput and the
+/// allocations and exit. Fails if they regressed compared to the report at
+/// `baseline_path`.
+fn bench_render_and_exit(
+    dir: &str,
+    baseline_path: Option<&str>,
+    config: &Config,
+    format: OutputFormat,
+) -> ! {
+    let baseline: Option<bench::Report> = baseline_path.map(|path| {
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| serde_json::from_str(&contents).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                exit_with_error(
+                    ErrorKind::Io,
+                    &format!("Could not read baseline {}: {}", path, e),
+                    Some("Create it with `tldr --bench-render <dir> --output json`."),
+                )
+            })
     });
-    if !quietly {
-        println!("Successfully deleted cache.");
+    let paths = bench::find_pages(Path::new(dir));
+    if paths.is_empty() {
+        exit_with_error(ErrorKind::Io, &format!("No pages found in {}", dir), None);
     }
+    let pages =
+        bench::read_pages(&paths).unwrap_or_else(|msg| exit_with_error(ErrorKind::Io, &msg, None));
+
+    let fill = Fill::new(Shell::Bash);
+    let report = bench::run(&pages, |page| {
+        let mut buffer = Vec::new();
+        render_page(
+            page,
+            RenderMode::Formatted,
+            None,
+            config,
+            &fill,
+            false,
+            &mut buffer,
+        )
+        .expect("Rendering pages from memory never fails");
+    });
+
+    match format {
+        OutputFormat::Json | OutputFormat::PsObjectJson | OutputFormat::Nuon => println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("The report can always be serialized")
+        ),
+        OutputFormat::Text | OutputFormat::Man | OutputFormat::Tsv => {
+            let compared = |value, baseline_value| match baseline {
+                Some(_) => format!(
+                    " ({:+.1}% compared to the baseline)",
+                    bench::change(value, baseline_value) * 100.0
+                ),
+                None => String::new(),
+            };
+            let number_format = NumberFormat::from_env();
+            println!(
+                "Rendered {} pages {} times in {:.2} s.",
+                number_format.count(report.pages as u64),
+                number_format.count(report.passes as u64),
+                report.seconds
+            );
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let (pages_per_second, bytes_per_page) = (
+                report.pages_per_second.round() as u64,
+                report.bytes_per_page.round() as u64,
+            );
+            println!(
+                "Throughput:  {} pages/s{}",
+                number_format.count(pages_per_second),
+                compared(
+                    report.pages_per_second,
+                    baseline.as_ref().map_or(0.0, |b| b.pages_per_second)
+                )
+            );
+            println!(
+                "Allocations: {:.1} per page ({} per page){}",
+                report.allocations_per_page,
+                number_format.size(bytes_per_page),
+                compared(
+                    report.allocations_per_page,
+                    baseline.as_ref().map_or(0.0, |b| b.allocations_per_page)
+                )
+            );
+        }
+    }
+
+    if let Some(ref baseline) = baseline {
+        let regressions = bench::regressions(&report, baseline);
+        for regression in &regressions {
+            eprintln!("Regression: {}", regression);
+        }
+        if !regressions.is_empty() {
+            process::exit(1);
+        }
+    }
+    process::exit(0);
 }
 
-/// Update the cache
-fn update_cache(cache: &Cache, quietly: bool) {
-    cache.update().unwrap_or_else(|e| {
-        match e {
-            CacheError(msg) | ConfigError(msg) | UpdateError(msg) => {
-                eprintln!("Could not update cache: {}", msg)
+/// Print the pages of the tools used in `workspace` that are in the cache,
+/// with their descriptions. Returns whether any page was printed.
+fn print_workspace_pages(cache: &Cache, workspace: &Workspace, languages: &[String]) -> bool {
+    let mut sections = Vec::new();
+    for (project_type, marker) in &workspace.types {
+        let pages: Vec<(&str, String)> = project_type
+            .pages
+            .iter()
+            .filter_map(|&name| {
+                let (page, _) = cache.read_page(name, languages)?;
+                let description = Page::read(&page[..])
+                    .description
+                    .into_iter()
+                    .next()
+                    .unwrap_or_default();
+                Some((name, description))
+            })
+            .collect();
+        if !pages.is_empty() {
+            sections.push((project_type.name, marker, pages));
+        }
+    }
+    if sections.is_empty() {
+        return false;
+    }
+
+    for (name, marker, pages) in sections {
+        println!("{} project ({}):", name, marker);
+        let width = pages
+            .iter()
+            .map(|(page, _)| width::display_width(page))
+            .max()
+            .unwrap_or(0);
+        for (page, description) in pages {
+            println!("  {}  {}", width::pad(page, width), description);
+        }
+        println!();
+    }
+    println!("Run `tldr <command>` to show a page, or `tldr --help` for all options.");
+    true
+}
+
+/// Print the description and the first example of the page for `name` and
+/// exit. If there is no page, exit with code 1 without printing anything.
+fn show_brief_page_and_exit(
+    cache: &Cache,
+    name: &str,
+    languages: &[String],
+    config: &Config,
+    hyperlinks: bool,
+) -> ! {
+    let (page, language) = match cache.read_page(name, languages) {
+        Some(found) => found,
+        None => process::exit(1),
+    };
+    let mut config = language
+        .map_or(config, |language| config.for_language(&language))
+        .clone();
+    config.display.compact = true;
+    let brief =
+        command_not_found::brief_page(Page::read(&page[..])).unwrap_or_else(|| process::exit(1));
+
+    let mut buffer = Vec::new();
+    formatter::print_page(
+        &brief,
+        &config,
+        &Fill::new(Shell::Bash),
+        hyperlinks,
+        &mut buffer,
+    )
+    .unwrap_or_else(|e| {
+        exit_with_error(
+            ErrorKind::Render,
+            &format!("Could not render page: {}", e),
+            None,
+        )
+    });
+    let _ = writeln!(buffer, "  Run `tldr {}` for more examples.", name);
+    if let Err(msg) = write_output(buffer, &config, true) {
+        exit_with_error(ErrorKind::Render, &msg, None);
+    }
+    process::exit(0);
+}
+
+/// Print the command-not-found hook for `shell` and exit.
+fn print_shell_hook_and_exit(shell: Shell) -> ! {
+    match command_not_found::shell_hook(shell) {
+        Some(hook) => print!("{}", hook),
+        None => exit_with_error(
+            ErrorKind::Usage,
+            "cmd does not support command-not-found hooks",
+            Some("Use `--shell` to choose another shell."),
+        ),
+    }
+    process::exit(0);
+}
+
+/// Print the translated pages of `language` that appear outdated and exit.
+fn show_translation_status_and_exit(cache: &Cache, language: &str, format: OutputFormat) -> ! {
+    if language == "en" {
+        exit_with_error(
+            ErrorKind::Usage,
+            "English is the language that translations are compared with",
+            Some("Choose the language of a translation, e.g. `--translation-status de`."),
+        );
+    }
+    let status = translation_status(cache, language)
+        .unwrap_or_else(|e| exit_with("Could not check the translation", &e));
+    if status.translated_pages == 0 {
+        exit_with_error(
+            ErrorKind::NoResults,
+            &format!("No pages translated into {} found in cache", language),
+            None,
+        );
+    }
+
+    match format {
+        OutputFormat::Json | OutputFormat::PsObjectJson | OutputFormat::Nuon => println!(
+            "{}",
+            serde_json::to_string_pretty(&status)
+                .expect("The translation status can always be serialized")
+        ),
+        OutputFormat::Text | OutputFormat::Man | OutputFormat::Tsv => {
+            for outdated in &status.outdated {
+                match outdated.staleness {
+                    Staleness::ExampleCount {
+                        english,
+                        translated,
+                    } => println!(
+                        "{}: {} examples in en, {} in {}",
+                        outdated.page, english, translated, language
+                    ),
+                    Staleness::Command {
+                        example,
+                        ref english,
+                        ref translated,
+                    } => {
+                        println!(
+                            "{}: the command of example {} differs",
+                            outdated.page, example
+                        );
+                        println!("  en: {}", english);
+                        println!("  {}: {}", language, translated);
+                    }
+                }
             }
-        };
+            println!(
+                "{} of {} translated pages appear outdated ({} of {} pages are translated).",
+                status.outdated.len(),
+                status.translated_pages,
+                status.translated_pages,
+                status.english_pages
+            );
+        }
+    }
+    if !status.outdated.is_empty() {
         process::exit(1);
+    }
+    process::exit(0);
+}
+
+/// Compare the manifest at `old_manifest_path` to the manifest of the cache,
+/// print the differences and exit
+fn show_cache_diff_and_exit(old_manifest_path: &str, format: OutputFormat) {
+    let diff = Cache::get_cache_dir().and_then(|(cache_dir, _)| {
+        if CacheInfo::load(&cache_dir)?.is_none() {
+            return Err(cache_not_found());
+        }
+        let new = parse_manifest(&CacheInfo::read_manifest(&cache_dir)?)?;
+        let old = fs::read_to_string(old_manifest_path)
+            .map_err(|e| CacheError(format!("Could not read {}: {}", old_manifest_path, e)))?;
+        Ok(ManifestDiff::new(&parse_manifest(&old)?, &new))
     });
+    let diff = diff.unwrap_or_else(|e| exit_with("Could not compare manifests", &e));
+
+    match format {
+        OutputFormat::Json | OutputFormat::PsObjectJson | OutputFormat::Nuon => println!(
+            "{}",
+            serde_json::to_string_pretty(&diff).expect("The diff can always be serialized")
+        ),
+        OutputFormat::Text | OutputFormat::Man | OutputFormat::Tsv => {
+            for path in &diff.added {
+                println!("+ {}", path);
+            }
+            for path in &diff.removed {
+                println!("- {}", path);
+            }
+            for path in &diff.changed {
+                println!("~ {}", path);
+            }
+            if diff.is_empty() {
+                println!("No pages changed.");
+                process::exit(0);
+            }
+            println!(
+                "{} added, {} removed, {} changed",
+                diff.added.len(),
+                diff.removed.len(),
+                diff.changed.len()
+            );
+        }
+    }
+    process::exit(0);
+}
+
+/// Clear the cache
+fn clear_cache(quietly: bool, to_trash: bool) {
+    let outcome =
+        Cache::clear(to_trash).unwrap_or_else(|e| exit_with("Could not delete cache", &e));
+    if !quietly {
+        match outcome {
+            ClearOutcome::Deleted => println!("Successfully deleted cache."),
+            #[cfg(feature = "trash")]
+            ClearOutcome::Trashed => println!("Successfully moved cache to the trash."),
+            ClearOutcome::BackedUp(dir) => {
+                println!("Successfully moved cache to {}.", dir.display());
+            }
+        }
+    }
+}
+
+/// Remove the pages unpacked from a zip or `SQLite` cache
+fn clean_aux(quietly: bool) {
+    let eviction =
+        Cache::clean_aux().unwrap_or_else(|e| exit_with("Could not clean auxiliary caches", &e));
+    if !quietly {
+        println!(
+            "Removed {} unpacked pages ({}).",
+            eviction.files,
+            NumberFormat::from_env().size(eviction.bytes)
+        );
+    }
+}
+
+/// Update the cache in a detached background process, so that the page can
+/// be shown from the outdated cache right away. Nothing happens if another
+/// process is updating the cache already.
+fn spawn_background_update(args: &Args) {
+    let cache_dir = match Cache::get_cache_dir() {
+        Ok((cache_dir, _)) => cache_dir,
+        Err(_) => return,
+    };
+    match CacheLock::try_lock(&cache_dir) {
+        // The background process takes the lock again
+        Ok(Some(lock)) => drop(lock),
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("Warning: Could not lock the cache: {}", e);
+            return;
+        }
+    }
+
+    let exe = match env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!("Warning: Could not start a background update: {}", e);
+            return;
+        }
+    };
+    let mut command = Command::new(exe);
+    command
+        .args(&["--update", "--quiet"])
+        .env(BACKGROUND_UPDATE_ENV_VAR, "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(profile) = get_profile(args) {
+        command.env("TEALDEER_PROFILE", profile);
+    }
+    detach(&mut command);
+    if let Err(e) = command.spawn() {
+        eprintln!("Warning: Could not start a background update: {}", e);
+    }
+}
+
+/// Exit after a page was shown, starting the automatic update that was
+/// deferred until then (see `auto_update_after_lookup`).
+fn finish_lookup(args: &Args, update_after_lookup: bool) -> ! {
+    start_deferred_update(args, update_after_lookup);
+    process::exit(0);
+}
+
+/// Start the automatic update that was deferred until the end of a lookup,
+/// if there is one.
+fn start_deferred_update(args: &Args, update_after_lookup: bool) {
+    if update_after_lookup {
+        // The whole page reaches the terminal (or the pager) before the
+        // update starts. The update process doesn't inherit stdout, so a
+        // pager doesn't wait for it to finish.
+        let _ = io::stdout().flush();
+        spawn_background_update(args);
+    }
+}
+
+/// Detach the process started by `command` from the terminal, so that it is
+/// not terminated along with tealdeer (e.g. by Ctrl+C).
+#[cfg(unix)]
+fn detach(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    // `setsid` is async-signal-safe
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+}
+
+#[cfg(windows)]
+fn detach(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const DETACHED_PROCESS: u32 = 0x0000_0008;
+    command.creation_flags(DETACHED_PROCESS);
+}
+
+#[cfg(not(any(unix, windows)))]
+fn detach(_command: &mut Command) {}
+
+/// Update the cache in the process started by `spawn_background_update`,
+/// unless another process is updating the cache already.
+fn run_background_update(cache: &Cache, updates: &UpdatesConfig, jobs: usize) {
+    let unlocked = Cache::get_cache_dir()
+        .ok()
+        .and_then(|(cache_dir, _)| CacheLock::try_lock(&cache_dir).ok())
+        .and_then(|lock| lock)
+        .is_some();
+    // The lock is released right away, `Cache::update` takes it again
+    if unlocked {
+        update_cache(cache, updates, jobs, true, true);
+    }
+}
+
+/// Update the cache and the extra pages sources of `updates`.
+///
+/// If an automatic update (`automatic`) fails, the cached pages are used
+/// instead of exiting.
+fn update_cache(
+    cache: &Cache,
+    updates: &UpdatesConfig,
+    jobs: usize,
+    quietly: bool,
+    automatic: bool,
+) {
+    let spinner = if quietly {
+        None
+    } else {
+        Some(Spinner::start("Updating the cache..."))
+    };
+    let started = (SystemTime::now(), Instant::now());
+    let updated = cache.update(jobs).map(|summary| {
+        (
+            summary,
+            repos::update_all(),
+            sources::update_all(&updates.extra_pages_sources, &updates.http),
+        )
+    });
+    drop(spinner);
+
+    if updates.record_update_history {
+        let attempt = UpdateAttempt::new(
+            started.0,
+            started.1.elapsed(),
+            automatic,
+            updated.as_ref().map(|(summary, _, _)| summary),
+        );
+        if let Err(e) = update_log::record(&attempt) {
+            if !quietly {
+                eprintln!("Warning: Could not record the update: {}", e.message());
+            }
+        }
+    }
+
+    let (summary, repos_updated, sources_updated) = match updated {
+        Ok(updated) => updated,
+        Err(ref e) if automatic && Cache::last_update().is_some() => {
+            eprintln!(
+                "Warning: Could not update cache, using the cached pages: {}",
+                e.message()
+            );
+            return;
+        }
+        Err(e) => {
+            // Without any pages, the bundled ones are better than nothing
+            let hint = if bundled::available() && Cache::last_update().is_none() {
+                Some("Use `tldr --seed-cache` to install the pages bundled with tealdeer.")
+            } else {
+                None
+            };
+            exit_with_error(
+                e.kind(),
+                &format!("Could not update cache: {}", e.message()),
+                hint,
+            );
+        }
+    };
+    for (url, e) in &summary.failed {
+        eprintln!(
+            "Warning: Could not download pages from {}: {}",
+            url,
+            e.message()
+        );
+    }
+    warn_quarantined(&summary.quarantined, "the archive", cache_quarantine_dir());
+    // A repository that can not be pulled keeps its pages, so this is no
+    // reason to fail the update
+    match repos_updated {
+        Ok(failed) => {
+            for (repo, msg) in failed {
+                eprintln!(
+                    "Warning: Could not update pages repository {}: {}",
+                    repo.url, msg
+                );
+            }
+        }
+        Err(e) => {
+            eprintln!(
+                "Warning: Could not update pages repositories: {}",
+                e.message()
+            );
+        }
+    }
+    warn_extra_sources(sources_updated);
+    if !quietly {
+        if summary.failed.is_empty() {
+            println!("Successfully updated cache.");
+        } else {
+            println!("Successfully updated cache from {}.", summary.url);
+        }
+    }
+}
+
+/// Return the directory that updates of the cache move invalid pages to.
+fn cache_quarantine_dir() -> Option<PathBuf> {
+    Cache::get_cache_dir()
+        .ok()
+        .map(|(cache_dir, _)| cache_dir.join(QUARANTINE_DIR))
+}
+
+/// Warn about the extra pages sources that could not be updated and about
+/// the invalid pages of their archives. A source that can not be updated
+/// keeps its pages, so this is no reason to fail the update.
+fn warn_extra_sources(updated: Result<Vec<SourceUpdate<'_>>, TealdeerError>) {
+    let updates = match updated {
+        Ok(updates) => updates,
+        Err(e) => {
+            eprintln!(
+                "Warning: Could not update the extra pages sources: {}",
+                e.message()
+            );
+            return;
+        }
+    };
+    for update in updates {
+        match update.result {
+            Ok(ref quarantined) => warn_quarantined(
+                quarantined,
+                &update.source.to_string(),
+                sources::pages_dir(update.source)
+                    .ok()
+                    .map(|dir| sources::quarantine_dir(&dir)),
+            ),
+            Err(ref e) => eprintln!(
+                "Warning: Could not update {}: {}",
+                update.source,
+                e.message()
+            ),
+        }
+    }
+}
+
+/// Warn about the invalid pages of an archive (described by `archive`) that
+/// an update moved to `quarantine_dir` instead of installing them.
+fn warn_quarantined(
+    quarantined: &[QuarantinedPage],
+    archive: &str,
+    quarantine_dir: Option<PathBuf>,
+) {
+    if quarantined.is_empty() {
+        return;
+    }
+    let location = quarantine_dir
+        .map(|dir| format!(", see {}", dir.display()))
+        .unwrap_or_default();
+    eprintln!(
+        "Warning: Skipped {} invalid {} of {}{}:",
+        quarantined.len(),
+        if quarantined.len() == 1 {
+            "page"
+        } else {
+            "pages"
+        },
+        archive,
+        location
+    );
+    for page in quarantined {
+        eprintln!("  {}: {}", page.path, page.problem);
+    }
+}
+
+/// Update the cache from the archive at `path` instead of downloading it.
+fn update_cache_from_file(cache: &Cache, path: &Path, jobs: usize, quietly: bool) {
+    let spinner = if quietly {
+        None
+    } else {
+        Some(Spinner::start("Updating the cache..."))
+    };
+    let updated = cache.update_from_file(path, jobs);
+    drop(spinner);
+    let quarantined = updated.unwrap_or_else(|e| exit_with("Could not update cache", &e));
+    warn_quarantined(&quarantined, "the archive", cache_quarantine_dir());
     if !quietly {
         println!("Successfully updated cache.");
     }
 }
 
+/// Populate an empty cache with the pages bundled with the binary.
+fn seed_cache(cache: &Cache, jobs: usize, quietly: bool) {
+    cache
+        .seed(jobs)
+        .unwrap_or_else(|e| exit_with("Could not seed cache", &e));
+    if !quietly {
+        println!("Successfully installed the bundled pages. Run `tldr --update` to get all pages.");
+    }
+}
+
+/// Pin the current version of the page `name` and exit.
+fn pin_page_and_exit(cache: &Cache, name: &str, languages: &[String], quietly: bool) -> ! {
+    let path = cache
+        .pin(name, languages)
+        .unwrap_or_else(|e| exit_with("Could not pin page", &e));
+    if !quietly {
+        println!(
+            "Pinned {}, updates won't change it until `tldr --unpin {}`.",
+            path, name
+        );
+    }
+    process::exit(0);
+}
+
+/// Remove the pinned copies of the page `name` and exit.
+fn unpin_page_and_exit(name: &str, quietly: bool) -> ! {
+    match pins::unpin(name) {
+        Ok(0) => exit_with_error(
+            ErrorKind::PageNotFound,
+            &format!("Page {} is not pinned.", name),
+            None,
+        ),
+        Ok(_) => {
+            if !quietly {
+                println!("Unpinned {}.", name);
+            }
+            process::exit(0);
+        }
+        Err(e) => exit_with("Could not unpin page", &e),
+    }
+}
+
+/// Write the pinned pages and pages repositories to a profile and exit.
+fn export_profile_and_exit(path: &str, quietly: bool) -> ! {
+    let exported = profile::export(Path::new(path))
+        .unwrap_or_else(|e| exit_with("Could not export the profile", &e));
+    if !quietly {
+        println!(
+            "Exported {} pinned pages and {} pages repositories to {}.",
+            exported.pins.len(),
+            exported.repos.len(),
+            path
+        );
+    }
+    process::exit(0);
+}
+
+/// Merge a profile into the pinned pages and pages repositories and exit.
+fn import_profile_and_exit(path: &str, quietly: bool) -> ! {
+    let summary = profile::read(Path::new(path))
+        .and_then(|profile| profile::import(&profile))
+        .unwrap_or_else(|e| exit_with("Could not import the profile", &e));
+    for path in &summary.kept {
+        eprintln!(
+            "Warning: {} is pinned with other contents already, kept it.",
+            path
+        );
+    }
+    for (url, e) in &summary.repos_failed {
+        eprintln!(
+            "Warning: Could not add pages repository {}: {}",
+            url,
+            e.message()
+        );
+    }
+    if !quietly {
+        println!(
+            "Imported {} pinned pages and {} pages repositories from {}.",
+            summary.pinned.len(),
+            summary.repos_added.len(),
+            path
+        );
+    }
+    process::exit(if summary.repos_failed.is_empty() {
+        0
+    } else {
+        1
+    });
+}
+
+/// Convert the cache to the configured `cache_format`.
+fn migrate_cache(cache: &Cache, quietly: bool) {
+    cache
+        .migrate()
+        .unwrap_or_else(|e| exit_with("Could not migrate cache", &e));
+    if !quietly {
+        println!("Successfully migrated cache.");
+    }
+}
+
+/// Clone (or pull) a repository of custom pages and register it.
+fn add_pages_repo(url: &str, quietly: bool) {
+    match repos::add(url) {
+        Ok(outcome) => {
+            if !quietly {
+                match outcome {
+                    AddOutcome::Cloned => println!("Successfully added pages repository {}.", url),
+                    AddOutcome::Pulled => println!(
+                        "Pages repository {} was added before, pulled it instead.",
+                        url
+                    ),
+                }
+            }
+        }
+        Err(e) => exit_with("", &e),
+    }
+}
+
 /// Show the config path (DEPRECATED)
 fn show_config_path() {
     match get_config_path() {
         Ok((config_file_path, _)) => {
             println!("Config path is: {}", config_file_path.to_str().unwrap());
         }
-        Err(ConfigError(msg)) => {
-            eprintln!("Could not look up config_path: {}", msg);
-            process::exit(1);
-        }
-        Err(_) => {
-            eprintln!("Unknown error");
-            process::exit(1);
-        }
+        Err(e) => exit_with("Could not look up config_path", &e),
     }
 }
 
+/// Return the selected config profile, if any.
+///
+/// The `--profile` flag takes precedence over the `TEALDEER_PROFILE` env
+/// variable.
+fn get_profile(args: &Args) -> Option<String> {
+    args.flag_profile
+        .clone()
+        .or_else(|| env::var("TEALDEER_PROFILE").ok())
+        .filter(|profile| !profile.is_empty())
+}
+
 /// Show file paths
-fn show_paths() {
+fn show_paths(profile: Option<&str>) {
     let config_dir = get_config_dir().map_or_else(
         |e| format!("[Error: {}]", e),
         |(mut path, source)| {
@@ -222,22 +2354,53 @@ fn show_paths() {
             }
         },
     );
-    let pages_dir = Cache::get_cache_dir()
-        .map(|(path, _)| path.join("tldr-master"))
+    let pages_dir = Cache::get_pages_dir()
         .map(|mut path| {
             path.push(""); // Trailing path separator
             path.to_str().unwrap_or("[Invalid]").to_string()
         })
         .unwrap_or_else(|e| format!("[Error: {}]", e));
-    println!("Config dir:  {}", config_dir);
-    println!("Config path: {}", config_path);
-    println!("Cache dir:   {}", cache_dir);
-    println!("Pages dir:   {}", pages_dir);
+    let themes_dir = theme::themes_dir().map_or_else(
+        |e| format!("[Error: {}]", e),
+        |mut path| {
+            path.push(""); // Trailing path separator
+            path.to_str().unwrap_or("[Invalid]").to_string()
+        },
+    );
+    let custom_pages_dir = match Config::load(false, profile) {
+        Ok(config) => match config.directories.custom_pages_dir {
+            Some(ref path) if path.is_dir() => path.display().to_string(),
+            Some(ref path) => format!("{} (does not exist)", path.display()),
+            None => "[Not set]".to_string(),
+        },
+        Err(e) => format!("[Error: {}]", e),
+    };
+    println!("Config dir:       {}", config_dir);
+    println!("Config path:      {}", config_path);
+    println!("Cache dir:        {}", cache_dir);
+    println!("Pages dir:        {}", pages_dir);
+    println!("Custom pages dir: {}", custom_pages_dir);
+    println!("Themes dir:       {}", themes_dir);
+}
+
+/// Show the options that are missing from the config file and exit
+fn show_missing_options_and_exit(minimal: bool) {
+    match missing_options(minimal) {
+        Ok(missing) if missing.is_empty() => {
+            println!("The config file contains all available options.");
+            process::exit(0);
+        }
+        Ok(missing) => {
+            print!("{}", missing);
+            process::exit(0);
+        }
+        Err(e) => exit_with("Could not compare config file", &e),
+    }
 }
 
 /// Create seed config file and exit
-fn create_config_and_exit() {
-    match make_default_config() {
+fn create_config_and_exit(minimal: bool) {
+    match make_default_config(minimal) {
         Ok(config_file_path) => {
             println!(
                 "Successfully created seed config file here: {}",
@@ -245,15 +2408,29 @@ fn create_config_and_exit() {
             );
             process::exit(0);
         }
-        Err(ConfigError(msg)) => {
-            eprintln!("Could not create seed config: {}", msg);
-            process::exit(1);
-        }
-        Err(_) => {
-            eprintln!("Unknown error");
-            process::exit(1);
-        }
+        Err(e) => exit_with("Could not create seed config", &e),
+    }
+}
+
+/// Exit because the arguments could not be parsed.
+///
+/// The output format is not known in this case, so the error is reported as
+/// JSON if the raw arguments ask for it.
+fn exit_with_usage_error(error: &docopt::Error) -> ! {
+    let args: Vec<String> = env::args().collect();
+    let json_requested = args.iter().any(|arg| arg == "--output=json")
+        || args
+            .windows(2)
+            .any(|pair| pair[0] == "--output" && pair[1] == "json");
+    if !error.fatal() || !json_requested {
+        error.exit();
     }
+    enable_json_errors();
+    let message = match error {
+        docopt::Error::WithProgramUsage(inner, _) => inner.to_string(),
+        other => other.to_string(),
+    };
+    exit_with_error(ErrorKind::Usage, &message, Some("See `tldr --help`."))
 }
 
 #[cfg(feature = "logging")]
@@ -261,23 +2438,37 @@ fn init_log() {
     env_logger::init();
 }
 
-#[cfg(not(feature = "logging"))]
-fn init_log() {}
-
-#[cfg(target_os = "linux")]
+#[cfg(not(feature = "logging"))]
+fn init_log() {}
+
+#[cfg(target_os = "linux")]
+fn get_os() -> OsType {
+    OsType::Linux
+}
+
+#[cfg(target_os = "macos")]
+fn get_os() -> OsType {
+    OsType::OsX
+}
+
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+fn get_os() -> OsType {
+    OsType::FreeBsd
+}
+
+#[cfg(target_os = "netbsd")]
+fn get_os() -> OsType {
+    OsType::NetBsd
+}
+
+#[cfg(target_os = "openbsd")]
 fn get_os() -> OsType {
-    OsType::Linux
+    OsType::OpenBsd
 }
 
-#[cfg(any(
-    target_os = "macos",
-    target_os = "freebsd",
-    target_os = "netbsd",
-    target_os = "openbsd",
-    target_os = "dragonfly"
-))]
+#[cfg(any(target_os = "solaris", target_os = "illumos"))]
 fn get_os() -> OsType {
-    OsType::OsX
+    OsType::SunOs
 }
 
 #[cfg(target_os = "windows")]
@@ -292,6 +2483,8 @@ fn get_os() -> OsType {
     target_os = "netbsd",
     target_os = "openbsd",
     target_os = "dragonfly",
+    target_os = "solaris",
+    target_os = "illumos",
     target_os = "windows"
 )))]
 fn get_os() -> OsType {
@@ -336,19 +2529,90 @@ fn get_languages(env_lang: Option<String>, env_language: Option<String>) -> Vec<
     vec!["en".to_string()]
 }
 
-fn main() {
-    // Initialize logger
-    init_log();
+/// Return the languages to look up pages and the help text in, in order.
+fn requested_languages(flag_language: Option<&str>) -> Vec<String> {
+    if let Some(lang) = flag_language {
+        // Language overwritten by console argument
+        vec![lang.to_string()]
+    } else {
+        get_languages(std::env::var("LANG").ok(), std::env::var("LANGUAGE").ok())
+    }
+}
 
-    // Parse arguments
-    let args: Args = Docopt::new(USAGE)
-        .and_then(|d| d.deserialize())
-        .unwrap_or_else(|e| e.exit());
+/// Pick the translation to show when a page is not available in any of the
+/// requested languages.
+///
+/// Prefers translations into the same base language as the most preferred
+/// language (e.g. `pt_PT` for `pt_BR`), then English, then the first
+/// available language.
+fn best_available_language<'a>(available: &'a [String], requested: &[String]) -> Option<&'a str> {
+    fn base_language(lang: &str) -> &str {
+        lang.split('_').next().unwrap_or(lang)
+    }
+
+    let preferred_base = requested.first().map(|lang| base_language(lang));
+    available
+        .iter()
+        .find(|lang| Some(base_language(lang)) == preferred_base)
+        .or_else(|| available.iter().find(|lang| *lang == "en"))
+        .or_else(|| available.first())
+        .map(String::as_str)
+}
+
+/// What the modes of a run share besides the arguments: the config and the
+/// settings derived from it and from the terminal.
+struct Context {
+    config: Config,
+    languages: Vec<String>,
+    fill: Fill,
+    enable_styles: bool,
+    enable_hyperlinks: bool,
+    non_interactive: bool,
+    update: UpdateState,
+}
+
+impl Context {
+    /// Warn if the cache is outdated, unless it was just updated.
+    fn check_cache(&self, args: &Args) {
+        if self.update != UpdateState::Updated {
+            let enable_warning_styles = should_use_styles(args.flag_color, Stream::Stderr);
+            check_cache(args, &self.config, enable_warning_styles);
+        }
+    }
+}
+
+/// What happened to the cache before the command runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UpdateState {
+    /// The cache was updated (or doesn't need updates)
+    Updated,
+    /// The automatic update starts once the page was shown
+    Deferred,
+    /// The cache was not updated in the foreground
+    NotUpdated,
+}
+
+/// Handle the flags that don't need the config or the cache. Most of them
+/// exit, the paths are shown before the other flags are handled.
+fn handle_standalone_flags(args: &Args, profile: Option<&str>) {
+    // Show the help text in the user's language and exit
+    if args.flag_help {
+        let languages = requested_languages(args.flag_language.as_deref());
+        print!("{}", help::localized_usage(USAGE, &languages));
+        process::exit(0);
+    }
 
     // Show version and exit
     if args.flag_version {
         let os = get_os();
         println!("{} v{} ({})", NAME, VERSION, os);
+        if args.flag_verbose {
+            let features: Vec<String> = FEATURES
+                .iter()
+                .map(|&(name, enabled)| format!("{}{}", if enabled { '+' } else { '-' }, name))
+                .collect();
+            println!("features: {}", features.join(" "));
+        }
         process::exit(0);
     }
 
@@ -358,151 +2622,707 @@ fn main() {
         show_config_path();
     }
     if args.flag_show_paths {
-        show_paths();
+        show_paths(profile);
     }
 
-    // Create a basic config and exit
-    if args.flag_seed_config {
-        create_config_and_exit();
+    // Print a theme and exit
+    if let Some(ref name) = args.flag_dump_theme {
+        match theme::dump_theme(name) {
+            Ok(theme) => print!("{}", theme),
+            Err(e) => exit_with("", &e),
+        }
+        process::exit(0);
     }
 
-    // Determine the usage of styles
-    #[cfg(target_os = "windows")]
-    let ansi_support = ansi_term::enable_ansi_support().is_ok();
-    #[cfg(not(target_os = "windows"))]
-    let ansi_support = true;
+    // Print the command-not-found hook for the shell and exit
+    if args.flag_shell_hook {
+        print_shell_hook_and_exit(args.flag_shell.unwrap_or_else(Shell::detect));
+    }
 
-    let enable_styles = match args.flag_color {
-        // Attempt to use styling if instructed
-        ColorOptions::Always => true,
-        // Enable styling if:
-        // * There is `ansi_support`
-        // * NO_COLOR env var isn't set: https://no-color.org/
-        // * The output stream is stdout (not being piped)
-        ColorOptions::Auto => {
-            ansi_support && env::var_os("NO_COLOR").is_none() && atty::is(Stream::Stdout)
+    // Print the module for the shell and exit
+    if let Some(shell) = args.flag_shell_integration {
+        match shell {
+            Shell::PowerShell => print!("{}", powershell::MODULE),
+            Shell::Nushell => print!("{}", nushell::MODULE),
+            _ => exit_with_error(
+                ErrorKind::Usage,
+                "Shell integration modules are only available for PowerShell and Nushell",
+                Some("Use `tldr --shell-hook` for a command-not-found hook."),
+            ),
         }
-        // Disable styling
-        ColorOptions::Never => false,
-    };
+        process::exit(0);
+    }
 
-    // Look up config file, if none is found fall back to default config.
-    let config = match Config::load(enable_styles) {
-        Ok(config) => config,
-        Err(ConfigError(msg)) => {
-            eprintln!("Could not load config: {}", msg);
-            process::exit(1);
-        }
-        Err(e) => {
-            eprintln!("Could not load config: {}", e);
-            process::exit(1);
+    // Print the completion script for the shell and exit
+    if let Some(shell) = args.flag_completions {
+        print!("{}", shell.script());
+        process::exit(0);
+    }
+
+    // Print the JSON Schema of the config file and exit
+    if args.flag_config_schema {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&config_schema::json_schema())
+                .expect("The config schema can always be serialized")
+        );
+        process::exit(0);
+    }
+
+    // Create a basic config and exit
+    if args.flag_seed_config {
+        if args.flag_diff {
+            show_missing_options_and_exit(args.flag_minimal);
         }
-    };
+        create_config_and_exit(args.flag_minimal);
+    }
+}
 
-    if args.flag_pager || config.display.use_pager {
-        configure_pager();
+/// Exit with a usage error if flags are combined that don't work together.
+fn check_flag_combinations(args: &Args) {
+    if !args.flag_seed_config && (args.flag_minimal || args.flag_diff) {
+        exit_with_error(
+            ErrorKind::Usage,
+            "The --minimal and --diff flags can only be used with --seed-config",
+            None,
+        );
+    }
+    if !args.flag_list && args.flag_output == OutputFormat::Tsv {
+        exit_with_error(
+            ErrorKind::Usage,
+            "The tsv output format can only be used with --list",
+            None,
+        );
+    }
+    if !args.flag_list && (args.flag_platform.is_some() || args.flag_long) {
+        exit_with_error(
+            ErrorKind::Usage,
+            "The --platform and --long flags can only be used with --list",
+            None,
+        );
+    }
+    if let Some(ref platform) = args.flag_platform {
+        if platform.is_empty() || platform.contains(&['/', '\\', '.'][..]) {
+            exit_with_error(
+                ErrorKind::Usage,
+                &format!("Invalid platform {:?}", platform),
+                Some("Use the name of a platform directory, e.g. linux or common."),
+            );
+        }
     }
+    if args.flag_markdown && args.flag_copyable {
+        exit_with_error(
+            ErrorKind::Usage,
+            "The --markdown and --copyable flags can not be combined",
+            None,
+        );
+    }
+}
 
+/// Create the cache for the sources, formats and lookups configured in
+/// `config` and `args`.
+fn create_cache(args: &Args, config: &Config) -> Cache {
     // Specify target OS
     let os: OsType = match args.flag_os {
         Some(os) => os,
         None => get_os(),
     };
 
+    let custom_pages_dirs = config
+        .directories
+        .custom_pages_dir
+        .iter()
+        .cloned()
+        .chain(repos::page_dirs())
+        .collect();
+    let mut cache = Cache::new(
+        ARCHIVE_URL,
+        os,
+        custom_pages_dirs,
+        config.directories.ignore.clone(),
+    );
+    cache = cache
+        .with_archive_urls(config.updates.archive_urls.clone())
+        .with_archive_layout(config.updates.archive_layout)
+        .with_http_settings(config.updates.http.clone())
+        .with_checksum(config.updates.checksum.clone())
+        .with_minisign_key(config.updates.archive_minisign_key.clone())
+        .with_backend(config.updates.cache_format)
+        .with_aux_cache_limit(config.updates.aux_cache_max_mb * 1024 * 1024)
+        .with_extra_pages_dirs(
+            config
+                .updates
+                .extra_pages_sources
+                .iter()
+                .filter_map(|source| sources::pages_dir(source).ok())
+                .collect(),
+        );
+    match config.updates.pages_source {
+        PagesSource::Archive { url: None } => {}
+        PagesSource::Archive { url: Some(ref url) } => {
+            cache = cache.with_archive_urls(vec![url.clone()]);
+        }
+        PagesSource::Directory { ref path } => cache = cache.with_pages_dir(path.clone()),
+        PagesSource::Git { ref url } => cache = cache.with_git_source(url.clone()),
+    }
+    if args.flag_os.is_none() {
+        if let Some(ref chain) = config.search.platform_chain {
+            cache = cache.with_platform_chain(chain.clone());
+        }
+    }
+    // With --verbose, lookups are traced as well, followed by where the page
+    // was found
+    if args.flag_trace_lookup || args.flag_verbose {
+        cache = cache.with_lookup_tracer(Box::new(|event| eprintln!("lookup: {}", event)));
+    }
+    cache
+}
+
+/// Update the cache if requested or due, with up to `jobs` threads.
+fn update_as_needed(cache: &Cache, args: &Args, config: &Config, jobs: usize) -> UpdateState {
+    if let Some(ref path) = args.flag_update_from_file {
+        update_cache_from_file(cache, Path::new(path), jobs, args.flag_quiet);
+        UpdateState::Updated
+    } else if let PagesSource::Directory { ref path } = config.updates.pages_source {
+        // The pages in a directory are always up to date
+        if args.flag_update {
+            warn_extra_sources(sources::update_all(
+                &config.updates.extra_pages_sources,
+                &config.updates.http,
+            ));
+            if !args.flag_quiet {
+                println!(
+                    "The pages are read from {} directly, they don't need to be updated.",
+                    path.display()
+                );
+            }
+        }
+        UpdateState::Updated
+    } else if args.flag_update && env::var_os(BACKGROUND_UPDATE_ENV_VAR).is_some() {
+        run_background_update(cache, &config.updates, jobs);
+        UpdateState::Updated
+    } else if !args.flag_update
+        && config.updates.auto_update_after_lookup
+        && args.arg_command.is_some()
+        && Cache::last_update().is_some()
+        && should_update_cache(args, config)
+    {
+        // Started once the page was shown, see `finish_lookup`
+        UpdateState::Deferred
+    } else if !args.flag_update
+        && config.updates.auto_update_in_background
+        && Cache::last_update().is_some()
+        && should_update_cache(args, config)
+    {
+        spawn_background_update(args);
+        UpdateState::NotUpdated
+    } else if should_update_cache(args, config) {
+        update_cache(
+            cache,
+            &config.updates,
+            jobs,
+            args.flag_quiet,
+            !args.flag_update,
+        );
+        UpdateState::Updated
+    } else {
+        UpdateState::NotUpdated
+    }
+}
+
+/// Show the page of `command` at `path` as the flags of `args` request. Flags
+/// that show something else than the page itself (e.g. `--url`) exit, the
+/// page itself is printed and this returns.
+fn show_page(path: &Path, command: &str, args: &Args, ctx: &Context) {
+    let config = &ctx.config;
+    if args.flag_strict {
+        check_page_strictly(path);
+    }
+    if let Some(ref section) = args.flag_section {
+        check_section(path, section);
+    }
+    if args.flag_url {
+        print_url_and_exit(path, args.flag_output);
+    }
+    if args.flag_attribution {
+        print_attribution_and_exit(path, command, config, args.flag_output);
+    }
+    if args.flag_check_upstream {
+        check_upstream_and_exit(path, command, config, args.flag_output, ctx.enable_styles);
+    }
+    if args.flag_demo {
+        print_demo_and_exit(
+            path,
+            command,
+            args.flag_section.as_deref(),
+            config,
+            &ctx.fill,
+            args.flag_demo_format,
+        );
+    }
+    if args.flag_run {
+        run_example_and_exit(path, config, ctx.fill.clone(), ctx.non_interactive);
+    }
+    if args.flag_output != OutputFormat::Text {
+        print_document_and_exit(
+            path,
+            command,
+            args.flag_section.as_deref(),
+            args.flag_output,
+        );
+    }
+    if let Err(msg) = print_page(
+        path,
+        args.render_mode(),
+        args.flag_section.as_deref(),
+        config,
+        &ctx.fill,
+        ctx.enable_hyperlinks,
+        args.flag_quiet,
+    ) {
+        exit_with_error(ErrorKind::Render, &msg, None);
+    }
+}
+
+/// Render the local page `file` and exit.
+fn render_file_and_exit(file: &str, args: &Args, ctx: &Context) -> ! {
+    let path = PathBuf::from(file);
+    let command = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.split('.').next().unwrap_or(name))
+        .unwrap_or_default();
+    show_page(&path, command, args, ctx);
+    process::exit(0);
+}
+
+/// List the pages of the cache and exit.
+fn list_pages_and_exit(cache: &Cache, args: &Args, ctx: &Context) -> ! {
+    ctx.check_cache(args);
+
+    if args.flag_platform.is_some()
+        || args.flag_language.is_some()
+        || args.flag_long
+        || args.flag_output != OutputFormat::Text
+    {
+        list_page_locations_and_exit(
+            cache,
+            args.flag_platform.as_deref(),
+            args.flag_language.as_deref(),
+            args.flag_long,
+            args.flag_output,
+        );
+    }
+
+    // Get list of pages
+    let pages = cache
+        .list_pages()
+        .unwrap_or_else(|e| exit_with("Could not get list of pages", &e));
+
+    // Print pages, marking pinned pages for humans
+    if atty::is(Stream::Stdout) {
+        let pinned = pins::pinned_names();
+        for page in &pages {
+            if pinned.contains(page) {
+                println!("{} (pinned)", page);
+            } else {
+                println!("{}", page);
+            }
+        }
+    } else {
+        println!("{}", pages.join("\n"));
+    }
+    process::exit(0);
+}
+
+/// Search the pages for `term` and exit.
+fn search_and_exit(cache: &Cache, term: &str, args: &Args, ctx: &Context) -> ! {
+    ctx.check_cache(args);
+
+    let results = search_or_exit(cache, term, &ctx.languages);
+    if results.is_empty() {
+        if args.flag_output.is_json() {
+            exit_with_error(
+                ErrorKind::NoResults,
+                &format!("No pages found matching {}", term),
+                None,
+            );
+        }
+        if !args.flag_quiet {
+            println!("No pages found matching {}", term);
+        }
+        process::exit(1);
+    }
+    print_search_results(&results);
+    process::exit(0);
+}
+
+/// Show the page of the command `words` from the cache and exit.
+fn show_command_and_exit(cache: &Cache, words: &[String], args: &Args, ctx: &Context) -> ! {
+    let config = &ctx.config;
+    let languages = &ctx.languages;
+    let update_after_lookup = ctx.update == UpdateState::Deferred;
+
+    let command = page_name_from_words(words);
+    let rewritten = config.search.rewrite.apply(&command).into_owned();
+    if (args.flag_trace_lookup || args.flag_verbose) && rewritten != command {
+        eprintln!("lookup: {} was rewritten to {}", command, rewritten);
+    }
+    let command = rewritten;
+
+    if let Err(e) = validate_page_name(&command) {
+        exit_with_error(ErrorKind::Usage, e.message(), None);
+    }
+
+    ctx.check_cache(args);
+
+    if let Some(ref compared) = args.flag_compare_lang {
+        compare_and_exit(cache, &command, compared, args, config, &ctx.fill);
+    }
+
+    // Search for command in cache
+    let mut found = cache
+        .find_page(&command, languages)
+        .map(|found| (command.clone(), found));
+    if found.is_none() {
+        // Several commands, unless a flag only works for a single page
+        let single_page_flag = args.flag_url
+            || args.flag_attribution
+            || args.flag_check_upstream
+            || args.flag_demo
+            || args.flag_run;
+        if !single_page_flag {
+            if let Some(pages) = find_separate_pages(cache, words, languages, config) {
+                show_pages_and_exit(&pages, args, config, &ctx.fill, ctx.enable_hyperlinks);
+            }
+        }
+
+        found = find_parent_page(cache, words, &command, languages);
+    }
+
+    if let Some((name, found)) = found {
+        if args.flag_verbose {
+            print_page_origin(&found);
+        }
+        if name != command && !args.flag_quiet {
+            eprintln!(
+                "Page {} not found, showing the page of {} instead.",
+                command, name
+            );
+        }
+        show_page(&found.path, &name, args, ctx);
+        finish_lookup(args, update_after_lookup);
+    }
+
+    let other_languages = cache.find_page_languages(&command);
+
+    // Render the best available translation instead, if enabled
+    if config.search.fallback_to_other_languages {
+        if let Some(language) = best_available_language(&other_languages, languages) {
+            if let Some(found) = cache.find_page(&command, &[language.to_string()]) {
+                if args.flag_verbose {
+                    print_page_origin(&found);
+                }
+                if !args.flag_quiet {
+                    eprintln!(
+                        "Page {} is not available in your languages, \
+                         showing the translation \"{}\" instead.",
+                        command, language
+                    );
+                }
+                show_page(&found.path, &command, args, ctx);
+                finish_lookup(args, update_after_lookup);
+            }
+        }
+    }
+
+    if !args.flag_quiet && !args.flag_output.is_json() {
+        handle_miss(
+            cache,
+            &command,
+            languages,
+            &other_languages,
+            config,
+            args.flag_suggest,
+        );
+    }
+    // The page may only be missing because the cache is outdated
+    start_deferred_update(args, update_after_lookup);
+    if args.flag_output.is_json() {
+        exit_with_miss(&command, &other_languages);
+    }
+    process::exit(1);
+}
+
+/// Show the usage (or the pages of the tools of the current project) and exit
+/// if no command was given, unless a flag that works without one was.
+fn handle_missing_command(cache: &Cache, args: &Args, ctx: &Context) {
+    // Some flags can be run without a command.
+    if args.flag_update
+        || args.flag_clear_cache
+        || args.flag_clean_aux
+        || args.flag_migrate_cache
+        || args.flag_seed_cache
+        || args.flag_update_from_file.is_some()
+        || args.flag_pin.is_some()
+        || args.flag_unpin.is_some()
+        || args.flag_config_path
+        || args.flag_show_paths
+        || args.flag_add_pages_repo.is_some()
+    {
+        return;
+    }
+
+    if args.flag_output.is_json() {
+        exit_with_error(
+            ErrorKind::Usage,
+            "No command given",
+            Some("See `tldr --help`."),
+        );
+    }
+
+    // Inside a project, list the pages of the tools it uses instead
+    let workspace = env::current_dir()
+        .ok()
+        .and_then(|dir| workspace::detect(&dir));
+    if let Some(workspace) = workspace {
+        if print_workspace_pages(cache, &workspace, &ctx.languages) {
+            process::exit(0);
+        }
+    }
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn main() {
+    // Initialize logger
+    init_log();
+
+    // Exit silently if the output is closed early, e.g. by `tldr tar | head`
+    signals::reset_sigpipe();
+
+    // Parse arguments
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.help(false).deserialize())
+        .unwrap_or_else(|e| exit_with_usage_error(&e));
+    if args.flag_output.is_json() {
+        enable_json_errors();
+    }
+
+    let profile = get_profile(&args);
+    handle_standalone_flags(&args, profile.as_deref());
+    check_flag_combinations(&args);
+
+    // Determine the usage of styles. Pages are written to stdout, warnings to
+    // stderr, and only one of them may be redirected.
+    let enable_styles = should_use_styles(args.flag_color, Stream::Stdout);
+    let enable_warning_styles = should_use_styles(args.flag_color, Stream::Stderr);
+
+    // Look up config file, if none is found fall back to default config.
+    let mut config = match Config::load(enable_styles, profile.as_deref()) {
+        Ok(config) => config,
+        Err(e) => exit_with("Could not load config", &e),
+    };
+    if args.flag_no_filter {
+        config.disable_render_filter();
+    }
+
+    // The brief pages of --command-not-found are never paged, and the pager
+    // would wait for input in non-interactive mode
+    let non_interactive = interactive::is_non_interactive(args.flag_non_interactive);
+    let use_pager = (args.flag_pager || config.display.use_pager)
+        && args.flag_command_not_found.is_none()
+        && !non_interactive;
+    if use_pager {
+        configure_pager();
+    }
+    // Screen readers would read the escape sequences of hyperlinks, and
+    // minimal terminals would print them
+    let enable_hyperlinks = should_use_hyperlinks(
+        enable_styles
+            && !config.display.accessible
+            && config.display.style_profile == StyleProfile::Full,
+        use_pager,
+    );
+
+    // Add a repository of custom pages, pass through
+    if let Some(ref url) = args.flag_add_pages_repo {
+        add_pages_repo(url, args.flag_quiet);
+    }
+
+    // Export or import the personal state and exit
+    if let Some(ref path) = args.flag_export_profile {
+        export_profile_and_exit(path, args.flag_quiet);
+    }
+    if let Some(ref path) = args.flag_import_profile {
+        import_profile_and_exit(path, args.flag_quiet);
+    }
+
     // Initialize cache
-    let cache = Cache::new(ARCHIVE_URL, os);
+    let cache = create_cache(&args, &config);
+    let languages = requested_languages(args.flag_language.as_deref());
+
+    // Show the page of a command that is not installed and exit, without
+    // checking or updating the cache
+    if let Some(ref name) = args.flag_command_not_found {
+        show_brief_page_and_exit(&cache, name, &languages, &config, enable_hyperlinks);
+    }
 
     // Clear cache, pass through
     if args.flag_clear_cache {
-        clear_cache(args.flag_quiet);
+        clear_cache(args.flag_quiet, config.updates.clear_to_trash);
+    }
+
+    // Clean auxiliary caches, pass through
+    if args.flag_clean_aux {
+        clean_aux(args.flag_quiet);
+    }
+
+    let jobs = match args.flag_jobs {
+        Some(0) => exit_with_error(
+            ErrorKind::Usage,
+            "The number of jobs must be at least 1.",
+            None,
+        ),
+        Some(jobs) => jobs,
+        None => cache_info::default_jobs(),
+    };
+
+    // Seed cache, pass through
+    if args.flag_seed_cache {
+        seed_cache(&cache, jobs, args.flag_quiet);
     }
 
     // Update cache, pass through
-    let cache_updated = if should_update_cache(&args, &config) {
-        update_cache(&cache, args.flag_quiet);
-        true
-    } else {
-        false
+    let update = update_as_needed(&cache, &args, &config, jobs);
+
+    // Migrate cache, pass through
+    if args.flag_migrate_cache && update != UpdateState::Updated {
+        migrate_cache(&cache, args.flag_quiet);
+    }
+
+    let fill = Fill::parse(
+        &args.flag_fill,
+        args.flag_shell.unwrap_or_else(Shell::detect),
+    )
+    .unwrap_or_else(|msg| exit_with_error(ErrorKind::Usage, &msg, None));
+    let ctx = Context {
+        config,
+        languages,
+        fill,
+        enable_styles,
+        enable_hyperlinks,
+        non_interactive,
+        update,
     };
+    let config = &ctx.config;
+
+    // Benchmark rendering and exit
+    if let Some(ref dir) = args.flag_bench_render {
+        bench_render_and_exit(dir, args.flag_baseline.as_deref(), config, args.flag_output);
+    }
 
     // Render local file and exit
     if let Some(ref file) = args.flag_render {
-        let path = PathBuf::from(file);
-        if let Err(msg) = print_page(&path, args.flag_markdown, &config) {
-            eprintln!("{}", msg);
-            process::exit(1);
-        } else {
-            process::exit(0);
-        };
+        render_file_and_exit(file, &args, &ctx);
+    }
+
+    if args.flag_list
+        || args.flag_search.is_some()
+        || args.flag_check_coverage.is_some()
+        || args.flag_batch.is_some()
+        || args.arg_command.is_some()
+    {
+        check_custom_pages_dir(config, args.flag_quiet, enable_warning_styles);
+    }
+
+    // Serve the cache to other machines
+    if let Some(ref address) = args.flag_share_cache {
+        share_cache_and_exit(
+            &cache,
+            address,
+            config.updates.share_token.clone(),
+            args.flag_quiet,
+        );
+    }
+
+    // Show cache statistics and exit
+    if args.flag_stats {
+        show_stats_and_exit(args.flag_output);
+    }
+
+    if args.flag_info {
+        show_info_and_exit(config, args.flag_output);
+    }
+
+    if let Some(count) = args.flag_update_history {
+        show_update_history_and_exit(
+            count,
+            config.updates.record_update_history,
+            args.flag_output,
+        );
+    }
+
+    // Compare manifests and exit
+    if let Some(ref old_manifest_path) = args.flag_cache_diff {
+        show_cache_diff_and_exit(old_manifest_path, args.flag_output);
     }
 
     // List cached commands and exit
     if args.flag_list {
-        if !cache_updated {
-            // Check cache for freshness
-            check_cache(&args, enable_styles);
-        }
-
-        // Get list of pages
-        let pages = cache.list_pages().unwrap_or_else(|e| {
-            match e {
-                CacheError(msg) | ConfigError(msg) | UpdateError(msg) => {
-                    eprintln!("Could not get list of pages: {}", msg)
-                }
-            }
-            process::exit(1);
-        });
+        list_pages_and_exit(&cache, &args, &ctx);
+    }
 
-        // Print pages
-        println!("{}", pages.join("\n"));
-        process::exit(0);
+    // Pin or unpin a page and exit
+    if let Some(ref page) = args.flag_pin {
+        pin_page_and_exit(&cache, page, &ctx.languages, args.flag_quiet);
+    }
+    if let Some(ref page) = args.flag_unpin {
+        unpin_page_and_exit(page, args.flag_quiet);
     }
 
-    // Show command from cache
-    if let Some(ref command) = args.arg_command {
-        let command = command.join("-");
+    // Check which commands of a list have pages and exit
+    if let Some(ref list_path) = args.flag_check_coverage {
+        ctx.check_cache(&args);
+        check_coverage_and_exit(&cache, list_path, &ctx.languages, config, args.flag_output);
+    }
 
-        if !cache_updated {
-            // Check cache for freshness
-            check_cache(&args, enable_styles);
-        }
+    // Check a translation for outdated pages and exit
+    if let Some(ref language) = args.flag_translation_status {
+        ctx.check_cache(&args);
+        show_translation_status_and_exit(&cache, language, args.flag_output);
+    }
 
-        let languages = if let Some(ref lang) = args.flag_language {
-            // Language overwritten by console argument
-            vec![lang.clone()]
-        } else {
-            get_languages(std::env::var("LANG").ok(), std::env::var("LANGUAGE").ok())
-        };
+    // Search for commands and exit
+    if let Some(ref term) = args.flag_search {
+        search_and_exit(&cache, term, &args, &ctx);
+    }
 
-        // Search for command in cache
-        if let Some(path) = cache.find_page(&command, &languages) {
-            if let Err(msg) = print_page(&path, args.flag_markdown, &config) {
-                eprintln!("{}", msg);
-                process::exit(1);
-            } else {
-                process::exit(0);
-            }
-        } else {
-            if !args.flag_quiet {
-                println!("Page {} not found in cache", &command);
-                println!("Try updating with `tldr --update`, or submit a pull request to:");
-                println!("https://github.com/tldr-pages/tldr");
-            }
-            process::exit(1);
-        }
+    // Show the pages of a list of commands and exit
+    if let Some(ref list_path) = args.flag_batch {
+        ctx.check_cache(&args);
+        show_batch_and_exit(
+            &cache,
+            list_path,
+            &ctx.languages,
+            &args,
+            config,
+            &ctx.fill,
+            enable_hyperlinks,
+        );
     }
 
-    // Some flags can be run without a command.
-    if !(args.flag_update || args.flag_clear_cache || args.flag_config_path || args.flag_show_paths)
-    {
-        eprintln!("{}", USAGE);
-        process::exit(1);
+    // Show command from cache
+    if let Some(ref words) = args.arg_command {
+        show_command_and_exit(&cache, words, &args, &ctx);
     }
+
+    handle_missing_command(&cache, &args, &ctx);
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{get_languages, Args, OsType, USAGE};
+    use crate::{best_available_language, get_languages, Args, OsType, USAGE};
     use docopt::{Docopt, Error};
 
     fn test_helper(argv: &[&str]) -> Result<Args, Error> {
@@ -559,6 +3379,23 @@ mod test {
             assert_eq!(lang_list, vec!["en"]);
         }
 
+        #[test]
+        fn best_available() {
+            let available = vec!["de".to_string(), "en".to_string(), "pt_PT".to_string()];
+            let requested = vec!["pt_BR".to_string(), "pt".to_string()];
+            assert_eq!(
+                best_available_language(&available, &requested),
+                Some("pt_PT")
+            );
+            let requested = vec!["fr".to_string()];
+            assert_eq!(best_available_language(&available, &requested), Some("en"));
+            let available = vec!["de".to_string(), "es".to_string()];
+            assert_eq!(best_available_language(&available, &requested), Some("de"));
+            assert_eq!(best_available_language(&[], &requested), None);
+            let requested = vec!["中文".to_string()];
+            assert_eq!(best_available_language(&available, &requested), Some("de"));
+        }
+
         #[test]
         fn no_duplicates() {
             let lang_list = get_languages(Some("de".into()), Some("fr:de:cn:de".into()));
@@ -0,0 +1,169 @@
+//! Converting pages into man pages.
+//!
+//! `tldr --output man <command>` prints the page as roff, so that it can be
+//! read with the paging and typography of `man`:
+//!
+//! ```text
+//! tldr --output man tar | man -l -
+//! ```
+//!
+//! Commands are set in bold and placeholders in italics, as in the synopsis
+//! of a man page.
+
+use std::fmt::Write;
+
+use crate::fill::next_placeholder;
+use crate::parser::{Page, Placeholder};
+
+/// Escape `text` for a roff text line: Backslashes and hyphens are escaped,
+/// and a leading `.` or `'` is kept from being read as a request.
+fn escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// Escape `text` for a quoted argument of a roff request.
+fn escape_argument(text: &str) -> String {
+    text.replace('\\', "\\e").replace('"', "\\(dq")
+}
+
+/// Format a placeholder in italics. The separators of alternatives are set
+/// in roman.
+fn format_placeholder(placeholder: &str) -> String {
+    match Placeholder::parse(placeholder) {
+        Placeholder::Alternatives(alternatives) => alternatives
+            .iter()
+            .map(|alternative| format!("\\fI{}\\fR", escape(alternative)))
+            .collect::<Vec<_>>()
+            .join("|"),
+        _ => format!("\\fI{}\\fR", escape(placeholder.trim())),
+    }
+}
+
+/// Format an example command in bold, with placeholders in italics.
+fn format_code(code: &str) -> String {
+    let mut formatted = String::new();
+    let mut rest = code;
+    while let Some((start, end)) = next_placeholder(rest) {
+        if start > 0 {
+            let _ = write!(formatted, "\\fB{}\\fR", escape(&rest[..start]));
+        }
+        formatted.push_str(&format_placeholder(&rest[start + 2..end - 2]));
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() {
+        let _ = write!(formatted, "\\fB{}\\fR", escape(rest));
+    }
+    formatted
+}
+
+/// Convert `page` into a man page in section 1.
+pub fn to_roff(page: &Page) -> String {
+    // Writing to a `String` never fails
+    let mut roff = String::new();
+    let _ = writeln!(
+        roff,
+        ".TH \"{}\" \"1\" \"\" \"tealdeer\" \"tldr pages\"",
+        escape_argument(&page.title.to_uppercase())
+    );
+
+    roff.push_str(".SH NAME\n");
+    match page.description.first() {
+        Some(summary) => {
+            let _ = writeln!(roff, "{} \\- {}", escape(&page.title), escape(summary));
+        }
+        None => {
+            let _ = writeln!(roff, "{}", escape(&page.title));
+        }
+    }
+
+    if !page.description.is_empty() || page.more_information.is_some() {
+        roff.push_str(".SH DESCRIPTION\n");
+        for line in &page.description {
+            let _ = writeln!(roff, "{}", escape(line));
+        }
+        if let Some(ref url) = page.more_information {
+            let _ = writeln!(roff, ".PP\nMore information:\n.UR {}\n.UE", url);
+        }
+    }
+
+    if !page.examples.is_empty() {
+        roff.push_str(".SH EXAMPLES\n");
+        for (i, example) in page.examples.iter().enumerate() {
+            for section in page.sections.iter().filter(|section| section.start == i) {
+                let _ = writeln!(roff, ".SS {}", escape(&section.name));
+            }
+            roff.push_str(".PP\n");
+            if !example.description.is_empty() {
+                let _ = writeln!(roff, "{}", escape(&example.description));
+            }
+            if !example.code.is_empty() {
+                let _ = writeln!(roff, ".RS 4\n.nf\n{}\n.fi\n.RE", format_code(&example.code));
+            }
+        }
+    }
+    roff
+}
+
+#[cfg(test)]
+mod test {
+    use super::{escape, format_code, to_roff};
+    use crate::parser::Page;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("tar -xf"), "tar \\-xf");
+        assert_eq!(escape("C:\\dir"), "C:\\edir");
+        assert_eq!(escape(".hidden"), "\\&.hidden");
+        assert_eq!(escape("'quoted'"), "\\&'quoted'");
+    }
+
+    #[test]
+    fn test_format_code() {
+        assert_eq!(
+            format_code("cp {{source}} {{target}}"),
+            "\\fBcp \\fR\\fIsource\\fR\\fB \\fR\\fItarget\\fR"
+        );
+        assert_eq!(format_code("ls -l"), "\\fBls \\-l\\fR");
+        assert_eq!(
+            format_code("service {{start|stop}}"),
+            "\\fBservice \\fR\\fIstart\\fR|\\fIstop\\fR"
+        );
+    }
+
+    #[test]
+    fn test_to_roff() {
+        let page = "# tar\n\n> Archiving utility.\n> Often combined with gzip.\n\
+                    > More information: <https://www.gnu.org/software/tar>.\n\n\
+                    - Extract an archive:\n\n`tar xf {{source.tar}}`\n";
+        assert_eq!(
+            to_roff(&Page::read(page.as_bytes())),
+            ".TH \"TAR\" \"1\" \"\" \"tealdeer\" \"tldr pages\"\n\
+             .SH NAME\n\
+             tar \\- Archiving utility.\n\
+             .SH DESCRIPTION\n\
+             Archiving utility.\n\
+             Often combined with gzip.\n\
+             .PP\n\
+             More information:\n\
+             .UR https://www.gnu.org/software/tar\n\
+             .UE\n\
+             .SH EXAMPLES\n\
+             .PP\n\
+             Extract an archive:\n\
+             .RS 4\n\
+             .nf\n\
+             \\fBtar xf \\fR\\fIsource.tar\\fR\n\
+             .fi\n\
+             .RE\n"
+        );
+
+        let runbook = "# deploy\n\n## Backups\n\n- Create a backup:\n\n`deploy backup`\n";
+        assert!(to_roff(&Page::read(runbook.as_bytes()))
+            .contains(".SH EXAMPLES\n.SS Backups\n.PP\nCreate a backup:\n"));
+    }
+}
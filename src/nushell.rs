@@ -0,0 +1,169 @@
+//! Integration with Nushell.
+//!
+//! `tldr --shell-integration nushell` prints a module with `tldr page` and
+//! `tldr examples` commands, which return pages as records. They are read
+//! from `--output nuon`, which prints a page like `--output json`, but in
+//! NUON, the object notation of Nushell. Lists of records with the same
+//! columns (like the examples) are written as tables. Other results are
+//! printed as JSON, which Nushell reads as NUON as well.
+
+use serde_json::Value;
+
+use crate::quoting::nushell_string;
+
+/// The module printed by `tldr --shell-integration nushell`.
+pub const MODULE: &str = r#"# tealdeer module, printed by `tldr --shell-integration nushell`.
+# Save it as tealdeer.nu and load it in your config with:
+#   use tealdeer.nu *
+
+def "nu-complete tldr pages" [] {
+    ^tldr --list | lines
+}
+
+# Show the page of a command as a record
+export def "tldr page" [
+    name: string@"nu-complete tldr pages"  # The command to show the page of
+    --platform: string                     # The platform to show the page for
+    --language: string                     # The language to show the page in
+] {
+    mut arguments = []
+    if $platform != null { $arguments = ($arguments | append ["--os" $platform]) }
+    if $language != null { $arguments = ($arguments | append ["--language" $language]) }
+    ^tldr ...$arguments --output nuon $name | from nuon
+}
+
+# List the examples of the page of a command as a table
+export def "tldr examples" [
+    name: string@"nu-complete tldr pages"  # The command to list the examples of
+] {
+    tldr page $name | get examples
+}
+"#;
+
+/// Return whether `key` can be written without quotes.
+fn is_bare_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        }
+        _ => false,
+    }
+}
+
+fn write_key(key: &str, nuon: &mut String) {
+    if is_bare_key(key) {
+        nuon.push_str(key);
+    } else {
+        nuon.push_str(&nushell_string(key));
+    }
+}
+
+/// Return the columns of `values` if it is a non-empty list of records that
+/// all have the same columns.
+fn table_columns(values: &[Value]) -> Option<Vec<&String>> {
+    let columns: Vec<&String> = values.first()?.as_object()?.keys().collect();
+    let same_columns = values.iter().all(|value| match value.as_object() {
+        Some(record) => record.keys().eq(columns.iter().copied()),
+        None => false,
+    });
+    if same_columns {
+        Some(columns)
+    } else {
+        None
+    }
+}
+
+fn write_list<'a, I: Iterator<Item = &'a Value>>(values: I, nuon: &mut String) {
+    nuon.push('[');
+    for (i, value) in values.enumerate() {
+        if i > 0 {
+            nuon.push_str(", ");
+        }
+        write_value(value, nuon);
+    }
+    nuon.push(']');
+}
+
+fn write_value(value: &Value, nuon: &mut String) {
+    match value {
+        Value::Null => nuon.push_str("null"),
+        Value::Bool(b) => nuon.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => nuon.push_str(&n.to_string()),
+        Value::String(s) => nuon.push_str(&nushell_string(s)),
+        Value::Array(values) => match table_columns(values) {
+            // A table: `[[a, b]; [1, 2], [3, 4]]`
+            Some(columns) => {
+                nuon.push_str("[[");
+                for (i, column) in columns.iter().enumerate() {
+                    if i > 0 {
+                        nuon.push_str(", ");
+                    }
+                    write_key(column, nuon);
+                }
+                nuon.push_str("]; ");
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        nuon.push_str(", ");
+                    }
+                    let record = value.as_object().expect("Table rows are records");
+                    write_list(record.values(), nuon);
+                }
+                nuon.push(']');
+            }
+            None => write_list(values.iter(), nuon),
+        },
+        Value::Object(record) => {
+            nuon.push('{');
+            for (i, (key, value)) in record.iter().enumerate() {
+                if i > 0 {
+                    nuon.push_str(", ");
+                }
+                write_key(key, nuon);
+                nuon.push_str(": ");
+                write_value(value, nuon);
+            }
+            nuon.push('}');
+        }
+    }
+}
+
+/// Write `value` as NUON.
+pub fn to_nuon(value: &Value) -> String {
+    let mut nuon = String::new();
+    write_value(value, &mut nuon);
+    nuon
+}
+
+#[cfg(test)]
+mod test {
+    use super::to_nuon;
+    use serde_json::json;
+
+    #[test]
+    fn test_values() {
+        assert_eq!(to_nuon(&json!(null)), "null");
+        assert_eq!(to_nuon(&json!(true)), "true");
+        assert_eq!(to_nuon(&json!(42)), "42");
+        assert_eq!(to_nuon(&json!("say \"hi\"\n")), "\"say \\\"hi\\\"\\n\"");
+        assert_eq!(to_nuon(&json!([])), "[]");
+        assert_eq!(to_nuon(&json!([1, "a"])), "[1, \"a\"]");
+        assert_eq!(
+            to_nuon(&json!({"name": "tar", "more information": null})),
+            "{\"more information\": null, name: \"tar\"}"
+        );
+    }
+
+    #[test]
+    fn test_tables() {
+        assert_eq!(
+            to_nuon(&json!([
+                {"command": "tar cf {{target}}", "placeholders": ["target"]},
+                {"command": "tar xf x", "placeholders": []},
+            ])),
+            "[[command, placeholders]; [\"tar cf {{target}}\", [\"target\"]], [\"tar xf x\", []]]"
+        );
+        // Records with different columns are a list
+        assert_eq!(to_nuon(&json!([{"a": 1}, {"b": 2}])), "[{a: 1}, {b: 2}]");
+    }
+}
@@ -0,0 +1,513 @@
+//! Parsing pages into a syntax tree.
+//!
+//! A `Page` holds the parts of a tldr page (title, description and
+//! examples) independently of the page format (`# title` or an underlined
+//! title) and of the blank lines between them. Long custom pages (e.g.
+//! internal runbooks) can group their examples with `## section` headings,
+//! which are kept as `Section`s. Rendering pages and the other
+//! output formats all work on a `Page`, and `Page::to_markdown` writes it back
+//! as markdown.
+//!
+//! The placeholders of example commands are parsed with `Placeholder::parse`,
+//! which recognizes the alternatives (`{{start|stop}}`) and ranges
+//! (`{{1..10}}`) of the tldr page conventions.
+//!
+//! Parsing is lenient: Irregular lines are skipped or taken as they are, so
+//! that every page can be shown. The irregularities are collected as
+//! diagnostics, which `--strict` reports as errors.
+
+use std::fmt::{self, Write};
+use std::io::BufRead;
+
+use crate::tokenizer::Tokenizer;
+use crate::types::LineType;
+
+/// An example of a page.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Example {
+    /// The text describing the example, empty if the page has none.
+    pub description: String,
+    /// The command of the example, empty if the page has none.
+    pub code: String,
+}
+
+/// A `## section` heading, which groups the examples after it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    /// The index of the first example of the section in `Page::examples`.
+    pub start: usize,
+}
+
+/// A parsed page.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Page {
+    /// The title of the page (usually the command), empty if the page has
+    /// none.
+    pub title: String,
+    /// The lines of the description, without the link to more information.
+    pub description: Vec<String>,
+    /// The link to more information about the command.
+    pub more_information: Option<String>,
+    pub examples: Vec<Example>,
+    /// The sections, in order. Examples before the first section belong to
+    /// none.
+    pub sections: Vec<Section>,
+}
+
+/// A placeholder of an example command, i.e. the text between `{{` and `}}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Placeholder<'a> {
+    /// A value to fill in, e.g. `{{path/to/file}}`.
+    Value(&'a str),
+    /// One of a few alternatives, e.g. `{{start|stop|restart}}`.
+    Alternatives(Vec<&'a str>),
+    /// A value in a range, e.g. `{{1..10}}`, with its start and end.
+    Range(&'a str, &'a str),
+}
+
+/// Return whether `bound` can be the start or end of a range, i.e. a number
+/// or a single word like `a` in `{{a..z}}`.
+fn is_range_bound(bound: &str) -> bool {
+    !bound.is_empty() && bound.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+impl<'a> Placeholder<'a> {
+    /// Parse the text of a placeholder, without the braces.
+    pub fn parse(text: &'a str) -> Self {
+        let text = text.trim();
+        if text.contains('|') {
+            let alternatives: Vec<&str> = text.split('|').map(str::trim).collect();
+            if alternatives
+                .iter()
+                .all(|alternative| !alternative.is_empty())
+            {
+                return Placeholder::Alternatives(alternatives);
+            }
+        } else if let Some(separator) = text.find("..") {
+            let (start, end) = (&text[..separator], &text[separator + 2..]);
+            if is_range_bound(start) && is_range_bound(end) {
+                return Placeholder::Range(start, end);
+            }
+        }
+        Placeholder::Value(text)
+    }
+}
+
+/// An irregularity found while parsing a page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The number of the line, starting at 1.
+    pub line: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Return whether the `{{` and `}}` of the placeholders in `code` match.
+fn braces_are_balanced(code: &str) -> bool {
+    let mut rest = code;
+    while let Some(start) = rest.find("{{") {
+        if rest[..start].contains("}}") {
+            return false;
+        }
+        match rest[start + 2..].find("}}") {
+            Some(end) if !rest[start + 2..start + 2 + end].contains("{{") => {
+                rest = &rest[start + 2 + end + 2..];
+            }
+            _ => return false,
+        }
+    }
+    !rest.contains("}}")
+}
+
+impl Page {
+    /// Parse a page from the tokens of `tokenizer`.
+    pub fn parse<R: BufRead>(tokenizer: &mut Tokenizer<R>) -> Self {
+        Self::parse_with_diagnostics(tokenizer).0
+    }
+
+    /// Parse a page from the tokens of `tokenizer`, and collect the
+    /// irregularities of the page.
+    pub fn parse_with_diagnostics<R: BufRead>(
+        tokenizer: &mut Tokenizer<R>,
+    ) -> (Self, Vec<Diagnostic>) {
+        let mut page = Self::default();
+        let mut diagnostics = vec![];
+        let mut report = |line, message| diagnostics.push(Diagnostic { line, message });
+        // The line of the description of the last example, while it has no
+        // command
+        let mut example_without_command = None;
+        while let Some(token) = tokenizer.next_token() {
+            let line = tokenizer.line_number();
+            match token {
+                LineType::Title(title) => {
+                    if page.title.is_empty() {
+                        page.title = title;
+                    } else {
+                        report(line, "Unexpected second title");
+                    }
+                }
+                LineType::Section(name) => {
+                    if name.is_empty() {
+                        report(line, "Section without a name");
+                    }
+                    page.sections.push(Section {
+                        name,
+                        start: page.examples.len(),
+                    });
+                }
+                LineType::Description(text) => {
+                    if !page.examples.is_empty() || !page.sections.is_empty() {
+                        report(line, "Unexpected description after the examples");
+                    }
+                    page.description.push(text);
+                }
+                LineType::MoreInformation(url) => {
+                    if page.more_information.is_some() {
+                        report(line, "Unexpected second link to more information");
+                    }
+                    page.more_information = Some(url);
+                }
+                LineType::ExampleText(text) => {
+                    if let Some(example_line) = example_without_command.take() {
+                        report(example_line, "Example without a command");
+                    }
+                    example_without_command = Some(line);
+                    page.examples.push(Example {
+                        description: text,
+                        code: String::new(),
+                    });
+                }
+                LineType::ExampleCode(code) => {
+                    if !braces_are_balanced(&code) {
+                        report(line, "Unbalanced braces in example command");
+                    }
+                    match page.examples.last_mut() {
+                        Some(example) if example.code.is_empty() => {
+                            example_without_command = None;
+                            example.code = code;
+                        }
+                        _ => {
+                            report(line, "Example command without a description");
+                            page.examples.push(Example {
+                                description: String::new(),
+                                code,
+                            });
+                        }
+                    }
+                }
+                LineType::Other(text) => {
+                    if text.starts_with('`') {
+                        report(line, "Unbalanced backticks in example command");
+                    } else {
+                        report(line, "Unexpected line");
+                    }
+                }
+                LineType::Empty => {}
+            }
+        }
+        if let Some(example_line) = example_without_command {
+            report(example_line, "Example without a command");
+        }
+        if page.title.is_empty() {
+            report(1, "Missing title");
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+        (page, diagnostics)
+    }
+
+    /// Parse a page from `reader`.
+    pub fn read<R: BufRead>(reader: R) -> Self {
+        Self::parse(&mut Tokenizer::new(reader))
+    }
+
+    /// Parse a page from `reader`, and collect the irregularities of the
+    /// page.
+    pub fn read_with_diagnostics<R: BufRead>(reader: R) -> (Self, Vec<Diagnostic>) {
+        Self::parse_with_diagnostics(&mut Tokenizer::new(reader))
+    }
+
+    /// Write the page as markdown in the `# title` format.
+    pub fn to_markdown(&self) -> String {
+        // Writing to a `String` never fails
+        let mut markdown = format!("# {}\n\n", self.title);
+        for line in &self.description {
+            let _ = writeln!(markdown, "> {}", line);
+        }
+        if let Some(ref url) = self.more_information {
+            let _ = writeln!(markdown, "> More information: <{}>.", url);
+        }
+        let mut sections = self.sections.iter().peekable();
+        for (i, example) in self.examples.iter().enumerate() {
+            while let Some(section) = sections.peek() {
+                if section.start > i {
+                    break;
+                }
+                let _ = write!(markdown, "\n## {}\n", section.name);
+                sections.next();
+            }
+            if !example.description.is_empty() {
+                let _ = write!(markdown, "\n- {}\n", example.description);
+            }
+            if !example.code.is_empty() {
+                let _ = write!(markdown, "\n`{}`\n", example.code);
+            }
+        }
+        for section in sections {
+            let _ = write!(markdown, "\n## {}\n", section.name);
+        }
+        markdown
+    }
+
+    /// Return the name of the section that the example at `index` belongs to.
+    pub fn section_of(&self, index: usize) -> Option<&str> {
+        self.sections
+            .iter()
+            .take_while(|section| section.start <= index)
+            .last()
+            .map(|section| section.name.as_str())
+    }
+
+    /// Keep only the examples of the sections called `name` (ignoring case).
+    /// Returns `false` and keeps the page as it is if it has no such section.
+    pub fn select_section(&mut self, name: &str) -> bool {
+        let name = name.trim().to_lowercase();
+        let mut examples = vec![];
+        let mut sections = vec![];
+        for (i, section) in self.sections.iter().enumerate() {
+            if section.name.to_lowercase() != name {
+                continue;
+            }
+            let end = self
+                .sections
+                .get(i + 1)
+                .map_or(self.examples.len(), |next| next.start);
+            sections.push(Section {
+                name: section.name.clone(),
+                start: examples.len(),
+            });
+            examples.extend_from_slice(&self.examples[section.start..end]);
+        }
+        if sections.is_empty() {
+            return false;
+        }
+        self.examples = examples;
+        self.sections = sections;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{braces_are_balanced, Diagnostic, Example, Page, Placeholder, Section};
+
+    const TAR: &str = "# tar\n\n> Archiving utility.\n> Often combined with gzip.\n\
+                       > More information: <https://www.gnu.org/software/tar>.\n\n\
+                       - Create an archive:\n\n`tar cf {{target.tar}} {{file}}`\n\n\
+                       - Extract an archive:\n\n`tar xf {{source.tar}}`\n";
+
+    fn tar() -> Page {
+        Page {
+            title: "tar".into(),
+            description: vec![
+                "Archiving utility.".into(),
+                "Often combined with gzip.".into(),
+            ],
+            more_information: Some("https://www.gnu.org/software/tar".into()),
+            examples: vec![
+                Example {
+                    description: "Create an archive:".into(),
+                    code: "tar cf {{target.tar}} {{file}}".into(),
+                },
+                Example {
+                    description: "Extract an archive:".into(),
+                    code: "tar xf {{source.tar}}".into(),
+                },
+            ],
+            sections: vec![],
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Page::read(TAR.as_bytes()), tar());
+    }
+
+    #[test]
+    fn test_parse_new_format() {
+        let page = "tar\n===\n\n> Archiving utility.\n> Often combined with gzip.\n\
+                    > More information: <https://www.gnu.org/software/tar>.\n\n\
+                    Create an archive:\n\n    tar cf {{target.tar}} {{file}}\n\n\
+                    Extract an archive:\n\n    tar xf {{source.tar}}\n";
+        assert_eq!(Page::read(page.as_bytes()), tar());
+    }
+
+    #[test]
+    fn test_parse_incomplete_examples() {
+        let page = "# ls\n\n- Only a description\n\n- List all files:\n\n`ls -a`\n\n`ls -l`\n";
+        assert_eq!(
+            Page::read(page.as_bytes()).examples,
+            vec![
+                Example {
+                    description: "Only a description".into(),
+                    code: String::new(),
+                },
+                Example {
+                    description: "List all files:".into(),
+                    code: "ls -a".into(),
+                },
+                Example {
+                    description: String::new(),
+                    code: "ls -l".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sections() {
+        let runbook =
+            "# deploy\n\n> Deploy the services.\n\n- Show the status:\n\n`deploy status`\n\n\
+                       ## Backups\n\n- Create a backup:\n\n`deploy backup`\n\n\
+                       - Restore a backup:\n\n`deploy restore {{backup}}`\n\n\
+                       ## Rollbacks\n\n- Roll back:\n\n`deploy rollback`\n";
+        let page = Page::read(runbook.as_bytes());
+        assert_eq!(
+            page.sections,
+            vec![
+                Section {
+                    name: "Backups".into(),
+                    start: 1,
+                },
+                Section {
+                    name: "Rollbacks".into(),
+                    start: 3,
+                },
+            ]
+        );
+        assert_eq!(page.section_of(0), None);
+        assert_eq!(page.section_of(2), Some("Backups"));
+        assert_eq!(page.section_of(3), Some("Rollbacks"));
+        assert_eq!(page.to_markdown(), runbook);
+        assert_eq!(Page::read_with_diagnostics(runbook.as_bytes()).1, vec![]);
+
+        let mut backups = page.clone();
+        assert!(backups.select_section("backups"));
+        assert_eq!(backups.examples, page.examples[1..3].to_vec());
+        assert_eq!(backups.section_of(0), Some("Backups"));
+
+        let mut unchanged = page.clone();
+        assert!(!unchanged.select_section("Monitoring"));
+        assert_eq!(unchanged, page);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        assert_eq!(Page::read_with_diagnostics(TAR.as_bytes()).1, vec![]);
+
+        let page = "# tar\n\n> Archiving utility.\n\n- Create an archive:\n\n\
+                    `tar cf {{target.tar}\n\n- Extract an archive:\n\n\
+                    - List an archive:\n\n`tar tf {{source.tar}}`\n\n\
+                    > Often combined with gzip.\n\n# tar\n\n`tar --help`\n\nAnything\n";
+        let diagnostics = Page::read_with_diagnostics(page.as_bytes()).1;
+        let diagnostic = |line, message| Diagnostic { line, message };
+        assert_eq!(
+            diagnostics,
+            vec![
+                diagnostic(5, "Example without a command"),
+                diagnostic(7, "Unbalanced backticks in example command"),
+                diagnostic(9, "Example without a command"),
+                diagnostic(15, "Unexpected description after the examples"),
+                diagnostic(17, "Unexpected second title"),
+                diagnostic(19, "Example command without a description"),
+                diagnostic(21, "Unexpected line"),
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "line 5: Example without a command"
+        );
+    }
+
+    #[test]
+    fn test_diagnostics_new_format() {
+        let page =
+            "tar\n===\n\n> Archiving utility.\n\nCreate an archive:\n\n    tar cf {{target\n";
+        assert_eq!(
+            Page::read_with_diagnostics(page.as_bytes()).1,
+            vec![Diagnostic {
+                line: 8,
+                message: "Unbalanced braces in example command",
+            }]
+        );
+        assert_eq!(
+            Page::read_with_diagnostics("".as_bytes()).1,
+            vec![Diagnostic {
+                line: 1,
+                message: "Missing title",
+            }]
+        );
+    }
+
+    #[test]
+    fn test_braces_are_balanced() {
+        assert!(braces_are_balanced("cp {{source}} {{target}}"));
+        assert!(braces_are_balanced("echo {a,b}"));
+        assert!(!braces_are_balanced("cp {{source} {{target}}"));
+        assert!(!braces_are_balanced("cp source}} target"));
+        assert!(!braces_are_balanced("cp {{source"));
+    }
+
+    #[test]
+    fn test_parse_placeholders() {
+        assert_eq!(
+            Placeholder::parse(" path/to/file "),
+            Placeholder::Value("path/to/file")
+        );
+        assert_eq!(
+            Placeholder::parse("start|stop | restart"),
+            Placeholder::Alternatives(vec!["start", "stop", "restart"])
+        );
+        assert_eq!(Placeholder::parse("1..10"), Placeholder::Range("1", "10"));
+        assert_eq!(Placeholder::parse("a..z"), Placeholder::Range("a", "z"));
+
+        // Anything else is a plain value
+        assert_eq!(Placeholder::parse("a||b"), Placeholder::Value("a||b"));
+        assert_eq!(Placeholder::parse("..."), Placeholder::Value("..."));
+        assert_eq!(
+            Placeholder::parse("path/to/../file"),
+            Placeholder::Value("path/to/../file")
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        assert_eq!(tar().to_markdown(), TAR);
+        assert_eq!(Page::read(tar().to_markdown().as_bytes()), tar());
+
+        let mut page = tar();
+        page.more_information = None;
+        page.examples.insert(
+            0,
+            Example {
+                description: String::new(),
+                code: "tar --help".into(),
+            },
+        );
+        page.examples[2].code.clear();
+        assert_eq!(Page::read(page.to_markdown().as_bytes()), page);
+    }
+
+    #[test]
+    fn test_round_trip_fixtures() {
+        let v1 = include_str!("../tests/inkscape-v1.md");
+        let v2 = include_str!("../tests/inkscape-v2.md");
+        assert_eq!(Page::read(v1.as_bytes()).to_markdown(), v1);
+        assert_eq!(Page::read(v2.as_bytes()), Page::read(v1.as_bytes()));
+    }
+}
@@ -0,0 +1,115 @@
+//! Pinned pages, which are protected from updates.
+//!
+//! `tldr --pin <command>` copies the page that is currently shown for the
+//! command into `PINS_DIR` in the cache directory, in the same layout as in
+//! the cache (e.g. `pins/pages/linux/tar.md`). Pinned pages take precedence
+//! over the pages in the cache (but not over custom pages), so updates don't
+//! change them until they are removed with `tldr --unpin <command>`. This
+//! helps users who rely on the documented behavior of an older version of a
+//! tool.
+
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::cache::Cache;
+use crate::error::TealdeerError::{self, CacheError};
+use crate::state;
+use crate::store::{self, DirectoryStore, PageStore};
+use crate::walk::IgnoreRules;
+
+/// The directory inside the cache directory that contains the pinned pages.
+pub const PINS_DIR: &str = "pins";
+
+pub fn pins_dir() -> Result<PathBuf, TealdeerError> {
+    let (cache_dir, _) = Cache::get_cache_dir()?;
+    Ok(cache_dir.join(PINS_DIR))
+}
+
+/// Open the pinned pages, or return `None` if no page was ever pinned.
+pub fn open() -> Option<DirectoryStore> {
+    let dir = pins_dir().ok()?;
+    if dir.is_dir() {
+        Some(DirectoryStore::open(&dir))
+    } else {
+        None
+    }
+}
+
+/// Return the path of the pinned copy of the page at `path` in the cache.
+fn pin_path(pins_dir: &Path, path: &str) -> PathBuf {
+    path.split('/')
+        .fold(pins_dir.to_path_buf(), |pin_path, component| {
+            pin_path.join(component)
+        })
+}
+
+/// Pin `contents` as the page at `path` (e.g. `pages/linux/tar.md`).
+pub fn pin(path: &str, contents: &[u8]) -> Result<(), TealdeerError> {
+    let pin_path = pin_path(&pins_dir()?, path);
+    let pin_err =
+        |e: io::Error| CacheError(format!("Could not pin page {}: {}", path, e)).with_io_hint(&e);
+    if let Some(parent) = pin_path.parent() {
+        fs::create_dir_all(parent).map_err(pin_err)?;
+    }
+    state::write_atomic(&pin_path, contents).map_err(pin_err)
+}
+
+/// Return the paths of all pinned pages (e.g. `pages/linux/tar.md`).
+pub fn pinned_paths(store: &dyn PageStore) -> Vec<String> {
+    store::walk_files(store, "", &IgnoreRules::default())
+        .into_iter()
+        .filter(|path| Path::new(path).extension() == Some(OsStr::new("md")))
+        .collect()
+}
+
+/// Return the name of the page at `path`.
+fn page_name(path: &str) -> &str {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    file_name.trim_end_matches(".md")
+}
+
+/// Return the names of the pinned pages.
+pub fn pinned_names() -> BTreeSet<String> {
+    open().map_or_else(BTreeSet::new, |store| {
+        pinned_paths(&store)
+            .iter()
+            .map(|path| page_name(path).to_string())
+            .collect()
+    })
+}
+
+/// Remove all pinned copies of the page `name`, in all languages and
+/// platforms. Return the number of removed pages.
+pub fn unpin(name: &str) -> Result<usize, TealdeerError> {
+    let pins_dir = pins_dir()?;
+    let paths = open().map_or_else(Vec::new, |store| pinned_paths(&store));
+    let mut removed = 0;
+    for path in paths {
+        if page_name(&path) == name {
+            fs::remove_file(pin_path(&pins_dir, &path))
+                .map_err(|e| CacheError(format!("Could not unpin page {}: {}", path, e)))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{page_name, pin_path};
+
+    use std::path::Path;
+
+    #[test]
+    fn test_pin_path() {
+        let pins_dir = Path::new("/cache/pins");
+        assert_eq!(
+            pin_path(pins_dir, "pages.de/linux/tar.md"),
+            pins_dir.join("pages.de").join("linux").join("tar.md")
+        );
+        assert_eq!(page_name("pages.de/linux/tar.md"), "tar");
+    }
+}
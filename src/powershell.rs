@@ -0,0 +1,148 @@
+//! Integration with PowerShell.
+//!
+//! `tldr --shell-integration powershell` prints a module with a `Get-Tldr`
+//! cmdlet, which completes page names and returns pages as objects with
+//! `-AsObject`. Those objects are read from `--output psobject-json`, which
+//! prints a page like `--output json`, but with `PascalCase` property names
+//! and without placeholders, so that `ConvertFrom-Json` turns it into an
+//! object that looks native to PowerShell.
+
+use serde_derive::Serialize;
+
+use crate::document::Document;
+
+/// The module printed by `tldr --shell-integration powershell`.
+pub const MODULE: &str = r#"# tealdeer module, printed by `tldr --shell-integration powershell`.
+# Save it as tealdeer.psm1 and import it with Import-Module, or load it in
+# your profile with:
+#   tldr --shell-integration powershell | Out-String | Invoke-Expression
+
+function Get-Tldr {
+    <#
+    .SYNOPSIS
+    Show the tldr page of a command.
+    .PARAMETER Name
+    The command to show the page of.
+    .PARAMETER Platform
+    The platform to show the page for, instead of the current one.
+    .PARAMETER Language
+    The language to show the page in.
+    .PARAMETER AsObject
+    Return the page as an object instead of printing it.
+    .EXAMPLE
+    Get-Tldr tar
+    .EXAMPLE
+    (Get-Tldr tar -AsObject).Examples | Select-Object -First 1
+    #>
+    [CmdletBinding()]
+    param(
+        [Parameter(Mandatory = $true, Position = 0, ValueFromPipeline = $true)]
+        [string[]] $Name,
+        [ValidateSet('linux', 'osx', 'freebsd', 'netbsd', 'openbsd', 'sunos', 'windows')]
+        [string] $Platform,
+        [string] $Language,
+        [switch] $AsObject
+    )
+    process {
+        foreach ($command in $Name) {
+            $arguments = @()
+            if ($Platform) { $arguments += '--os', $Platform }
+            if ($Language) { $arguments += '--language', $Language }
+            if ($AsObject) {
+                tldr @arguments --output psobject-json $command | ConvertFrom-Json
+            } else {
+                tldr @arguments $command
+            }
+        }
+    }
+}
+
+Register-ArgumentCompleter -CommandName Get-Tldr -ParameterName Name -ScriptBlock {
+    param($CommandName, $ParameterName, $WordToComplete, $CommandAst, $FakeBoundParameters)
+    tldr --list | Where-Object { $_ -like "$WordToComplete*" } | ForEach-Object {
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }
+}
+"#;
+
+/// An example of a page object.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ExampleObject {
+    pub description: String,
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+}
+
+/// A page, as printed by `--output psobject-json`.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PageObject {
+    pub name: String,
+    pub language: Option<String>,
+    /// The lines of the description, joined with spaces.
+    pub description: String,
+    pub more_information: Option<String>,
+    pub examples: Vec<ExampleObject>,
+}
+
+impl From<Document> for PageObject {
+    fn from(document: Document) -> Self {
+        Self {
+            name: document.name,
+            language: document.language,
+            description: document.description,
+            more_information: document.more_information,
+            examples: document
+                .examples
+                .into_iter()
+                .map(|example| ExampleObject {
+                    description: example.description,
+                    command: example.command,
+                    section: example.section,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ExampleObject, PageObject};
+    use crate::document::Document;
+    use crate::parser::Page;
+
+    #[test]
+    fn test_page_object() {
+        let page = "# tar\n\n> Archiving utility.\n> Often combined with gzip.\n\
+                    > More information: <https://www.gnu.org/software/tar>.\n\n\
+                    - Create an archive:\n\n`tar cf {{target.tar}} {{file}}`\n\n\
+                    - Extract an archive:\n\n`tar xf {{source.tar}}`\n";
+        assert_eq!(
+            PageObject::from(Document::from_page(
+                Page::read(page.as_bytes()),
+                "tar",
+                Some("en".into())
+            )),
+            PageObject {
+                name: "tar".into(),
+                language: Some("en".into()),
+                description: "Archiving utility. Often combined with gzip.".into(),
+                more_information: Some("https://www.gnu.org/software/tar".into()),
+                examples: vec![
+                    ExampleObject {
+                        description: "Create an archive:".into(),
+                        command: "tar cf {{target.tar}} {{file}}".into(),
+                        section: None,
+                    },
+                    ExampleObject {
+                        description: "Extract an archive:".into(),
+                        command: "tar xf {{source.tar}}".into(),
+                        section: None,
+                    },
+                ],
+            }
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Exporting and importing the personal state of tealdeer.
+//!
+//! `tldr --export-profile <file>` writes the pinned pages and the registered
+//! pages repositories into a single TOML file, which can be kept in a
+//! dotfiles repository. `tldr --import-profile <file>` merges such a file
+//! into the state of another machine: Pages that are not pinned yet are
+//! pinned and repositories that are not registered yet are cloned. Pages
+//! that are pinned already are kept as they are. The cache itself is not
+//! part of the profile, it can be downloaded again.
+//!
+//! Profiles carry a version, which is increased whenever the format changes
+//! incompatibly, so that older versions of tealdeer refuse to import profiles
+//! they would misread.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::TealdeerError::{self, CacheError};
+use crate::pins;
+use crate::repos;
+use crate::state;
+use crate::store::PageStore;
+
+/// The version of the profile format written by this version of tealdeer.
+pub const PROFILE_VERSION: u32 = 1;
+
+/// A pinned page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinnedPage {
+    /// The path of the page, e.g. `pages/linux/tar.md`.
+    pub path: String,
+    pub contents: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    pub version: u32,
+    /// The URLs of the registered pages repositories, in the order they were
+    /// added.
+    #[serde(default)]
+    pub repos: Vec<String>,
+    #[serde(default, rename = "pin")]
+    pub pins: Vec<PinnedPage>,
+}
+
+/// What `import` did.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub pinned: Vec<String>,
+    /// Pages that were pinned with other contents already.
+    pub kept: Vec<String>,
+    pub repos_added: Vec<String>,
+    /// Repositories that could not be added, with the reason.
+    pub repos_failed: Vec<(String, TealdeerError)>,
+}
+
+/// Return whether `path` is the path of a page in the layout of the cache,
+/// e.g. `pages.de/linux/tar.md`. Imported profiles must not write anywhere
+/// else.
+fn is_page_path(path: &str) -> bool {
+    let components: Vec<&str> = path.split('/').collect();
+    components.len() == 3
+        && components[0].starts_with("pages")
+        && Path::new(components[2]).extension() == Some(OsStr::new("md"))
+        && components.iter().all(|component| {
+            !component.is_empty()
+                && *component != "."
+                && *component != ".."
+                && !component.contains('\\')
+        })
+}
+
+/// Collect the profile of this machine.
+pub fn collect() -> Result<Profile, TealdeerError> {
+    let mut pins = vec![];
+    if let Some(store) = pins::open() {
+        for path in pins::pinned_paths(&store) {
+            let contents = store
+                .read(&path)
+                .map_err(|e| CacheError(format!("Could not read pinned page {}: {}", path, e)))?;
+            pins.push(PinnedPage {
+                path,
+                contents: String::from_utf8_lossy(&contents).into_owned(),
+            });
+        }
+    }
+    Ok(Profile {
+        version: PROFILE_VERSION,
+        repos: repos::registered()?
+            .into_iter()
+            .map(|repo| repo.url)
+            .collect(),
+        pins,
+    })
+}
+
+/// Write the profile of this machine to `path`.
+pub fn export(path: &Path) -> Result<Profile, TealdeerError> {
+    let profile = collect()?;
+    let serialized = toml::to_string(&profile)
+        .map_err(|e| CacheError(format!("Could not serialize the profile: {}", e)))?;
+    state::write_atomic(path, serialized.as_bytes()).map_err(|e| {
+        CacheError(format!("Could not write {}: {}", path.display(), e)).with_io_hint(&e)
+    })?;
+    Ok(profile)
+}
+
+/// Read the profile at `path`.
+pub fn read(path: &Path) -> Result<Profile, TealdeerError> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        CacheError(format!("Could not read {}: {}", path.display(), e)).with_io_hint(&e)
+    })?;
+    parse(&contents).map_err(|e| match e {
+        CacheError(message) => CacheError(format!("{}: {}", path.display(), message)),
+        e => e,
+    })
+}
+
+fn parse(contents: &str) -> Result<Profile, TealdeerError> {
+    let profile: Profile =
+        toml::from_str(contents).map_err(|e| CacheError(format!("Invalid profile: {}", e)))?;
+    if profile.version > PROFILE_VERSION {
+        return Err(CacheError(format!(
+            "The profile has version {}, but this version of tealdeer only \
+             supports profiles up to version {}.",
+            profile.version, PROFILE_VERSION
+        ))
+        .with_hint("Upgrade tealdeer to import this profile."));
+    }
+    if let Some(pin) = profile.pins.iter().find(|pin| !is_page_path(&pin.path)) {
+        return Err(CacheError(format!(
+            "Invalid path of a pinned page: {:?}",
+            pin.path
+        )));
+    }
+    Ok(profile)
+}
+
+/// Merge `profile` into the state of this machine.
+pub fn import(profile: &Profile) -> Result<ImportSummary, TealdeerError> {
+    let mut summary = ImportSummary::default();
+
+    let store = pins::open();
+    for pin in &profile.pins {
+        let existing = store.as_ref().and_then(|store| store.read(&pin.path).ok());
+        match existing {
+            Some(ref contents) if contents == pin.contents.as_bytes() => {}
+            Some(_) => summary.kept.push(pin.path.clone()),
+            None => {
+                pins::pin(&pin.path, pin.contents.as_bytes())?;
+                summary.pinned.push(pin.path.clone());
+            }
+        }
+    }
+
+    let registered: Vec<String> = repos::registered()?
+        .into_iter()
+        .map(|repo| repo.url)
+        .collect();
+    for url in &profile.repos {
+        if registered.contains(url) {
+            continue;
+        }
+        match repos::add(url) {
+            Ok(_) => summary.repos_added.push(url.clone()),
+            Err(e) => summary.repos_failed.push((url.clone(), e)),
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_page_path, parse, PinnedPage, Profile, PROFILE_VERSION};
+
+    #[test]
+    fn test_is_page_path() {
+        assert!(is_page_path("pages/linux/tar.md"));
+        assert!(is_page_path("pages.de/common/tar.md"));
+        assert!(!is_page_path("pages/linux/tar.txt"));
+        assert!(!is_page_path("pages/../../.bashrc.md"));
+        assert!(!is_page_path("pages/linux/sub/tar.md"));
+        assert!(!is_page_path("config/linux/tar.md"));
+        assert!(!is_page_path("pages/linux\\..\\x/tar.md"));
+    }
+
+    #[test]
+    fn test_parse() {
+        let profile = Profile {
+            version: PROFILE_VERSION,
+            repos: vec!["https://git.example.com/pages.git".into()],
+            pins: vec![PinnedPage {
+                path: "pages/common/tar.md".into(),
+                contents: "# tar\n".into(),
+            }],
+        };
+        assert_eq!(parse(&toml::to_string(&profile).unwrap()).unwrap(), profile);
+
+        let newer = format!("version = {}\n", PROFILE_VERSION + 1);
+        assert!(parse(&newer)
+            .unwrap_err()
+            .message()
+            .contains("only supports profiles up to version"));
+        let escaping = "version = 1\n[[pin]]\npath = \"../x.md\"\ncontents = \"\"\n";
+        assert!(parse(escaping).is_err());
+    }
+}
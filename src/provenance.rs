@@ -0,0 +1,177 @@
+//! Where a page comes from and under which license it may be reused.
+//!
+//! Pages from the cache belong to the tldr-pages project and are licensed
+//! under CC BY 4.0, which requires attribution when they are reproduced (e.g.
+//! in generated documentation). Custom pages, pages from repositories added
+//! with `--add-pages-repo` and local files are reported with their origin, but
+//! without a license, which is up to their authors.
+
+use std::path::{Path, PathBuf};
+
+use serde_derive::Serialize;
+
+use crate::cache::Cache;
+use crate::cache_info::CacheInfo;
+use crate::generation;
+use crate::layout::TLDR_PAGES_DIR;
+use crate::pins::PINS_DIR;
+use crate::repos;
+use crate::store::UNPACKED_DIR;
+
+/// The tldr-pages project, which the pages in the cache belong to.
+pub const TLDR_PAGES_PROJECT: &str = "tldr-pages";
+pub const TLDR_PAGES_URL: &str = "https://github.com/tldr-pages/tldr";
+
+/// A license of pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct License {
+    pub name: &'static str,
+    pub url: &'static str,
+}
+
+/// The license of the tldr-pages project.
+pub const CC_BY_4_0: License = License {
+    name: "CC BY 4.0",
+    url: "https://creativecommons.org/licenses/by/4.0/",
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Origin {
+    /// A page from the cache.
+    TldrPages,
+    /// A page from the custom pages directory.
+    Custom,
+    /// A page from a repository added with `--add-pages-repo`.
+    Repository,
+    /// Any other file, e.g. rendered with `--render`.
+    Local,
+}
+
+/// The origin of a page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Provenance {
+    pub origin: Origin,
+    /// The project the page belongs to, if known.
+    pub project: Option<&'static str>,
+    pub project_url: Option<&'static str>,
+    pub license: Option<License>,
+    /// The path of the page in its project or repository.
+    pub upstream_path: Option<String>,
+    /// Where the page was downloaded or cloned from, if known.
+    pub source_url: Option<String>,
+    /// The local file the page was read from.
+    pub path: PathBuf,
+}
+
+/// Return `path` relative to `dir` with `/` as separator, if it is inside.
+fn relative_path(path: &Path, dir: &Path) -> Option<String> {
+    let relative = path.strip_prefix(dir).ok()?;
+    Some(
+        relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+impl Provenance {
+    fn new(origin: Origin, path: &Path) -> Self {
+        Self {
+            origin,
+            project: None,
+            project_url: None,
+            license: None,
+            upstream_path: None,
+            source_url: None,
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Determine the origin of the page at `path`.
+    pub fn of_page(path: &Path, custom_pages_dir: Option<&Path>) -> Self {
+        if let Ok((cache_dir, _)) = Cache::get_cache_dir() {
+            let active = generation::resolve_active(&cache_dir);
+            let pages_dirs = [
+                active.join(UNPACKED_DIR),
+                active,
+                cache_dir.join(TLDR_PAGES_DIR),
+                cache_dir.join(PINS_DIR),
+            ];
+            if let Some(upstream_path) = pages_dirs.iter().find_map(|dir| relative_path(path, dir))
+            {
+                return Self {
+                    project: Some(TLDR_PAGES_PROJECT),
+                    project_url: Some(TLDR_PAGES_URL),
+                    license: Some(CC_BY_4_0),
+                    upstream_path: Some(upstream_path),
+                    source_url: CacheInfo::load(&cache_dir)
+                        .ok()
+                        .flatten()
+                        .and_then(|info| info.source_url),
+                    ..Self::new(Origin::TldrPages, path)
+                };
+            }
+        }
+
+        if let Some((repo, dir)) = repos::repo_containing(path) {
+            return Self {
+                upstream_path: relative_path(path, &dir),
+                source_url: Some(repo.url),
+                ..Self::new(Origin::Repository, path)
+            };
+        }
+
+        match custom_pages_dir {
+            Some(dir) if path.starts_with(dir) => Self::new(Origin::Custom, path),
+            _ => Self::new(Origin::Local, path),
+        }
+    }
+
+    /// A sentence crediting the authors of the page `command`, or `None` if
+    /// there is no license requiring attribution.
+    pub fn attribution(&self, command: &str) -> Option<String> {
+        let license = self.license?;
+        Some(format!(
+            "The \"{}\" page is from the {} project ({}), licensed under {} ({}).",
+            command, self.project?, self.project_url?, license.name, license.url
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{relative_path, Origin, Provenance, CC_BY_4_0};
+
+    use std::path::Path;
+
+    #[test]
+    fn test_relative_path() {
+        let dir = Path::new("/cache/tldr-master");
+        assert_eq!(
+            relative_path(&dir.join("pages").join("common").join("tar.md"), dir).as_deref(),
+            Some("pages/common/tar.md")
+        );
+        assert_eq!(relative_path(Path::new("/tmp/tar.md"), dir), None);
+    }
+
+    #[test]
+    fn test_attribution() {
+        let custom = Provenance::new(Origin::Custom, Path::new("tar.page.md"));
+        assert_eq!(custom.attribution("tar"), None);
+
+        let upstream = Provenance {
+            project: Some("tldr-pages"),
+            project_url: Some("https://github.com/tldr-pages/tldr"),
+            license: Some(CC_BY_4_0),
+            ..Provenance::new(Origin::TldrPages, Path::new("tar.md"))
+        };
+        assert_eq!(
+            upstream.attribution("tar").unwrap(),
+            "The \"tar\" page is from the tldr-pages project \
+             (https://github.com/tldr-pages/tldr), licensed under CC BY 4.0 \
+             (https://creativecommons.org/licenses/by/4.0/)."
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Validating the pages of a new generation before it is activated.
+//!
+//! A single malformed file in the pages archive (binary data, a page without
+//! a title, a huge file) would otherwise break listing, indexing and
+//! searching pages until the next update fixes it upstream. Every update
+//! checks that the pages look like tldr pages before the new generation is
+//! activated. Invalid pages are moved to `QUARANTINE_DIR` in the cache
+//! directory, where they can be inspected, and reported after the update.
+//! The quarantine only keeps the pages of the last update.
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::error::TealdeerError::{self, UpdateError};
+use crate::walk::{walk, IgnoreRules};
+
+/// The directory inside the cache directory that invalid pages are moved to.
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+/// The maximum size of a page in bytes. The largest upstream pages are a few
+/// kilobytes.
+pub const MAX_PAGE_SIZE: u64 = 64 * 1024;
+
+/// Why a page is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The page is larger than `MAX_PAGE_SIZE`, with its size in bytes.
+    TooLarge(u64),
+    NotUtf8,
+    /// The page does not start with a `# <title>` line.
+    NoTitle,
+    Unreadable(String),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge(size) => write!(
+                f,
+                "too large ({} bytes, at most {} are allowed)",
+                size, MAX_PAGE_SIZE
+            ),
+            Self::NotUtf8 => write!(f, "not valid UTF-8"),
+            Self::NoTitle => write!(f, "does not start with a `# <title>` line"),
+            Self::Unreadable(e) => write!(f, "could not be read: {}", e),
+        }
+    }
+}
+
+/// A page that was moved to the quarantine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuarantinedPage {
+    /// The path of the page, e.g. `pages/common/tar.md`.
+    pub path: String,
+    pub problem: Problem,
+}
+
+/// Check whether `contents` look like a tldr page.
+pub fn check_page(contents: &[u8]) -> Result<(), Problem> {
+    if contents.len() as u64 > MAX_PAGE_SIZE {
+        return Err(Problem::TooLarge(contents.len() as u64));
+    }
+    let text = std::str::from_utf8(contents).map_err(|_| Problem::NotUtf8)?;
+    let first_line = text
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("");
+    if first_line.starts_with("# ") && !first_line[2..].trim().is_empty() {
+        Ok(())
+    } else {
+        Err(Problem::NoTitle)
+    }
+}
+
+/// Check a page file, without reading files that are too large.
+fn check_page_file(path: &Path) -> Result<(), Problem> {
+    let size = fs::metadata(path)
+        .map_err(|e| Problem::Unreadable(e.to_string()))?
+        .len();
+    if size > MAX_PAGE_SIZE {
+        return Err(Problem::TooLarge(size));
+    }
+    check_page(&fs::read(path).map_err(|e| Problem::Unreadable(e.to_string()))?)
+}
+
+/// Move the invalid pages (`*.md` files in the pages directories) below
+/// `pages_dir` to `quarantine_dir`, replacing the pages quarantined before.
+/// Return the moved pages, sorted by path.
+pub fn quarantine_invalid_pages(
+    pages_dir: &Path,
+    quarantine_dir: &Path,
+) -> Result<Vec<QuarantinedPage>, TealdeerError> {
+    let quarantine_err = |e: std::io::Error| {
+        UpdateError(format!(
+            "Could not move invalid pages to {}: {}",
+            quarantine_dir.display(),
+            e
+        ))
+        .with_io_hint(&e)
+    };
+    if quarantine_dir.exists() {
+        fs::remove_dir_all(quarantine_dir).map_err(quarantine_err)?;
+    }
+
+    let rules = IgnoreRules::default();
+    let mut quarantined = vec![];
+    for entry in walk(pages_dir, 3, &rules, |_| true).filter_map(Result::ok) {
+        let relative = match entry.path().strip_prefix(pages_dir) {
+            Ok(relative) => relative,
+            Err(_) => continue,
+        };
+        let in_pages_dir = relative
+            .components()
+            .next()
+            .and_then(|dir| dir.as_os_str().to_str())
+            .map_or(false, |dir| dir == "pages" || dir.starts_with("pages."));
+        if entry.depth() != 3
+            || !in_pages_dir
+            || !entry.file_type().is_file()
+            || entry.path().extension() != Some(OsStr::new("md"))
+        {
+            continue;
+        }
+        if let Err(problem) = check_page_file(entry.path()) {
+            let target = quarantine_dir.join(relative);
+            if let Some(dir) = target.parent() {
+                fs::create_dir_all(dir).map_err(quarantine_err)?;
+            }
+            fs::rename(entry.path(), &target).map_err(quarantine_err)?;
+            let path: Vec<_> = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect();
+            quarantined.push(QuarantinedPage {
+                path: path.join("/"),
+                problem,
+            });
+        }
+    }
+    quarantined.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(quarantined)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_page, quarantine_invalid_pages, Problem, QuarantinedPage, MAX_PAGE_SIZE};
+
+    use std::fs;
+    use std::path::Path;
+
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check_page() {
+        assert_eq!(check_page(b"# tar\n\n> Archiving utility.\n"), Ok(()));
+        assert_eq!(check_page(b"\xef\xbb\xbf\n# tar\n"), Ok(()));
+        assert_eq!(check_page(b"> No title.\n"), Err(Problem::NoTitle));
+        assert_eq!(check_page(b"#\n"), Err(Problem::NoTitle));
+        assert_eq!(check_page(b""), Err(Problem::NoTitle));
+        assert_eq!(check_page(b"# tar\n\xff\xfe"), Err(Problem::NotUtf8));
+        let large = vec![b'#'; MAX_PAGE_SIZE as usize + 1];
+        assert_eq!(
+            check_page(&large),
+            Err(Problem::TooLarge(MAX_PAGE_SIZE + 1))
+        );
+    }
+
+    fn write(root: &Path, path: &str, contents: &[u8]) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_quarantine_corrupted_corpus() {
+        let dir = TempDir::new().unwrap();
+        let pages_dir = dir.path().join("pages-root");
+        let quarantine_dir = dir.path().join("quarantine");
+        write(&pages_dir, "pages/common/tar.md", b"# tar\n\n> Archive.\n");
+        write(
+            &pages_dir,
+            "pages.de/linux/ls.md",
+            b"# ls\n\n> Auflisten.\n",
+        );
+        write(&pages_dir, "pages/common/binary.md", b"\x00\x9f\x92\x96");
+        write(&pages_dir, "pages.de/common/untitled.md", b"> No title.\n");
+        write(
+            &pages_dir,
+            "pages/linux/huge.md",
+            &vec![b'a'; MAX_PAGE_SIZE as usize + 1],
+        );
+        // Files that are not pages are left alone
+        write(&pages_dir, "pages/README.md", b"Not a page.\n");
+        write(&pages_dir, "pages/common/notes.txt", b"\xff");
+        write(&quarantine_dir, "pages/common/old.md", b"");
+
+        let quarantined = quarantine_invalid_pages(&pages_dir, &quarantine_dir).unwrap();
+        assert_eq!(
+            quarantined,
+            vec![
+                QuarantinedPage {
+                    path: "pages.de/common/untitled.md".into(),
+                    problem: Problem::NoTitle,
+                },
+                QuarantinedPage {
+                    path: "pages/common/binary.md".into(),
+                    problem: Problem::NotUtf8,
+                },
+                QuarantinedPage {
+                    path: "pages/linux/huge.md".into(),
+                    problem: Problem::TooLarge(MAX_PAGE_SIZE + 1),
+                },
+            ]
+        );
+        assert!(pages_dir.join("pages/common/tar.md").is_file());
+        assert!(pages_dir.join("pages.de/linux/ls.md").is_file());
+        assert!(pages_dir.join("pages/README.md").is_file());
+        assert!(pages_dir.join("pages/common/notes.txt").is_file());
+        assert!(!pages_dir.join("pages/common/binary.md").exists());
+        assert!(quarantine_dir.join("pages/common/binary.md").is_file());
+        assert!(quarantine_dir.join("pages/linux/huge.md").is_file());
+        assert!(!quarantine_dir.join("pages/common/old.md").exists());
+    }
+}
@@ -0,0 +1,225 @@
+//! Quoting of values for different shells.
+//!
+//! Values substituted into example commands must be quoted according to the
+//! rules of the shell the command will be run in, so that values containing
+//! spaces or special characters are passed as a single, literal argument.
+
+use std::borrow::Cow;
+use std::env;
+use std::fmt::Write;
+
+use serde_derive::Deserialize;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[allow(clippy::enum_variant_names)]
+pub enum Shell {
+    /// POSIX compatible shells (sh, bash, zsh, ...)
+    Bash,
+    Fish,
+    PowerShell,
+    Cmd,
+    Nushell,
+}
+
+impl Shell {
+    /// Guess the shell of the user from the environment.
+    pub fn detect() -> Self {
+        if cfg!(windows) {
+            return Self::PowerShell;
+        }
+        match env::var("SHELL") {
+            Ok(ref shell) if shell.ends_with("/fish") || shell == "fish" => Self::Fish,
+            Ok(ref shell) if shell.ends_with("/pwsh") || shell == "pwsh" => Self::PowerShell,
+            Ok(ref shell) if shell.ends_with("/nu") || shell == "nu" => Self::Nushell,
+            _ => Self::Bash,
+        }
+    }
+}
+
+/// Return whether `c` never needs to be quoted in `shell`.
+fn is_safe_char(c: char, shell: Shell) -> bool {
+    match c {
+        'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' | '/' | ':' | '+' => true,
+        // Commas build arrays in PowerShell and separate arguments in cmd
+        ',' => shell == Shell::Bash || shell == Shell::Fish,
+        // A leading `@` is used for splatting in PowerShell
+        '@' => shell != Shell::PowerShell,
+        // Backslashes are path separators on Windows and not special there
+        '\\' => shell == Shell::Cmd || shell == Shell::PowerShell,
+        _ => false,
+    }
+}
+
+/// Quote `value` so that `shell` passes it to the command as a single,
+/// literal argument.
+///
+/// Values that only consist of characters without special meaning are
+/// returned as is.
+pub fn quote(value: &str, shell: Shell) -> Cow<'_, str> {
+    if !value.is_empty() && value.chars().all(|c| is_safe_char(c, shell)) {
+        return Cow::Borrowed(value);
+    }
+    Cow::Owned(match shell {
+        // Single quotes preserve everything, a single quote is written as
+        // `'\''` (end quote, escaped quote, start quote).
+        Shell::Bash => format!("'{}'", value.replace('\'', "'\\''")),
+        // Inside single quotes, fish only treats `\'` and `\\` specially.
+        Shell::Fish => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'")),
+        // Inside single quotes, PowerShell only treats (typographic) single
+        // quotes specially, which are escaped by doubling them.
+        Shell::PowerShell => {
+            let mut quoted = String::with_capacity(value.len() + 2);
+            quoted.push('\'');
+            for c in value.chars() {
+                if c == '\'' || c == '\u{2018}' || c == '\u{2019}' {
+                    quoted.push(c);
+                }
+                quoted.push(c);
+            }
+            quoted.push('\'');
+            quoted
+        }
+        Shell::Cmd => quote_cmd(value),
+        Shell::Nushell => nushell_string(value),
+    })
+}
+
+/// Write `value` as a double-quoted Nushell string, which is also how
+/// strings are written in NUON.
+pub fn nushell_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{{{:x}}}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Quote `value` for the Windows command prompt.
+///
+/// This follows the rules most programs use to split their command line
+/// into arguments: Double quotes are escaped with a backslash, and
+/// backslashes are only special in front of a double quote. Note that `cmd`
+/// offers no way to escape `%` inside double quotes, so environment
+/// variables in values are still expanded.
+fn quote_cmd(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    let mut backslashes = 0;
+    for c in value.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Escape the preceding backslashes and the quote itself
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    // Backslashes in front of the closing quote must be escaped as well
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod test {
+    use super::{quote, Shell};
+
+    #[test]
+    fn test_safe_values() {
+        for &shell in &[
+            Shell::Bash,
+            Shell::Fish,
+            Shell::PowerShell,
+            Shell::Cmd,
+            Shell::Nushell,
+        ] {
+            assert_eq!(quote("path/to/file.txt", shell), "path/to/file.txt");
+            assert_eq!(quote("host:22", shell), "host:22");
+        }
+        assert_eq!(quote("user@host", Shell::Bash), "user@host");
+        assert_eq!(quote("user@host", Shell::PowerShell), "'user@host'");
+        assert_eq!(quote("a,b", Shell::Fish), "a,b");
+        assert_eq!(quote("a,b", Shell::Cmd), "\"a,b\"");
+    }
+
+    #[test]
+    fn test_empty_value() {
+        assert_eq!(quote("", Shell::Bash), "''");
+        assert_eq!(quote("", Shell::Fish), "''");
+        assert_eq!(quote("", Shell::PowerShell), "''");
+        assert_eq!(quote("", Shell::Cmd), "\"\"");
+        assert_eq!(quote("", Shell::Nushell), "\"\"");
+    }
+
+    #[test]
+    fn test_bash() {
+        assert_eq!(quote("my file", Shell::Bash), "'my file'");
+        assert_eq!(quote("it's", Shell::Bash), "'it'\\''s'");
+        assert_eq!(quote("$HOME `id` \\n", Shell::Bash), "'$HOME `id` \\n'");
+        assert_eq!(quote("*.txt", Shell::Bash), "'*.txt'");
+    }
+
+    #[test]
+    fn test_fish() {
+        assert_eq!(quote("my file", Shell::Fish), "'my file'");
+        assert_eq!(quote("it's", Shell::Fish), "'it\\'s'");
+        assert_eq!(quote("a\\b", Shell::Fish), "'a\\\\b'");
+        assert_eq!(quote("$HOME (id)", Shell::Fish), "'$HOME (id)'");
+    }
+
+    #[test]
+    fn test_powershell() {
+        assert_eq!(quote("my file", Shell::PowerShell), "'my file'");
+        assert_eq!(quote("it's", Shell::PowerShell), "'it''s'");
+        assert_eq!(
+            quote("it\u{2019}s", Shell::PowerShell),
+            "'it\u{2019}\u{2019}s'"
+        );
+        assert_eq!(quote("$env:PATH", Shell::PowerShell), "'$env:PATH'");
+        assert_eq!(quote("C:\\Users", Shell::PowerShell), "C:\\Users");
+    }
+
+    #[test]
+    fn test_cmd() {
+        assert_eq!(quote("my file", Shell::Cmd), "\"my file\"");
+        assert_eq!(quote("say \"hi\"", Shell::Cmd), "\"say \\\"hi\\\"\"");
+        assert_eq!(
+            quote("C:\\Program Files\\", Shell::Cmd),
+            "\"C:\\Program Files\\\\\""
+        );
+        assert_eq!(quote("a\\\"b", Shell::Cmd), "\"a\\\\\\\"b\"");
+        assert_eq!(quote("a & b", Shell::Cmd), "\"a & b\"");
+    }
+
+    #[test]
+    fn test_nushell() {
+        assert_eq!(quote("my file", Shell::Nushell), "\"my file\"");
+        assert_eq!(quote("say \"hi\"", Shell::Nushell), "\"say \\\"hi\\\"\"");
+        assert_eq!(quote("C:\\Users", Shell::Nushell), "\"C:\\\\Users\"");
+        assert_eq!(
+            quote("$env.PATH (ls)", Shell::Nushell),
+            "\"$env.PATH (ls)\""
+        );
+        assert_eq!(quote("a\nb\u{1b}", Shell::Nushell), "\"a\\nb\\u{1b}\"");
+    }
+}
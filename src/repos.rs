@@ -0,0 +1,353 @@
+//! Custom pages from git repositories.
+//!
+//! `tldr --add-pages-repo <url>` clones a git repository of custom pages into
+//! `REPOS_DIR` in the cache directory and registers it, so that teams can
+//! distribute pages for their internal tools. Like in the custom pages
+//! directory, pages are called `<name>.page.md` and live at the top level of
+//! the repository. Pages in the configured custom pages directory take
+//! precedence, followed by the repositories in the order they were added.
+//! Every update of the cache also pulls all registered repositories.
+//!
+//! Git is run as an external command. Cloning and pulling can be left out by
+//! building without the `git` feature, already cloned repositories are still
+//! used in that case.
+
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::cache::Cache;
+use crate::cache_info::sha256_hex;
+use crate::error::TealdeerError::{self, CacheError, UpdateError};
+use crate::state;
+
+/// The directory inside the cache directory that contains the repositories.
+pub const REPOS_DIR: &str = "repos";
+
+/// The file inside `REPOS_DIR` that lists the registered repositories.
+const REGISTRY_FILE_NAME: &str = "repos.toml";
+
+/// The tldr-pages repository, cloned by updates with the `git` pages source.
+pub const TLDR_PAGES_GIT_URL: &str = "https://github.com/tldr-pages/tldr.git";
+
+/// A registered repository of pages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PagesRepo {
+    pub url: String,
+    /// The name of the clone inside `REPOS_DIR`.
+    pub dir: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Registry {
+    #[serde(default, rename = "repo")]
+    repos: Vec<PagesRepo>,
+}
+
+/// What `add` did.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+pub enum AddOutcome {
+    Cloned,
+    /// The repository was already registered and has been pulled instead.
+    Pulled,
+}
+
+fn repos_dir() -> Result<PathBuf, TealdeerError> {
+    let (cache_dir, _) = Cache::get_cache_dir()?;
+    Ok(cache_dir.join(REPOS_DIR))
+}
+
+fn load_registry() -> Result<Registry, TealdeerError> {
+    let contents = state::load(&repos_dir()?.join(REGISTRY_FILE_NAME))
+        .map_err(|e| CacheError(format!("Could not read pages repositories: {}", e)))?;
+    match contents {
+        Some(contents) => toml::from_str(&contents)
+            .map_err(|e| CacheError(format!("Invalid list of pages repositories: {}", e))),
+        None => Ok(Registry::default()),
+    }
+}
+
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+fn save_registry(registry: &Registry) -> Result<(), TealdeerError> {
+    let serialized = toml::to_string(registry)
+        .map_err(|e| CacheError(format!("Could not serialize pages repositories: {}", e)))?;
+    state::save(&repos_dir()?.join(REGISTRY_FILE_NAME), &serialized).map_err(|e| {
+        CacheError(format!("Could not write pages repositories: {}", e)).with_io_hint(&e)
+    })
+}
+
+/// Return the name of the directory to clone the repository at `url` into.
+///
+/// The name consists of the last component of the URL, which is readable,
+/// and a part of its checksum, which tells apart repositories with the same
+/// name on different hosts.
+pub fn dir_name(url: &str) -> String {
+    let last_component = url
+        .trim_end_matches('/')
+        .rsplit(&['/', ':', '\\'][..])
+        .next()
+        .unwrap_or("");
+    let name: String = last_component
+        .trim_end_matches(".git")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}-{}", name, &sha256_hex(url.as_bytes())[..8])
+}
+
+/// Return the registered repositories, in the order they were added.
+pub fn registered() -> Result<Vec<PagesRepo>, TealdeerError> {
+    Ok(load_registry()?.repos)
+}
+
+/// Return the directories of all registered repositories, in the order they
+/// were added.
+///
+/// Repositories that can not be read are skipped, so that a broken registry
+/// never prevents pages from being shown.
+pub fn page_dirs() -> Vec<PathBuf> {
+    let repos_dir = match repos_dir() {
+        Ok(dir) => dir,
+        Err(_) => return vec![],
+    };
+    match load_registry() {
+        Ok(registry) => registry
+            .repos
+            .into_iter()
+            .map(|repo| repos_dir.join(repo.dir))
+            .filter(|dir| dir.is_dir())
+            .collect(),
+        Err(e) => {
+            debug!("Ignoring pages repositories: {}", e);
+            vec![]
+        }
+    }
+}
+
+/// Return the registered repository containing the file at `path`, and the
+/// directory of its clone.
+pub fn repo_containing(path: &Path) -> Option<(PagesRepo, PathBuf)> {
+    let repos_dir = repos_dir().ok()?;
+    load_registry().ok()?.repos.into_iter().find_map(|repo| {
+        let dir = repos_dir.join(&repo.dir);
+        if path.starts_with(&dir) {
+            Some((repo, dir))
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(feature = "git")]
+mod git {
+    use std::ffi::OsStr;
+    use std::io;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+
+    use log::debug;
+
+    /// Run git with `args`, returning its error output if it fails.
+    fn git<I, S>(args: I) -> Result<(), String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut command = Command::new("git");
+        command
+            .args(args)
+            // Fail instead of waiting for credentials
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null());
+        debug!("Running {:?}", command);
+        let output = command.output().map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => String::from("git is not installed"),
+            _ => format!("Could not run git: {}", e),
+        })?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        }
+    }
+
+    pub fn clone(url: &str, dir: &Path) -> Result<(), String> {
+        git(&[
+            OsStr::new("clone"),
+            OsStr::new("--quiet"),
+            OsStr::new("--depth=1"),
+            OsStr::new("--"),
+            OsStr::new(url),
+            dir.as_os_str(),
+        ])
+    }
+
+    pub fn pull(dir: &Path) -> Result<(), String> {
+        git(&[
+            OsStr::new("-C"),
+            dir.as_os_str(),
+            OsStr::new("pull"),
+            OsStr::new("--quiet"),
+            OsStr::new("--ff-only"),
+        ])
+    }
+
+    /// Check out the latest commit of the default branch of `url` in the
+    /// clone at `dir`, without fetching the history.
+    pub fn fetch_latest(url: &str, dir: &Path) -> Result<(), String> {
+        git(&[
+            OsStr::new("-C"),
+            dir.as_os_str(),
+            OsStr::new("fetch"),
+            OsStr::new("--quiet"),
+            OsStr::new("--depth=1"),
+            OsStr::new("--"),
+            OsStr::new(url),
+            OsStr::new("HEAD"),
+        ])?;
+        git(&[
+            OsStr::new("-C"),
+            dir.as_os_str(),
+            OsStr::new("reset"),
+            OsStr::new("--quiet"),
+            OsStr::new("--hard"),
+            OsStr::new("FETCH_HEAD"),
+        ])
+    }
+}
+
+/// Clone the repository at `url` into `dir` (shallow), or update the
+/// existing clone to the latest commit.
+///
+/// The clone is updated by fetching from `url` directly, so changing the URL
+/// needs no new clone, and force pushes upstream don't break it.
+#[cfg(feature = "git")]
+pub fn sync_clone(url: &str, dir: &Path) -> Result<(), TealdeerError> {
+    let synced = if dir.join(".git").is_dir() {
+        debug!("Fetching {} into {}", url, dir.display());
+        git::fetch_latest(url, dir)
+    } else {
+        debug!("Cloning {} into {}", url, dir.display());
+        let cloned = git::clone(url, dir);
+        // Don't leave an incomplete clone behind
+        if cloned.is_err() && dir.exists() {
+            if let Err(e) = std::fs::remove_dir_all(dir) {
+                debug!("Could not remove {}: {}", dir.display(), e);
+            }
+        }
+        cloned
+    };
+    synced.map_err(|e| UpdateError(format!("Could not clone or fetch {}: {}", url, e)))
+}
+
+#[cfg(not(feature = "git"))]
+pub fn sync_clone(_url: &str, _dir: &Path) -> Result<(), TealdeerError> {
+    Err(UpdateError(
+        "tealdeer was built without support for git repositories (the `git` feature).".into(),
+    ))
+}
+
+/// Clone the repository at `url` and register it.
+///
+/// If the repository is registered already, it is pulled instead.
+#[cfg(feature = "git")]
+pub fn add(url: &str) -> Result<AddOutcome, TealdeerError> {
+    use std::fs;
+
+    let repos_dir = repos_dir()?;
+    let mut registry = load_registry()?;
+    if let Some(repo) = registry.repos.iter().find(|repo| repo.url == url) {
+        let dir = repos_dir.join(&repo.dir);
+        if dir.is_dir() {
+            git::pull(&dir).map_err(|e| {
+                UpdateError(format!("Could not pull pages repository {}: {}", url, e))
+            })?;
+            return Ok(AddOutcome::Pulled);
+        }
+    }
+
+    fs::create_dir_all(&repos_dir).map_err(|e| {
+        CacheError(format!("Could not create repositories directory: {}", e)).with_io_hint(&e)
+    })?;
+    let dir_name = dir_name(url);
+    let dir = repos_dir.join(&dir_name);
+    if let Err(e) = git::clone(url, &dir) {
+        if dir.exists() {
+            if let Err(e) = fs::remove_dir_all(&dir) {
+                debug!("Could not remove {}: {}", dir.display(), e);
+            }
+        }
+        return Err(UpdateError(format!(
+            "Could not clone pages repository {}: {}",
+            url, e
+        )));
+    }
+
+    if registry.repos.iter().all(|repo| repo.url != url) {
+        registry.repos.push(PagesRepo {
+            url: url.into(),
+            dir: dir_name,
+        });
+    }
+    save_registry(&registry)?;
+    Ok(AddOutcome::Cloned)
+}
+
+#[cfg(not(feature = "git"))]
+pub fn add(_url: &str) -> Result<AddOutcome, TealdeerError> {
+    Err(UpdateError(
+        "tealdeer was built without support for git repositories (the `git` feature).".into(),
+    ))
+}
+
+/// Pull all registered repositories.
+///
+/// Returns the repositories that could not be pulled, with the reason.
+#[cfg(feature = "git")]
+pub fn update_all() -> Result<Vec<(PagesRepo, String)>, TealdeerError> {
+    let repos_dir = repos_dir()?;
+    Ok(load_registry()?
+        .repos
+        .into_iter()
+        .filter_map(|repo| {
+            debug!("Pulling pages repository {}", repo.url);
+            match git::pull(&repos_dir.join(&repo.dir)) {
+                Ok(()) => None,
+                Err(e) => Some((repo, e)),
+            }
+        })
+        .collect())
+}
+
+#[cfg(not(feature = "git"))]
+#[allow(clippy::unnecessary_wraps)] // Same signature as with the `git` feature
+pub fn update_all() -> Result<Vec<(PagesRepo, String)>, TealdeerError> {
+    Ok(vec![])
+}
+
+#[cfg(test)]
+mod test {
+    use super::dir_name;
+
+    #[test]
+    fn test_dir_name() {
+        let name = dir_name("https://git.example.com/team/tldr-pages.git");
+        assert!(name.starts_with("tldr-pages-"), "{}", name);
+        assert_eq!(name.len(), "tldr-pages-".len() + 8);
+        assert!(dir_name("git@example.com:team/pages/").starts_with("pages-"));
+        assert!(dir_name("/srv/git/my pages").starts_with("my_pages-"));
+        assert_ne!(
+            dir_name("https://a.example.com/pages"),
+            dir_name("https://b.example.com/pages")
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Rewriting command names before looking up their pages.
+//!
+//! Rules from the config map command names that have no page of their own to
+//! the name of the canonical page, e.g. versioned binaries (`python3.12` to
+//! `python3`) or tools renamed by a distribution (`fdfind` to `fd`). Every
+//! rule is a pair of a regular expression, which has to match the whole name,
+//! and a replacement, which may refer to capture groups (`$1`). The first
+//! matching rule wins.
+
+use std::borrow::Cow;
+
+use log::debug;
+use regex::Regex;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+#[derive(Debug, Clone)]
+struct RewriteRule {
+    pattern: String,
+    replacement: String,
+    regex: Regex,
+}
+
+/// The rules for rewriting command names, in order.
+#[derive(Debug, Clone, Default)]
+pub struct RewriteRules {
+    rules: Vec<RewriteRule>,
+}
+
+impl RewriteRules {
+    /// Create rules from `(pattern, replacement)` pairs.
+    pub fn new<'a, I>(rules: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let rules = rules
+            .into_iter()
+            .map(|(pattern, replacement)| {
+                let regex = Regex::new(&format!("^(?:{})$", pattern))
+                    .map_err(|e| format!("Invalid rewrite pattern {:?}: {}", pattern, e))?;
+                Ok(RewriteRule {
+                    pattern: pattern.into(),
+                    replacement: replacement.into(),
+                    regex,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Rewrite the command `name` with the first matching rule.
+    ///
+    /// If no rule matches, the name is returned unchanged.
+    pub fn apply<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        for rule in &self.rules {
+            if rule.regex.is_match(name) {
+                let rewritten = rule.regex.replace(name, rule.replacement.as_str());
+                debug!(
+                    "Rewrote {:?} to {:?} (rule {:?} -> {:?})",
+                    name, rewritten, rule.pattern, rule.replacement
+                );
+                return Cow::Owned(rewritten.into_owned());
+            }
+        }
+        Cow::Borrowed(name)
+    }
+}
+
+impl PartialEq for RewriteRules {
+    fn eq(&self, other: &Self) -> bool {
+        self.rules.len() == other.rules.len()
+            && self
+                .rules
+                .iter()
+                .zip(&other.rules)
+                .all(|(a, b)| a.pattern == b.pattern && a.replacement == b.replacement)
+    }
+}
+
+impl Eq for RewriteRules {}
+
+impl<'de> Deserialize<'de> for RewriteRules {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pairs = Vec::<(String, String)>::deserialize(deserializer)?;
+        Self::new(
+            pairs
+                .iter()
+                .map(|(pattern, replacement)| (pattern.as_str(), replacement.as_str())),
+        )
+        .map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for RewriteRules {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            self.rules
+                .iter()
+                .map(|rule| (&rule.pattern, &rule.replacement)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RewriteRules;
+
+    #[test]
+    fn test_apply() {
+        let rules = RewriteRules::new(vec![
+            (r"python(\d)\.\d+", "python$1"),
+            ("pip3", "pip"),
+            ("pip.*", "never-reached"),
+        ])
+        .unwrap();
+        assert_eq!(rules.apply("python3.12"), "python3");
+        assert_eq!(rules.apply("pip3"), "pip");
+        assert_eq!(rules.apply("pipx"), "never-reached");
+        // Patterns have to match the whole name
+        assert_eq!(rules.apply("xpip3"), "xpip3");
+        assert_eq!(rules.apply("tar"), "tar");
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(RewriteRules::new(vec![("(", "x")]).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//! Interactively fill in and run an example of a page.
+//!
+//! Nothing is ever executed without an explicit confirmation. Running
+//! examples must additionally be enabled with `allow_run` in the config.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::Command;
+
+use crate::fill::{placeholders, Fill};
+use crate::history::{read_history, suggest};
+use crate::parser::{Example, Page, Placeholder};
+use crate::quoting::Shell;
+
+/// Collect the examples of a page that have a command.
+pub fn examples<R: BufRead>(reader: R) -> Vec<Example> {
+    Page::read(reader)
+        .examples
+        .into_iter()
+        .filter(|example| !example.code.is_empty())
+        .collect()
+}
+
+/// Print `prompt` to `output` and read a line from `input`, without the
+/// trailing newline. Returns `None` at the end of the input.
+fn prompt<R: BufRead, W: Write>(input: &mut R, output: &mut W, prompt: &str) -> Option<String> {
+    write!(output, "{}", prompt).ok()?;
+    output.flush().ok()?;
+    let mut line = String::new();
+    match input.read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim_end_matches(&['\r', '\n'][..]).to_string()),
+    }
+}
+
+/// Describe the values of a placeholder with alternatives or a range, e.g.
+/// ` (one of start, stop)`, or return an empty string for other placeholders.
+fn describe_choices(placeholder: &str) -> String {
+    match Placeholder::parse(placeholder) {
+        Placeholder::Alternatives(alternatives) => {
+            format!(" (one of {})", alternatives.join(", "))
+        }
+        Placeholder::Range(start, end) => format!(" ({} to {})", start, end),
+        Placeholder::Value(_) => String::new(),
+    }
+}
+
+/// Let the user choose an example and fill in its placeholders.
+///
+/// Placeholders that already have a value in `fill` are not asked for. Values
+/// suggested from the shell `history` (most recent first) are used if the
+/// user enters nothing, or else the first of the alternatives of the
+/// placeholder, if it has any. Returns the final command, or `None` if the user
+/// aborted.
+pub fn choose_command<R: BufRead, W: Write>(
+    examples: &[Example],
+    fill: &mut Fill,
+    history: &[String],
+    input: &mut R,
+    output: &mut W,
+) -> Result<Option<String>, String> {
+    if examples.is_empty() {
+        return Err("The page does not contain any examples.".into());
+    }
+    for (i, example) in examples.iter().enumerate() {
+        let _ = writeln!(output, "{:>3}) {}", i + 1, example.description);
+        let _ = writeln!(output, "       {}", example.code);
+    }
+
+    let example = loop {
+        let selection = match prompt(
+            input,
+            output,
+            &format!("Select an example [1-{}]: ", examples.len()),
+        ) {
+            Some(selection) => selection,
+            None => return Ok(None),
+        };
+        match selection.trim().parse::<usize>() {
+            Ok(n) if n >= 1 && n <= examples.len() => break &examples[n - 1],
+            _ => {
+                let _ = writeln!(
+                    output,
+                    "Please enter a number between 1 and {}.",
+                    examples.len()
+                );
+            }
+        }
+    };
+
+    for placeholder in placeholders(&example.code) {
+        if fill.contains(placeholder) {
+            continue;
+        }
+        let suggestion = suggest(&example.code, placeholder, history).or_else(|| {
+            match Placeholder::parse(placeholder) {
+                Placeholder::Alternatives(alternatives) => Some(alternatives[0].to_string()),
+                _ => None,
+            }
+        });
+        let choices = describe_choices(placeholder);
+        let message = match suggestion {
+            Some(ref suggestion) => format!(
+                "Value for {{{{{}}}}}{} [{}]: ",
+                placeholder, choices, suggestion
+            ),
+            None => format!("Value for {{{{{}}}}}{}: ", placeholder, choices),
+        };
+        match (prompt(input, output, &message), suggestion) {
+            (Some(ref value), Some(ref suggestion)) if value.is_empty() => {
+                fill.set(placeholder, suggestion)
+            }
+            (Some(value), _) => fill.set(placeholder, &value),
+            (None, _) => return Ok(None),
+        }
+    }
+
+    Ok(Some(fill.apply(&example.code)))
+}
+
+/// Return the program and arguments to run `command` in `shell`.
+fn shell_command(shell: Shell, command: &str) -> Command {
+    let (program, flag) = match shell {
+        Shell::Bash => ("sh", "-c"),
+        Shell::Fish => ("fish", "-c"),
+        Shell::PowerShell if cfg!(windows) => ("powershell", "-Command"),
+        Shell::PowerShell => ("pwsh", "-Command"),
+        Shell::Cmd => ("cmd", "/C"),
+        Shell::Nushell => ("nu", "-c"),
+    };
+    let mut cmd = Command::new(program);
+    cmd.arg(flag).arg(command);
+    cmd
+}
+
+/// Interactively choose, fill in and run an example of the page at `path`.
+///
+/// If `use_history` is set, placeholder values are suggested from the shell
+/// history. Returns the exit code of the command, or `None` if nothing was
+/// run.
+pub fn run_example(path: &Path, fill: &mut Fill, use_history: bool) -> Result<Option<i32>, String> {
+    let file = File::open(path).map_err(|msg| format!("Could not open file: {}", msg))?;
+    let examples = examples(BufReader::new(file));
+    let history = if use_history { read_history() } else { vec![] };
+
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut output = std::io::stderr();
+
+    let command = match choose_command(&examples, fill, &history, &mut input, &mut output)? {
+        Some(command) => command,
+        None => return Ok(None),
+    };
+
+    let _ = writeln!(output, "\nCommand: {}", command);
+    let confirmation = prompt(&mut input, &mut output, "Run this command? [y/N]: ");
+    match confirmation
+        .as_ref()
+        .map(|answer| answer.trim().to_lowercase())
+    {
+        Some(ref answer) if answer == "y" || answer == "yes" => {}
+        _ => {
+            let _ = writeln!(output, "Not running the command.");
+            return Ok(None);
+        }
+    }
+
+    let status = shell_command(fill.shell(), &command)
+        .status()
+        .map_err(|e| format!("Could not run command: {}", e))?;
+    Ok(Some(status.code().unwrap_or(1)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{choose_command, examples};
+
+    use crate::fill::Fill;
+    use crate::parser::Example;
+    use crate::quoting::Shell;
+
+    fn page_examples() -> Vec<Example> {
+        let page = "# cp\n\n> Copy files.\n\n\
+                    - Copy a file:\n\n`cp {{source}} {{target}}`\n\n\
+                    - Show the version:\n\n`cp --version`\n";
+        examples(page.as_bytes())
+    }
+
+    #[test]
+    fn test_examples() {
+        assert_eq!(
+            page_examples(),
+            vec![
+                Example {
+                    description: "Copy a file:".into(),
+                    code: "cp {{source}} {{target}}".into(),
+                },
+                Example {
+                    description: "Show the version:".into(),
+                    code: "cp --version".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_choose_command() {
+        let mut fill = Fill::parse(&["target=b".into()], Shell::Bash).unwrap();
+        let mut input = "3\n1\nmy file\n".as_bytes();
+        let mut output = Vec::new();
+        let command = choose_command(&page_examples(), &mut fill, &[], &mut input, &mut output);
+        assert_eq!(command, Ok(Some("cp 'my file' b".into())));
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Please enter a number between 1 and 2."));
+        assert!(output.contains("Value for {{source}}: "));
+        assert!(!output.contains("Value for {{target}}"));
+    }
+
+    #[test]
+    fn test_choose_command_aborted() {
+        let mut fill = Fill::new(Shell::Bash);
+        let mut input = "1\n".as_bytes();
+        let command = choose_command(
+            &page_examples(),
+            &mut fill,
+            &[],
+            &mut input,
+            &mut Vec::new(),
+        );
+        assert_eq!(command, Ok(None));
+    }
+
+    #[test]
+    fn test_choose_command_with_history() {
+        let mut fill = Fill::new(Shell::Bash);
+        let history = vec!["cp notes.txt backup/".to_string()];
+        let mut input = "1\n\nother\n".as_bytes();
+        let mut output = Vec::new();
+        let command = choose_command(
+            &page_examples(),
+            &mut fill,
+            &history,
+            &mut input,
+            &mut output,
+        );
+        assert_eq!(command, Ok(Some("cp notes.txt other".into())));
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Value for {{source}} [notes.txt]: "));
+    }
+
+    #[test]
+    fn test_choose_command_with_choices() {
+        let examples = vec![Example {
+            description: "Control a unit:".into(),
+            code: "systemctl {{start|stop}} --lines {{1..100}}".into(),
+        }];
+        let mut fill = Fill::new(Shell::Bash);
+        let mut input = "1\n\n10\n".as_bytes();
+        let mut output = Vec::new();
+        let command = choose_command(&examples, &mut fill, &[], &mut input, &mut output);
+        assert_eq!(command, Ok(Some("systemctl start --lines 10".into())));
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Value for {{start|stop}} (one of start, stop) [start]: "));
+        assert!(output.contains("Value for {{1..100}} (1 to 100): "));
+    }
+}
@@ -0,0 +1,106 @@
+//! Search for pages by name and description.
+//!
+//! Names and descriptions are compared after folding case and diacritics.
+//! Descriptions also match if they contain the stems of all words of the
+//! search term, using the analyzer for the language of the page (see the
+//! `analyzer` module).
+
+use serde_derive::Serialize;
+
+use crate::analyzer::{fold, Analyzers};
+use crate::cache::Cache;
+use crate::error::TealdeerError;
+use crate::tokenizer::Tokenizer;
+use crate::types::LineType;
+
+/// A single page matching a search term.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct SearchResult {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// How well a page matches the search term. Lower is better.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum MatchQuality {
+    ExactName,
+    NamePrefix,
+    NameContains,
+    Description,
+}
+
+/// Return the first description line of the page `contents`.
+fn page_description(contents: &[u8]) -> Option<String> {
+    let mut tokenizer = Tokenizer::new(contents);
+    while let Some(token) = tokenizer.next_token() {
+        match token {
+            LineType::Description(text) => return Some(text),
+            LineType::ExampleText(_) | LineType::ExampleCode(_) => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Search the cache for pages whose name or description contains `term`.
+///
+/// The comparison ignores case and diacritics, descriptions also match if
+/// they contain all words of `term` in another inflection. Results are
+/// ordered by match quality (exact name, name prefix, name substring,
+/// description) and then by name.
+pub fn search_pages(
+    cache: &Cache,
+    term: &str,
+    languages: &[String],
+) -> Result<Vec<SearchResult>, TealdeerError> {
+    let folded_term = fold(term);
+    let default_language = languages.first().map_or("en", String::as_str);
+    let mut analyzers = Analyzers::default();
+    let mut matches = Vec::new();
+
+    for name in cache.list_pages()? {
+        let folded_name = fold(&name);
+        let page = cache.read_page(&name, languages);
+        let description = page
+            .as_ref()
+            .and_then(|(contents, _)| page_description(contents));
+        let matches_description = |description: &str, analyzers: &mut Analyzers| {
+            if fold(description).contains(&folded_term) {
+                return true;
+            }
+            // Custom pages are assumed to be in the preferred language
+            let language = page
+                .as_ref()
+                .and_then(|(_, language)| language.as_deref())
+                .unwrap_or(default_language);
+            let analyzer = analyzers.get(language);
+            let term_stems = analyzer.terms(term);
+            let description_stems = analyzer.terms(description);
+            !term_stems.is_empty()
+                && term_stems
+                    .iter()
+                    .all(|stem| description_stems.contains(stem))
+        };
+
+        let quality = if folded_name == folded_term {
+            MatchQuality::ExactName
+        } else if folded_name.starts_with(&folded_term) {
+            MatchQuality::NamePrefix
+        } else if folded_name.contains(&folded_term) {
+            MatchQuality::NameContains
+        } else {
+            match description {
+                Some(ref description) if matches_description(description, &mut analyzers) => {
+                    MatchQuality::Description
+                }
+                _ => continue,
+            }
+        };
+
+        matches.push((quality, SearchResult { name, description }));
+    }
+
+    // `list_pages` returns sorted names and the sort is stable
+    matches.sort_by_key(|&(quality, _)| quality);
+    Ok(matches.into_iter().map(|(_, result)| result).collect())
+}
@@ -0,0 +1,432 @@
+//! Sharing the cache over the network.
+//!
+//! `tldr --share-cache <address>` serves the pages of the cache over HTTP, so
+//! that one machine with internet access can update a whole classroom or
+//! offline lab. The other machines download the pages from it instead of
+//! GitHub:
+//!
+//! ```toml
+//! [updates]
+//! archive_urls = ["http://teacher.local:8080/tldr.tar.gz"]
+//! ```
+//!
+//! The pages are packed into an archive with the pages directories at the
+//! root, which is packed again once an update activates new pages. Next to
+//! it, its checksum (for `verify_archive_checksum`) and the manifest of the
+//! cache (for `--cache-diff`) are served. Requests are handled one after
+//! another and only `GET` and `HEAD` are understood, which is all that
+//! updates and tools like `curl` need. Clients have `TIMEOUT` in total to send at most
+//! `MAX_REQUEST_HEAD_BYTES` of request line and headers.
+//!
+//! For tools and small team instances, the pages can also be listed and
+//! searched as JSON:
+//!
+//! - `/api/pages?platform=linux&language=en` lists the pages, optionally
+//!   filtered by platform and language.
+//! - `/api/search?q=archive&language=de&platform=linux` searches the pages
+//!   like `--search` (in English, unless another language is given).
+//!
+//! Both are paginated with `offset` (default 0) and `limit` (default
+//! `DEFAULT_LIMIT`, at most `MAX_LIMIT`) and return
+//! `{"total": ..., "offset": ..., "limit": ..., "items": [...]}`. If a
+//! `share_token` is configured, the API requires it as a bearer token. The
+//! archive stays public, so that the other machines can still update.
+//!
+//! The config (and with it the token) is read once at startup, changes only
+//! apply after a restart. Updates of the cache are picked up while serving.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use log::debug;
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::cache::{Cache, PageEntry};
+use crate::cache_info::{sha256_hex, CacheInfo};
+use crate::error::TealdeerError;
+use crate::search::search_pages;
+
+/// The path of the pages archive.
+pub const ARCHIVE_PATH: &str = "/tldr.tar.gz";
+
+/// The path of the checksum of the archive, in the format of `sha256sum`.
+const CHECKSUM_PATH: &str = "/tldr.tar.gz.sha256";
+
+/// The path of the manifest of the cache.
+const MANIFEST_PATH: &str = "/manifest.sha256";
+
+/// The paths below this prefix require the token, if one is configured.
+const API_PREFIX: &str = "/api/";
+
+/// The path of the list of pages.
+const PAGES_API_PATH: &str = "/api/pages";
+
+/// The path of the search.
+const SEARCH_API_PATH: &str = "/api/search";
+
+/// The number of items per page of results, unless the client asks for
+/// another number.
+const DEFAULT_LIMIT: usize = 50;
+
+/// The maximum number of items per page of results.
+const MAX_LIMIT: usize = 1000;
+
+/// Connections that don't send their request line and headers within this
+/// time are closed, so that they don't block others.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The maximum size of the request line and headers of a request in bytes.
+/// Larger requests are answered with `431 Request Header Fields Too Large`.
+const MAX_REQUEST_HEAD_BYTES: u64 = 16 * 1024;
+
+/// The archive of the pages of a cache generation.
+struct Packed {
+    pages_dir: PathBuf,
+    archive: Vec<u8>,
+    checksum: String,
+}
+
+/// A response to a request.
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    /// Additional headers, as name and value.
+    headers: Vec<(&'static str, &'static str)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: Vec<u8>) -> Self {
+        Self {
+            status: "200 OK",
+            content_type,
+            headers: vec![],
+            body,
+        }
+    }
+
+    fn json(value: &serde_json::Value) -> Self {
+        Self::ok("application/json", format!("{}\n", value).into_bytes())
+    }
+
+    fn error(status: &'static str, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            headers: vec![],
+            body: format!("{}\n", message).into_bytes(),
+        }
+    }
+
+    fn bad_request(message: &str) -> Self {
+        Self::error("400 Bad Request", message)
+    }
+
+    fn unauthorized() -> Self {
+        let mut response = Self::error("401 Unauthorized", "A valid bearer token is required");
+        response.headers.push(("WWW-Authenticate", "Bearer"));
+        response
+    }
+}
+
+/// The parameters of the query string of a request.
+struct Query(Vec<(String, String)>);
+
+impl Query {
+    fn parse(query: &str) -> Self {
+        let pairs = match Url::parse("http://localhost/") {
+            Ok(mut url) => {
+                url.set_query(Some(query));
+                url.query_pairs().into_owned().collect()
+            }
+            Err(_) => vec![],
+        };
+        Self(pairs)
+    }
+
+    /// Return the value of the parameter `name`. If it is given more than
+    /// once, the last value counts.
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Return the value of the numeric parameter `name`, or `default` if it
+    /// is not given.
+    fn number(&self, name: &str, default: usize) -> Result<usize, Response> {
+        match self.get(name) {
+            None => Ok(default),
+            Some(value) => value
+                .parse()
+                .map_err(|_| Response::bad_request(&format!("Invalid `{}`: {:?}", name, value))),
+        }
+    }
+}
+
+/// Return the page of `items` selected by the `offset` and `limit`
+/// parameters of `query`, with the total number of items.
+fn paginate<T: Serialize>(items: &[T], query: &Query) -> Response {
+    let offset = match query.number("offset", 0) {
+        Ok(offset) => offset,
+        Err(response) => return response,
+    };
+    let limit = match query.number("limit", DEFAULT_LIMIT) {
+        Ok(limit) if limit <= MAX_LIMIT => limit,
+        Ok(_) => return Response::bad_request(&format!("`limit` must be at most {}", MAX_LIMIT)),
+        Err(response) => return response,
+    };
+    let page = items.iter().skip(offset).take(limit).collect::<Vec<_>>();
+    Response::json(&json!({
+        "total": items.len(),
+        "offset": offset,
+        "limit": limit,
+        "items": page,
+    }))
+}
+
+/// Compare `a` and `b` in constant time (for strings of the same length), so
+/// that the token can't be guessed from response times.
+/// A connection whose reads fail once `deadline` has passed.
+///
+/// A read timeout alone only limits the time between two reads, so a client
+/// that sends a byte every few seconds could keep its request open forever.
+struct DeadlineStream {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.deadline.checked_duration_since(Instant::now()) {
+            Some(remaining) if remaining > Duration::from_secs(0) => {
+                self.stream.set_read_timeout(Some(remaining))?;
+                self.stream.read(buf)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "The request was not received in time",
+            )),
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Serves the pages of a cache.
+pub struct Share<'a> {
+    cache: &'a Cache,
+    packed: Option<Packed>,
+    token: Option<String>,
+}
+
+impl<'a> Share<'a> {
+    pub fn new(cache: &'a Cache) -> Self {
+        Self {
+            cache,
+            packed: None,
+            token: None,
+        }
+    }
+
+    /// Require `token` as bearer token for the API.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// Return whether the value of the `Authorization` header grants access
+    /// to the API.
+    fn authorized(&self, authorization: Option<&str>) -> bool {
+        let token = match self.token {
+            Some(ref token) => token,
+            None => return true,
+        };
+        let mut parts = authorization.unwrap_or_default().splitn(2, ' ');
+        match (parts.next(), parts.next()) {
+            (Some(scheme), Some(credentials)) if scheme.eq_ignore_ascii_case("Bearer") => {
+                constant_time_eq(credentials.trim().as_bytes(), token.as_bytes())
+            }
+            _ => false,
+        }
+    }
+
+    /// List the pages of the cache, filtered by the parameters `platform`
+    /// and `language`.
+    fn list_pages(&self, query: &Query) -> Result<Response, TealdeerError> {
+        let platform = query.get("platform");
+        let language = query.get("language");
+        let pages: Vec<PageEntry> = self
+            .cache
+            .all_pages()?
+            .into_iter()
+            .filter(|page| platform.iter().all(|&platform| page.platform == platform))
+            .filter(|page| language.iter().all(|&language| page.language == language))
+            .collect();
+        Ok(paginate(&pages, query))
+    }
+
+    /// Search the pages for the parameter `q` in the language `language`,
+    /// optionally only the pages for the platform `platform`.
+    fn search(&self, query: &Query) -> Result<Response, TealdeerError> {
+        let term = match query.get("q") {
+            Some(term) if !term.trim().is_empty() => term,
+            _ => return Ok(Response::bad_request("The search term `q` is missing")),
+        };
+        let languages = vec![query.get("language").unwrap_or("en").to_string()];
+        let mut results = search_pages(self.cache, term, &languages)?;
+        if let Some(platform) = query.get("platform") {
+            results.retain(|result| {
+                self.cache
+                    .page_platforms(&result.name, &languages)
+                    .iter()
+                    .any(|p| p == platform)
+            });
+        }
+        Ok(paginate(&results, query))
+    }
+
+    /// Return the archive of the active pages, packing them if they changed.
+    fn packed(&mut self) -> Result<&Packed, TealdeerError> {
+        let pages_dir = Cache::get_active_pages_dir()?;
+        let outdated = match self.packed {
+            Some(ref packed) => packed.pages_dir != pages_dir,
+            None => true,
+        };
+        if outdated {
+            debug!("Packing the pages of {}", pages_dir.display());
+            let archive = self.cache.pack_pages()?;
+            self.packed = Some(Packed {
+                pages_dir,
+                checksum: sha256_hex(&archive),
+                archive,
+            });
+        }
+        Ok(self.packed.as_ref().expect("The pages were packed above"))
+    }
+
+    /// Return the response to a request for `path` with the query string
+    /// `query`, sent with the `Authorization` header `authorization`.
+    fn respond(
+        &mut self,
+        path: &str,
+        query: &str,
+        authorization: Option<&str>,
+    ) -> Result<Response, TealdeerError> {
+        if path.starts_with(API_PREFIX) && !self.authorized(authorization) {
+            return Ok(Response::unauthorized());
+        }
+        Ok(match path {
+            ARCHIVE_PATH => Response::ok("application/gzip", self.packed()?.archive.clone()),
+            CHECKSUM_PATH => Response::ok(
+                "text/plain; charset=utf-8",
+                format!("{}  tldr.tar.gz\n", self.packed()?.checksum).into_bytes(),
+            ),
+            MANIFEST_PATH => {
+                let (cache_dir, _) = Cache::get_cache_dir()?;
+                Response::ok(
+                    "text/plain; charset=utf-8",
+                    CacheInfo::read_manifest(&cache_dir)?.into_bytes(),
+                )
+            }
+            PAGES_API_PATH => self.list_pages(&Query::parse(query))?,
+            SEARCH_API_PATH => self.search(&Query::parse(query))?,
+            _ => Response::error("404 Not Found", "Not found"),
+        })
+    }
+
+    /// Handle a single request on `stream`. Returns the request line.
+    fn handle(&mut self, stream: &mut TcpStream) -> io::Result<String> {
+        let deadline_stream = DeadlineStream {
+            stream: stream.try_clone()?,
+            deadline: Instant::now() + TIMEOUT,
+        };
+        let mut reader = BufReader::new(deadline_stream.take(MAX_REQUEST_HEAD_BYTES));
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let request_line = request_line.trim_end().to_string();
+        // Only the authorization is needed, but all headers must be read
+        // before responding
+        let mut authorization = None;
+        let mut header = String::new();
+        let mut complete = false;
+        while reader.read_line(&mut header)? > 0 {
+            if header.trim_end().is_empty() {
+                complete = true;
+                break;
+            }
+            let mut parts = header.splitn(2, ':');
+            if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                if name.trim().eq_ignore_ascii_case("Authorization") {
+                    authorization = Some(value.trim().to_string());
+                }
+            }
+            header.clear();
+        }
+        let too_large = !complete && reader.get_ref().limit() == 0;
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default();
+        let target = parts.next().unwrap_or_default();
+        let mut target_parts = target.splitn(2, '?');
+        let path = target_parts.next().unwrap_or_default();
+        let query = target_parts.next().unwrap_or_default();
+        let response = match method {
+            _ if too_large => Response::error(
+                "431 Request Header Fields Too Large",
+                "The request headers are too large",
+            ),
+            "GET" | "HEAD" => self
+                .respond(path, query, authorization.as_deref())
+                .unwrap_or_else(|e| Response::error("500 Internal Server Error", e.message())),
+            _ => Response::error("405 Method Not Allowed", "Method not allowed"),
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            response.status,
+            response.content_type,
+            response.body.len()
+        )?;
+        for (name, value) in &response.headers {
+            write!(stream, "{}: {}\r\n", name, value)?;
+        }
+        write!(stream, "\r\n")?;
+        if method != "HEAD" {
+            stream.write_all(&response.body)?;
+        }
+        stream.flush()?;
+        Ok(format!("{} -> {}", request_line, response.status))
+    }
+
+    /// Serve the cache to the connections of `listener`, forever. Every
+    /// request is logged to stderr unless `quietly` is set.
+    pub fn serve(&mut self, listener: &TcpListener, quietly: bool) {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    debug!("Could not accept connection: {}", e);
+                    continue;
+                }
+            };
+            let peer = stream
+                .peer_addr()
+                .map_or_else(|_| "unknown".to_string(), |addr| addr.to_string());
+            match self.handle(&mut stream) {
+                Ok(request) if !quietly => eprintln!("{}: {}", peer, request),
+                Ok(_) => {}
+                Err(e) => debug!("Could not handle request from {}: {}", peer, e),
+            }
+        }
+    }
+}
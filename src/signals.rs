@@ -0,0 +1,95 @@
+//! Handling of signals.
+//!
+//! Like most command line tools, tealdeer is terminated silently by `SIGPIPE`
+//! when the reading end of its output is closed early (e.g. by `head` or a
+//! pager), instead of failing on the next write.
+//!
+//! While updating the cache, the first `SIGINT` (Ctrl+C) only sets a flag, so
+//! that the update can stop at the next opportunity and clean up after itself.
+//! A second `SIGINT` terminates the process immediately.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+use log::debug;
+use signal_hook::consts::SIGINT;
+use signal_hook::low_level;
+
+/// The exit code of processes terminated by `SIGINT`.
+pub const SIGINT_EXIT_CODE: i32 = 130;
+
+static REGISTER: Once = Once::new();
+static HANDLING: AtomicBool = AtomicBool::new(false);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Restore the default behavior for `SIGPIPE`, which Rust ignores.
+pub fn reset_sigpipe() {
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+}
+
+/// Ignores `SIGPIPE` as long as it is alive, e.g. while writing to a child
+/// process that may exit without reading all of its input.
+pub struct SigpipeIgnored {
+    // Prevent construction without `new`
+    _private: (),
+}
+
+impl SigpipeIgnored {
+    pub fn new() -> Self {
+        #[cfg(unix)]
+        unsafe {
+            libc::signal(libc::SIGPIPE, libc::SIG_IGN);
+        }
+        Self { _private: () }
+    }
+}
+
+impl Drop for SigpipeIgnored {
+    fn drop(&mut self) {
+        reset_sigpipe();
+    }
+}
+
+/// Catches `SIGINT` as long as it is alive, see `interrupted`.
+///
+/// Otherwise, `SIGINT` terminates the process as usual.
+pub struct InterruptHandler {
+    // Prevent construction without `new`
+    _private: (),
+}
+
+impl InterruptHandler {
+    pub fn new() -> Self {
+        REGISTER.call_once(|| {
+            // The handler only calls async-signal-safe functions
+            let result = unsafe {
+                low_level::register(SIGINT, || {
+                    if !HANDLING.load(Ordering::SeqCst) || INTERRUPTED.swap(true, Ordering::SeqCst)
+                    {
+                        low_level::exit(SIGINT_EXIT_CODE);
+                    }
+                })
+            };
+            if let Err(e) = result {
+                debug!("Could not register SIGINT handler: {}", e);
+            }
+        });
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        HANDLING.store(true, Ordering::SeqCst);
+        Self { _private: () }
+    }
+
+    /// Return whether `SIGINT` was received since the handler was created.
+    pub fn interrupted(&self) -> bool {
+        INTERRUPTED.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for InterruptHandler {
+    fn drop(&mut self) {
+        HANDLING.store(false, Ordering::SeqCst);
+    }
+}
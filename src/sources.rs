@@ -0,0 +1,232 @@
+//! Extra sources of pages.
+//!
+//! Besides the pages of the cache (from `pages_source`), pages can come from
+//! the `extra_pages_sources`, e.g. an archive with the pages of the internal
+//! tools of a company. Lookups search them in the configured order before the
+//! pages of the cache, so that their pages can also replace official ones.
+//! Custom and pinned pages still take precedence.
+//!
+//! Archives and git repositories are downloaded into `SOURCES_DIR` in the
+//! cache directory by every update, directories are read directly. A source
+//! that can not be updated keeps its pages.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+use crate::cache::{directory_to_create, Cache};
+use crate::config::PagesSource;
+use crate::error::TealdeerError::{self, CacheError, ConfigError, UpdateError};
+use crate::fetch::{fetch_from_mirrors, Checksum, HttpSettings};
+use crate::layout::{ArchiveLayout, LayoutSetting};
+use crate::lock::CacheLock;
+use crate::quarantine::{self, QuarantinedPage};
+use crate::repos;
+use crate::signals::InterruptHandler;
+
+/// The directory inside the cache directory that contains the pages of the
+/// extra sources.
+pub const SOURCES_DIR: &str = "sources";
+
+/// The outcome of updating an extra pages source.
+#[derive(Debug)]
+pub struct SourceUpdate<'a> {
+    pub source: &'a PagesSource,
+    /// The invalid pages of an archive, which were not installed, or why the
+    /// source could not be updated.
+    pub result: Result<Vec<QuarantinedPage>, TealdeerError>,
+}
+
+/// Return the directory that the pages of `source` are read from.
+pub fn pages_dir(source: &PagesSource) -> Result<PathBuf, TealdeerError> {
+    match source {
+        PagesSource::Directory { path } => Ok(path.clone()),
+        PagesSource::Archive { url: Some(url) } | PagesSource::Git { url } => {
+            let (cache_dir, _) = Cache::get_cache_dir()?;
+            Ok(cache_dir.join(SOURCES_DIR).join(repos::dir_name(url)))
+        }
+        PagesSource::Archive { url: None } => Err(ConfigError(
+            "An archive in `extra_pages_sources` has no `url`.".into(),
+        )),
+    }
+}
+
+/// Return the path next to `pages_dir` with `suffix` appended to its name.
+fn sibling(pages_dir: &Path, suffix: &str) -> PathBuf {
+    let mut name = pages_dir.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    pages_dir.with_file_name(name)
+}
+
+/// Return the directory that the invalid pages of the archive of a source
+/// with the pages directory `pages_dir` are moved to.
+pub fn quarantine_dir(pages_dir: &Path) -> PathBuf {
+    sibling(pages_dir, ".quarantine")
+}
+
+/// Install the pages of the compressed archive `bytes` into `pages_dir`,
+/// replacing the pages installed before once the new ones are complete.
+fn install_archive(
+    bytes: &[u8],
+    url: &str,
+    pages_dir: &Path,
+) -> Result<Vec<QuarantinedPage>, TealdeerError> {
+    let staging_dir = sibling(pages_dir, ".staging");
+    let new_dir = sibling(pages_dir, ".new");
+    let old_dir = sibling(pages_dir, ".old");
+    let remove = |dir: &Path| {
+        if dir.exists() {
+            if let Err(e) = fs::remove_dir_all(dir) {
+                debug!("Could not remove {}: {}", dir.display(), e);
+            }
+        }
+    };
+    // Remove the leftovers of failed updates
+    for dir in &[&staging_dir, &new_dir, &old_dir] {
+        remove(dir);
+    }
+
+    let installed = (|| {
+        Cache::unpack(bytes, &staging_dir)
+            .map_err(|e| UpdateError(format!("Could not unpack compressed data: {}", e)))?;
+        let layout = ArchiveLayout::resolve(LayoutSetting::Auto, &staging_dir, Some(url))
+            .ok_or_else(|| UpdateError("Unknown layout of the pages archive.".into()))?;
+        layout.normalize(&staging_dir, &new_dir)?;
+        quarantine::quarantine_invalid_pages(&new_dir, &quarantine_dir(pages_dir))
+    })();
+    remove(&staging_dir);
+    let quarantined = match installed {
+        Ok(quarantined) => quarantined,
+        Err(e) => {
+            remove(&new_dir);
+            return Err(e);
+        }
+    };
+
+    let replace_err = |e: std::io::Error| {
+        UpdateError(format!("Could not replace {}: {}", pages_dir.display(), e)).with_io_hint(&e)
+    };
+    if pages_dir.exists() {
+        fs::rename(pages_dir, &old_dir).map_err(replace_err)?;
+    }
+    fs::rename(&new_dir, pages_dir).map_err(replace_err)?;
+    remove(&old_dir);
+    Ok(quarantined)
+}
+
+/// Download the pages of `source`, unless it is a directory.
+fn update(
+    source: &PagesSource,
+    http: &HttpSettings,
+    interrupts: &InterruptHandler,
+) -> Result<Vec<QuarantinedPage>, TealdeerError> {
+    let pages_dir = pages_dir(source)?;
+    match source {
+        PagesSource::Directory { .. } => Ok(vec![]),
+        PagesSource::Git { url } => repos::sync_clone(url, &pages_dir).map(|()| vec![]),
+        PagesSource::Archive { url } => {
+            let urls: Vec<String> = url.iter().cloned().collect();
+            let fetched =
+                fetch_from_mirrors(&urls, http, &Checksum::Unverified, None, None, interrupts)?;
+            install_archive(&fetched.bytes, &fetched.url, &pages_dir)
+        }
+    }
+}
+
+/// Update the extra pages `sources` that are not directories, in order. The
+/// cache is locked while they are updated.
+pub fn update_all<'a>(
+    sources: &'a [PagesSource],
+    http: &HttpSettings,
+) -> Result<Vec<SourceUpdate<'a>>, TealdeerError> {
+    if sources.is_empty() {
+        return Ok(vec![]);
+    }
+    let (cache_dir, _) = Cache::get_cache_dir()?;
+    let sources_dir = cache_dir.join(SOURCES_DIR);
+    fs::create_dir_all(directory_to_create(&sources_dir)).map_err(|e| {
+        CacheError(format!("Could not create {}: {}", sources_dir.display(), e)).with_io_hint(&e)
+    })?;
+    let _lock = CacheLock::lock(&cache_dir)
+        .map_err(|e| CacheError(format!("Could not lock the cache: {}", e)).with_io_hint(&e))?;
+
+    let interrupts = InterruptHandler::new();
+    Ok(sources
+        .iter()
+        .filter_map(|source| match source {
+            PagesSource::Directory { .. } => None,
+            source => {
+                debug!("Updating {}", source);
+                Some(SourceUpdate {
+                    source,
+                    result: update(source, http, &interrupts),
+                })
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{install_archive, quarantine_dir};
+
+    use std::fs;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tempfile::TempDir;
+
+    fn archive(pages: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, contents) in pages {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn test_install_archive() {
+        let dir = TempDir::new().unwrap();
+        let pages_dir = dir.path().join("internal");
+
+        let first = archive(&[
+            ("pages/common/deploy.md", "# deploy\n"),
+            ("pages/common/old.md", "# old\n"),
+        ]);
+        assert!(install_archive(&first, "internal.tar.gz", &pages_dir)
+            .unwrap()
+            .is_empty());
+        assert!(pages_dir.join("pages/common/old.md").is_file());
+
+        // The new pages replace the old ones, invalid pages are quarantined
+        let second = archive(&[
+            ("internal-main/pages/common/deploy.md", "# deploy\n"),
+            ("internal-main/pages/linux/broken.md", "No title\n"),
+        ]);
+        let quarantined = install_archive(&second, "internal.tar.gz", &pages_dir).unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].path, "pages/linux/broken.md");
+        assert!(pages_dir.join("pages/common/deploy.md").is_file());
+        assert!(!pages_dir.join("pages/common/old.md").exists());
+        assert!(quarantine_dir(&pages_dir)
+            .join("pages/linux/broken.md")
+            .is_file());
+
+        // A broken archive keeps the installed pages
+        assert!(install_archive(b"not an archive", "internal.tar.gz", &pages_dir).is_err());
+        assert!(pages_dir.join("pages/common/deploy.md").is_file());
+        let mut entries: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        entries.sort();
+        assert_eq!(entries, vec!["internal", "internal.quarantine"]);
+    }
+}
@@ -0,0 +1,65 @@
+//! A spinner on stderr for work that takes a while.
+//!
+//! Updates download and unpack the pages before anything else is printed,
+//! which can take a few seconds on slow connections. A spinner tells the user
+//! that tealdeer is busy. It is only shown if stderr is a terminal, so that
+//! logs and pipes are not cluttered.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use atty::Stream;
+
+use crate::width::display_width;
+
+const FRAMES: &[char] = &['|', '/', '-', '\\'];
+const INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shows a spinner with a message on stderr as long as it is alive.
+pub struct Spinner {
+    done: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    /// Show a spinner with `message` on stderr, if it is a terminal.
+    pub fn start(message: &str) -> Self {
+        let done = Arc::new(AtomicBool::new(false));
+        let thread = if atty::is(Stream::Stderr) {
+            let done = Arc::clone(&done);
+            let message = message.to_string();
+            Some(thread::spawn(move || {
+                let mut stderr = io::stderr();
+                for frame in FRAMES.iter().cycle() {
+                    if done.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let _ = write!(stderr, "\r{} {}", frame, message);
+                    let _ = stderr.flush();
+                    thread::park_timeout(INTERVAL);
+                }
+                // Remove the spinner, so that following output starts on an
+                // empty line
+                let blank = " ".repeat(display_width(&message) + 2);
+                let _ = write!(stderr, "\r{}\r", blank);
+                let _ = stderr.flush();
+            }))
+        } else {
+            None
+        };
+        Self { done, thread }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! Crash-safe state files.
+//!
+//! Files that tealdeer writes to keep track of its own state (like the cache
+//! metadata) must never be left half-written, even if the process is killed
+//! or the machine crashes in the middle of writing them. `write_atomic` first
+//! writes the new contents to a temporary file next to the target, flushes it
+//! to disk and then renames it over the target, so readers observe either the
+//! old or the new contents.
+//!
+//! On top of that, `save` prepends a checksum of the contents, which `load`
+//! verifies. A state file that can not be read or whose checksum does not
+//! match is moved aside (to `<name>.corrupt`) and treated as missing, so the
+//! state is reset instead of tealdeer failing until the file is deleted by
+//! hand. Files without a checksum (written by older versions) are accepted as
+//! they are.
+//!
+//! Auxiliary caches (files that can be recreated at any time, like unpacked
+//! pages) are kept within a size budget: `touch` marks a file as used and
+//! `evict_least_recently_used` removes the files that were not used for the
+//! longest time once the budget is exceeded.
+//!
+//! Logs (like the history of updates) are appended to line by line with
+//! `append_log`. A log that grows beyond its size budget is rotated: it is
+//! moved to `<name>.1`, replacing the previous one, so at most two files are
+//! kept. A line that was cut off by a crash is only ever the last line of a
+//! log, readers are expected to skip it.
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::SystemTime;
+
+use filetime::FileTime;
+use log::debug;
+use walkdir::WalkDir;
+
+use crate::cache_info::sha256_hex;
+
+/// The prefix of the line holding the checksum of a state file.
+const CHECKSUM_PREFIX: &str = "# sha256: ";
+
+/// Return `path` with `suffix` appended to the file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().map_or_else(OsString::new, OsString::from);
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
+
+/// Flush the directory entries of `dir` to disk.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+/// Directories can not be opened on other platforms, renames are durable once
+/// they return.
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Replace the contents of the file at `path` with `contents` atomically.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp_path = with_suffix(path, &format!(".tmp-{}", process::id()));
+    let written = (|| {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    written?;
+
+    if let Some(dir) = path.parent() {
+        // The contents are complete either way, this only makes the rename
+        // itself durable
+        if let Err(e) = sync_dir(dir) {
+            debug!("Could not sync directory {}: {}", dir.display(), e);
+        }
+    }
+    Ok(())
+}
+
+/// Write the state file at `path` atomically, with a checksum of `contents`.
+pub fn save(path: &Path, contents: &str) -> io::Result<()> {
+    let checksummed = format!(
+        "{}{}\n{}",
+        CHECKSUM_PREFIX,
+        sha256_hex(contents.as_bytes()),
+        contents
+    );
+    write_atomic(path, checksummed.as_bytes())
+}
+
+/// Return the contents of a state file without the checksum line, or `None`
+/// if the checksum does not match.
+fn verify(raw: &str) -> Option<&str> {
+    if !raw.starts_with(CHECKSUM_PREFIX) {
+        return Some(raw);
+    }
+    let newline = raw.find('\n')?;
+    let checksum = raw[CHECKSUM_PREFIX.len()..newline].trim();
+    let contents = &raw[newline + 1..];
+    if sha256_hex(contents.as_bytes()) == checksum {
+        Some(contents)
+    } else {
+        None
+    }
+}
+
+/// Load the state file at `path`, as written by `save`.
+///
+/// Returns `Ok(None)` if the file does not exist or was corrupt. Corrupt files
+/// are moved aside, so the next `save` starts from scratch.
+pub fn load(path: &Path) -> io::Result<Option<String>> {
+    let raw = match fs::read(path) {
+        Ok(raw) => raw,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let contents = String::from_utf8(raw)
+        .ok()
+        .and_then(|raw| verify(&raw).map(String::from));
+    if contents.is_none() {
+        let corrupt_path = with_suffix(path, ".corrupt");
+        debug!(
+            "State file {} is corrupt, moving it to {}",
+            path.display(),
+            corrupt_path.display()
+        );
+        fs::rename(path, &corrupt_path)?;
+    }
+    Ok(contents)
+}
+
+/// Return the path that the log at `path` is rotated to.
+fn rotated_log(path: &Path) -> PathBuf {
+    with_suffix(path, ".1")
+}
+
+/// Append `line` to the log at `path`, rotating the log first if the line
+/// would make it larger than `max_bytes`.
+pub fn append_log(path: &Path, line: &str, max_bytes: u64) -> io::Result<()> {
+    let size = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => 0,
+        Err(e) => return Err(e),
+    };
+    if size > 0 && size + line.len() as u64 + 1 > max_bytes {
+        debug!("Rotating log {}", path.display());
+        fs::rename(path, rotated_log(path))?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    // A single write, so that concurrent writers don't interleave lines
+    file.write_all(format!("{}\n", line).as_bytes())
+}
+
+/// Return the lines of the log at `path` (including the rotated one), oldest
+/// first. A log that does not exist has no lines.
+pub fn read_log(path: &Path) -> io::Result<Vec<String>> {
+    let mut lines = Vec::new();
+    for path in &[rotated_log(path), path.to_path_buf()] {
+        match fs::read(path) {
+            Ok(contents) => lines.extend(
+                String::from_utf8_lossy(&contents)
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(String::from),
+            ),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(lines)
+}
+
+/// Mark the file at `path` of an auxiliary cache as used now.
+pub fn touch(path: &Path) -> io::Result<()> {
+    filetime::set_file_mtime(path, FileTime::now())
+}
+
+/// The files removed from auxiliary caches.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Eviction {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Remove the least recently used files below `dirs` until they take up at
+/// most `max_bytes`. The file at `keep` (e.g. a page that is about to be
+/// rendered) is never removed.
+pub fn evict_least_recently_used(
+    dirs: &[PathBuf],
+    max_bytes: u64,
+    keep: Option<&Path>,
+) -> io::Result<Eviction> {
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = Vec::new();
+    for dir in dirs {
+        for entry in WalkDir::new(dir) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                // Directories that don't exist contain no files
+                Err(ref e)
+                    if e.io_error().map(io::Error::kind) == Some(io::ErrorKind::NotFound) =>
+                {
+                    continue
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            let last_used = metadata.modified()?;
+            files.push((last_used, metadata.len(), entry.into_path()));
+        }
+    }
+
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    files.sort();
+    let mut eviction = Eviction::default();
+    for (_, size, path) in files {
+        if total <= max_bytes {
+            break;
+        }
+        if Some(path.as_path()) == keep {
+            continue;
+        }
+        debug!("Evicting {}", path.display());
+        fs::remove_file(&path)?;
+        total -= size;
+        eviction.files += 1;
+        eviction.bytes += size;
+    }
+    Ok(eviction)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        append_log, evict_least_recently_used, load, read_log, save, write_atomic, Eviction,
+    };
+
+    use std::fs;
+
+    use filetime::FileTime;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state");
+        write_atomic(&path, b"old").unwrap();
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.toml");
+        assert_eq!(load(&path).unwrap(), None);
+        save(&path, "a = 1\n").unwrap();
+        assert_eq!(load(&path).unwrap().as_deref(), Some("a = 1\n"));
+
+        // Files without a checksum are accepted
+        fs::write(&path, "a = 2\n").unwrap();
+        assert_eq!(load(&path).unwrap().as_deref(), Some("a = 2\n"));
+    }
+
+    #[test]
+    fn test_corruption() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.toml");
+        save(&path, "a = 1\n").unwrap();
+        let truncated = fs::read_to_string(&path).unwrap().replace("a = 1", "a =");
+        fs::write(&path, truncated).unwrap();
+
+        assert_eq!(load(&path).unwrap(), None);
+        assert!(!path.exists());
+        assert!(dir.path().join("state.toml.corrupt").is_file());
+
+        fs::write(&path, b"\xff\xfe").unwrap();
+        assert_eq!(load(&path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_log_rotation() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("log");
+        assert!(read_log(&path).unwrap().is_empty());
+
+        for line in &["one", "two", "three", "four"] {
+            append_log(&path, line, 10).unwrap();
+        }
+        // "one" and "two" fit into 10 bytes, "three" starts a new log, which
+        // is rotated again for "four"
+        assert_eq!(read_log(&path).unwrap(), vec!["three", "four"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "four\n");
+
+        // A line that does not fit into an empty log is written anyway
+        append_log(&path, "a very long line", 10).unwrap();
+        assert_eq!(read_log(&path).unwrap(), vec!["four", "a very long line"]);
+    }
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let dir = TempDir::new().unwrap();
+        let unpacked = dir.path().join("unpacked");
+        fs::create_dir(&unpacked).unwrap();
+        for (name, mtime) in &[("old", 1000), ("new", 1001), ("current", 1002)] {
+            let path = unpacked.join(name);
+            fs::write(&path, "1234").unwrap();
+            filetime::set_file_mtime(&path, FileTime::from_unix_time(*mtime, 0)).unwrap();
+        }
+        let dirs = vec![unpacked.clone(), dir.path().join("missing")];
+
+        // The oldest file is removed first
+        assert_eq!(
+            evict_least_recently_used(&dirs, 8, None).unwrap(),
+            Eviction { files: 1, bytes: 4 }
+        );
+        assert!(!unpacked.join("old").exists());
+
+        // The kept file survives even if it does not fit
+        let current = unpacked.join("current");
+        assert_eq!(
+            evict_least_recently_used(&dirs, 0, Some(&current)).unwrap(),
+            Eviction { files: 1, bytes: 4 }
+        );
+        assert!(current.exists());
+        assert!(!unpacked.join("new").exists());
+    }
+}
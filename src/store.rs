@@ -0,0 +1,723 @@
+//! Storage backends for the pages in the cache.
+//!
+//! The pages of every cache generation (see the `generation` module) are
+//! kept in one of the `Backend`s, which are accessed through the `PageStore`
+//! trait:
+//!
+//! * `directory`: Every page is a file in `pages[.<lang>]/<platform>/`.
+//! * `zip`: All pages are packed into a single zip archive
+//!   (`PAGES_ARCHIVE_NAME`), which takes up much less space and fewer inodes
+//!   than thousands of small files. Pages that need to be opened as files
+//!   (e.g. to render them) are unpacked into `UNPACKED_DIR` on first use.
+//!   The unpacked pages are an auxiliary cache, which is kept within the
+//!   `aux_cache_max_mb` budget.
+//! * `sqlite`: All pages are stored in a single `SQLite` database
+//!   (`PAGES_DATABASE_NAME`), which can be queried with other tools. Pages are
+//!   unpacked like with the `zip` backend. This backend requires the `sqlite`
+//!   feature.
+//!
+//! The backend of a generation is detected when it is opened, so a change of
+//! the `cache_format` option takes effect with the next update (or with
+//! `--migrate-cache`). Paths in a store are relative to its root and always
+//! use `/` as separator.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+#[cfg(feature = "share")]
+use flate2::write::GzEncoder;
+#[cfg(feature = "share")]
+use flate2::Compression;
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::error::TealdeerError::{self, CacheError, UpdateError};
+use crate::state;
+use crate::walk::{walk, IgnoreRules};
+
+/// The archive containing all pages of a generation with the `zip` backend.
+pub const PAGES_ARCHIVE_NAME: &str = "pages.zip";
+
+/// The database containing all pages of a generation with the `sqlite`
+/// backend.
+pub const PAGES_DATABASE_NAME: &str = "pages.sqlite";
+
+/// The version of the schema of `PAGES_DATABASE_NAME`.
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+const DATABASE_FORMAT_VERSION: &str = "1";
+
+#[cfg(not(feature = "sqlite"))]
+const SQLITE_UNSUPPORTED: &str =
+    "tealdeer was built without support for SQLite caches (the `sqlite` feature)";
+
+/// The directory next to `PAGES_ARCHIVE_NAME` or `PAGES_DATABASE_NAME` that
+/// pages are unpacked into.
+pub const UNPACKED_DIR: &str = "unpacked";
+
+/// How the pages of the cache are stored.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// One file per page
+    Directory,
+    /// A single zip archive
+    Zip,
+    /// A single `SQLite` database
+    Sqlite,
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Self::Directory
+    }
+}
+
+/// An entry of a directory in a `PageStore`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Read access to the pages of a cache generation.
+pub trait PageStore: Send + Sync {
+    /// The `<pages dir>/<platform>` directories (e.g. `pages.de/linux`).
+    ///
+    /// They are listed once when the store is opened, so that lookups don't
+    /// probe directories that don't exist.
+    fn platform_dirs(&self) -> &BTreeSet<String>;
+
+    /// Return the entries of the directory `dir` (`""` for the root),
+    /// sorted by name. Directories that don't exist have no entries.
+    fn list(&self, dir: &str) -> Vec<Entry>;
+
+    /// Return whether there is a file at `path`.
+    fn is_file(&self, path: &str) -> bool;
+
+    /// Read the file at `path`.
+    fn read(&self, path: &str) -> io::Result<Vec<u8>>;
+
+    /// Return the path of a file with the contents of the file at `path`,
+    /// e.g. to render or memory-map it.
+    fn local_path(&self, path: &str) -> io::Result<PathBuf>;
+
+    /// Return the path of the file at `path` if it is a plain file, without
+    /// unpacking it.
+    fn file_path(&self, _path: &str) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// Join `dir` and `name` to a path in a store.
+pub fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Return whether `name` is the name of a pages directory.
+fn is_pages_dir_name(name: &str) -> bool {
+    name == "pages" || name.starts_with("pages.")
+}
+
+/// Return the paths of all files below `dir` in `store`, skipping the entries
+/// ignored by `rules`.
+pub fn walk_files(store: &dyn PageStore, dir: &str, rules: &IgnoreRules) -> Vec<String> {
+    let mut files = Vec::new();
+    for entry in store.list(dir) {
+        if rules.is_ignored(&entry.name) {
+            continue;
+        }
+        let path = join(dir, &entry.name);
+        if entry.is_dir {
+            files.extend(walk_files(store, &path, rules));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Collect the platform directories of all pages directories in `store`.
+fn collect_platform_dirs(store: &dyn PageStore) -> BTreeSet<String> {
+    store
+        .list("")
+        .into_iter()
+        .filter(|entry| entry.is_dir && is_pages_dir_name(&entry.name))
+        .flat_map(|lang_dir| {
+            store
+                .list(&lang_dir.name)
+                .into_iter()
+                .filter(|entry| entry.is_dir)
+                .map(move |platform| join(&lang_dir.name, &platform.name))
+        })
+        .collect()
+}
+
+/// Return the entries of the directory `dir` in a sorted index of files.
+fn list_index<V>(files: &BTreeMap<String, V>, dir: &str) -> Vec<Entry> {
+    let prefix = join(dir, "");
+    let mut entries: Vec<Entry> = Vec::new();
+    for path in files.range(prefix.clone()..).map(|(path, _)| path) {
+        if !path.starts_with(&prefix) {
+            break;
+        }
+        let rest = &path[prefix.len()..];
+        let (name, is_dir) = match rest.find('/') {
+            Some(end) => (&rest[..end], true),
+            None => (rest, false),
+        };
+        // Files are sorted by path, so entries of the same directory are next
+        // to each other
+        if entries.last().map(|entry| entry.name.as_str()) != Some(name) {
+            entries.push(Entry {
+                name: name.to_string(),
+                is_dir,
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries
+}
+
+fn not_found(path: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} is not in the cache", path),
+    )
+}
+
+/// Unpack the file at `path` from a store packed into `root`, unless it has
+/// been unpacked before, and return the path of the unpacked file.
+fn unpack(store: &dyn PageStore, root: &Path, path: &str) -> io::Result<PathBuf> {
+    let unpacked = root.join(UNPACKED_DIR).join(path);
+    if !unpacked.is_file() {
+        debug!("Unpacking {}", path);
+        let contents = store.read(path)?;
+        if let Some(dir) = unpacked.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Other processes may unpack the same page at the same time
+        state::write_atomic(&unpacked, &contents)?;
+    } else if let Err(e) = state::touch(&unpacked) {
+        // Only the order of evictions depends on this
+        debug!("Could not mark {} as used: {}", unpacked.display(), e);
+    }
+    Ok(unpacked)
+}
+
+/// Pages stored as files in a directory.
+pub struct DirectoryStore {
+    root: PathBuf,
+    platform_dirs: BTreeSet<String>,
+}
+
+impl DirectoryStore {
+    pub fn open(root: &Path) -> Self {
+        let mut store = Self {
+            root: root.to_path_buf(),
+            platform_dirs: BTreeSet::new(),
+        };
+        store.platform_dirs = collect_platform_dirs(&store);
+        store
+    }
+}
+
+impl PageStore for DirectoryStore {
+    fn platform_dirs(&self) -> &BTreeSet<String> {
+        &self.platform_dirs
+    }
+
+    fn list(&self, dir: &str) -> Vec<Entry> {
+        let mut entries: Vec<Entry> = fs::read_dir(self.root.join(dir))
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                Some(Entry {
+                    is_dir: entry.path().is_dir(),
+                    name: entry.file_name().into_string().ok()?,
+                })
+            })
+            .collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        entries
+    }
+
+    fn is_file(&self, path: &str) -> bool {
+        self.root.join(path).is_file()
+    }
+
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.root.join(path))
+    }
+
+    fn local_path(&self, path: &str) -> io::Result<PathBuf> {
+        Ok(self.root.join(path))
+    }
+
+    fn file_path(&self, path: &str) -> Option<PathBuf> {
+        Some(self.root.join(path))
+    }
+}
+
+/// Pages packed into a zip archive.
+pub struct ZipStore {
+    /// The directory containing the archive.
+    root: PathBuf,
+    archive: Mutex<ZipArchive<File>>,
+    /// The index of every file in the archive, by path.
+    files: BTreeMap<String, usize>,
+    platform_dirs: BTreeSet<String>,
+}
+
+impl ZipStore {
+    pub fn open(root: &Path) -> io::Result<Self> {
+        let invalid_data = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+        let mut archive =
+            ZipArchive::new(File::open(root.join(PAGES_ARCHIVE_NAME))?).map_err(invalid_data)?;
+        let mut files = BTreeMap::new();
+        for i in 0..archive.len() {
+            let file = archive.by_index(i).map_err(invalid_data)?;
+            if !file.is_dir() {
+                files.insert(file.name().to_string(), i);
+            }
+        }
+        let mut store = Self {
+            root: root.to_path_buf(),
+            archive: Mutex::new(archive),
+            files,
+            platform_dirs: BTreeSet::new(),
+        };
+        store.platform_dirs = collect_platform_dirs(&store);
+        Ok(store)
+    }
+}
+
+impl PageStore for ZipStore {
+    fn platform_dirs(&self) -> &BTreeSet<String> {
+        &self.platform_dirs
+    }
+
+    fn list(&self, dir: &str) -> Vec<Entry> {
+        list_index(&self.files, dir)
+    }
+
+    fn is_file(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let index = *self.files.get(path).ok_or_else(|| not_found(path))?;
+        let mut archive = self.archive.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut file = archive
+            .by_index(index)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    fn local_path(&self, path: &str) -> io::Result<PathBuf> {
+        unpack(self, &self.root, path)
+    }
+}
+
+/// Pages stored in an `SQLite` database.
+#[cfg(feature = "sqlite")]
+pub struct SqliteStore {
+    /// The directory containing the database.
+    root: PathBuf,
+    connection: Mutex<rusqlite::Connection>,
+    /// The row id of every file in the database, by path.
+    files: BTreeMap<String, i64>,
+    platform_dirs: BTreeSet<String>,
+}
+
+#[cfg(feature = "sqlite")]
+fn sqlite_err(e: rusqlite::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    pub fn open(root: &Path) -> io::Result<Self> {
+        use rusqlite::{Connection, OpenFlags};
+
+        let connection = Connection::open_with_flags(
+            root.join(PAGES_DATABASE_NAME),
+            OpenFlags::SQLITE_OPEN_READ_ONLY,
+        )
+        .map_err(sqlite_err)?;
+        let format_version: String = connection
+            .query_row(
+                "SELECT value FROM meta WHERE key = 'format_version'",
+                rusqlite::NO_PARAMS,
+                |row| row.get(0),
+            )
+            .map_err(sqlite_err)?;
+        if format_version != DATABASE_FORMAT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported database format version {}", format_version),
+            ));
+        }
+        let files = {
+            let mut statement = connection
+                .prepare("SELECT path, rowid FROM files")
+                .map_err(sqlite_err)?;
+            let rows = statement
+                .query_map(rusqlite::NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(sqlite_err)?;
+            rows.collect::<Result<_, _>>().map_err(sqlite_err)?
+        };
+        let mut store = Self {
+            root: root.to_path_buf(),
+            connection: Mutex::new(connection),
+            files,
+            platform_dirs: BTreeSet::new(),
+        };
+        store.platform_dirs = collect_platform_dirs(&store);
+        Ok(store)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl PageStore for SqliteStore {
+    fn platform_dirs(&self) -> &BTreeSet<String> {
+        &self.platform_dirs
+    }
+
+    fn list(&self, dir: &str) -> Vec<Entry> {
+        list_index(&self.files, dir)
+    }
+
+    fn is_file(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let rowid = *self.files.get(path).ok_or_else(|| not_found(path))?;
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        connection
+            .query_row(
+                "SELECT contents FROM files WHERE rowid = ?",
+                &[rowid],
+                |row| row.get(0),
+            )
+            .map_err(sqlite_err)
+    }
+
+    fn local_path(&self, path: &str) -> io::Result<PathBuf> {
+        unpack(self, &self.root, path)
+    }
+}
+
+/// Open the pages stored in the generation at `root`.
+pub fn open(root: &Path) -> Result<Box<dyn PageStore>, TealdeerError> {
+    let open_err = |e| {
+        CacheError(format!(
+            "Could not open the pages in {}: {}",
+            root.display(),
+            e
+        ))
+    };
+    if root.join(PAGES_ARCHIVE_NAME).is_file() {
+        Ok(Box::new(ZipStore::open(root).map_err(open_err)?))
+    } else if root.join(PAGES_DATABASE_NAME).is_file() {
+        open_sqlite(root).map_err(open_err)
+    } else {
+        Ok(Box::new(DirectoryStore::open(root)))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn open_sqlite(root: &Path) -> io::Result<Box<dyn PageStore>> {
+    Ok(Box::new(SqliteStore::open(root)?))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn open_sqlite(_root: &Path) -> io::Result<Box<dyn PageStore>> {
+    Err(io::Error::new(io::ErrorKind::Other, SQLITE_UNSUPPORTED))
+}
+
+/// The files to pack from the directory `root`, with their paths in the
+/// store.
+fn files_to_pack(root: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    let rules = IgnoreRules::default();
+    let mut files = Vec::new();
+    for entry in walk(root, usize::MAX, &rules, |_| true) {
+        let entry = entry.map_err(io::Error::from)?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        let name = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.push((name, entry.into_path()));
+    }
+    Ok(files)
+}
+
+/// Remove the top-level entries of `root` that contain the packed `files`.
+fn remove_packed(root: &Path, files: &[(String, PathBuf)]) -> io::Result<()> {
+    let top_levels: BTreeSet<&str> = files
+        .iter()
+        .filter_map(|(name, _)| name.split('/').next())
+        .collect();
+    for top_level in top_levels {
+        let path = root.join(top_level);
+        if path.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Pack the pages in the directory `root` into an archive in the same
+/// directory, and remove them.
+fn pack_zip(root: &Path) -> io::Result<()> {
+    let files = files_to_pack(root)?;
+    let mut writer = ZipWriter::new(File::create(root.join(PAGES_ARCHIVE_NAME))?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, path) in &files {
+        writer.start_file(name.as_str(), options)?;
+        writer.write_all(&fs::read(path)?)?;
+    }
+    writer.finish()?.sync_all()?;
+    remove_packed(root, &files)
+}
+
+/// Pack the pages in the directory `root` into a database in the same
+/// directory, and remove them.
+#[cfg(feature = "sqlite")]
+fn pack_sqlite(root: &Path) -> io::Result<()> {
+    use rusqlite::{params, Connection};
+
+    let files = files_to_pack(root)?;
+    let mut connection = Connection::open(root.join(PAGES_DATABASE_NAME)).map_err(sqlite_err)?;
+    let transaction = connection.transaction().map_err(sqlite_err)?;
+    transaction
+        .execute_batch(
+            "CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE files (path TEXT PRIMARY KEY, contents BLOB NOT NULL);",
+        )
+        .map_err(sqlite_err)?;
+    transaction
+        .execute(
+            "INSERT INTO meta (key, value) VALUES ('format_version', ?)",
+            &[DATABASE_FORMAT_VERSION],
+        )
+        .map_err(sqlite_err)?;
+    {
+        let mut insert = transaction
+            .prepare("INSERT INTO files (path, contents) VALUES (?, ?)")
+            .map_err(sqlite_err)?;
+        for (name, path) in &files {
+            insert
+                .execute(params![name, fs::read(path)?])
+                .map_err(sqlite_err)?;
+        }
+    }
+    transaction.commit().map_err(sqlite_err)?;
+    remove_packed(root, &files)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn pack_sqlite(_root: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Other, SQLITE_UNSUPPORTED))
+}
+
+/// Convert the pages extracted to the directory `root` to `backend`.
+pub fn convert(root: &Path, backend: Backend) -> Result<(), TealdeerError> {
+    match backend {
+        Backend::Directory => Ok(()),
+        Backend::Zip => pack_zip(root)
+            .map_err(|e| UpdateError(format!("Could not pack pages into an archive: {}", e))),
+        Backend::Sqlite => pack_sqlite(root)
+            .map_err(|e| UpdateError(format!("Could not pack pages into a database: {}", e))),
+    }
+}
+
+/// Copy all files of `store` into the directory `target`, which is then
+/// converted to `backend`.
+pub fn export(store: &dyn PageStore, target: &Path, backend: Backend) -> Result<(), TealdeerError> {
+    let export_err = |e: io::Error| CacheError(format!("Could not copy pages: {}", e));
+    let rules = IgnoreRules::default();
+    for path in walk_files(store, "", &rules) {
+        let target_path = target.join(&path);
+        if let Some(dir) = target_path.parent() {
+            fs::create_dir_all(dir).map_err(export_err)?;
+        }
+        fs::write(&target_path, store.read(&path).map_err(export_err)?).map_err(export_err)?;
+    }
+    convert(target, backend)
+}
+
+/// Pack all files of `store` into a gzipped tar archive, with the pages
+/// directories at the root (e.g. `pages/common/tar.md`).
+#[cfg(feature = "share")]
+pub fn pack_tar_gz(store: &dyn PageStore) -> io::Result<Vec<u8>> {
+    let rules = IgnoreRules::default();
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for path in walk_files(store, "", &rules) {
+        let contents = store.read(&path)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, &path, &contents[..])?;
+    }
+    builder.into_inner()?.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        convert, export, open, walk_files, Backend, Entry, PAGES_ARCHIVE_NAME, PAGES_DATABASE_NAME,
+        UNPACKED_DIR,
+    };
+
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use crate::walk::IgnoreRules;
+
+    fn create_pages() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (path, contents) in &[
+            ("pages/common/tar.md", "# tar\n"),
+            ("pages/linux/ls.md", "# ls\n"),
+            ("pages.de/common/tar.md", "# tar (de)\n"),
+            ("LICENSE.md", "License\n"),
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    #[cfg(feature = "share")]
+    #[test]
+    fn test_pack_tar_gz() {
+        use super::pack_tar_gz;
+
+        let dir = create_pages();
+        let store = open(dir.path()).unwrap();
+        let packed = pack_tar_gz(&*store).unwrap();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(&packed[..]));
+        let mut paths: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                entry
+                    .unwrap()
+                    .path()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "LICENSE.md",
+                "pages.de/common/tar.md",
+                "pages/common/tar.md",
+                "pages/linux/ls.md"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_backends() {
+        let mut backends = vec![Backend::Directory, Backend::Zip];
+        if cfg!(feature = "sqlite") {
+            backends.push(Backend::Sqlite);
+        }
+        for backend in &backends {
+            let dir = create_pages();
+            convert(dir.path(), *backend).unwrap();
+            assert_eq!(
+                dir.path().join(PAGES_ARCHIVE_NAME).is_file(),
+                *backend == Backend::Zip
+            );
+            assert_eq!(
+                dir.path().join(PAGES_DATABASE_NAME).is_file(),
+                *backend == Backend::Sqlite
+            );
+            let store = open(dir.path()).unwrap();
+
+            let platform_dirs: Vec<&str> = store.platform_dirs().iter().map(|s| &s[..]).collect();
+            assert_eq!(
+                platform_dirs,
+                vec!["pages.de/common", "pages/common", "pages/linux"]
+            );
+            assert_eq!(
+                store.list(""),
+                vec![
+                    Entry {
+                        name: "LICENSE.md".into(),
+                        is_dir: false
+                    },
+                    Entry {
+                        name: "pages".into(),
+                        is_dir: true
+                    },
+                    Entry {
+                        name: "pages.de".into(),
+                        is_dir: true
+                    },
+                ]
+            );
+            assert_eq!(store.list("pages/osx"), vec![]);
+            assert!(store.is_file("pages/linux/ls.md"));
+            assert!(!store.is_file("pages/linux"));
+            assert_eq!(
+                store.read("pages.de/common/tar.md").unwrap(),
+                b"# tar (de)\n"
+            );
+            assert!(store.read("pages/osx/ls.md").is_err());
+
+            let local_path = store.local_path("pages/common/tar.md").unwrap();
+            assert_eq!(fs::read(&local_path).unwrap(), b"# tar\n");
+
+            let mut files = walk_files(&*store, "", &IgnoreRules::default());
+            files.retain(|path| !path.starts_with(UNPACKED_DIR));
+            assert_eq!(files.len(), 4);
+        }
+    }
+
+    #[test]
+    fn test_export() {
+        let dir = create_pages();
+        convert(dir.path(), Backend::Zip).unwrap();
+        let zip_store = open(dir.path()).unwrap();
+
+        let target = TempDir::new().unwrap();
+        export(&*zip_store, target.path(), Backend::Directory).unwrap();
+        assert_eq!(
+            fs::read(target.path().join("pages.de/common/tar.md")).unwrap(),
+            b"# tar (de)\n"
+        );
+        assert!(!target.path().join(PAGES_ARCHIVE_NAME).exists());
+    }
+}
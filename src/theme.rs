@@ -0,0 +1,108 @@
+//! Themes, i.e. named sets of styles.
+//!
+//! A theme is a TOML file called `<name>.toml` in the `themes` directory below
+//! the config directory. It contains a table for every style target, just like
+//! the `style.*` tables of the config file. The theme selected with
+//! `display.theme` provides the base styles and the `style` tables of the
+//! config file are applied on top of it. Theme files take precedence over the
+//! built-in themes of the same name.
+
+use std::fs;
+use std::path::PathBuf;
+
+use toml::value::Table;
+use toml::Value;
+
+use crate::config::{default_config_value, get_config_dir, validate_styles};
+use crate::config_schema::STYLE_TARGETS;
+use crate::error::TealdeerError::{self, ConfigError};
+
+/// The names of the built-in themes.
+pub const BUILTIN_THEMES: &[&str] = &["default", "plain"];
+
+/// Return the built-in theme called `name`.
+fn builtin_theme(name: &str) -> Option<Value> {
+    match name {
+        "default" => default_config_value().get("style").cloned(),
+        "plain" => Some(Value::Table(Table::new())),
+        _ => None,
+    }
+}
+
+/// Return the directory containing the theme files.
+pub fn themes_dir() -> Result<PathBuf, TealdeerError> {
+    get_config_dir().map(|(config_dir, _)| config_dir.join("themes"))
+}
+
+/// Check that `theme` only contains valid styles for known style targets.
+fn validate_theme(theme: &Value) -> Result<(), String> {
+    let table = theme
+        .as_table()
+        .ok_or_else(|| "A theme must be a table".to_string())?;
+    if let Some(key) = table
+        .keys()
+        .find(|key| !STYLE_TARGETS.iter().any(|(target, _)| target == key))
+    {
+        return Err(format!("Unknown style target {}", key));
+    }
+    validate_styles(theme.clone())
+}
+
+/// Load the theme called `name`, either from the themes directory or from the
+/// built-in themes.
+pub fn load_theme(name: &str) -> Result<Value, TealdeerError> {
+    if name.is_empty() || name.contains(&['/', '\\', '.'][..]) {
+        return Err(ConfigError(format!("Invalid theme name {:?}", name)));
+    }
+
+    let path = themes_dir()?.join(format!("{}.toml", name));
+    let theme = if path.is_file() {
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| ConfigError(format!("Could not read {}: {}", path.display(), e)))?;
+        toml::from_str(&contents)
+            .map_err(|e| ConfigError(format!("Could not parse {}: {}", path.display(), e)))?
+    } else {
+        builtin_theme(name).ok_or_else(|| {
+            ConfigError(format!(
+                "Theme {} not found. Create {} or use one of the built-in themes ({}).",
+                name,
+                path.display(),
+                BUILTIN_THEMES.join(", ")
+            ))
+        })?
+    };
+
+    validate_theme(&theme).map_err(|e| ConfigError(format!("Invalid theme {}: {}", name, e)))?;
+    Ok(theme)
+}
+
+/// Render the theme called `name` as the contents of a theme file.
+pub fn dump_theme(name: &str) -> Result<String, TealdeerError> {
+    let theme = load_theme(name)?;
+    Ok(toml::to_string(&theme).expect("Themes can always be serialized"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{builtin_theme, validate_theme, BUILTIN_THEMES};
+
+    #[test]
+    fn test_builtin_themes_are_valid() {
+        for name in BUILTIN_THEMES {
+            let theme = builtin_theme(name).unwrap();
+            assert_eq!(validate_theme(&theme), Ok(()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_validate_theme() {
+        let valid = toml::from_str("[command_name]\nforeground = \"red\"\n").unwrap();
+        assert_eq!(validate_theme(&valid), Ok(()));
+
+        let unknown_target = toml::from_str("[headline]\nbold = true\n").unwrap();
+        assert!(validate_theme(&unknown_target).is_err());
+
+        let invalid_color = toml::from_str("[url]\nforeground = \"pink\"\n").unwrap();
+        assert!(validate_theme(&invalid_color).is_err());
+    }
+}
@@ -27,6 +27,10 @@ pub struct Tokenizer<R: BufRead> {
     current_line: String,
     /// The tldr page format.
     format: TldrFormat,
+    /// The number of lines read so far.
+    lines_read: usize,
+    /// The number of the line of the last token, starting at 1.
+    token_line: usize,
 }
 
 impl<R> Tokenizer<R>
@@ -39,9 +43,17 @@ where
             first_line: true,
             current_line: String::new(),
             format: TldrFormat::Undecided,
+            lines_read: 0,
+            token_line: 0,
         }
     }
 
+    /// Return the number of the line that the last token was read from,
+    /// starting at 1.
+    pub fn line_number(&self) -> usize {
+        self.token_line
+    }
+
     pub fn next_token(&mut self) -> Option<LineType> {
         self.current_line.clear();
         let bytes_read = self.reader.read_line(&mut self.current_line);
@@ -52,6 +64,9 @@ where
                 None
             }
             Ok(_) => {
+                self.lines_read += 1;
+                self.token_line = self.lines_read;
+
                 // Handle new titles
                 if self.first_line && !self.current_line.starts_with('#') {
                     // It's the new format! Drop next line.
@@ -61,6 +76,7 @@ where
                         warn!("Could not read line from token reader: {:?}", e);
                         return None;
                     }
+                    self.lines_read += 1;
                     self.first_line = false;
                     self.format = TldrFormat::V2;
                     return Some(LineType::Title(self.current_line.trim_end().to_string()));
@@ -108,5 +124,6 @@ mod test {
         assert_eq!(title, LineType::Title("The Title".to_string()));
         let empty = tokenizer.next_token().unwrap();
         assert_eq!(empty, LineType::Empty);
+        assert_eq!(tokenizer.line_number(), 3);
     }
 }
@@ -0,0 +1,169 @@
+//! Finding translations that appear outdated.
+//!
+//! `tldr --translation-status <lang>` compares every page of a translation
+//! with the English page for the same platform. A translation appears
+//! outdated if it has a different number of examples or if the command of an
+//! example differs. Placeholders are usually translated as well, so their
+//! contents are ignored (`tar xf {{pfad/zur/datei}}` matches
+//! `tar xf {{path/to/file}}`).
+
+use std::io::BufReader;
+
+use serde_derive::Serialize;
+
+use crate::cache::Cache;
+use crate::error::TealdeerError;
+use crate::fill::next_placeholder;
+use crate::parser::Example;
+use crate::run::examples;
+
+/// Why a translation appears outdated.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Staleness {
+    /// The pages have a different number of examples.
+    ExampleCount { english: usize, translated: usize },
+    /// The command of the example with the (1-based) number `example`
+    /// differs.
+    Command {
+        example: usize,
+        english: String,
+        translated: String,
+    },
+}
+
+/// A translated page that appears outdated.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct OutdatedPage {
+    /// The path of the page in the pages directory, e.g. `linux/tar.md`.
+    pub page: String,
+    #[serde(flatten)]
+    pub staleness: Staleness,
+}
+
+/// The status of a translation.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TranslationStatus {
+    pub language: String,
+    /// The number of English pages.
+    pub english_pages: usize,
+    /// The number of English pages that are translated.
+    pub translated_pages: usize,
+    pub outdated: Vec<OutdatedPage>,
+}
+
+/// Replace the contents of all placeholders in `code` with nothing.
+fn mask_placeholders(code: &str) -> String {
+    let mut masked = String::with_capacity(code.len());
+    let mut rest = code;
+    while let Some((start, end)) = next_placeholder(rest) {
+        masked.push_str(&rest[..start]);
+        masked.push_str("{{}}");
+        rest = &rest[end..];
+    }
+    masked.push_str(rest);
+    masked
+}
+
+fn page_examples(page: &[u8]) -> Vec<Example> {
+    examples(BufReader::new(page))
+}
+
+/// Compare a translated page with the English one and return why the
+/// translation appears outdated, if it does.
+pub fn compare(english: &[u8], translated: &[u8]) -> Option<Staleness> {
+    let english = page_examples(english);
+    let translated = page_examples(translated);
+    if english.len() != translated.len() {
+        return Some(Staleness::ExampleCount {
+            english: english.len(),
+            translated: translated.len(),
+        });
+    }
+    english
+        .into_iter()
+        .zip(translated)
+        .enumerate()
+        .find(|(_, (english, translated))| {
+            mask_placeholders(&english.code) != mask_placeholders(&translated.code)
+        })
+        .map(|(i, (english, translated))| Staleness::Command {
+            example: i + 1,
+            english: english.code,
+            translated: translated.code,
+        })
+}
+
+/// Check all pages of the translation into `language` in the cache.
+pub fn translation_status(
+    cache: &Cache,
+    language: &str,
+) -> Result<TranslationStatus, TealdeerError> {
+    let english_pages = cache.language_pages("en")?;
+    let translated_pages = cache.language_pages(language)?;
+    let mut status = TranslationStatus {
+        language: language.to_string(),
+        english_pages: english_pages.len(),
+        ..TranslationStatus::default()
+    };
+    for page in english_pages {
+        if translated_pages.binary_search(&page).is_err() {
+            continue;
+        }
+        status.translated_pages += 1;
+        let english = cache.read_language_page("en", &page)?;
+        let translated = cache.read_language_page(language, &page)?;
+        if let Some(staleness) = compare(&english, &translated) {
+            status.outdated.push(OutdatedPage { page, staleness });
+        }
+    }
+    Ok(status)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compare, mask_placeholders, Staleness};
+
+    const ENGLISH: &str = "# tar\n\n> Archiving utility.\n\n\
+                           - Extract an archive:\n\n`tar xf {{path/to/file}}`\n\n\
+                           - List the contents:\n\n`tar tvf {{path/to/file}}`\n";
+
+    #[test]
+    fn test_mask_placeholders() {
+        assert_eq!(
+            mask_placeholders("cp {{pfad/zur/datei}} {{ziel}}"),
+            "cp {{}} {{}}"
+        );
+        assert_eq!(mask_placeholders("ls -l"), "ls -l");
+    }
+
+    #[test]
+    fn test_compare() {
+        let up_to_date = "# tar\n\n> Archivierungsprogramm.\n\n\
+                          - Ein Archiv entpacken:\n\n`tar xf {{pfad/zur/datei}}`\n\n\
+                          - Den Inhalt auflisten:\n\n`tar tvf {{pfad/zur/datei}}`\n";
+        assert_eq!(compare(ENGLISH.as_bytes(), up_to_date.as_bytes()), None);
+
+        let missing_example = "# tar\n\n> Archivierungsprogramm.\n\n\
+                               - Ein Archiv entpacken:\n\n`tar xf {{pfad/zur/datei}}`\n";
+        assert_eq!(
+            compare(ENGLISH.as_bytes(), missing_example.as_bytes()),
+            Some(Staleness::ExampleCount {
+                english: 2,
+                translated: 1
+            })
+        );
+
+        let old_command = "# tar\n\n> Archivierungsprogramm.\n\n\
+                           - Ein Archiv entpacken:\n\n`tar xf {{pfad/zur/datei}}`\n\n\
+                           - Den Inhalt auflisten:\n\n`tar tf {{pfad/zur/datei}}`\n";
+        assert_eq!(
+            compare(ENGLISH.as_bytes(), old_command.as_bytes()),
+            Some(Staleness::Command {
+                example: 2,
+                english: "tar tvf {{path/to/file}}".into(),
+                translated: "tar tf {{pfad/zur/datei}}".into(),
+            })
+        );
+    }
+}
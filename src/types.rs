@@ -10,16 +10,42 @@ use serde_derive::{Deserialize, Serialize};
 pub enum OsType {
     Linux,
     OsX,
+    FreeBsd,
+    NetBsd,
+    OpenBsd,
     SunOs,
     Windows,
     Other,
 }
 
+impl OsType {
+    /// The platform directories to look up pages in, in order, before
+    /// falling back to `common`.
+    ///
+    /// Many pages for BSD tools only exist for one of the BSDs, so the other
+    /// BSDs (and macOS, which shares much of their userland) are tried next.
+    pub fn platform_chain(self) -> &'static [&'static str] {
+        match self {
+            Self::Linux => &["linux"],
+            Self::OsX => &["osx"],
+            Self::FreeBsd => &["freebsd", "netbsd", "openbsd", "osx"],
+            Self::NetBsd => &["netbsd", "openbsd", "freebsd", "osx"],
+            Self::OpenBsd => &["openbsd", "netbsd", "freebsd", "osx"],
+            Self::SunOs => &["sunos"],
+            Self::Windows => &["windows"],
+            Self::Other => &[],
+        }
+    }
+}
+
 impl fmt::Display for OsType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Linux => write!(f, "Linux"),
-            Self::OsX => write!(f, "macOS / BSD"),
+            Self::OsX => write!(f, "macOS"),
+            Self::FreeBsd => write!(f, "FreeBSD"),
+            Self::NetBsd => write!(f, "NetBSD"),
+            Self::OpenBsd => write!(f, "OpenBSD"),
             Self::SunOs => write!(f, "SunOS"),
             Self::Windows => write!(f, "Windows"),
             Self::Other => write!(f, "Unknown OS"),
@@ -35,16 +61,84 @@ pub enum ColorOptions {
     Never,
 }
 
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Text,
+    Json,
+    /// JSON for `ConvertFrom-Json` in PowerShell. Pages are printed as
+    /// objects with `PascalCase` properties, other results as with `Json`.
+    #[serde(rename = "psobject-json")]
+    PsObjectJson,
+    /// Pages are printed as man pages (roff), other results as with `Text`.
+    Man,
+    /// NUON for Nushell. Pages are printed as records with tables, other
+    /// results as with `Json`, which Nushell reads as NUON as well.
+    Nuon,
+    /// Tab-separated values, for the list of pages only.
+    Tsv,
+}
+
+impl OutputFormat {
+    /// Return whether results and errors are printed as JSON.
+    pub fn is_json(self) -> bool {
+        self != Self::Text && self != Self::Man && self != Self::Tsv
+    }
+}
+
+/// How pages are rendered as text.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum RenderMode {
+    /// Styled and indented for reading.
+    Formatted,
+    /// The raw markdown of the page.
+    Markdown,
+    /// Every command on a single line, without indentation or styles, so that
+    /// it can be copied with a triple-click.
+    Copyable,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum LineType {
     Empty,
     Title(String),
+    /// A `## section` heading of a long (custom) page.
+    Section(String),
     Description(String),
     ExampleText(String),
     ExampleCode(String),
+    /// The URL of a `More information: <url>.` description line.
+    MoreInformation(String),
     Other(String),
 }
 
+/// Return the URL of a description line like `More information: <url>.`.
+fn more_information_url(description: &str) -> Option<String> {
+    const PREFIX: &str = "More information:";
+    if !description.starts_with(PREFIX) {
+        return None;
+    }
+    let url = description[PREFIX.len()..]
+        .trim()
+        .trim_end_matches('.')
+        .trim_start_matches('<')
+        .trim_end_matches('>');
+    if url.is_empty() {
+        None
+    } else {
+        Some(url.into())
+    }
+}
+
+impl LineType {
+    /// Create a description line, or a `MoreInformation` line if the
+    /// description only contains a link.
+    fn description(text: &str) -> Self {
+        more_information_url(text)
+            .map_or_else(|| Self::Description(text.into()), Self::MoreInformation)
+    }
+}
+
 impl<'a> From<&'a str> for LineType {
     /// Convert a string slice to a `LineType`. Newlines and trailing whitespace are trimmed.
     fn from(line: &'a str) -> Self {
@@ -52,16 +146,19 @@ impl<'a> From<&'a str> for LineType {
         let mut chars = trimmed.chars();
         match chars.next() {
             None => Self::Empty,
-            Some('#') => Self::Title(
+            Some('#') if trimmed.starts_with("##") => Self::Section(
                 trimmed
                     .trim_start_matches(|chr: char| chr == '#' || chr.is_whitespace())
                     .into(),
             ),
-            Some('>') => Self::Description(
+            Some('#') => Self::Title(
                 trimmed
-                    .trim_start_matches(|chr: char| chr == '>' || chr.is_whitespace())
+                    .trim_start_matches(|chr: char| chr == '#' || chr.is_whitespace())
                     .into(),
             ),
+            Some('>') => Self::description(
+                trimmed.trim_start_matches(|chr: char| chr == '>' || chr.is_whitespace()),
+            ),
             Some(' ') => Self::ExampleCode(trimmed.trim_start_matches(char::is_whitespace).into()),
             Some(_) => Self::ExampleText(trimmed.into()),
         }
@@ -76,16 +173,19 @@ impl LineType {
         let mut chars = trimmed.chars();
         match chars.next() {
             None => Self::Empty,
-            Some('#') => Self::Title(
+            Some('#') if trimmed.starts_with("##") => Self::Section(
                 trimmed
                     .trim_start_matches(|chr: char| chr == '#' || chr.is_whitespace())
                     .into(),
             ),
-            Some('>') => Self::Description(
+            Some('#') => Self::Title(
                 trimmed
-                    .trim_start_matches(|chr: char| chr == '>' || chr.is_whitespace())
+                    .trim_start_matches(|chr: char| chr == '#' || chr.is_whitespace())
                     .into(),
             ),
+            Some('>') => Self::description(
+                trimmed.trim_start_matches(|chr: char| chr == '>' || chr.is_whitespace()),
+            ),
             Some('-') => Self::ExampleText(
                 trimmed
                     .trim_start_matches(|chr: char| chr == '-' || chr.is_whitespace())
@@ -138,6 +238,14 @@ mod test {
             LineType::from("# Hello there"),
             LineType::Title("Hello there".into())
         );
+        assert_eq!(
+            LineType::from("## Backups "),
+            LineType::Section("Backups".into())
+        );
+        assert_eq!(
+            LineType::from_v1("##Restoring"),
+            LineType::Section("Restoring".into())
+        );
         assert_eq!(
             LineType::from("> tis a description \n"),
             LineType::Description("tis a description".into())
@@ -151,4 +259,20 @@ mod test {
             LineType::ExampleCode("$ cargo run".into())
         );
     }
+
+    #[test]
+    fn test_more_information() {
+        assert_eq!(
+            LineType::from("> More information: <https://www.gnu.org/software/tar>."),
+            LineType::MoreInformation("https://www.gnu.org/software/tar".into())
+        );
+        assert_eq!(
+            LineType::from_v1("> More information: https://example.com"),
+            LineType::MoreInformation("https://example.com".into())
+        );
+        assert_eq!(
+            LineType::from("> More information is available online."),
+            LineType::Description("More information is available online.".into())
+        );
+    }
 }
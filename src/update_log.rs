@@ -0,0 +1,182 @@
+//! A local log of update attempts.
+//!
+//! If `record_update_history` is enabled, the outcome of every update of the
+//! cache (manual, automatic or in the background) is appended to
+//! `UPDATE_LOG_FILE_NAME` in the cache directory, as a line of JSON: when it
+//! started, how long it took, the mirror that worked and the size of the
+//! archive, or why it failed. `tldr --update-history <n>` shows the last
+//! attempts, which helps to tell flaky mirrors and proxies apart from one-off
+//! failures. The log never leaves the machine.
+//!
+//! The log is rotated once it grows beyond `MAX_LOG_BYTES` (see
+//! `state::append_log`), so only the recent attempts are kept.
+
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::cache::{Cache, UpdateSummary};
+use crate::error::TealdeerError::{self, CacheError};
+use crate::human;
+use crate::state;
+
+/// The file inside the cache directory that update attempts are logged to.
+pub const UPDATE_LOG_FILE_NAME: &str = "update_history.jsonl";
+
+/// The size at which the log is rotated. An attempt takes a few hundred
+/// bytes, so this keeps at least a few hundred attempts.
+const MAX_LOG_BYTES: u64 = 256 * 1024;
+
+/// A mirror that an update tried before the one that worked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedMirror {
+    pub url: String,
+    pub error: String,
+}
+
+/// The outcome of an update attempt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateAttempt {
+    /// When the update started, in RFC 3339 format.
+    pub started: String,
+    pub duration_ms: u64,
+    /// Whether the update was started automatically instead of by
+    /// `--update`.
+    pub automatic: bool,
+    /// The URL of the mirror (or git repository) that the pages came from.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// The size of the downloaded archive in bytes.
+    #[serde(default)]
+    pub bytes: Option<u64>,
+    #[serde(default)]
+    pub failed_mirrors: Vec<FailedMirror>,
+    /// Why the update failed, unset if it succeeded.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl UpdateAttempt {
+    /// Describe an update that started at `started`, took `duration` and
+    /// ended with `result`.
+    pub fn new(
+        started: SystemTime,
+        duration: Duration,
+        automatic: bool,
+        result: Result<&UpdateSummary, &TealdeerError>,
+    ) -> Self {
+        let started = started
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs());
+        let mut attempt = Self {
+            started: human::iso8601(started),
+            duration_ms: u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
+            automatic,
+            url: None,
+            bytes: None,
+            failed_mirrors: vec![],
+            error: None,
+        };
+        match result {
+            Ok(summary) => {
+                attempt.url = Some(summary.url.clone());
+                attempt.bytes = summary.bytes;
+                attempt.failed_mirrors = summary
+                    .failed
+                    .iter()
+                    .map(|(url, e)| FailedMirror {
+                        url: url.clone(),
+                        error: e.message().to_string(),
+                    })
+                    .collect();
+            }
+            Err(e) => attempt.error = Some(e.message().to_string()),
+        }
+        attempt
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Append `attempt` to the log in the cache directory.
+pub fn record(attempt: &UpdateAttempt) -> Result<(), TealdeerError> {
+    let (cache_dir, _) = Cache::get_cache_dir()?;
+    let path = cache_dir.join(UPDATE_LOG_FILE_NAME);
+    let line = serde_json::to_string(attempt).expect("Update attempts can always be serialized");
+    state::append_log(&path, &line, MAX_LOG_BYTES).map_err(|e| {
+        CacheError(format!("Could not write {}: {}", path.display(), e)).with_io_hint(&e)
+    })
+}
+
+/// Parse the last `count` attempts of the `lines` of the log, oldest first.
+/// Lines that can't be parsed (e.g. cut off by a crash) are skipped.
+fn parse_recent(lines: &[String], count: usize) -> Vec<UpdateAttempt> {
+    let mut attempts: Vec<UpdateAttempt> = lines
+        .iter()
+        .rev()
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(attempt) => Some(attempt),
+            Err(e) => {
+                debug!("Skipping invalid line of the update log: {}", e);
+                None
+            }
+        })
+        .take(count)
+        .collect();
+    attempts.reverse();
+    attempts
+}
+
+/// Return the last `count` logged attempts, oldest first.
+pub fn recent(count: usize) -> Result<Vec<UpdateAttempt>, TealdeerError> {
+    let (cache_dir, _) = Cache::get_cache_dir()?;
+    let path = cache_dir.join(UPDATE_LOG_FILE_NAME);
+    let lines = state::read_log(&path).map_err(|e| {
+        CacheError(format!("Could not read {}: {}", path.display(), e)).with_io_hint(&e)
+    })?;
+    Ok(parse_recent(&lines, count))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_recent, UpdateAttempt};
+
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::cache::UpdateSummary;
+    use crate::error::TealdeerError::UpdateError;
+
+    #[test]
+    fn test_parse_recent() {
+        let summary = UpdateSummary {
+            url: "https://b.example.com/tldr.tar.gz".into(),
+            bytes: Some(1234),
+            failed: vec![(
+                "https://a.example.com/tldr.tar.gz".into(),
+                UpdateError("timed out".into()),
+            )],
+            quarantined: vec![],
+        };
+        let started = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let succeeded =
+            UpdateAttempt::new(started, Duration::from_millis(1500), false, Ok(&summary));
+        assert_eq!(succeeded.started, "2020-09-13T12:26:40Z");
+        assert_eq!(succeeded.failed_mirrors[0].error, "timed out");
+        let error = UpdateError("Could not resolve host".into());
+        let failed = UpdateAttempt::new(started, Duration::from_secs(30), true, Err(&error));
+        assert!(!failed.succeeded());
+
+        let lines = vec![
+            serde_json::to_string(&succeeded).unwrap(),
+            serde_json::to_string(&failed).unwrap(),
+            // Cut off by a crash
+            "{\"started\":\"2020-09-".into(),
+        ];
+        assert_eq!(parse_recent(&lines, 10), vec![succeeded, failed.clone()]);
+        assert_eq!(parse_recent(&lines, 1), vec![failed]);
+    }
+}
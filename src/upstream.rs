@@ -0,0 +1,156 @@
+//! Comparing a cached page with its current upstream version.
+//!
+//! `tldr --check-upstream <command>` downloads the current version of a
+//! single page from the tldr-pages repository and compares it line by line
+//! with the cached copy. This tells whether the cache is behind for that page
+//! without a full update. Only pages from the cache have an upstream version,
+//! the path of a page in the repository is taken from its provenance.
+
+use std::fs;
+use std::path::Path;
+
+use serde_derive::Serialize;
+
+use crate::error::TealdeerError::{self, CacheError};
+use crate::fetch::{fetch_page, HttpSettings};
+use crate::provenance::{Origin, Provenance};
+
+/// The URL of the tldr-pages repository that single pages are downloaded
+/// from, followed by the path of the page.
+pub const DEFAULT_UPSTREAM_PAGES_URL: &str =
+    "https://raw.githubusercontent.com/tldr-pages/tldr/main";
+
+/// A line of the difference between two versions of a page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "op", content = "line", rename_all = "kebab-case")]
+pub enum DiffLine {
+    Unchanged(String),
+    /// A line that is only in the upstream version.
+    Added(String),
+    /// A line that is only in the cached version.
+    Removed(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpstreamStatus {
+    UpToDate,
+    /// The upstream version differs from the cached one.
+    Outdated,
+    /// The page was removed from the repository.
+    RemovedUpstream,
+}
+
+/// The result of comparing a cached page with its upstream version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UpstreamComparison {
+    pub command: String,
+    /// The path of the page in the repository, e.g. `pages/common/tar.md`.
+    pub upstream_path: String,
+    pub upstream_url: String,
+    pub status: UpstreamStatus,
+    /// The lines of both versions, empty unless the page is outdated.
+    pub diff: Vec<DiffLine>,
+}
+
+/// Return the difference between the lines of `old` and `new`, based on their
+/// longest common subsequence.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j] is the length of the longest common subsequence of
+    // old[i..] and new[j..]
+    let mut common = vec![vec![0_usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push(DiffLine::Unchanged(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            diff.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// Compare the cached page of `command` at `path` with its version at
+/// `base_url`.
+pub fn compare(
+    command: &str,
+    path: &Path,
+    provenance: &Provenance,
+    base_url: &str,
+    http: &HttpSettings,
+) -> Result<UpstreamComparison, TealdeerError> {
+    let upstream_path = match (provenance.origin, &provenance.upstream_path) {
+        (Origin::TldrPages, Some(upstream_path)) => upstream_path.clone(),
+        _ => {
+            return Err(CacheError(format!(
+                "The page {} is not from tldr-pages, so it has no upstream version.",
+                command
+            ))
+            .with_hint("Run `tldr --attribution` to see where the page comes from."))
+        }
+    };
+    let cached = fs::read_to_string(path).map_err(|e| {
+        CacheError(format!("Could not read {}: {}", path.display(), e)).with_io_hint(&e)
+    })?;
+
+    let upstream_url = format!("{}/{}", base_url.trim_end_matches('/'), upstream_path);
+    let (status, diff) = match fetch_page(http, &upstream_url)? {
+        None => (UpstreamStatus::RemovedUpstream, vec![]),
+        Some(ref upstream) if upstream.lines().eq(cached.lines()) => {
+            (UpstreamStatus::UpToDate, vec![])
+        }
+        Some(upstream) => (UpstreamStatus::Outdated, diff_lines(&cached, &upstream)),
+    };
+    Ok(UpstreamComparison {
+        command: command.into(),
+        upstream_path,
+        upstream_url,
+        status,
+        diff,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff_lines, DiffLine};
+
+    #[test]
+    fn test_diff_lines() {
+        use DiffLine::{Added, Removed, Unchanged};
+
+        assert_eq!(
+            diff_lines("# tar\n\n- Old\n`tar`\n", "# tar\n\n- New\n`tar`\n- More\n"),
+            vec![
+                Unchanged("# tar".into()),
+                Unchanged(String::new()),
+                Removed("- Old".into()),
+                Added("- New".into()),
+                Unchanged("`tar`".into()),
+                Added("- More".into()),
+            ]
+        );
+        assert_eq!(diff_lines("a\n", "a"), vec![Unchanged("a".into())]);
+        assert_eq!(diff_lines("", "a"), vec![Added("a".into())]);
+        assert_eq!(diff_lines("a", ""), vec![Removed("a".into())]);
+    }
+}
@@ -0,0 +1,160 @@
+//! Traversal of the directories containing pages.
+//!
+//! All page directories (the cache and the custom pages directory) are
+//! traversed with `walk`, which skips entries that can not be pages: hidden
+//! files and directories (including VCS directories like `.git`), editor
+//! backups and swap files, and entries matching the ignore globs from the
+//! config. This way, keeping the custom pages in a git repository does not
+//! add junk to the list of pages.
+
+use std::path::Path;
+
+use glob::Pattern;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use walkdir::{DirEntry, WalkDir};
+
+/// File names that are always ignored, in addition to hidden files.
+const DEFAULT_IGNORE_GLOBS: &[&str] = &["*~", "*.swp", "*.swo", "#*#", "CVS", "_darcs"];
+
+/// The rules deciding which files and directories are skipped.
+///
+/// Globs are matched against the file name only. In the config, only the
+/// globs of the user are (de)serialized, the default rules always apply.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IgnoreRules {
+    globs: Vec<String>,
+    patterns: Vec<Pattern>,
+}
+
+impl IgnoreRules {
+    /// Create rules ignoring the default entries and those matching `globs`.
+    pub fn new(globs: &[String]) -> Result<Self, String> {
+        let patterns = DEFAULT_IGNORE_GLOBS
+            .iter()
+            .map(|glob| Pattern::new(glob).expect("Invalid default ignore glob"))
+            .map(Ok)
+            .chain(globs.iter().map(|glob| {
+                Pattern::new(glob).map_err(|e| format!("Invalid ignore glob {:?}: {}", glob, e.msg))
+            }))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            globs: globs.to_vec(),
+            patterns,
+        })
+    }
+
+    /// Return whether the file or directory called `file_name` is ignored.
+    pub fn is_ignored(&self, file_name: &str) -> bool {
+        file_name.starts_with('.') || self.patterns.iter().any(|p| p.matches(file_name))
+    }
+}
+
+impl Default for IgnoreRules {
+    fn default() -> Self {
+        Self::new(&[]).expect("Invalid default ignore globs")
+    }
+}
+
+impl<'de> Deserialize<'de> for IgnoreRules {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let globs = Vec::<String>::deserialize(deserializer)?;
+        Self::new(&globs).map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for IgnoreRules {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.globs.serialize(serializer)
+    }
+}
+
+/// Walk the entries below `root` up to `max_depth`, following symlinks.
+///
+/// Ignored entries are skipped, as well as entries for which `filter` returns
+/// false. Directories that are skipped are not descended into. The rules never
+/// apply to `root` itself, which may well be hidden (e.g. `~/.tldr`).
+pub fn walk<'a, P>(
+    root: &Path,
+    max_depth: usize,
+    rules: &'a IgnoreRules,
+    mut filter: P,
+) -> impl Iterator<Item = walkdir::Result<DirEntry>> + 'a
+where
+    P: FnMut(&DirEntry) -> bool + 'a,
+{
+    WalkDir::new(root)
+        .min_depth(1) // Skip root directory
+        .max_depth(max_depth)
+        .follow_links(true) // Pages may be symlinks
+        .into_iter()
+        .filter_entry(move |entry| {
+            if entry.depth() == 0 {
+                return true;
+            }
+            let ignored = entry
+                .file_name()
+                .to_str()
+                .map_or(true, |name| rules.is_ignored(name));
+            !ignored && filter(entry)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{walk, IgnoreRules};
+
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_default_rules() {
+        let rules = IgnoreRules::default();
+        for name in &[".git", ".tar.md.swp", "tar.md~", "#tar.md#", "CVS"] {
+            assert!(rules.is_ignored(name), "{} should be ignored", name);
+        }
+        for name in &["tar.md", "tar.page.md", "common", "git-commit.md"] {
+            assert!(!rules.is_ignored(name), "{} should not be ignored", name);
+        }
+    }
+
+    #[test]
+    fn test_user_globs() {
+        let rules = IgnoreRules::new(&["*.draft.md".into(), "wip-*".into()]).unwrap();
+        assert!(rules.is_ignored("tar.draft.md"));
+        assert!(rules.is_ignored("wip-tar.md"));
+        assert!(!rules.is_ignored("tar.md"));
+
+        assert!(IgnoreRules::new(&["[tar".into()]).is_err());
+    }
+
+    #[test]
+    fn test_walk() {
+        let tempdir = TempDir::new().unwrap();
+        let dir = tempdir.path().join(".pages");
+        fs::create_dir_all(dir.join(".git").join("objects")).unwrap();
+        fs::create_dir_all(dir.join("common")).unwrap();
+        for name in &[
+            "common/tar.md",
+            "common/tar.md~",
+            ".git/objects/1",
+            "notes.txt",
+        ] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let rules = IgnoreRules::default();
+        let mut paths: Vec<String> = walk(&dir, usize::MAX, &rules, |entry| {
+            entry.file_name() != "notes.txt"
+        })
+        .map(|entry| {
+            let entry = entry.unwrap();
+            let path = entry.path().strip_prefix(&dir).unwrap();
+            path.to_string_lossy().replace('\\', "/")
+        })
+        .collect();
+        paths.sort();
+        assert_eq!(paths, vec!["common", "common/tar.md"]);
+    }
+}
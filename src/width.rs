@@ -0,0 +1,171 @@
+//! Display width of text in terminals.
+//!
+//! Characters of CJK scripts (and some other symbols) occupy two columns in a
+//! terminal, combining characters none. Text that is aligned in columns must
+//! therefore be padded according to its display width instead of its length.
+//! ANSI escape sequences (e.g. styles) do not occupy any columns.
+
+use std::env;
+
+use unicode_width::UnicodeWidthChar;
+
+/// The width that is assumed if the width of the terminal is unknown.
+const DEFAULT_TERMINAL_WIDTH: usize = 80;
+
+/// Return the number of columns `text` occupies in a terminal.
+pub fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip CSI sequences (`ESC [ ... <final byte>`) and OSC sequences
+            // (`ESC ] ... ESC \`)
+            match chars.next() {
+                Some('[') => {
+                    for c in &mut chars {
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next() == Some('\\')) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        } else {
+            width += c.width().unwrap_or(0);
+        }
+    }
+    width
+}
+
+/// Pad `text` with spaces to a display width of at least `width` columns.
+pub fn pad(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(display_width(text));
+    format!("{}{}", text, " ".repeat(padding))
+}
+
+/// Split `line` into lines of at most `width` columns.
+///
+/// A style that is active at a break is ended before it and continued on the
+/// next line, so that padding after a part is never styled. Always returns at
+/// least one (possibly empty) line.
+pub fn wrap(line: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut current_width = 0;
+    // The last SGR sequence (`ESC [ ... m`) that did not reset the style
+    let mut active_style: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            let mut sequence = c.to_string();
+            match chars.next() {
+                Some('[') => {
+                    sequence.push('[');
+                    for c in &mut chars {
+                        sequence.push(c);
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                    if sequence.ends_with('m') {
+                        active_style = if sequence == "\x1b[0m" || sequence == "\x1b[m" {
+                            None
+                        } else {
+                            Some(sequence.clone())
+                        };
+                    }
+                }
+                Some(c) => sequence.push(c),
+                None => {}
+            }
+            current.push_str(&sequence);
+            continue;
+        }
+
+        let char_width = c.width().unwrap_or(0);
+        if current_width > 0 && current_width + char_width > width {
+            if active_style.is_some() {
+                current.push_str("\x1b[0m");
+            }
+            lines.push(current);
+            current = active_style.clone().unwrap_or_default();
+            current_width = 0;
+        }
+        current.push(c);
+        current_width += char_width;
+    }
+    lines.push(current);
+    lines
+}
+
+/// Return the width of the terminal in columns.
+///
+/// The `COLUMNS` environment variable takes precedence over the size of the
+/// terminal that stdout is connected to.
+pub fn terminal_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|&columns| columns > 0)
+        .or_else(stdout_width)
+        .unwrap_or(DEFAULT_TERMINAL_WIDTH)
+}
+
+#[cfg(unix)]
+fn stdout_width() -> Option<usize> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if result == 0 && size.ws_col > 0 {
+        Some(usize::from(size.ws_col))
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn stdout_width() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::{display_width, pad, wrap};
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("tar"), 3);
+        assert_eq!(display_width("アーカイブ"), 10);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width("\x1b[4;36mtar\x1b[0m"), 3);
+        assert_eq!(
+            display_width("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\"),
+            4
+        );
+    }
+
+    #[test]
+    fn test_pad() {
+        assert_eq!(pad("ab", 4), "ab  ");
+        assert_eq!(pad("日本", 6), "日本  ");
+        assert_eq!(pad("abcdef", 4), "abcdef");
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("", 4), vec![""]);
+        assert_eq!(wrap("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("日本語", 4), vec!["日本", "語"]);
+        // Styles are continued on the next line
+        assert_eq!(
+            wrap("\x1b[31mabcdef\x1b[0mgh", 4),
+            vec!["\x1b[31mabcd\x1b[0m", "\x1b[31mef\x1b[0mgh"]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Detecting the type of the project in the current directory.
+//!
+//! Running `tldr` without a command inside a project lists the pages of the
+//! tools the project is built with, e.g. `cargo` next to a `Cargo.toml`. The
+//! project is the closest directory (starting at the current one) that
+//! contains a marker file of a known project type.
+
+use std::path::{Path, PathBuf};
+
+/// A type of project, recognized by its marker files.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ProjectType {
+    pub name: &'static str,
+    /// Files or directories that only exist in this type of project.
+    pub markers: &'static [&'static str],
+    /// The pages of the tools used in this type of project, most relevant
+    /// first.
+    pub pages: &'static [&'static str],
+}
+
+const PROJECT_TYPES: &[ProjectType] = &[
+    ProjectType {
+        name: "Rust",
+        markers: &["Cargo.toml"],
+        pages: &["cargo", "rustup", "rustc"],
+    },
+    ProjectType {
+        name: "Node.js",
+        markers: &["package.json"],
+        pages: &["npm", "npx", "node"],
+    },
+    ProjectType {
+        name: "Python",
+        markers: &["pyproject.toml", "setup.py", "requirements.txt"],
+        pages: &["pip", "python"],
+    },
+    ProjectType {
+        name: "Go",
+        markers: &["go.mod"],
+        pages: &["go"],
+    },
+    ProjectType {
+        name: "Docker",
+        markers: &[
+            "Dockerfile",
+            "compose.yaml",
+            "compose.yml",
+            "docker-compose.yaml",
+            "docker-compose.yml",
+        ],
+        pages: &["docker", "docker-compose"],
+    },
+    ProjectType {
+        name: "Make",
+        markers: &["Makefile", "makefile", "GNUmakefile"],
+        pages: &["make"],
+    },
+];
+
+/// A detected project: its directory and its types, with the marker found
+/// for each type.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Workspace {
+    pub dir: PathBuf,
+    pub types: Vec<(&'static ProjectType, &'static str)>,
+}
+
+/// Return the types of the project in `dir`, with the marker found for each.
+fn project_types(dir: &Path) -> Vec<(&'static ProjectType, &'static str)> {
+    PROJECT_TYPES
+        .iter()
+        .filter_map(|project_type| {
+            project_type
+                .markers
+                .iter()
+                .find(|marker| dir.join(marker).exists())
+                .map(|&marker| (project_type, marker))
+        })
+        .collect()
+}
+
+/// Find the closest project that contains `dir`.
+pub fn detect(dir: &Path) -> Option<Workspace> {
+    dir.ancestors().find_map(|dir| {
+        let types = project_types(dir);
+        if types.is_empty() {
+            None
+        } else {
+            Some(Workspace {
+                dir: dir.to_path_buf(),
+                types,
+            })
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::detect;
+
+    #[test]
+    fn test_detect() {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().join("project");
+        let src = project.join("src");
+        fs::create_dir_all(&src).unwrap();
+        assert_eq!(detect(&src), None);
+
+        fs::write(project.join("Cargo.toml"), "").unwrap();
+        fs::write(project.join("Makefile"), "").unwrap();
+        let workspace = detect(&src).unwrap();
+        assert_eq!(workspace.dir, project);
+        let types: Vec<_> = workspace
+            .types
+            .iter()
+            .map(|(project_type, marker)| (project_type.name, *marker))
+            .collect();
+        assert_eq!(types, vec![("Rust", "Cargo.toml"), ("Make", "Makefile")]);
+
+        // The closest project wins
+        fs::write(src.join("package.json"), "{}").unwrap();
+        assert_eq!(detect(&src).unwrap().dir, src);
+    }
+}
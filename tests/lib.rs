@@ -8,6 +8,7 @@ extern crate tempfile;
 
 use std::fs::{create_dir_all, File};
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::{Duration, SystemTime};
 
@@ -16,6 +17,15 @@ use predicates::boolean::PredicateBooleanExt;
 use predicates::prelude::predicate::str::{contains, is_empty, similar};
 use tempfile::{Builder, TempDir};
 
+/// Return the path of a tiny pages archive with the same structure as the
+/// upstream archive, which is replayed instead of downloading the pages.
+fn fixture_archive() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("tldr-pages.tar.gz")
+}
+
 struct TestEnv {
     pub cache_dir: TempDir,
     pub config_dir: TempDir,
@@ -37,11 +47,21 @@ impl TestEnv {
 
     /// Add entry for that environment.
     fn add_entry(&self, name: &str, contents: &str) {
+        self.add_translated_entry(name, contents, "en");
+    }
+
+    /// Add entry in the given language for that environment.
+    fn add_translated_entry(&self, name: &str, contents: &str, language: &str) {
+        let pages_dir = if language == "en" {
+            "pages".to_string()
+        } else {
+            format!("pages.{}", language)
+        };
         let dir = self
             .cache_dir
             .path()
             .join("tldr-master")
-            .join("pages")
+            .join(pages_dir)
             .join("common");
         create_dir_all(&dir).unwrap();
 
@@ -65,6 +85,29 @@ impl TestEnv {
 
     /// Return a new `Command` with env vars set.
     fn command(&self) -> Command {
+        let mut cmd = self.build().command();
+        self.set_env(&mut cmd);
+        cmd
+    }
+
+    /// Return a new `Command` with env vars set that runs tealdeer with
+    /// `args` in a pseudo terminal (using `script` from util-linux), so that
+    /// it may prompt for input. Its stdout contains the output of tealdeer
+    /// on both stdout and stderr, and the echoed input.
+    #[cfg(target_os = "linux")]
+    fn terminal_command(&self, args: &[&str]) -> Command {
+        let build = self.build();
+        let command_line: Vec<String> = std::iter::once(build.path().to_str().unwrap())
+            .chain(args.iter().copied())
+            .map(|arg| format!("'{}'", arg))
+            .collect();
+        let mut cmd = Command::new("script");
+        cmd.args(&["-qec", &command_line.join(" "), "/dev/null"]);
+        self.set_env(&mut cmd);
+        cmd
+    }
+
+    fn build(&self) -> escargot::CargoRun {
         let mut build = escargot::CargoBuild::new()
             .bin("tldr")
             .current_release()
@@ -75,8 +118,10 @@ impl TestEnv {
         if !self.features.is_empty() {
             build = build.arg(&format!("--feature {}", self.features.join(",")));
         }
-        let run = build.run().unwrap();
-        let mut cmd = run.command();
+        build.run().unwrap()
+    }
+
+    fn set_env(&self, cmd: &mut Command) {
         cmd.env(
             "TEALDEER_CACHE_DIR",
             self.cache_dir.path().to_str().unwrap(),
@@ -85,7 +130,8 @@ impl TestEnv {
             "TEALDEER_CONFIG_DIR",
             self.config_dir.path().to_str().unwrap(),
         );
-        cmd
+        // Updates never use the network, see `fixture_archive`
+        cmd.env("TEALDEER_REPLAY_ARCHIVE", fixture_archive());
     }
 }
 
@@ -99,6 +145,34 @@ fn test_missing_cache() {
         .stderr(contains("Cache not found. Please run `tldr --update`."));
 }
 
+#[test]
+fn test_localized_help() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .env("LANG", "de_DE.UTF-8")
+        .args(&["--help"])
+        .assert()
+        .success()
+        .stdout(contains("Optionen:"))
+        .stdout(contains(
+            "-l --list             Alle Befehle im Cache auflisten",
+        ));
+
+    // English is used for languages without a translation and with --language
+    for args in &[&["--help"][..], &["-h", "--language", "en"][..]] {
+        testenv
+            .command()
+            .env("LANG", "xx_XX.UTF-8")
+            .args(*args)
+            .assert()
+            .success()
+            .stdout(contains(
+                "-l --list             List all commands in the cache",
+            ));
+    }
+}
 #[test]
 fn test_update_cache() {
     let testenv = TestEnv::new();
@@ -117,282 +191,1085 @@ fn test_update_cache() {
         .success()
         .stdout(contains("Successfully updated cache."));
 
-    testenv.command().args(&["sl"]).assert().success();
-}
-
-#[test]
-fn test_quiet_cache() {
-    let testenv = TestEnv::new();
     testenv
         .command()
-        .args(&["--update", "--quiet"])
+        .args(&["sl"])
         .assert()
         .success()
-        .stdout(is_empty());
+        .stdout(contains("Steam locomotive"));
 
-    testenv
-        .command()
-        .args(&["--clear-cache", "--quiet"])
-        .assert()
-        .success()
-        .stdout(is_empty());
+    // The whole pipeline ran: the pages were extracted into a generation and
+    // the metadata and manifest were written
+    let cache_dir = testenv.cache_dir.path();
+    assert!(cache_dir.join("generations").is_dir());
+    assert!(!cache_dir.join("tldr-staging").exists());
+    let manifest = std::fs::read_to_string(cache_dir.join("manifest.sha256")).unwrap();
+    assert_eq!(manifest.lines().count(), 6);
+    assert!(manifest.contains("  pages.de/common/tar.md\n"));
+    let cache_info = std::fs::read_to_string(cache_dir.join("cache_info.toml")).unwrap();
+    assert!(cache_info.contains("source_url = \"https://"));
 }
 
 #[test]
-fn test_quiet_failures() {
+fn test_update_checksum() {
     let testenv = TestEnv::new();
+    let config_file_path = testenv.config_dir.path().join("config.toml");
 
+    // The checksum of tests/fixtures/tldr-pages.tar.gz
+    std::fs::write(
+        &config_file_path,
+        "[updates]\narchive_sha256 = \
+         \"2931E4F674950325DA9550F5F0C4F657C091AFDFE536B832F8AA76B07DD6478B\"\n",
+    )
+    .unwrap();
     testenv
         .command()
-        .args(&["--update", "-q"])
+        .args(&["--update"])
         .assert()
         .success()
-        .stdout(is_empty());
+        .stdout(contains("Successfully updated cache."));
 
+    std::fs::write(
+        &config_file_path,
+        format!("[updates]\narchive_sha256 = \"{}\"\n", "0".repeat(64)),
+    )
+    .unwrap();
     testenv
         .command()
-        .args(&["fakeprogram", "-q"])
+        .args(&["--update"])
         .assert()
         .failure()
-        .stdout(is_empty());
+        .stderr(contains("The SHA-256 checksum of the archive from"))
+        .stderr(contains(format!("expected {}.", "0".repeat(64))));
+
+    std::fs::write(&config_file_path, "[updates]\narchive_sha256 = \"abc\"\n").unwrap();
+    testenv
+        .command()
+        .args(&["--update"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid SHA-256 checksum \"abc\""));
 }
 
 #[test]
-fn test_quiet_old_cache() {
+fn test_update_from_file() {
+    use std::io::Cursor;
+    use std::process::Stdio;
+
     let testenv = TestEnv::new();
+    let archive = fixture_archive();
 
     testenv
         .command()
-        .args(&["--update", "-q"])
+        .args(&["--update-from-file", archive.to_str().unwrap(), "sl"])
+        .env_remove("TEALDEER_REPLAY_ARCHIVE")
         .assert()
         .success()
-        .stdout(is_empty());
-
-    filetime::set_file_mtime(
-        testenv.cache_dir.path().join("tldr-master"),
-        filetime::FileTime::from_unix_time(1, 0),
-    )
-    .unwrap();
+        .stdout(contains("Successfully updated cache."))
+        .stdout(contains("Steam locomotive"));
+    let cache_info =
+        std::fs::read_to_string(testenv.cache_dir.path().join("cache_info.toml")).unwrap();
+    assert!(cache_info.contains("source_url = \"file://"));
 
+    // Zip archives from stdin, like the `tldr.zip` of tldr-pages releases
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file("pages/common/zipped.md", zip::write::FileOptions::default())
+        .unwrap();
+    zip.write_all(b"# zipped\n\n> From a zip archive.\n")
+        .unwrap();
+    let zipped = zip.finish().unwrap().into_inner();
+    let mut child = testenv
+        .command()
+        .args(&["--update-from-file", "-", "--quiet"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(&zipped).unwrap();
+    child
+        .wait_with_output()
+        .unwrap()
+        .assert()
+        .success()
+        .stdout(is_empty());
     testenv
         .command()
-        .args(&["tldr"])
+        .args(&["zipped"])
         .assert()
         .success()
-        .stderr(contains("The cache hasn't been updated for more than "));
+        .stdout(contains("From a zip archive."));
 
+    // The archive is verified like a downloaded one
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[updates]\nverify_archive_checksum = true\n",
+    )
+    .unwrap();
     testenv
         .command()
-        .args(&["tldr", "--quiet"])
+        .args(&["--update-from-file", archive.to_str().unwrap()])
         .assert()
-        .success()
-        .stderr(contains("The cache hasn't been updated for more than ").not());
+        .failure()
+        .stderr(contains("tldr-pages.tar.gz.sha256"));
 }
 
 #[test]
-fn test_setup_seed_config() {
+fn test_update_quarantines_invalid_pages() {
+    use std::io::Cursor;
+
     let testenv = TestEnv::new();
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let pages: &[(&str, &[u8])] = &[
+        ("pages/common/valid.md", b"# valid\n\n> A valid page.\n"),
+        ("pages/common/binary.md", b"\x00\xff\xfe\x00"),
+        ("pages/linux/untitled.md", b"> No title.\n"),
+    ];
+    for (path, contents) in pages {
+        zip.start_file(*path, zip::write::FileOptions::default())
+            .unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    let archive = testenv.input_dir.path().join("tldr.zip");
+    std::fs::write(&archive, zip.finish().unwrap().into_inner()).unwrap();
 
     testenv
         .command()
-        .args(&["--seed-config"])
+        .args(&["--update-from-file", archive.to_str().unwrap()])
         .assert()
         .success()
-        .stdout(contains("Successfully created seed config file"));
-}
-
-#[test]
-fn test_show_paths() {
-    let testenv = TestEnv::new();
+        .stdout(contains("Successfully updated cache."))
+        .stderr(contains("Skipped 2 invalid pages of the archive"))
+        .stderr(contains("  pages/common/binary.md: not valid UTF-8\n"))
+        .stderr(contains(
+            "  pages/linux/untitled.md: does not start with a `# <title>` line\n",
+        ));
+    assert!(testenv
+        .cache_dir
+        .path()
+        .join("quarantine/pages/common/binary.md")
+        .is_file());
 
     testenv
         .command()
-        .args(&["--show-paths"])
+        .args(&["--list"])
         .assert()
         .success()
-        .stdout(contains(format!(
-            "Config dir:  {}",
-            testenv.config_dir.path().to_str().unwrap(),
-        )))
-        .stdout(contains(format!(
-            "Config path: {}",
-            testenv
-                .config_dir
-                .path()
-                .join("config.toml")
-                .to_str()
-                .unwrap(),
-        )))
-        .stdout(contains(format!(
-            "Cache dir:   {}",
-            testenv.cache_dir.path().to_str().unwrap(),
-        )))
-        .stdout(contains(format!(
-            "Pages dir:   {}",
-            testenv
-                .cache_dir
-                .path()
-                .join("tldr-master")
-                .to_str()
-                .unwrap(),
-        )));
+        .stdout(similar("valid\n"));
 }
 
 #[test]
-fn test_markdown_rendering() {
+fn test_pages_source_directory() {
     let testenv = TestEnv::new();
+    let checkout = testenv.input_dir.path().join("tldr");
+    create_dir_all(checkout.join("pages").join("common")).unwrap();
+    let page = checkout.join("pages").join("common").join("foo.md");
+    std::fs::write(&page, "# foo\n\n> Foo.\n\n- First version:\n\n`foo`\n").unwrap();
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        format!(
+            "[updates]\npages_source = {{ type = \"directory\", path = '{}' }}\n",
+            checkout.display()
+        ),
+    )
+    .unwrap();
 
+    // Pages are read without a cache
     testenv
         .command()
-        .args(&["--update"])
+        .args(&["foo"])
         .assert()
         .success()
-        .stdout(contains("Successfully updated cache."));
-
-    let expected = include_str!("tar-markdown.expected");
+        .stdout(contains("First version"));
     testenv
         .command()
-        .args(&["-m", "tar"])
+        .args(&["--list"])
         .assert()
         .success()
-        .stdout(similar(expected));
-}
-
-fn _test_correct_rendering(
-    input_file: &str,
-    filename: &str,
-    expected: &'static str,
-    color_option: &str,
-) {
-    let testenv = TestEnv::new();
-
-    // Create input file
-    let file_path = testenv.input_dir.path().join(filename);
-    println!("Testfile path: {:?}", &file_path);
-    let mut file = File::create(&file_path).unwrap();
-    file.write_all(input_file.as_bytes()).unwrap();
+        .stdout(contains("foo"));
 
+    // Edits show up right away
+    std::fs::write(&page, "# foo\n\n> Foo.\n\n- Edited version:\n\n`foo`\n").unwrap();
     testenv
         .command()
-        .args(&["--color", color_option, "-f", &file_path.to_str().unwrap()])
+        .args(&["foo"])
         .assert()
         .success()
-        .stdout(similar(expected));
-}
-
-/// An end-to-end integration test for direct file rendering (v1 syntax).
-#[test]
-fn test_correct_rendering_v1() {
-    _test_correct_rendering(
-        include_str!("inkscape-v1.md"),
-        "inkscape-v1.md",
-        include_str!("inkscape-default.expected"),
-        "always",
-    );
-}
-
-/// An end-to-end integration test for direct file rendering (v2 syntax).
-#[test]
-fn test_correct_rendering_v2() {
-    _test_correct_rendering(
-        include_str!("inkscape-v2.md"),
-        "inkscape-v2.md",
-        include_str!("inkscape-default.expected"),
-        "always",
-    );
-}
+        .stdout(contains("Edited version"));
 
-#[test]
-/// An end-to-end integration test for direct file rendering with the `--color auto` option. This
-/// will not use styling since output is not stdout.
-fn test_rendering_color_auto() {
-    _test_correct_rendering(
-        include_str!("inkscape-v2.md"),
-        "inkscape-v2.md",
-        include_str!("inkscape-default-no-color.expected"),
-        "auto",
-    );
-}
+    testenv
+        .command()
+        .args(&["--update"])
+        .assert()
+        .success()
+        .stdout(contains("don't need to be updated"));
 
-#[test]
-/// An end-to-end integration test for direct file rendering with the `--color never` option.
-fn test_rendering_color_never() {
-    _test_correct_rendering(
-        include_str!("inkscape-v2.md"),
-        "inkscape-v2.md",
-        include_str!("inkscape-default-no-color.expected"),
-        "never",
-    );
+    // The directory must contain pages
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        format!(
+            "[updates]\npages_source = {{ type = \"directory\", path = '{}' }}\n",
+            testenv.input_dir.path().display()
+        ),
+    )
+    .unwrap();
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .failure()
+        .stderr(contains("does not contain a `pages` directory"));
 }
 
-/// An end-to-end integration test for rendering with custom syntax config.
 #[test]
-fn test_correct_rendering_with_config() {
+fn test_pages_source_git() {
     let testenv = TestEnv::new();
+    let repo = testenv.input_dir.path().join("tldr");
+    create_dir_all(repo.join("pages").join("common")).unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(&["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(&repo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    let page = repo.join("pages").join("common").join("foo.md");
+    git(&["init", "--quiet"]);
+    std::fs::write(&page, "# foo\n\n> Foo.\n\n- First version:\n\n`foo`\n").unwrap();
+    git(&["add", "."]);
+    git(&["commit", "--quiet", "-m", "Add foo"]);
+    let url = format!("file://{}", repo.display());
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        format!(
+            "[updates]\npages_source = {{ type = \"git\", url = '{}' }}\n",
+            url
+        ),
+    )
+    .unwrap();
 
-    // Setup config file
-    // TODO should be config::CONFIG_FILE_NAME
-    let config_file_path = testenv.config_dir.path().join("config.toml");
-    println!("Config path: {:?}", &config_file_path);
-
-    let mut config_file = File::create(&config_file_path).unwrap();
-    config_file
-        .write(include_str!("config.toml").as_bytes())
-        .unwrap();
-
-    // Create input file
-    let file_path = testenv.input_dir.path().join("inkscape-v2.md");
-    println!("Testfile path: {:?}", &file_path);
-
-    let mut file = File::create(&file_path).unwrap();
-    file.write_all(include_str!("inkscape-v2.md").as_bytes())
-        .unwrap();
-
-    // Load expected output
-    let expected = include_str!("inkscape-with-config.expected");
+    testenv
+        .command()
+        .args(&["--update", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Successfully updated cache."))
+        .stdout(contains("First version"));
+    assert!(testenv.cache_dir.path().join("tldr-git/.git").is_dir());
+    let cache_info =
+        std::fs::read_to_string(testenv.cache_dir.path().join("cache_info.toml")).unwrap();
+    assert!(cache_info.contains(&format!("source_url = \"{}\"", url)));
 
+    // Later updates fetch the latest commit
+    std::fs::write(&page, "# foo\n\n> Foo.\n\n- Merged version:\n\n`foo`\n").unwrap();
+    git(&["commit", "--quiet", "-am", "Update foo"]);
     testenv
         .command()
-        .args(&["--color", "always", "-f", &file_path.to_str().unwrap()])
+        .args(&["--update", "--quiet", "foo"])
         .assert()
         .success()
-        .stdout(similar(expected));
+        .stdout(contains("Merged version"));
 }
 
 #[test]
-fn test_spaces_find_command() {
+fn test_extra_pages_sources() {
     let testenv = TestEnv::new();
+    let team_dir = testenv.input_dir.path().join("team");
+    let common_dir = team_dir.join("pages").join("common");
+    create_dir_all(&common_dir).unwrap();
+    std::fs::write(
+        common_dir.join("sl.md"),
+        "# sl\n\n> Internal locomotive.\n\n- Run it:\n\n`sl`\n",
+    )
+    .unwrap();
+    std::fs::write(
+        common_dir.join("deploy.md"),
+        "# deploy\n\n> Deploy the team services.\n\n- Deploy:\n\n`deploy`\n",
+    )
+    .unwrap();
+    let missing_repo = testenv.input_dir.path().join("missing");
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        format!(
+            "[[updates.extra_pages_sources]]\ntype = \"directory\"\npath = '{}'\n\n\
+             [[updates.extra_pages_sources]]\ntype = \"archive\"\n\
+             url = \"https://intranet.example.com/tldr-internal.tar.gz\"\n\n\
+             [[updates.extra_pages_sources]]\ntype = \"git\"\nurl = 'file://{}'\n",
+            team_dir.display(),
+            missing_repo.display()
+        ),
+    )
+    .unwrap();
 
+    // A source that can not be updated is no reason to fail the update
     testenv
         .command()
         .args(&["--update"])
         .assert()
         .success()
-        .stdout(contains("Successfully updated cache."));
+        .stdout(contains("Successfully updated cache."))
+        .stderr(contains(
+            "Warning: Could not update the git repository file://",
+        ));
+    let sources: Vec<_> = std::fs::read_dir(testenv.cache_dir.path().join("sources"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(sources.len(), 1, "{:?}", sources);
+    assert!(sources[0].join("pages").join("common").is_dir());
 
+    // The first source takes precedence over the pages of the cache
     testenv
         .command()
-        .args(&["git", "checkout"])
+        .args(&["sl"])
         .assert()
-        .success();
-}
-
-#[test]
-fn test_pager_flag_enable() {
-    let testenv = TestEnv::new();
-
+        .success()
+        .stdout(contains("Internal locomotive"));
     testenv
         .command()
-        .args(&["--update"])
+        .args(&["deploy"])
         .assert()
         .success()
-        .stdout(contains("Successfully updated cache."));
-
+        .stdout(contains("Deploy the team services"));
     testenv
         .command()
-        .args(&["--pager", "tar"])
+        .args(&["--list"])
         .assert()
-        .success();
+        .success()
+        .stdout(contains("deploy"));
+}
+
+#[test]
+fn test_share_cache() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let teacher = TestEnv::new();
+    teacher
+        .command()
+        .args(&["--update", "--quiet"])
+        .assert()
+        .success();
+
+    let mut server = teacher
+        .command()
+        .args(&["--share-cache", "127.0.0.1:0", "--quiet"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(server.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let url = line
+        .split_whitespace()
+        .find(|word| word.starts_with("http://"))
+        .unwrap()
+        .trim_end_matches(',')
+        .to_string();
+
+    let student = TestEnv::new();
+    std::fs::write(
+        student.config_dir.path().join("config.toml"),
+        format!(
+            "[updates]\narchive_urls = [\"{}\"]\nverify_archive_checksum = true\n",
+            url
+        ),
+    )
+    .unwrap();
+    let update = student
+        .command()
+        .env_remove("TEALDEER_REPLAY_ARCHIVE")
+        .args(&["--update"])
+        .output()
+        .unwrap();
+    let show = student.command().args(&["sl"]).output().unwrap();
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    assert!(update.status.success(), "{:?}", update);
+    assert!(String::from_utf8_lossy(&show.stdout).contains("Steam locomotive"));
+}
+
+#[test]
+fn test_share_cache_api() {
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpStream;
+    use std::process::Stdio;
+
+    let testenv = TestEnv::new();
+    testenv
+        .command()
+        .args(&["--update", "--quiet"])
+        .assert()
+        .success();
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[updates]\nshare_token = \"s3cret\"\n",
+    )
+    .unwrap();
+
+    let mut server = testenv
+        .command()
+        .args(&["--share-cache", "127.0.0.1:0", "--quiet"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(server.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let address = line
+        .split_whitespace()
+        .find(|word| word.starts_with("http://"))
+        .unwrap()
+        .trim_start_matches("http://")
+        .split('/')
+        .next()
+        .unwrap()
+        .to_string();
+
+    let get = |target: &str, token: Option<&str>| {
+        let mut stream = TcpStream::connect(&address).unwrap();
+        let authorization = token.map_or_else(String::new, |token| {
+            format!("Authorization: Bearer {}\r\n", token)
+        });
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: localhost\r\n{}\r\n",
+            target, authorization
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let mut parts = response.splitn(2, "\r\n\r\n");
+        let head = parts.next().unwrap().to_string();
+        let body = parts.next().unwrap_or_default().to_string();
+        (head, body)
+    };
+
+    let unauthorized = get("/api/pages", None);
+    let wrong_token = get("/api/pages", Some("guess"));
+    let archive = get("/tldr.tar.gz.sha256", None);
+    let all = get("/api/pages?limit=1000", Some("s3cret"));
+    let paginated = get(
+        "/api/pages?platform=common&language=en&offset=1&limit=1",
+        Some("s3cret"),
+    );
+    let search = get("/api/search?q=locomotive", Some("s3cret"));
+    let no_term = get("/api/search", Some("s3cret"));
+    let too_many = get("/api/pages?limit=100000", Some("s3cret"));
+    // The server stops reading oversized requests, so the rest of this one
+    // may reset the connection after the response was received
+    let too_large = {
+        let mut stream = TcpStream::connect(&address).unwrap();
+        write!(
+            stream,
+            "GET /api/pages HTTP/1.1\r\nCookie: {}\r\n\r\n",
+            "x".repeat(20 * 1024)
+        )
+        .unwrap();
+        let mut response = vec![];
+        let mut chunk = [0; 64];
+        while !response.ends_with(b"\r\n") {
+            match stream.read(&mut chunk) {
+                Ok(read) if read > 0 => response.extend_from_slice(&chunk[..read]),
+                _ => break,
+            }
+        }
+        String::from_utf8_lossy(&response).into_owned()
+    };
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    assert!(
+        unauthorized.0.starts_with("HTTP/1.1 401"),
+        "{:?}",
+        unauthorized
+    );
+    assert!(unauthorized.0.contains("WWW-Authenticate: Bearer"));
+    assert!(wrong_token.0.starts_with("HTTP/1.1 401"));
+    assert!(archive.0.starts_with("HTTP/1.1 200"));
+
+    let all: serde_json::Value = serde_json::from_str(&all.1).unwrap();
+    let total = all["total"].as_u64().unwrap();
+    assert!(total > 1);
+    assert_eq!(all["items"].as_array().unwrap().len() as u64, total);
+    assert!(all["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|page| page["name"] == "sl" && page["language"] == "en"));
+
+    let paginated: serde_json::Value = serde_json::from_str(&paginated.1).unwrap();
+    assert_eq!(paginated["offset"], 1);
+    assert_eq!(paginated["limit"], 1);
+    assert!(paginated["total"].as_u64().unwrap() < total);
+    let items = paginated["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["platform"], "common");
+
+    let search: serde_json::Value = serde_json::from_str(&search.1).unwrap();
+    assert_eq!(search["items"][0]["name"], "sl");
+
+    assert!(no_term.0.starts_with("HTTP/1.1 400"));
+    assert!(too_many.0.starts_with("HTTP/1.1 400"));
+    assert!(too_large.starts_with("HTTP/1.1 431"), "{:?}", too_large);
+}
+
+#[test]
+fn test_share_cache_stalled_client() {
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpStream;
+    use std::process::Stdio;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let testenv = TestEnv::new();
+    testenv
+        .command()
+        .args(&["--update", "--quiet"])
+        .assert()
+        .success();
+
+    let mut server = testenv
+        .command()
+        .args(&["--share-cache", "127.0.0.1:0", "--quiet"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(server.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let address = line
+        .split_whitespace()
+        .find(|word| word.starts_with("http://"))
+        .unwrap()
+        .trim_start_matches("http://")
+        .split('/')
+        .next()
+        .unwrap()
+        .to_string();
+
+    // A client that keeps sending its headers, one byte at a time, well
+    // within the read timeout of the server
+    let mut stalled = TcpStream::connect(&address).unwrap();
+    write!(stalled, "GET /tldr.tar.gz.sha256 HTTP/1.1\r\nX-Slow: ").unwrap();
+    let trickle = thread::spawn(move || {
+        for _ in 0..60 {
+            thread::sleep(Duration::from_millis(500));
+            if stalled.write_all(b"x").is_err() {
+                break;
+            }
+        }
+    });
+    thread::sleep(Duration::from_millis(200));
+
+    let start = Instant::now();
+    let mut stream = TcpStream::connect(&address).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(60)))
+        .unwrap();
+    write!(
+        stream,
+        "GET /tldr.tar.gz.sha256 HTTP/1.1\r\nHost: localhost\r\n\r\n"
+    )
+    .unwrap();
+    let mut response = String::new();
+    let read = stream.read_to_string(&mut response);
+    let elapsed = start.elapsed();
+    server.kill().unwrap();
+    server.wait().unwrap();
+    trickle.join().unwrap();
+
+    read.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{:?}", response);
+    // The stalled client is cut off after the timeout of 10 seconds
+    assert!(elapsed < Duration::from_secs(20), "{:?}", elapsed);
+}
+
+#[test]
+fn test_version_verbose() {
+    let testenv = TestEnv::new();
+
+    // The binary is built with the default features
+    testenv
+        .command()
+        .args(&["--version", "--verbose"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "features: -bundled-pages +git -logging +mmap +share",
+        ));
+    testenv
+        .command()
+        .args(&["--version"])
+        .assert()
+        .success()
+        .stdout(contains("features").not());
+}
+
+#[test]
+fn test_update_jobs() {
+    let testenv = TestEnv::new();
+    let manifest_path = testenv.cache_dir.path().join("manifest.sha256");
+
+    testenv
+        .command()
+        .args(&["--update", "--quiet", "--jobs", "1"])
+        .assert()
+        .success();
+    let sequential = std::fs::read_to_string(&manifest_path).unwrap();
+
+    testenv
+        .command()
+        .args(&["--update", "--quiet", "--jobs", "3"])
+        .assert()
+        .success();
+    assert_eq!(std::fs::read_to_string(&manifest_path).unwrap(), sequential);
+
+    testenv
+        .command()
+        .args(&["--update", "--jobs", "0"])
+        .assert()
+        .failure()
+        .stderr(contains("The number of jobs must be at least 1."));
+}
+
+#[test]
+fn test_record_archive() {
+    let testenv = TestEnv::new();
+    let recorded = testenv.input_dir.path().join("recorded.tar.gz");
+
+    testenv
+        .command()
+        .env("TEALDEER_RECORD_ARCHIVE", &recorded)
+        .args(&["--update", "--quiet"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        std::fs::read(&recorded).unwrap(),
+        std::fs::read(fixture_archive()).unwrap()
+    );
+
+    // A missing recording is an error, the network is not used instead
+    testenv
+        .command()
+        .env(
+            "TEALDEER_REPLAY_ARCHIVE",
+            testenv.input_dir.path().join("missing"),
+        )
+        .args(&["--update"])
+        .assert()
+        .failure()
+        .stderr(contains("Could not read recorded archive"));
+}
+
+#[test]
+fn test_quiet_cache() {
+    let testenv = TestEnv::new();
+    testenv
+        .command()
+        .args(&["--update", "--quiet"])
+        .assert()
+        .success()
+        .stdout(is_empty());
+
+    testenv
+        .command()
+        .args(&["--clear-cache", "--quiet"])
+        .assert()
+        .success()
+        .stdout(is_empty());
+}
+
+#[test]
+fn test_quiet_failures() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--update", "-q"])
+        .assert()
+        .success()
+        .stdout(is_empty());
+
+    testenv
+        .command()
+        .args(&["fakeprogram", "-q"])
+        .assert()
+        .failure()
+        .stdout(is_empty());
+}
+
+#[test]
+fn test_quiet_old_cache() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--update", "-q"])
+        .assert()
+        .success()
+        .stdout(is_empty());
+
+    filetime::set_file_mtime(
+        testenv.cache_dir.path().join("tldr-master"),
+        filetime::FileTime::from_unix_time(1, 0),
+    )
+    .unwrap();
+
+    testenv
+        .command()
+        .args(&["tldr"])
+        .assert()
+        .success()
+        .stderr(contains("The cache hasn't been updated for more than "));
+
+    testenv
+        .command()
+        .args(&["tldr", "--quiet"])
+        .assert()
+        .success()
+        .stderr(contains("The cache hasn't been updated for more than ").not());
+}
+
+#[test]
+fn test_setup_seed_config() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--seed-config"])
+        .assert()
+        .success()
+        .stdout(contains("Successfully created seed config file"));
+}
+
+#[test]
+fn test_seed_config_minimal_and_diff() {
+    let testenv = TestEnv::new();
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+
+    testenv
+        .command()
+        .args(&["--seed-config", "--minimal"])
+        .assert()
+        .success();
+    let contents = std::fs::read_to_string(&config_file_path).unwrap();
+    assert!(contents.contains("[display]\ncompact = false\n"));
+    assert!(!contents.contains('#'));
+
+    // Existing config files are not overwritten
+    let mut config_file = File::create(&config_file_path).unwrap();
+    config_file
+        .write_all(b"[display]\ncompact = true\n")
+        .unwrap();
+    testenv
+        .command()
+        .args(&["--seed-config"])
+        .assert()
+        .failure()
+        .stderr(contains("already exists"));
+
+    testenv
+        .command()
+        .args(&["--seed-config", "--diff"])
+        .assert()
+        .success()
+        .stdout(contains("use_pager = false"))
+        .stdout(contains("compact").not());
+    assert_eq!(
+        std::fs::read_to_string(&config_file_path).unwrap(),
+        "[display]\ncompact = true\n"
+    );
+}
+
+#[test]
+fn test_show_paths() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--show-paths"])
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "Config dir:       {}",
+            testenv.config_dir.path().to_str().unwrap(),
+        )))
+        .stdout(contains(format!(
+            "Config path:      {}",
+            testenv
+                .config_dir
+                .path()
+                .join("config.toml")
+                .to_str()
+                .unwrap(),
+        )))
+        .stdout(contains(format!(
+            "Cache dir:        {}",
+            testenv.cache_dir.path().to_str().unwrap(),
+        )))
+        .stdout(contains(format!(
+            "Pages dir:        {}",
+            testenv
+                .cache_dir
+                .path()
+                .join("tldr-master")
+                .to_str()
+                .unwrap(),
+        )));
+}
+
+#[test]
+fn test_markdown_rendering() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--update"])
+        .assert()
+        .success()
+        .stdout(contains("Successfully updated cache."));
+
+    let expected = include_str!("tar-markdown.expected");
+    testenv
+        .command()
+        .args(&["-m", "tar"])
+        .assert()
+        .success()
+        .stdout(similar(expected));
+}
+
+fn _test_correct_rendering(
+    input_file: &str,
+    filename: &str,
+    expected: &'static str,
+    color_option: &str,
+) {
+    let testenv = TestEnv::new();
+
+    // Create input file
+    let file_path = testenv.input_dir.path().join(filename);
+    println!("Testfile path: {:?}", &file_path);
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(input_file.as_bytes()).unwrap();
+
+    testenv
+        .command()
+        .args(&["--color", color_option, "-f", &file_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(similar(expected));
+}
+
+/// An end-to-end integration test for direct file rendering (v1 syntax).
+#[test]
+fn test_correct_rendering_v1() {
+    _test_correct_rendering(
+        include_str!("inkscape-v1.md"),
+        "inkscape-v1.md",
+        include_str!("inkscape-default.expected"),
+        "always",
+    );
+}
+
+/// An end-to-end integration test for direct file rendering (v2 syntax).
+#[test]
+fn test_correct_rendering_v2() {
+    _test_correct_rendering(
+        include_str!("inkscape-v2.md"),
+        "inkscape-v2.md",
+        include_str!("inkscape-default.expected"),
+        "always",
+    );
+}
+
+#[test]
+/// An end-to-end integration test for direct file rendering with the `--color auto` option. This
+/// will not use styling since output is not stdout.
+fn test_rendering_color_auto() {
+    _test_correct_rendering(
+        include_str!("inkscape-v2.md"),
+        "inkscape-v2.md",
+        include_str!("inkscape-default-no-color.expected"),
+        "auto",
+    );
+}
+
+#[test]
+/// An end-to-end integration test for direct file rendering with the `--color never` option.
+fn test_rendering_color_never() {
+    _test_correct_rendering(
+        include_str!("inkscape-v2.md"),
+        "inkscape-v2.md",
+        include_str!("inkscape-default-no-color.expected"),
+        "never",
+    );
+}
+
+/// An end-to-end integration test for rendering with custom syntax config.
+#[test]
+fn test_correct_rendering_with_config() {
+    let testenv = TestEnv::new();
+
+    // Setup config file
+    // TODO should be config::CONFIG_FILE_NAME
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+    println!("Config path: {:?}", &config_file_path);
+
+    let mut config_file = File::create(&config_file_path).unwrap();
+    config_file
+        .write(include_str!("config.toml").as_bytes())
+        .unwrap();
+
+    // Create input file
+    let file_path = testenv.input_dir.path().join("inkscape-v2.md");
+    println!("Testfile path: {:?}", &file_path);
+
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(include_str!("inkscape-v2.md").as_bytes())
+        .unwrap();
+
+    // Load expected output
+    let expected = include_str!("inkscape-with-config.expected");
+
+    testenv
+        .command()
+        .args(&["--color", "always", "-f", &file_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(similar(expected));
+}
+
+#[test]
+fn test_strict_rendering() {
+    let testenv = TestEnv::new();
+    let file_path = testenv.input_dir.path().join("cp.md");
+    std::fs::write(
+        &file_path,
+        "# cp\n\n> Copy files.\n\n- Copy a file:\n\n`cp {{source}} {{target}\n\n\
+         - Copy a directory:\n\n`cp -r {{source}} {{target}}`\n",
+    )
+    .unwrap();
+
+    // Irregular pages are rendered leniently by default
+    testenv
+        .command()
+        .args(&["-f", file_path.to_str().unwrap()])
+        .assert()
+        .success();
+
+    testenv
+        .command()
+        .args(&["--strict", "-f", file_path.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(contains(
+            "is irregular:\n  line 5: Example without a command\n  \
+             line 7: Unbalanced backticks in example command\n",
+        ));
+
+    testenv
+        .command()
+        .args(&[
+            "--strict",
+            "--output",
+            "json",
+            "-f",
+            file_path.to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("\"kind\":\"invalid-page\""));
+
+    // Regular pages pass
+    testenv
+        .command()
+        .args(&[
+            "--strict",
+            "-f",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/inkscape-v2.md"),
+        ])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_bench_render() {
+    let testenv = TestEnv::new();
+    let pages = testenv.input_dir.path().join("pages").join("common");
+    std::fs::create_dir_all(&pages).unwrap();
+    std::fs::copy("tests/inkscape-v2.md", pages.join("inkscape.md")).unwrap();
+
+    testenv
+        .command()
+        .args(&["--bench-render", testenv.input_dir.path().to_str().unwrap()])
+        .args(&["--output", "json"])
+        .assert()
+        .success()
+        .stdout(contains("\"pages\": 1,"))
+        .stdout(contains("\"pages_per_second\": "))
+        .stdout(contains("\"allocations_per_page\": "));
+
+    // A baseline that cannot be reached fails the benchmark
+    let baseline_path = testenv.input_dir.path().join("baseline.json");
+    std::fs::write(
+        &baseline_path,
+        "{\"pages\": 1, \"passes\": 1, \"seconds\": 1.0, \"pages_per_second\": 1e12, \
+         \"allocations_per_page\": 1e12, \"bytes_per_page\": 1.0}",
+    )
+    .unwrap();
+    testenv
+        .command()
+        .args(&["--bench-render", testenv.input_dir.path().to_str().unwrap()])
+        .args(&["--baseline", baseline_path.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stdout(contains("Rendered 1 pages"))
+        .stderr(contains("Regression: Throughput dropped by"));
+}
+
+#[test]
+fn test_spaces_find_command() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--update"])
+        .assert()
+        .success()
+        .stdout(contains("Successfully updated cache."));
+
+    testenv
+        .command()
+        .args(&["git", "checkout"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_pager_flag_enable() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--update"])
+        .assert()
+        .success()
+        .stdout(contains("Successfully updated cache."));
+
+    testenv
+        .command()
+        .args(&["--pager", "tar"])
+        .assert()
+        .success();
 }
 
 #[test]
@@ -401,112 +1278,3187 @@ fn test_list_flag_rendering() {
 
     testenv
         .command()
-        .args(&["--list"])
+        .args(&["--list"])
+        .assert()
+        .failure()
+        .stderr(contains("Cache not found. Please run `tldr --update`."));
+
+    testenv.add_entry("foo", "");
+
+    testenv
+        .command()
+        .args(&["--list"])
+        .assert()
+        .success()
+        .stdout("foo\n");
+
+    testenv.add_entry("bar", "");
+    testenv.add_entry("baz", "");
+    testenv.add_entry("qux", "");
+
+    testenv
+        .command()
+        .args(&["--list"])
+        .assert()
+        .success()
+        .stdout("bar\nbaz\nfoo\nqux\n");
+}
+
+#[test]
+fn test_list_filters() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "");
+    testenv.add_translated_entry("bar", "", "de");
+    let linux_dir = testenv.cache_dir.path().join("tldr-master/pages/linux");
+    create_dir_all(&linux_dir).unwrap();
+    File::create(linux_dir.join("foo.md")).unwrap();
+    File::create(linux_dir.join("top.md")).unwrap();
+
+    testenv
+        .command()
+        .args(&["--list", "--os", "linux", "--long"])
+        .assert()
+        .success()
+        .stdout("foo  common    en\nfoo  linux     en\ntop  linux     en\n");
+
+    testenv
+        .command()
+        .args(&["--list", "--os", "linux", "--platform", "linux"])
+        .assert()
+        .success()
+        .stdout("foo\ntop\n");
+
+    testenv
+        .command()
+        .args(&["--list", "--language", "de", "--long"])
+        .assert()
+        .success()
+        .stdout("bar  common    de\n");
+
+    testenv
+        .command()
+        .args(&["--long", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains("can only be used with --list"));
+}
+
+#[test]
+fn test_list_output_formats() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "");
+    let page_path = testenv
+        .cache_dir
+        .path()
+        .join("tldr-master/pages/common/foo.md");
+
+    let assert = testenv
+        .command()
+        .args(&["--list", "--output", "json"])
+        .assert()
+        .success();
+    let pages: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(
+        pages,
+        serde_json::json!([{
+            "name": "foo",
+            "platform": "common",
+            "language": "en",
+            "path": page_path.to_str().unwrap(),
+        }])
+    );
+
+    testenv
+        .command()
+        .args(&["--list", "--output", "tsv"])
+        .assert()
+        .success()
+        .stdout(format!("foo\tcommon\ten\t{}\n", page_path.display()));
+
+    testenv
+        .command()
+        .args(&["--output", "tsv", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains("can only be used with --list"));
+}
+
+#[test]
+fn test_autoupdate_cache() {
+    let testenv = TestEnv::new();
+
+    // The first time, if automatic updates are disabled, the cache should not be found
+    testenv
+        .command()
+        .args(&["--list"])
+        .assert()
+        .failure()
+        .stderr(contains("Cache not found. Please run `tldr --update`."));
+
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+    let cache_file_path = testenv.cache_dir.path().join("tldr-master");
+
+    // Activate automatic updates, set the auto-update interval to 24 hours
+    let mut config_file = File::create(&config_file_path).unwrap();
+    config_file
+        .write("[updates]\nauto_update = true\nauto_update_interval_hours = 24".as_bytes())
+        .unwrap();
+    config_file.flush().unwrap();
+
+    // Helper function that runs `tldr --list` and asserts that the cache is automatically updated
+    // or not, depending on the value of `expected`.
+    let check_cache_updated = |expected| {
+        let assert = testenv.command().args(&["--list"]).assert().success();
+        let pred = contains("Successfully updated cache");
+        if expected {
+            assert.stdout(pred)
+        } else {
+            assert.stdout(pred.not())
+        };
+    };
+
+    // The cache is updated the first time we run `tldr --list`
+    check_cache_updated(true);
+
+    // The cache is not updated with a subsequent call
+    check_cache_updated(false);
+
+    // We update the modification and access times such that they are about 23 hours from now.
+    // auto-update interval is 24 hours, the cache should not be updated
+    let new_mtime = SystemTime::now() - Duration::from_secs(82_800);
+    filetime::set_file_mtime(&cache_file_path, new_mtime.into()).unwrap();
+    check_cache_updated(false);
+
+    // We update the modification and access times such that they are about 25 hours from now.
+    // auto-update interval is 24 hours, the cache should be updated
+    let new_mtime = SystemTime::now() - Duration::from_secs(90_000);
+    filetime::set_file_mtime(&cache_file_path, new_mtime.into()).unwrap();
+    check_cache_updated(true);
+
+    // The cache is not updated with a subsequent call
+    check_cache_updated(false);
+}
+
+#[test]
+fn test_autoupdate_failure() {
+    let testenv = TestEnv::new();
+    testenv.command().args(&["--update"]).assert().success();
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[updates]\nauto_update = true\nauto_update_interval_hours = 24\n",
+    )
+    .unwrap();
+    let new_mtime = SystemTime::now() - Duration::from_secs(90_000);
+    filetime::set_file_mtime(
+        testenv.cache_dir.path().join("tldr-master"),
+        new_mtime.into(),
+    )
+    .unwrap();
+
+    // A failed automatic update falls back to the cached pages
+    testenv
+        .command()
+        .env("TEALDEER_REPLAY_ARCHIVE", "/nonexistent")
+        .args(&["sl"])
+        .assert()
+        .success()
+        .stdout(contains("Steam locomotive"))
+        .stderr(contains(
+            "Warning: Could not update cache, using the cached pages",
+        ));
+
+    // Explicit updates still fail
+    testenv
+        .command()
+        .env("TEALDEER_REPLAY_ARCHIVE", "/nonexistent")
+        .args(&["--update"])
+        .assert()
+        .failure()
+        .stderr(contains("Could not update cache"));
+}
+
+#[test]
+fn test_autoupdate_in_background() {
+    let testenv = TestEnv::new();
+    testenv.command().args(&["--update"]).assert().success();
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[updates]\nauto_update = true\nauto_update_interval_hours = 24\n\
+         auto_update_in_background = true\n",
+    )
+    .unwrap();
+    let cache_file_path = testenv.cache_dir.path().join("tldr-master");
+    let new_mtime = SystemTime::now() - Duration::from_secs(90_000);
+    filetime::set_file_mtime(&cache_file_path, new_mtime.into()).unwrap();
+
+    // The page is shown from the outdated cache
+    testenv
+        .command()
+        .args(&["sl"])
+        .assert()
+        .success()
+        .stdout(contains("Steam locomotive"))
+        .stdout(contains("Successfully updated cache").not());
+
+    // The cache is updated in the background
+    let is_updated = || {
+        let mtime = std::fs::metadata(&cache_file_path)
+            .and_then(|metadata| metadata.modified())
+            .unwrap();
+        mtime > new_mtime + Duration::from_secs(3600)
+    };
+    for _ in 0..300 {
+        if is_updated() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    assert!(is_updated());
+}
+
+#[test]
+fn test_auto_update_after_lookup() {
+    let testenv = TestEnv::new();
+    testenv.command().args(&["--update"]).assert().success();
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[updates]\nauto_update = true\nauto_update_interval_hours = 24\n\
+         auto_update_after_lookup = true\n",
+    )
+    .unwrap();
+    let cache_file_path = testenv.cache_dir.path().join("tldr-master");
+    let new_mtime = SystemTime::now() - Duration::from_secs(90_000);
+    filetime::set_file_mtime(&cache_file_path, new_mtime.into()).unwrap();
+    let is_updated = || {
+        let mtime = std::fs::metadata(&cache_file_path)
+            .and_then(|metadata| metadata.modified())
+            .unwrap();
+        mtime > new_mtime + Duration::from_secs(3600)
+    };
+
+    let wait_for_update = || {
+        for _ in 0..300 {
+            if is_updated() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        assert!(is_updated());
+    };
+
+    // A missing page may be added by the update, so it starts one as well
+    testenv
+        .command()
+        .args(&["does-not-exist"])
+        .assert()
+        .failure()
+        .stdout(contains("Successfully updated cache").not());
+    wait_for_update();
+    // Let the update finish before making the cache outdated again
+    std::thread::sleep(Duration::from_secs(1));
+    filetime::set_file_mtime(&cache_file_path, new_mtime.into()).unwrap();
+
+    // The page is shown from the outdated cache, then the cache is updated
+    // in the background
+    testenv
+        .command()
+        .args(&["sl"])
+        .assert()
+        .success()
+        .stdout(contains("Steam locomotive"))
+        .stdout(contains("Successfully updated cache").not());
+    wait_for_update();
+}
+
+#[test]
+fn test_parallel_updates() {
+    let testenv = TestEnv::new();
+    let updates: Vec<_> = (0..4)
+        .map(|_| {
+            let mut command = testenv.command();
+            command.args(&["--update", "--quiet"]);
+            command.spawn().unwrap()
+        })
+        .collect();
+    for mut update in updates {
+        assert!(update.wait().unwrap().success());
+    }
+
+    testenv
+        .command()
+        .args(&["sl"])
+        .assert()
+        .success()
+        .stdout(contains("Steam locomotive"));
+}
+
+#[test]
+#[cfg(target_os = "windows")]
+fn test_pager_warning() {
+    let testenv = TestEnv::new();
+    testenv
+        .command()
+        .args(&["--update"])
+        .assert()
+        .success()
+        .stdout(contains("Successfully updated cache."));
+
+    // Regular call should not show a "pager flag not available on windows" warning
+    testenv
+        .command()
+        .args(&["tar"])
+        .assert()
+        .success()
+        .stderr(contains("pager flag not available on Windows").not());
+
+    // But it should be shown if the pager flag is true
+    testenv
+        .command()
+        .args(&["tar", "-p"])
+        .assert()
+        .success()
+        .stderr(contains("pager flag not available on Windows"));
+}
+
+#[test]
+fn test_search() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("foo", "# foo\n\n> Frobnicate the foo.\n");
+    testenv.add_entry("foobar", "# foobar\n\n> Bar the foo.\n");
+    testenv.add_entry("baz", "# baz\n\n> Works well with foo.\n");
+    testenv.add_entry("qux", "# qux\n\n> Unrelated.\n");
+
+    testenv
+        .command()
+        .args(&["--search", "foo"])
+        .assert()
+        .success()
+        .stdout("foo - Frobnicate the foo.\nfoobar - Bar the foo.\nbaz - Works well with foo.\n");
+
+    testenv
+        .command()
+        .args(&["--search", "nothing"])
+        .assert()
+        .failure()
+        .stdout(contains("No pages found matching nothing"));
+}
+
+#[test]
+fn test_search_folding_and_stemming() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("gzip", "# gzip\n\n> Compress files.\n");
+    testenv.add_translated_entry("gzip", "# gzip\n\n> Comprime des fichiers.\n", "fr");
+    testenv.add_entry("cafe", "# cafe\n\n> Brew coffee.\n");
+
+    // Inflections are found with the stemmer of the page's language
+    testenv
+        .command()
+        .args(&["--search", "compressing"])
+        .assert()
+        .success()
+        .stdout("gzip - Compress files.\n");
+    testenv
+        .command()
+        .args(&["--language", "fr", "--search", "comprimer"])
+        .assert()
+        .success()
+        .stdout("gzip - Comprime des fichiers.\n");
+
+    // Diacritics are ignored
+    testenv
+        .command()
+        .args(&["--language", "fr", "--search", "comprimé"])
+        .assert()
+        .success()
+        .stdout("gzip - Comprime des fichiers.\n");
+    testenv
+        .command()
+        .args(&["--search", "café"])
+        .assert()
+        .success()
+        .stdout("cafe - Brew coffee.\n");
+}
+
+#[test]
+fn test_on_miss() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("docker", "# docker\n\n> Manage containers.\n");
+    testenv.add_entry("docker-compose", "# docker-compose\n\n> Run containers.\n");
+
+    // By default, only an error message is shown
+    testenv
+        .command()
+        .args(&["dock"])
+        .assert()
+        .failure()
+        .stdout(contains("Page dock not found in cache"))
+        .stdout(contains("Did you mean").not());
+
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+
+    let mut config_file = File::create(&config_file_path).unwrap();
+    config_file
+        .write_all(b"[search]\non_miss = \"suggest\"\n")
+        .unwrap();
+    testenv
+        .command()
+        .args(&["dock"])
+        .assert()
+        .failure()
+        .stdout(contains("Did you mean: docker, docker-compose?"));
+
+    let mut config_file = File::create(&config_file_path).unwrap();
+    config_file
+        .write_all(b"[search]\non_miss = \"search\"\n")
+        .unwrap();
+    testenv
+        .command()
+        .args(&["dock"])
+        .assert()
+        .failure()
+        .stdout(contains(
+            "docker - Manage containers.\ndocker-compose - Run containers.\n",
+        ));
+}
+
+#[test]
+fn test_on_miss_other_languages() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("docker", "# docker\n\n> Manage containers.\n");
+    testenv.add_translated_entry("dockr", "# dockr\n\n> Container verwalten.\n", "de");
+
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[search]\non_miss = \"suggest\"\n",
+    )
+    .unwrap();
+
+    // The configured action follows the hint about other languages
+    testenv
+        .command()
+        .args(&["--language", "en", "dockr"])
+        .assert()
+        .failure()
+        .stdout(contains("The page is available in other languages: de"))
+        .stdout(contains("Did you mean: docker?"));
+}
+
+#[test]
+fn test_suggest() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("docker", "# docker\n\n> Manage containers.\n");
+    testenv.add_entry("docker-compose", "# docker-compose\n\n> Run containers.\n");
+    testenv.add_entry("git", "# git\n\n> Version control.\n");
+
+    // Suggestions are sorted by similarity
+    testenv
+        .command()
+        .args(&["--suggest", "5", "docker-compse"])
+        .assert()
+        .failure()
+        .stdout(contains("Did you mean: docker-compose, docker?"));
+    testenv
+        .command()
+        .args(&["--suggest", "1", "gti"])
+        .assert()
+        .failure()
+        .stdout(contains("Did you mean: git?"));
+
+    // The number of suggestions can be configured
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[search]\non_miss = \"suggest\"\nsuggestions = 1\n",
+    )
+    .unwrap();
+    testenv
+        .command()
+        .args(&["docker-compse"])
+        .assert()
+        .failure()
+        .stdout(contains("Did you mean: docker-compose?"));
+    testenv
+        .command()
+        .args(&["--suggest", "0", "docker-compse"])
+        .assert()
+        .failure()
+        .stdout(contains("Did you mean").not());
+}
+
+#[test]
+fn test_rewrite_rules() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("python3", "# python3\n\n> Python 3.\n");
+    testenv.add_entry("fd", "# fd\n\n> Find files.\n");
+
+    let mut config_file = File::create(testenv.config_dir.path().join("config.toml")).unwrap();
+    config_file
+        .write_all(
+            br#"
+[search]
+rewrite = [['python(\d)\.\d+', "python$1"], ["fdfind", "fd"]]
+"#,
+        )
+        .unwrap();
+
+    testenv
+        .command()
+        .args(&["python3.12"])
+        .assert()
+        .success()
+        .stdout(contains("Python 3."));
+    testenv
+        .command()
+        .args(&["fdfind"])
+        .assert()
+        .success()
+        .stdout(contains("Find files."));
+    testenv
+        .command()
+        .args(&["python"])
+        .assert()
+        .failure()
+        .stdout(contains("Page python not found in cache"));
+}
+
+#[test]
+fn test_page_in_other_languages() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv.add_translated_entry("bar", "# bar\n\n> Bar auf Deutsch.\n", "de");
+    testenv.add_translated_entry("bar", "# bar\n\n> Bar en français.\n", "fr");
+
+    testenv
+        .command()
+        .args(&["--language", "en", "bar"])
+        .assert()
+        .failure()
+        .stdout(contains("The page is available in other languages: de, fr"));
+
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+    let mut config_file = File::create(&config_file_path).unwrap();
+    config_file
+        .write_all(b"[search]\nfallback_to_other_languages = true\n")
+        .unwrap();
+
+    testenv
+        .command()
+        .args(&["--language", "fr_CA", "bar"])
+        .assert()
+        .success()
+        .stderr(contains("showing the translation \"fr\" instead"))
+        .stdout(contains("Bar en français."));
+
+    // Multibyte language names are not cut into their bytes
+    testenv
+        .command()
+        .args(&["--language", "中文", "bar"])
+        .assert()
+        .success()
+        .stderr(contains("showing the translation \"de\" instead"))
+        .stdout(contains("Bar auf Deutsch."));
+}
+
+#[test]
+fn test_invalid_page_names() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+
+    for name in &["../../etc/passwd", "..", "foo/bar"] {
+        testenv
+            .command()
+            .args(&[name])
+            .assert()
+            .failure()
+            .stderr(contains("Invalid page name"));
+    }
+}
+
+#[test]
+fn test_special_page_names() {
+    let testenv = TestEnv::new();
+
+    let names = ["g++", "clang-format-17.0", "[", "7z", "git-checkout"];
+    for name in &names {
+        testenv.add_entry(name, &format!("# {}\n\n> The {} page.\n", name, name));
+    }
+
+    for name in &names {
+        testenv
+            .command()
+            .args(&[name])
+            .assert()
+            .success()
+            .stdout(contains(format!("The {} page.", name)));
+    }
+
+    // Lookups are case insensitive and multiple words are joined by dashes
+    testenv
+        .command()
+        .args(&["G++"])
+        .assert()
+        .success()
+        .stdout(contains("The g++ page."));
+    testenv
+        .command()
+        .args(&["Git checkout"])
+        .assert()
+        .success()
+        .stdout(contains("The git-checkout page."));
+
+    testenv
+        .command()
+        .args(&["--list"])
+        .assert()
+        .success()
+        .stdout("7z\n[\nclang-format-17.0\ng++\ngit-checkout\n");
+
+    testenv
+        .command()
+        .args(&["--search", "+"])
+        .assert()
+        .success()
+        .stdout("g++ - The g++ page.\n");
+}
+
+#[test]
+fn test_custom_pages_dir() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Upstream foo.\n");
+
+    let custom_pages_dir = testenv.input_dir.path().join("custom");
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+    let mut config_file = File::create(&config_file_path).unwrap();
+    writeln!(
+        config_file,
+        "[directories]\ncustom_pages_dir = {:?}",
+        custom_pages_dir.to_str().unwrap()
+    )
+    .unwrap();
+
+    // A missing directory results in a single warning
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stdout(contains("Upstream foo."))
+        .stderr(contains("The custom pages directory").count(1))
+        .stderr(contains("does not exist"));
+    testenv
+        .command()
+        .args(&["--show-paths"])
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "Custom pages dir: {} (does not exist)",
+            custom_pages_dir.to_str().unwrap()
+        )));
+
+    // Custom pages take precedence over the cache
+    create_dir_all(&custom_pages_dir).unwrap();
+    let mut page = File::create(custom_pages_dir.join("foo.page.md")).unwrap();
+    page.write_all(b"# foo\n\n> Custom foo.\n").unwrap();
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stdout(contains("Custom foo."))
+        .stderr(is_empty());
+}
+
+#[test]
+fn test_pages_repo() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Upstream foo.\n");
+
+    let repo = testenv.input_dir.path().join("team-pages");
+    create_dir_all(&repo).unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(&["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(&repo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "--quiet"]);
+    std::fs::write(repo.join("foo.page.md"), "# foo\n\n> Team foo.\n").unwrap();
+    git(&["add", "."]);
+    git(&["commit", "--quiet", "-m", "Add foo"]);
+
+    testenv
+        .command()
+        .args(&["--add-pages-repo", repo.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Successfully added pages repository"));
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stdout(contains("Team foo."));
+
+    // Updates pull the repository
+    std::fs::write(repo.join("bar.page.md"), "# bar\n\n> Team bar.\n").unwrap();
+    git(&["add", "."]);
+    git(&["commit", "--quiet", "-m", "Add bar"]);
+    testenv
+        .command()
+        .args(&["--update", "--quiet"])
+        .assert()
+        .success()
+        .stderr(is_empty());
+    testenv
+        .command()
+        .args(&["bar"])
+        .assert()
+        .success()
+        .stdout(contains("Team bar."));
+
+    // Adding it again only pulls it
+    testenv
+        .command()
+        .args(&["--add-pages-repo", repo.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("was added before"));
+
+    testenv
+        .command()
+        .args(&["--add-pages-repo", "/nonexistent/repo"])
+        .assert()
+        .failure()
+        .stderr(contains("Could not clone pages repository"));
+}
+
+#[test]
+fn test_create_custom_pages_dir() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Upstream foo.\n");
+
+    let custom_pages_dir = testenv.input_dir.path().join("custom");
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+    let mut config_file = File::create(&config_file_path).unwrap();
+    writeln!(
+        config_file,
+        "[directories]\ncustom_pages_dir = {:?}\ncreate_custom_pages_dir = true",
+        custom_pages_dir.to_str().unwrap()
+    )
+    .unwrap();
+
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stderr(is_empty());
+    assert!(custom_pages_dir.is_dir());
+}
+
+#[test]
+fn test_config_include() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Do foo:\n\n`foo`\n");
+
+    let config_dir = testenv.config_dir.path();
+    let mut config_file = File::create(config_dir.join("config.toml")).unwrap();
+    config_file
+        .write_all(b"include = [\"theme.toml\"]\n\n[display]\ncompact = true\n")
+        .unwrap();
+    let mut theme_file = File::create(config_dir.join("theme.toml")).unwrap();
+    theme_file
+        .write_all(b"[display]\ncompact = false\n\n[style.example_code]\nforeground = \"red\"\n")
+        .unwrap();
+
+    // The including file overrides `compact`, the style comes from the include
+    testenv
+        .command()
+        .args(&["--color", "always", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("  Foo.\n  Do foo:\n"))
+        .stdout(contains("\u{1b}[31m"));
+
+    let mut theme_file = File::create(config_dir.join("theme.toml")).unwrap();
+    theme_file
+        .write_all(b"include = [\"config.toml\"]\n")
+        .unwrap();
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .failure()
+        .stderr(contains("Config files include each other"));
+}
+
+#[test]
+fn test_config_profiles() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Do foo:\n\n`foo`\n");
+
+    let mut config_file = File::create(testenv.config_dir.path().join("config.toml")).unwrap();
+    config_file
+        .write_all(b"[display]\ncompact = false\n\n[profile.work.display]\ncompact = true\n")
+        .unwrap();
+
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stdout(contains("  Foo.\n\n  Do foo:\n"));
+    testenv
+        .command()
+        .args(&["--profile", "work", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("  Foo.\n  Do foo:\n"));
+    testenv
+        .command()
+        .args(&["foo"])
+        .env("TEALDEER_PROFILE", "work")
+        .assert()
+        .success()
+        .stdout(contains("  Foo.\n  Do foo:\n"));
+    testenv
+        .command()
+        .args(&["--profile", "home", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Profile home not found in config file (available profiles: work)",
+        ));
+}
+
+#[test]
+fn test_config_schema() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--config-schema"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "\"$schema\": \"http://json-schema.org/draft-07/schema#\"",
+        ))
+        .stdout(contains("\"auto_update_interval_hours\""));
+}
+
+#[test]
+fn test_fill_placeholders() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archive.\n\n- Create an archive:\n\n`tar cf {{target.tar}} {{file}}`\n",
+    );
+
+    testenv
+        .command()
+        .args(&[
+            "--fill",
+            "target.tar=my archive.tar",
+            "--fill",
+            "file=it's",
+            "--shell",
+            "bash",
+            "tar",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("tar cf 'my archive.tar' 'it'\\''s'"));
+
+    testenv
+        .command()
+        .args(&[
+            "--fill",
+            "target.tar=my archive.tar",
+            "--shell",
+            "cmd",
+            "tar",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("tar cf \"my archive.tar\" file"));
+
+    testenv
+        .command()
+        .args(&["--fill", "invalid", "tar"])
+        .assert()
+        .failure()
+        .stderr(contains("expected `placeholder=value`"));
+}
+
+#[test]
+fn test_fill_style() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archive.\n\n- Create an archive:\n\n`tar cf {{target.tar}} {{file}}`\n",
+    );
+
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+    std::fs::write(
+        &config_file_path,
+        "[style.example_code]\nforeground = \"blue\"\n\
+         [style.example_variable]\nforeground = \"green\"\n\
+         [style.placeholder]\nforeground = \"red\"\n",
+    )
+    .unwrap();
+
+    testenv
+        .command()
+        .args(&["--color", "always", "--fill", "target.tar=a.tar", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[31ma.tar"))
+        .stdout(contains("\x1b[32mfile"));
+}
+
+#[test]
+fn test_copyable() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archive.\n> More information: <https://example.com/tar>.\n\n\
+         - Create an archive:\n\n`tar cf {{target.tar}} {{path/to/file1 path/to/file2 ...}}`\n",
+    );
+
+    testenv
+        .command()
+        .args(&[
+            "--copyable",
+            "--color",
+            "always",
+            "--fill",
+            "target.tar=my archive.tar",
+            "--shell",
+            "bash",
+            "tar",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            "# Archive.\n\
+             # More information: https://example.com/tar\n\
+             \n\
+             # Create an archive:\n\
+             tar cf 'my archive.tar' path/to/file1 path/to/file2 ...\n",
+        );
+
+    testenv
+        .command()
+        .args(&["--copyable", "--markdown", "tar"])
+        .assert()
+        .failure()
+        .stderr(contains("can not be combined"));
+}
+
+#[test]
+fn test_demo() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n\n- Extract an archive:\n\n`tar xf {{source.tar}}`\n",
+    );
+
+    let assert = testenv
+        .command()
+        .args(&["--demo", "tar", "--fill", "source.tar=a.tar"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    let mut lines = stdout.lines();
+    let header: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+    assert_eq!(header["version"], 2);
+    assert_eq!(header["title"], "tldr tar");
+    let typed: String = lines
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .filter(|event| event[0].as_f64().unwrap() > 1.5)
+        .map(|event| event[2].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(typed, "tar xf a.tar\r\n");
+
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+    std::fs::write(
+        &config_file_path,
+        "[demo]\ntyping_delay_ms = 0\npause_ms = 0\n",
+    )
+    .unwrap();
+    testenv
+        .command()
+        .args(&["--demo", "--demo-format", "script", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("#!/bin/sh\n"))
+        .stdout(contains(
+            "type_slowly 'tar xf source.tar'\nprintf '%s' '\n'\nsleep 0.000\n",
+        ));
+}
+
+#[test]
+fn test_page_sections() {
+    let testenv = TestEnv::new();
+    let runbook = testenv.input_dir.path().join("deploy.md");
+    std::fs::write(
+        &runbook,
+        "# deploy\n\n> Deploy the services.\n\n- Show the status:\n\n`deploy status`\n\n\
+         ## Backups\n\n- Create a backup:\n\n`deploy backup`\n\n\
+         ## Rollbacks\n\n- Roll back:\n\n`deploy rollback`\n",
+    )
+    .unwrap();
+    let runbook = runbook.to_str().unwrap();
+
+    testenv
+        .command()
+        .args(&["--render", runbook])
+        .assert()
+        .success()
+        .stdout(contains("== Backups ==\n"))
+        .stdout(contains("== Rollbacks ==\n"));
+
+    testenv
+        .command()
+        .args(&["--render", runbook, "--section", "backups", "--copyable"])
+        .assert()
+        .success()
+        .stdout("# Deploy the services.\n\n## Backups\n\n# Create a backup:\ndeploy backup\n");
+
+    testenv
+        .command()
+        .args(&["--render", runbook, "--section", "Rollbacks", "--markdown"])
+        .assert()
+        .success()
+        .stdout(
+            "# deploy\n\n> Deploy the services.\n\n## Rollbacks\n\n- Roll back:\n\n`deploy rollback`\n",
+        );
+
+    testenv
+        .command()
+        .args(&["--render", runbook, "--output", "json"])
+        .assert()
+        .success()
+        .stdout(contains("\"section\": \"Backups\""));
+
+    testenv
+        .command()
+        .args(&["--render", runbook, "--section", "Monitoring"])
+        .assert()
+        .failure()
+        .stderr(contains("Page deploy has no section \"Monitoring\""))
+        .stderr(contains("Its sections are: Backups, Rollbacks."));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_run_example() {
+    use std::process::Stdio;
+
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "echo",
+        "# echo\n\n> Print text.\n\n- Print some text:\n\n`echo {{text}}`\n",
+    );
+
+    let run = |input: &str| {
+        let mut child = testenv
+            .terminal_command(&["--color", "never", "--shell", "bash", "--run", "echo"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    };
+
+    // Running examples is disabled by default
+    run("1\nhello world\ny\n")
+        .assert()
+        .failure()
+        .stdout(contains("Running examples is disabled"));
+
+    let mut config_file = File::create(testenv.config_dir.path().join("config.toml")).unwrap();
+    config_file.write_all(b"[run]\nallow_run = true\n").unwrap();
+
+    // The output after the command is the output of the command
+    let ran = |output: &str| {
+        let (_, after) = output.split_at(output.find("Command: ").unwrap());
+        after
+            .lines()
+            .skip(1)
+            .any(|line| line.ends_with("hello world"))
+    };
+
+    let output = run("1\nhello world\nn\n");
+    output.clone().assert().failure();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Command: echo 'hello world'"));
+    assert!(stdout.contains("Not running the command."));
+    assert!(!ran(&stdout));
+
+    let output = run("1\nhello world\ny\n");
+    output.clone().assert().success();
+    assert!(ran(&String::from_utf8_lossy(&output.stdout)));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_run_example_history_defaults() {
+    use std::process::Stdio;
+
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "echo",
+        "# echo\n\n> Print text.\n\n- Print some text:\n\n`echo {{text}}`\n",
+    );
+    let mut config_file = File::create(testenv.config_dir.path().join("config.toml")).unwrap();
+    config_file
+        .write_all(b"[run]\nallow_run = true\nhistory_defaults = true\n")
+        .unwrap();
+    let history_path = testenv.input_dir.path().join("history");
+    let mut history_file = File::create(&history_path).unwrap();
+    history_file
+        .write_all(b"echo 'from history'\nls\n")
+        .unwrap();
+
+    let mut child = testenv
+        .terminal_command(&["--color", "never", "--shell", "bash", "--run", "echo"])
+        .env("SHELL", "/bin/bash")
+        .env("HISTFILE", &history_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"1\n\ny\n").unwrap();
+    child
+        .wait_with_output()
+        .unwrap()
+        .assert()
+        .success()
+        .stdout(contains("Command: echo 'from history'"))
+        .stdout(contains("Value for {{text}} [from history]: "));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_interactive() {
+    use std::process::Stdio;
+
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "echo",
+        "# echo\n\n> Print text.\n\n- Print some text:\n\n`echo {{text}}`\n",
+    );
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[run]\nallow_run = true\n",
+    )
+    .unwrap();
+
+    // Without stdin, nothing is asked
+    testenv
+        .command()
+        .args(&["--shell", "bash", "--run", "echo"])
+        .stdin(Stdio::null())
+        .assert()
+        .failure()
+        .stderr(contains("not possible in non-interactive mode"));
+
+    // Piped answers are ignored
+    let mut child = testenv
+        .command()
+        .args(&["--shell", "bash", "--run", "echo"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let _ = child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"1\nhello world\ny\n");
+    child
+        .wait_with_output()
+        .unwrap()
+        .assert()
+        .failure()
+        .stdout(is_empty())
+        .stderr(contains("not possible in non-interactive mode"));
+
+    // In a terminal, the flag enables the mode
+    #[cfg(target_os = "linux")]
+    testenv
+        .terminal_command(&["--non-interactive", "--shell", "bash", "--run", "echo"])
+        .assert()
+        .failure()
+        .stdout(contains("not possible in non-interactive mode"));
+}
+
+#[test]
+fn test_cache_diff() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv.add_entry("bar", "# bar\n\n> Bar.\n");
+
+    let old_manifest = testenv.input_dir.path().join("old-manifest.sha256");
+    let mut file = File::create(&old_manifest).unwrap();
+    file.write_all(
+        b"0000000000000000000000000000000000000000000000000000000000000000  pages/common/foo.md\n\
+          0000000000000000000000000000000000000000000000000000000000000000  pages/common/baz.md\n",
+    )
+    .unwrap();
+
+    testenv
+        .command()
+        .args(&["--cache-diff", old_manifest.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(similar(
+            "+ pages/common/bar.md\n- pages/common/baz.md\n~ pages/common/foo.md\n\
+             1 added, 1 removed, 1 changed\n",
+        ));
+
+    testenv
+        .command()
+        .args(&[
+            "--cache-diff",
+            old_manifest.to_str().unwrap(),
+            "--output",
+            "json",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("\"added\": [\n    \"pages/common/bar.md\"\n  ]"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinked_cache_dir() {
+    use std::os::unix::fs::symlink;
+
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    File::create(testenv.cache_dir.path().join("unrelated.txt")).unwrap();
+    File::create(testenv.cache_dir.path().join("update_history.jsonl.1")).unwrap();
+
+    let link = testenv.input_dir.path().join("cache-link");
+    symlink(testenv.cache_dir.path(), &link).unwrap();
+
+    testenv
+        .command()
+        .env("TEALDEER_CACHE_DIR", &link)
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stdout(contains("Foo."));
+
+    testenv
+        .command()
+        .env("TEALDEER_CACHE_DIR", &link)
+        .args(&["--clear-cache"])
+        .assert()
+        .success();
+
+    // The symlink, its target and unrelated files are kept
+    assert!(std::fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert!(testenv.cache_dir.path().join("unrelated.txt").is_file());
+    assert!(!testenv.cache_dir.path().join("tldr-master").exists());
+    assert!(!testenv
+        .cache_dir
+        .path()
+        .join("update_history.jsonl.1")
+        .exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinked_pages() {
+    use std::os::unix::fs::symlink;
+
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+
+    let target = testenv.input_dir.path().join("bar.md");
+    let mut file = File::create(&target).unwrap();
+    file.write_all(b"# bar\n\n> Bar.\n").unwrap();
+    symlink(
+        &target,
+        testenv
+            .cache_dir
+            .path()
+            .join("tldr-master")
+            .join("pages")
+            .join("common")
+            .join("bar.md"),
+    )
+    .unwrap();
+
+    testenv
+        .command()
+        .args(&["--list"])
+        .assert()
+        .success()
+        .stdout(similar("bar\nfoo\n"));
+    testenv
+        .command()
+        .args(&["bar"])
+        .assert()
+        .success()
+        .stdout(contains("Bar."));
+}
+
+#[cfg(not(feature = "trash"))]
+#[test]
+fn test_clear_to_trash_creates_backup() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    File::create(testenv.config_dir.path().join("config.toml"))
+        .unwrap()
+        .write_all(b"[updates]\nclear_to_trash = true\n")
+        .unwrap();
+
+    testenv
+        .command()
+        .args(&["--clear-cache"])
+        .assert()
+        .success()
+        .stdout(contains("Successfully moved cache to"));
+
+    assert!(!testenv.cache_dir.path().exists());
+    let name = testenv
+        .cache_dir
+        .path()
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let backups: Vec<_> = std::fs::read_dir(testenv.cache_dir.path().parent().unwrap())
+        .unwrap()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map_or(false, |backup| {
+                    backup.starts_with(&format!("{}-backup-", name))
+                })
+        })
+        .collect();
+    assert_eq!(backups.len(), 1);
+    assert!(backups[0]
+        .join("tldr-master")
+        .join("pages")
+        .join("common")
+        .join("foo.md")
+        .is_file());
+    std::fs::remove_dir_all(&backups[0]).unwrap();
+}
+
+#[test]
+fn test_ignored_custom_pages() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+
+    let custom_pages_dir = testenv.input_dir.path().join("custom");
+    create_dir_all(custom_pages_dir.join(".git")).unwrap();
+    for name in &[
+        "bar.page.md",
+        "bar.page.md~",
+        ".#baz.page.md",
+        "qux.draft.page.md",
+        ".git/quux.page.md",
+    ] {
+        File::create(custom_pages_dir.join(name))
+            .unwrap()
+            .write_all(b"# page\n\n> Page.\n")
+            .unwrap();
+    }
+
+    let mut config_file = File::create(testenv.config_dir.path().join("config.toml")).unwrap();
+    writeln!(
+        config_file,
+        "[directories]\ncustom_pages_dir = {:?}\nignore = [\"*.draft.page.md\"]",
+        custom_pages_dir.to_str().unwrap()
+    )
+    .unwrap();
+
+    testenv
+        .command()
+        .args(&["--list"])
+        .assert()
+        .success()
+        .stdout(similar("bar\nfoo\n"));
+}
+
+#[test]
+fn test_color_per_stream() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Do foo:\n\n`foo`\n");
+    let mut config_file = File::create(testenv.config_dir.path().join("config.toml")).unwrap();
+    config_file
+        .write_all(
+            b"[directories]\ncustom_pages_dir = \"/nonexistent/tldr\"\n\n\
+              [style.example_code]\nforeground = \"red\"\n",
+        )
+        .unwrap();
+
+    // Forced colors are used on both streams, even if they are not terminals
+    testenv
+        .command()
+        .args(&["--color", "always", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("\u{1b}["))
+        .stderr(contains("\u{1b}[33mWarning: The custom pages directory"));
+
+    // Otherwise, colors are only used on terminals
+    testenv
+        .command()
+        .args(&["--color", "auto", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("\u{1b}[").not())
+        .stderr(contains("Warning: The custom pages directory"))
+        .stderr(contains("\u{1b}[").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_closed_stdout() {
+    use std::process::Stdio;
+
+    let testenv = TestEnv::new();
+    let page = format!(
+        "# foo\n\n> Foo.\n{}",
+        "\n- Do foo:\n\n`foo`\n".repeat(10000)
+    );
+    testenv.add_entry("foo", &page);
+
+    // Like `tldr foo | head -c 0`
+    let mut child = testenv
+        .command()
+        .args(&["foo"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+    assert!(!stderr.contains("Could not write page"), "{}", stderr);
+}
+
+#[test]
+fn test_stats() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv.add_entry("bar", "# bar\n\n> Bar.\n");
+    testenv.add_translated_entry("foo", "de", "# foo\n\n> Foo.\n");
+
+    testenv
+        .command()
+        .env("LANG", "C")
+        .args(&["--stats"])
+        .assert()
+        .success()
+        .stdout(contains(" ago)\n"))
+        .stdout(contains("Pages:       3\n"))
+        .stdout(contains("Languages:   2\n"))
+        .stdout(contains("Size:        "));
+
+    testenv
+        .command()
+        .args(&["--stats", "--output", "json"])
+        .assert()
+        .success()
+        .stdout(contains("\"pages\": 3"))
+        .stdout(contains("\"last_update\": \"20"))
+        .stdout(contains("Z\""));
+}
+
+#[test]
+fn test_stats_release_tag() {
+    let testenv = TestEnv::new();
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[updates]\narchive_urls = [\"https://github.com/tldr-pages/tldr/archive/refs/tags/v2.3.tar.gz\"]\n",
+    )
+    .unwrap();
+    testenv.command().args(&["--update"]).assert().success();
+
+    testenv
+        .command()
+        .env("LANG", "C")
+        .args(&["--stats"])
+        .assert()
+        .success()
+        .stdout(contains("Release:     v2.3\n"));
+
+    testenv
+        .command()
+        .args(&["--info", "--output", "json"])
+        .assert()
+        .success()
+        .stdout(contains("\"release_tag\": \"v2.3\""));
+}
+
+#[test]
+fn test_info() {
+    let testenv = TestEnv::new();
+
+    // Without a cache, the location and the sources are still shown
+    testenv
+        .command()
+        .args(&["--info"])
+        .assert()
+        .success()
+        .stdout(contains("(env variable)"))
+        .stdout(contains("Mirror:"));
+
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv.add_translated_entry("foo", "# foo\n\n> Foo.\n", "de");
+    let linux_dir = testenv.cache_dir.path().join("tldr-master/pages/linux");
+    create_dir_all(&linux_dir).unwrap();
+    std::fs::write(linux_dir.join("bar.md"), "# bar\n\n> Bar.\n").unwrap();
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[updates]\narchive_urls = [\"https://mirror.example.com/tldr.tar.gz\"]\n",
+    )
+    .unwrap();
+
+    testenv
+        .command()
+        .env("LANG", "C")
+        .args(&["--info"])
+        .assert()
+        .success()
+        .stdout(contains("Pages:          3\n"))
+        .stdout(contains("  de            1\n"))
+        .stdout(contains("  en            2\n"))
+        .stdout(contains("  common        2\n"))
+        .stdout(contains("  linux         1\n"))
+        .stdout(contains(
+            "  Mirror:       https://mirror.example.com/tldr.tar.gz\n",
+        ));
+
+    let output = testenv
+        .command()
+        .args(&["--info", "--output", "json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let info: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(info["pages"], 3);
+    assert_eq!(info["languages"]["en"], 2);
+    assert_eq!(info["platforms"]["linux"], 1);
+    assert_eq!(info["sources"]["pages_source"]["type"], "archive");
+    assert_eq!(
+        info["sources"]["archive_urls"][0],
+        "https://mirror.example.com/tldr.tar.gz"
+    );
+}
+
+#[test]
+fn test_update_history() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--update-history", "5"])
+        .assert()
+        .success()
+        .stdout(contains("No updates were recorded."))
+        .stdout(contains("record_update_history = true"));
+
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[updates]\nrecord_update_history = true\n",
+    )
+    .unwrap();
+    for _ in 0..2 {
+        testenv.command().args(&["--update"]).assert().success();
+    }
+
+    let output = testenv
+        .command()
+        .args(&["--update-history", "5"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.lines().count(), 2, "{}", output);
+    assert!(
+        output.lines().all(|line| line.contains("  ok  ")),
+        "{}",
+        output
+    );
+
+    let output = testenv
+        .command()
+        .args(&["--update-history", "1", "--output", "json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let attempts: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(attempts.as_array().unwrap().len(), 1);
+    assert_eq!(attempts[0]["automatic"], false);
+    assert!(attempts[0]["error"].is_null());
+}
+
+#[test]
+fn test_url() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n> More information: <https://example.com/foo>.\n\n- Run:\n\n`foo`\n",
+    );
+    testenv.add_entry("bar", "# bar\n\n> Bar.\n");
+
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stdout(contains("  More information: https://example.com/foo\n"));
+
+    testenv
+        .command()
+        .args(&["--url", "foo"])
+        .assert()
+        .success()
+        .stdout("https://example.com/foo\n");
+
+    testenv
+        .command()
+        .args(&["--url", "foo", "--output", "json"])
+        .assert()
+        .success()
+        .stdout("{\"url\":\"https://example.com/foo\"}\n");
+
+    testenv
+        .command()
+        .args(&["--url", "bar"])
+        .assert()
+        .failure()
+        .stderr(contains("does not link to more information"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_render_filter_command() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Runs on host.internal.\n");
+    let config_path = testenv.config_dir.path().join("config.toml");
+
+    std::fs::write(
+        &config_path,
+        "[display]\nrender_filter_command = \"sed 's/host\\\\.internal/[redacted]/'\"\n",
+    )
+    .unwrap();
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stdout(contains("Runs on [redacted].").and(contains("host.internal").not()));
+    testenv
+        .command()
+        .args(&["--no-filter", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Runs on host.internal."));
+
+    // The unfiltered page is never printed if the filter fails
+    std::fs::write(
+        &config_path,
+        "[display]\nrender_filter_command = \"exit 2\"\n",
+    )
+    .unwrap();
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .failure()
+        .stdout(is_empty())
+        .stderr(contains("The render filter \"exit 2\" failed"));
+}
+
+#[test]
+fn test_platform_chain() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Common foo.\n");
+    let pages_dir = testenv.cache_dir.path().join("tldr-master").join("pages");
+    for (platform, page) in &[("netbsd", "foo"), ("freebsd", "foo"), ("freebsd", "bar")] {
+        create_dir_all(pages_dir.join(platform)).unwrap();
+        std::fs::write(
+            pages_dir.join(platform).join(format!("{}.md", page)),
+            format!("# {}\n\n> {} {}.\n", page, platform, page),
+        )
+        .unwrap();
+    }
+
+    // The other BSDs are tried before common
+    testenv
+        .command()
+        .args(&["--os", "openbsd", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("netbsd foo."));
+    testenv
+        .command()
+        .args(&["--os", "openbsd", "bar"])
+        .assert()
+        .success()
+        .stdout(contains("freebsd bar."));
+    testenv
+        .command()
+        .args(&["--os", "linux", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Common foo."));
+
+    let config_path = testenv.config_dir.path().join("config.toml");
+    std::fs::write(&config_path, "[search]\nplatform_chain = [\"freebsd\"]\n").unwrap();
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stdout(contains("freebsd foo."));
+
+    std::fs::write(&config_path, "[search]\nplatform_chain = [\"../x\"]\n").unwrap();
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid platform \"../x\""));
+}
+
+#[test]
+fn test_attribution() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+
+    testenv
+        .command()
+        .args(&["--attribution", "foo"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "Source:          tldr-pages (https://github.com/tldr-pages/tldr)\n",
+        ))
+        .stdout(contains("License:         CC BY 4.0"))
+        .stdout(contains("Upstream path:   pages/common/foo.md\n"))
+        .stdout(contains("The \"foo\" page is from the tldr-pages project"));
+
+    testenv
+        .command()
+        .args(&["--attribution", "foo", "--output", "json"])
+        .assert()
+        .success()
+        .stdout(contains("\"origin\": \"tldr-pages\""))
+        .stdout(contains("\"upstream_path\": \"pages/common/foo.md\""))
+        .stdout(contains("\"name\": \"CC BY 4.0\""));
+
+    // Local files have no license
+    let page = testenv.input_dir.path().join("bar.md");
+    std::fs::write(&page, "# bar\n\n> Bar.\n").unwrap();
+    testenv
+        .command()
+        .args(&["--attribution", "--render", page.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Source:          Local file\n"))
+        .stdout(contains("License:         [Unknown").and(contains("page is from").not()));
+}
+
+/// Serve one response per request, in order, on localhost. `None` is served
+/// as 404 Not Found. Return the URL of the server.
+fn serve_responses(responses: Vec<Option<&'static str>>) -> String {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 && line.trim_end() != "" {
+                line.clear();
+            }
+            let (status, body) = match response {
+                Some(body) => ("200 OK", body),
+                None => ("404 Not Found", ""),
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+    });
+    url
+}
+
+/// Serve a single response with `status` and `body` on localhost. Return the
+/// URL of the server.
+fn serve_once(status: &'static str, body: Vec<u8>) -> String {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 0 && line.trim_end() != "" {
+            line.clear();
+        }
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(&body).unwrap();
+    });
+    url
+}
+
+#[test]
+fn test_update_mirror_failover() {
+    let testenv = TestEnv::new();
+    let not_found = serve_once("404 Not Found", b"Not Found".to_vec());
+    let server_error = serve_once("500 Internal Server Error", b"Oops".to_vec());
+    let mirror = serve_once("200 OK", std::fs::read(fixture_archive()).unwrap());
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        format!(
+            "[updates]\narchive_urls = [\"{}/tldr.tar.gz\", \"{}/tldr.tar.gz\", \"{}/tldr.tar.gz\"]\n",
+            not_found, server_error, mirror
+        ),
+    )
+    .unwrap();
+
+    testenv
+        .command()
+        .env_remove("TEALDEER_REPLAY_ARCHIVE")
+        .args(&["--update"])
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "Successfully updated cache from {}/tldr.tar.gz.",
+            mirror
+        )))
+        .stderr(contains(format!(
+            "Could not download pages from {}/tldr.tar.gz: HTTP error: HTTP status client error (404 Not Found)",
+            not_found
+        )))
+        .stderr(contains("(500 Internal Server Error)"));
+
+    testenv
+        .command()
+        .args(&["sl"])
+        .assert()
+        .success()
+        .stdout(contains("Steam locomotive"));
+    let cache_info =
+        std::fs::read_to_string(testenv.cache_dir.path().join("cache_info.toml")).unwrap();
+    assert!(cache_info.contains(&format!("source_url = \"{}/tldr.tar.gz\"", mirror)));
+}
+
+#[test]
+fn test_check_upstream() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Old example:\n\n`foo`\n");
+    let url = serve_responses(vec![
+        Some("# foo\n\n> Foo.\n\n- New example:\n\n`foo`\n"),
+        Some("# foo\n\n> Foo.\n\n- Old example:\n\n`foo`\n"),
+        None,
+    ]);
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        format!("[updates]\nupstream_pages_url = \"{}/\"\n", url),
+    )
+    .unwrap();
+
+    testenv
+        .command()
+        .args(&["--check-upstream", "foo"])
+        .assert()
+        .failure()
+        .stdout(contains(format!(
+            "The cached page foo differs from the upstream version at {}/pages/common/foo.md:",
+            url
+        )))
+        .stdout(contains(
+            "  > Foo.\n  \n- - Old example:\n+ - New example:\n  \n",
+        ));
+
+    testenv
+        .command()
+        .args(&["--check-upstream", "foo", "--output", "json"])
+        .assert()
+        .success()
+        .stdout(contains("\"status\": \"up-to-date\""))
+        .stdout(contains("\"upstream_path\": \"pages/common/foo.md\""));
+
+    testenv
+        .command()
+        .args(&["--check-upstream", "foo"])
+        .assert()
+        .failure()
+        .stdout(contains("The page foo was removed upstream"));
+
+    // Local files have no upstream version
+    let page = testenv.input_dir.path().join("bar.md");
+    std::fs::write(&page, "# bar\n\n> Bar.\n").unwrap();
+    testenv
+        .command()
+        .args(&["--check-upstream", "--render", page.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(contains("has no upstream version"));
+}
+
+#[test]
+fn test_check_coverage() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("git-checkout", "# git checkout\n\n> Checkout.\n");
+    testenv.add_entry("tar", "# tar\n\n> Archive.\n");
+
+    let list = testenv.input_dir.path().join("commands.txt");
+    std::fs::write(&list, "# Our tools\ngit checkout\ntar\n\nin-house-tool\n").unwrap();
+
+    testenv
+        .command()
+        .args(&["--check-coverage", list.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stdout(contains("+ git-checkout (common)\n"))
+        .stdout(contains("+ tar (common)\n"))
+        .stdout(contains("- in-house-tool\n"))
+        .stdout(contains("2 of 3 commands have pages."));
+
+    testenv
+        .command()
+        .args(&[
+            "--check-coverage",
+            list.to_str().unwrap(),
+            "--output",
+            "json",
+        ])
+        .assert()
+        .failure()
+        .stdout(contains("\"missing\": [\n    \"in-house-tool\"\n  ]"));
+
+    std::fs::write(&list, "tar\n").unwrap();
+    testenv
+        .command()
+        .args(&["--check-coverage", list.to_str().unwrap()])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_batch() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("git-checkout", "# git checkout\n\n> Checkout a branch.\n");
+    testenv.add_entry("tar", "# tar\n\n> Archive files.\n");
+
+    let batch = |args: &[&str]| {
+        let mut command = assert_cmd::Command::from_std(testenv.command());
+        command
+            .args(&["--batch", "-"])
+            .args(args)
+            .write_stdin("tar\ngit checkout\n\ntar\nin-house-tool\n");
+        command
+    };
+
+    batch(&["--batch-separator", "----"])
+        .assert()
+        .failure()
+        .stdout(contains("Archive files.").count(1))
+        .stdout(contains("Checkout a branch."))
+        .stdout(contains("----").count(1))
+        .stderr(contains("Page in-house-tool not found in cache"));
+
+    batch(&["--output", "json"])
+        .assert()
+        .failure()
+        .stdout(contains("\"name\":\"tar\"").count(1))
+        .stdout(contains("\"name\":\"git checkout\""))
+        .stderr(contains(
+            "\"message\":\"Pages not found in cache: in-house-tool\"",
+        ));
+
+    // Existence can be checked with --check-coverage
+    let mut command = assert_cmd::Command::from_std(testenv.command());
+    command
+        .args(&["--check-coverage", "-"])
+        .write_stdin("tar\n")
+        .assert()
+        .success()
+        .stdout(contains("1 of 1 commands have pages."));
+}
+
+#[test]
+fn test_themes() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Run:\n\n`foo {{bar}}`\n");
+
+    let themes_dir = testenv.config_dir.path().join("themes");
+    create_dir_all(&themes_dir).unwrap();
+    File::create(themes_dir.join("corp.toml"))
+        .unwrap()
+        .write_all(b"[example_code]\nforeground = \"red\"\n[example_text]\nbold = true\n")
+        .unwrap();
+    File::create(testenv.config_dir.path().join("config.toml"))
+        .unwrap()
+        .write_all(b"[display]\ntheme = \"corp\"\n[style.example_text]\nbold = false\n")
+        .unwrap();
+
+    // The config overrides the theme
+    testenv
+        .command()
+        .args(&["--color", "always", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[31m \x1b[0mbar"))
+        .stdout(contains("  Run:\n"));
+
+    testenv
+        .command()
+        .args(&["--dump-theme", "corp"])
+        .assert()
+        .success()
+        .stdout(contains("[example_code]\nforeground = \"red\"\n"));
+    testenv
+        .command()
+        .args(&["--dump-theme", "default"])
+        .assert()
+        .success()
+        .stdout(contains("[command_name]\n"))
+        .stdout(contains("foreground = \"cyan\"\n"));
+
+    File::create(themes_dir.join("broken.toml"))
+        .unwrap()
+        .write_all(b"[headline]\nbold = true\n")
+        .unwrap();
+    testenv
+        .command()
+        .args(&["--dump-theme", "broken"])
+        .assert()
+        .failure()
+        .stderr(contains("Unknown style target headline"));
+    testenv
+        .command()
+        .args(&["--dump-theme", "missing"])
+        .assert()
+        .failure()
+        .stderr(contains("Theme missing not found"));
+}
+
+#[test]
+fn test_language_settings() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Run:\n\n`foo`\n");
+    testenv.add_translated_entry("foo", "# foo\n\n> フー。\n\n- 実行する:\n\n`foo`\n", "ja");
+
+    File::create(testenv.config_dir.path().join("config.toml"))
+        .unwrap()
+        .write_all(b"[language.ja.display]\nindent = 4\nexample_indent = 8\n")
+        .unwrap();
+
+    testenv
+        .command()
+        .args(&["--language", "ja", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("\n    フー。\n"))
+        .stdout(contains("\n        foo\n"));
+
+    testenv
+        .command()
+        .args(&["--language", "en", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("\n  Foo.\n"))
+        .stdout(contains("\n      foo\n"));
+}
+
+#[test]
+fn test_json_errors() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--output", "json", "sl"])
+        .assert()
+        .failure()
+        .stdout(is_empty())
+        .stderr(
+            "{\"error\":{\"hint\":\"Please run `tldr --update`.\",\"kind\":\"cache\",\
+             \"message\":\"Cache not found.\"}}\n",
+        );
+
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv
+        .command()
+        .args(&["--output", "json", "bar"])
+        .assert()
+        .failure()
+        .stdout(is_empty())
+        .stderr(contains("\"kind\":\"page-not-found\""))
+        .stderr(contains("\"message\":\"Page bar not found in cache\""));
+
+    testenv
+        .command()
+        .args(&["--output", "json", "--search", "nothing"])
+        .assert()
+        .failure()
+        .stderr(contains("\"kind\":\"no-results\""))
+        .stderr(contains(
+            "\"hint\":\"Run `tldr --list` to list all pages.\"",
+        ));
+
+    // Errors while parsing the arguments
+    testenv
+        .command()
+        .args(&["--output", "json", "--os", "lindows", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains("{\"error\":{"))
+        .stderr(contains("\"kind\":\"usage\""))
+        .stderr(contains("Usage:").not());
+
+    // Without --output json, errors stay readable
+    testenv
+        .command()
+        .args(&["bar"])
+        .assert()
+        .failure()
+        .stdout(contains("Page bar not found in cache"))
+        .stderr(contains("error").not());
+}
+
+#[test]
+fn test_zip_cache_format() {
+    let testenv = TestEnv::new();
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[updates]\ncache_format = \"zip\"\n",
+    )
+    .unwrap();
+
+    testenv
+        .command()
+        .args(&["--update"])
+        .assert()
+        .success()
+        .stdout(contains("Successfully updated cache."));
+
+    // The pages are packed into a single archive
+    let pages_dir = testenv.cache_dir.path().join("tldr-master");
+    assert!(pages_dir.join("pages.zip").is_file());
+    assert!(!pages_dir.join("pages").exists());
+    let manifest =
+        std::fs::read_to_string(testenv.cache_dir.path().join("manifest.sha256")).unwrap();
+    assert!(manifest.contains("  pages.de/common/tar.md\n"));
+
+    testenv
+        .command()
+        .args(&["sl"])
+        .assert()
+        .success()
+        .stdout(contains("Steam locomotive"));
+
+    testenv
+        .command()
+        .args(&["--list"])
+        .assert()
+        .success()
+        .stdout(contains("sl\n"));
+
+    testenv
+        .command()
+        .args(&["--search", "locomotive"])
+        .assert()
+        .success()
+        .stdout(contains("sl - "));
+
+    // Switching back takes effect with the next update
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[updates]\ncache_format = \"directory\"\n",
+    )
+    .unwrap();
+    testenv.command().args(&["--update"]).assert().success();
+    assert!(!pages_dir.join("pages.zip").exists());
+    assert!(pages_dir.join("pages").is_dir());
+}
+
+#[test]
+fn test_single_language_archive() {
+    let testenv = TestEnv::new();
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[updates]\ncache_format = \"zip\"\narchive_urls = [\"https://tldr.sh/assets/tldr-pages.de.tar.gz\"]\n",
+    )
+    .unwrap();
+    let archive = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("tldr-pages.de.tar.gz");
+
+    testenv
+        .command()
+        .env("TEALDEER_REPLAY_ARCHIVE", &archive)
+        .args(&["--update"])
+        .assert()
+        .success();
+
+    // The pages of the archive are German pages, not English ones
+    let manifest =
+        std::fs::read_to_string(testenv.cache_dir.path().join("manifest.sha256")).unwrap();
+    assert!(manifest.contains("  pages.de/common/tar.md\n"));
+    assert!(!manifest.contains("  pages/"));
+
+    testenv
+        .command()
+        .args(&["--language", "de", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("Archivierungswerkzeug"));
+}
+
+#[test]
+fn test_migrate_cache() {
+    let testenv = TestEnv::new();
+    testenv.command().args(&["--update"]).assert().success();
+    let pages_dir = testenv.cache_dir.path().join("tldr-master");
+    assert!(pages_dir.join("pages").is_dir());
+
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[updates]\ncache_format = \"zip\"\n",
+    )
+    .unwrap();
+    testenv
+        .command()
+        .env("TEALDEER_REPLAY_ARCHIVE", "/nonexistent")
+        .args(&["--migrate-cache"])
+        .assert()
+        .success()
+        .stdout(contains("Successfully migrated cache."));
+    assert!(pages_dir.join("pages.zip").is_file());
+    assert!(!pages_dir.join("pages").exists());
+
+    testenv
+        .command()
+        .args(&["sl"])
+        .assert()
+        .success()
+        .stdout(contains("Steam locomotive"));
+}
+
+#[test]
+fn test_clean_aux() {
+    let testenv = TestEnv::new();
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[updates]\ncache_format = \"zip\"\n",
+    )
+    .unwrap();
+    testenv.command().args(&["--update"]).assert().success();
+    testenv.command().args(&["sl"]).assert().success();
+
+    testenv
+        .command()
+        .args(&["--clean-aux"])
+        .assert()
+        .success()
+        .stdout(contains("Removed 1 unpacked pages"));
+    testenv
+        .command()
+        .args(&["--clean-aux"])
+        .assert()
+        .success()
+        .stdout(contains("Removed 0 unpacked pages"));
+
+    // Pages are unpacked again when needed
+    testenv
+        .command()
+        .args(&["sl"])
+        .assert()
+        .success()
+        .stdout(contains("Steam locomotive"));
+}
+
+#[test]
+fn test_command_not_found() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "sl",
+        "# sl\n\n> Steam locomotive.\n> More information: <https://example.com>.\n\n\
+         - Let a train run:\n\n`sl`\n\n- Let it fly:\n\n`sl -F`\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--command-not-found", "sl"])
+        .assert()
+        .success()
+        .stdout("  Steam locomotive.\n  Let a train run:\n      sl\n\n  Run `tldr sl` for more examples.\n");
+
+    // Without a page, nothing is printed
+    testenv
+        .command()
+        .args(&["--command-not-found", "nonexistent"])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr("");
+}
+
+#[test]
+fn test_page_as_json() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "cp",
+        "# cp\n\n> Copy files.\n\n- Copy a file:\n\n`cp {{source}} {{target}}`\n\n\
+         - Copy a file into itself:\n\n`cp {{source}} {{source}}`\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--output", "json", "cp"])
+        .assert()
+        .success()
+        .stdout(similar(
+            r#"{
+  "name": "cp",
+  "language": "en",
+  "description": "Copy files.",
+  "more_information": null,
+  "examples": [
+    {
+      "description": "Copy a file:",
+      "command": "cp {{source}} {{target}}",
+      "placeholders": [
+        "source",
+        "target"
+      ],
+      "choices": []
+    },
+    {
+      "description": "Copy a file into itself:",
+      "command": "cp {{source}} {{source}}",
+      "placeholders": [
+        "source"
+      ],
+      "choices": []
+    }
+  ]
+}
+"#,
+        ));
+}
+
+#[test]
+fn test_placeholder_choices() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "systemctl",
+        "# systemctl\n\n> Control units.\n\n- Control a unit:\n\n\
+         `systemctl {{start|stop}} {{unit}} -n {{1..100}}`\n",
+    );
+
+    // Alternatives are highlighted separately and ranges are dimmed
+    testenv
+        .command()
+        .args(&["--color", "always", "systemctl"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[4mstart\x1b[2m|\x1b[0m\x1b[4;36mstop"))
+        .stdout(contains("\x1b[2;4m1..100"));
+
+    testenv
+        .command()
+        .args(&["--output", "json", "systemctl"])
+        .assert()
+        .success()
+        .stdout(contains(
+            r#""choices": [
+        {
+          "kind": "alternatives",
+          "placeholder": "start|stop",
+          "values": [
+            "start",
+            "stop"
+          ]
+        },
+        {
+          "kind": "range",
+          "placeholder": "1..100",
+          "start": "1",
+          "end": "100"
+        }
+      ]"#,
+        ));
+}
+
+#[test]
+fn test_accessible_rendering() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "systemctl",
+        "# systemctl\n\n> Control units.\n\n- Control a unit:\n\n\
+         `systemctl {{start|stop}} {{unit}} -n {{1..100}}`\n",
+    );
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+    std::fs::write(&config_file_path, "[display]\naccessible = true\n").unwrap();
+
+    // Placeholders are marked instead of colored, even with colors enabled
+    testenv
+        .command()
+        .args(&["--color", "always", "systemctl"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "      systemctl <start or stop> <unit> -n <1 to 100>\n",
+        ))
+        .stdout(contains("\x1b[").not());
+
+    std::fs::write(
+        &config_file_path,
+        "[display]\naccessible = true\nplaceholder_markers = [\"VALUE: \", \"\"]\n",
+    )
+    .unwrap();
+    testenv
+        .command()
+        .args(&["systemctl"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "systemctl VALUE: start or stop VALUE: unit -n VALUE: 1 to 100\n",
+        ));
+}
+
+#[test]
+fn test_minimal_ansi() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "systemctl",
+        "# systemctl\n\n> Control units.\n\n- Control a unit:\n\n\
+         `systemctl {{start|stop}} {{unit}}`\n",
+    );
+
+    // The separators of alternatives are dimmed ...
+    testenv
+        .command()
+        .args(&["--color", "always", "systemctl"])
+        .assert()
+        .success()
+        .stdout(contains("start\x1b[2m|"));
+
+    // ... unless the terminal only supports the basic styles
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+    std::fs::write(&config_file_path, "[display]\nminimal_ansi = true\n").unwrap();
+    testenv
+        .command()
+        .args(&["--color", "always", "systemctl"])
+        .assert()
+        .success()
+        .stdout(contains("start|stop"))
+        .stdout(contains("\x1b[2m").not());
+}
+#[test]
+fn test_page_as_man_page() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "cp",
+        "# cp\n\n> Copy files.\n\n- Copy a file:\n\n`cp {{source}} {{target}}`\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--output", "man", "cp"])
+        .assert()
+        .success()
+        .stdout(
+            ".TH \"CP\" \"1\" \"\" \"tealdeer\" \"tldr pages\"\n\
+             .SH NAME\ncp \\- Copy files.\n\
+             .SH DESCRIPTION\nCopy files.\n\
+             .SH EXAMPLES\n.PP\nCopy a file:\n\
+             .RS 4\n.nf\n\\fBcp \\fR\\fIsource\\fR\\fB \\fR\\fItarget\\fR\n.fi\n.RE\n",
+        );
+
+    // Other results are printed as text
+    testenv
+        .command()
+        .args(&["--output", "man", "--list"])
+        .assert()
+        .success()
+        .stdout("cp\n");
+}
+
+#[test]
+fn test_powershell_integration() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "sl",
+        "# sl\n\n> Steam locomotive.\n> More information: <https://example.com>.\n\n\
+         - Let a train run:\n\n`sl`\n\n- Let it fly:\n\n`sl -F`\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--output", "psobject-json", "sl"])
+        .assert()
+        .success()
+        .stdout(
+            r#"{"Name":"sl","Language":"en","Description":"Steam locomotive.","MoreInformation":"https://example.com","Examples":[{"Description":"Let a train run:","Command":"sl"},{"Description":"Let it fly:","Command":"sl -F"}]}"#
+                .to_owned()
+                + "\n",
+        );
+
+    testenv
+        .command()
+        .args(&["--shell-integration", "powershell"])
+        .assert()
+        .success()
+        .stdout(contains("function Get-Tldr"))
+        .stdout(contains("Register-ArgumentCompleter -CommandName Get-Tldr"));
+    testenv
+        .command()
+        .args(&["--shell-integration", "bash"])
+        .assert()
+        .failure()
+        .stderr(contains("only available for PowerShell and Nushell"));
+}
+
+#[test]
+fn test_shell_hook() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--shell-hook", "--shell", "bash"])
+        .assert()
+        .success()
+        .stdout(contains("command_not_found_handle()"))
+        .stdout(contains("tldr --command-not-found \"$1\""));
+    testenv
+        .command()
+        .args(&["--shell-hook", "--shell", "fish"])
+        .assert()
+        .success()
+        .stdout(contains("function fish_command_not_found"));
+    testenv
+        .command()
+        .args(&["--shell-hook", "--shell", "cmd"])
+        .assert()
+        .failure()
+        .stderr(contains("cmd does not support command-not-found hooks"));
+}
+
+#[test]
+fn test_nushell_integration() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "cp",
+        "# cp\n\n> Copy files.\n\n- Copy a file:\n\n`cp {{source}} {{target}}`\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--shell-integration", "nushell"])
+        .assert()
+        .success()
+        .stdout(contains("export def \"tldr page\""))
+        .stdout(contains("--output nuon"));
+
+    testenv
+        .command()
+        .args(&["--shell-hook", "--shell", "nushell"])
+        .assert()
+        .success()
+        .stdout(contains("$env.config.hooks.command_not_found"));
+
+    testenv
+        .command()
+        .args(&["--output", "nuon", "cp"])
+        .assert()
+        .success()
+        .stdout(
+            "{description: \"Copy files.\", \
+             examples: [[choices, command, description, placeholders]; \
+             [[], \"cp {{source}} {{target}}\", \"Copy a file:\", [\"source\", \"target\"]]], \
+             language: \"en\", more_information: null, name: \"cp\"}\n",
+        );
+}
+
+#[test]
+fn test_completions() {
+    let testenv = TestEnv::new();
+    testenv
+        .command()
+        .args(&["--completions", "zsh"])
+        .assert()
+        .success()
+        .stdout(contains("#compdef tldr"))
+        .stdout(contains("tldr --list"));
+    testenv
+        .command()
+        .args(&["--completions", "powershell"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_several_pages() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("tar", "# tar\n\n> Archiving utility.\n");
+    testenv.add_entry("git-log", "# git log\n\n> Show the history.\n");
+    testenv.add_entry("ssh", "# ssh\n\n> Secure shell.\n");
+
+    testenv
+        .command()
+        .args(&["tar", "git-log", "ssh"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "=== tar ===\n\n  Archiving utility.\n\n=== git-log ===\n\n  Show the history.\n\n\
+             === ssh ===\n\n  Secure shell.\n",
+        ));
+
+    // Words are joined first
+    testenv
+        .command()
+        .args(&["git", "log"])
+        .assert()
+        .success()
+        .stdout(contains("Show the history."))
+        .stdout(contains("===").not());
+
+    // Every word must have a page
+    testenv
+        .command()
+        .args(&["tar", "missing"])
+        .assert()
+        .failure()
+        .stdout(contains("Page tar-missing not found in cache"));
+
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+    std::fs::write(
+        &config_file_path,
+        "[display]\npage_header = \"## {name}\"\n",
+    )
+    .unwrap();
+    testenv
+        .command()
+        .args(&["tar", "ssh"])
+        .assert()
+        .success()
+        .stdout(contains("## tar\n"))
+        .stdout(contains("## ssh\n"));
+}
+
+#[test]
+fn test_subcommand_fallback() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("git", "# git\n\n> Version control.\n");
+    testenv.add_entry("git-remote", "# git remote\n\n> Manage remotes.\n");
+    testenv.add_translated_entry("git-stash", "# git stash\n\n> Änderungen ablegen.\n", "de");
+
+    testenv
+        .command()
+        .args(&["git", "remote", "add"])
+        .assert()
+        .success()
+        .stdout(contains("Manage remotes."))
+        .stderr(contains(
+            "Page git-remote-add not found, showing the page of git-remote instead.",
+        ));
+
+    // Single words are typos rather than subcommands
+    testenv
+        .command()
+        .args(&["git-frobnicate"])
         .assert()
         .failure()
-        .stderr(contains("Cache not found. Please run `tldr --update`."));
+        .stdout(contains("Page git-frobnicate not found in cache"));
 
-    testenv.add_entry("foo", "");
+    testenv
+        .command()
+        .args(&["--quiet", "git", "frobnicate"])
+        .assert()
+        .success()
+        .stdout(contains("Version control."))
+        .stderr(is_empty());
 
     testenv
         .command()
-        .args(&["--list"])
+        .args(&["git", "remote"])
         .assert()
         .success()
-        .stdout("foo\n");
+        .stdout(contains("Manage remotes."))
+        .stderr(contains("instead").not());
 
-    testenv.add_entry("bar", "");
-    testenv.add_entry("baz", "");
-    testenv.add_entry("qux", "");
+    // A translation of the page is preferred to the page of the parent
+    testenv
+        .command()
+        .args(&["--language", "en", "git", "stash"])
+        .assert()
+        .failure()
+        .stdout(contains("The page is available in other languages: de"));
+}
+
+#[test]
+fn test_workspace_pages() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("cargo", "# cargo\n\n> Manage Rust projects.\n");
+    testenv.add_entry("make", "# make\n\n> Run Makefile targets.\n");
+    let project = testenv.input_dir.path().join("project");
+    let src = project.join("src");
+    std::fs::create_dir_all(&src).unwrap();
 
+    // Outside of a project, the usage is shown
     testenv
         .command()
-        .args(&["--list"])
+        .current_dir(&src)
+        .assert()
+        .failure()
+        .stderr(contains("Usage:"));
+
+    std::fs::write(project.join("Cargo.toml"), "").unwrap();
+    std::fs::write(project.join("Makefile"), "").unwrap();
+    testenv
+        .command()
+        .current_dir(&src)
         .assert()
         .success()
-        .stdout("bar\nbaz\nfoo\nqux\n");
+        .stdout(
+            "Rust project (Cargo.toml):\n  cargo  Manage Rust projects.\n\n\
+             Make project (Makefile):\n  make  Run Makefile targets.\n\n\
+             Run `tldr <command>` to show a page, or `tldr --help` for all options.\n",
+        );
 }
 
 #[test]
-fn test_autoupdate_cache() {
+fn test_trace_lookup() {
     let testenv = TestEnv::new();
+    testenv.add_entry("bar", "# bar\n\n> Bar.\n");
+    testenv.add_translated_entry("foo", "# foo\n\n> Foo.\n", "de");
 
-    // The first time, if automatic updates are disabled, the cache should not be found
     testenv
         .command()
-        .args(&["--list"])
+        .args(&["--trace-lookup", "--os", "linux", "--language", "de", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Foo."))
+        .stderr(contains(
+            "lookup: pages.de/linux: not in the cache, skipped\n\
+             lookup: pages.de/common/foo.md: found\n\
+             lookup: using pages.de/common/foo.md\n",
+        ));
+
+    testenv
+        .command()
+        .args(&["--trace-lookup", "--os", "linux", "baz"])
         .assert()
         .failure()
-        .stderr(contains("Cache not found. Please run `tldr --update`."));
+        .stderr(contains("lookup: pages/common/baz.md: not found"))
+        .stderr(contains("lookup: no page found for baz"));
+}
 
-    let config_file_path = testenv.config_dir.path().join("config.toml");
-    let cache_file_path = testenv.cache_dir.path().join("tldr-master");
+#[test]
+fn test_verbose_lookup() {
+    let testenv = TestEnv::new();
+    testenv.add_translated_entry("foo", "# foo\n\n> Foo.\n", "de");
 
-    // Activate automatic updates, set the auto-update interval to 24 hours
+    testenv
+        .command()
+        .args(&["--verbose", "--os", "linux", "--language", "de", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Foo."))
+        .stderr(contains("lookup: pages.de/common/foo.md: found"))
+        .stderr(contains("page: "))
+        .stderr(contains(
+            "foo.md (cache, language de, platform common, memory-mapped)\n",
+        ));
+
+    let custom_pages_dir = testenv.input_dir.path().join("custom");
+    let config_file_path = testenv.config_dir.path().join("config.toml");
     let mut config_file = File::create(&config_file_path).unwrap();
-    config_file
-        .write("[updates]\nauto_update = true\nauto_update_interval_hours = 24".as_bytes())
-        .unwrap();
-    config_file.flush().unwrap();
+    writeln!(
+        config_file,
+        "[directories]\ncustom_pages_dir = {:?}",
+        custom_pages_dir.to_str().unwrap()
+    )
+    .unwrap();
+    create_dir_all(&custom_pages_dir).unwrap();
+    let mut page = File::create(custom_pages_dir.join("foo.page.md")).unwrap();
+    page.write_all(b"# foo\n\n> Custom foo.\n").unwrap();
 
-    // Helper function that runs `tldr --list` and asserts that the cache is automatically updated
-    // or not, depending on the value of `expected`.
-    let check_cache_updated = |expected| {
-        let assert = testenv.command().args(&["--list"]).assert().success();
-        let pred = contains("Successfully updated cache");
-        if expected {
-            assert.stdout(pred)
-        } else {
-            assert.stdout(pred.not())
-        };
-    };
+    testenv
+        .command()
+        .args(&["--verbose", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Custom foo."))
+        .stderr(contains("foo.page.md (custom page)\n"));
 
-    // The cache is updated the first time we run `tldr --list`
-    check_cache_updated(true);
+    // Without --verbose, the lookup stays quiet
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stderr(is_empty());
+}
 
-    // The cache is not updated with a subsequent call
-    check_cache_updated(false);
+#[test]
+fn test_mixed_case_custom_page() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("tar", "# tar\n\n> Archiving utility.\n");
 
-    // We update the modification and access times such that they are about 23 hours from now.
-    // auto-update interval is 24 hours, the cache should not be updated
-    let new_mtime = SystemTime::now() - Duration::from_secs(82_800);
-    filetime::set_file_mtime(&cache_file_path, new_mtime.into()).unwrap();
-    check_cache_updated(false);
+    let custom_pages_dir = testenv.input_dir.path().join("custom");
+    create_dir_all(&custom_pages_dir).unwrap();
+    std::fs::write(
+        custom_pages_dir.join("MyTool.page.md"),
+        "# MyTool\n\n> An in-house tool.\n",
+    )
+    .unwrap();
+    let mut config_file = File::create(testenv.config_dir.path().join("config.toml")).unwrap();
+    writeln!(
+        config_file,
+        "[directories]\ncustom_pages_dir = {:?}",
+        custom_pages_dir.to_str().unwrap()
+    )
+    .unwrap();
 
-    // We update the modification and access times such that they are about 25 hours from now.
-    // auto-update interval is 24 hours, the cache should be updated
-    let new_mtime = SystemTime::now() - Duration::from_secs(90_000);
-    filetime::set_file_mtime(&cache_file_path, new_mtime.into()).unwrap();
-    check_cache_updated(true);
+    testenv
+        .command()
+        .args(&["MyTool"])
+        .assert()
+        .success()
+        .stdout(contains("An in-house tool."));
 
-    // The cache is not updated with a subsequent call
-    check_cache_updated(false);
+    // Upstream pages are still found regardless of the case
+    testenv
+        .command()
+        .args(&["TAR"])
+        .assert()
+        .success()
+        .stdout(contains("Archiving utility."));
 }
 
 #[test]
-#[cfg(target_os = "windows")]
-fn test_pager_warning() {
+fn test_memory_mapped_pages() {
     let testenv = TestEnv::new();
+    testenv.command().args(&["--update"]).assert().success();
+
+    // Updated caches keep their pages in generations
     testenv
         .command()
-        .args(&["--update"])
+        .args(&["--verbose", "sl"])
         .assert()
         .success()
-        .stdout(contains("Successfully updated cache."));
+        .stdout(contains("Steam locomotive"))
+        .stderr(contains("generations"))
+        .stderr(contains(
+            "(cache, language en, platform common, memory-mapped)\n",
+        ));
+}
 
-    // Regular call should not show a "pager flag not available on windows" warning
+#[test]
+fn test_seed_cache_without_bundled_pages() {
+    let testenv = TestEnv::new();
+
+    // The tests are built without a snapshot of the pages
     testenv
         .command()
-        .args(&["tar"])
+        .args(&["--seed-cache"])
+        .assert()
+        .failure()
+        .stderr(contains("Could not seed cache"));
+    testenv
+        .command()
+        .args(&["sl"])
+        .assert()
+        .failure()
+        .stderr(contains("Cache not found"));
+}
+
+#[test]
+fn test_output_encoding() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Comprimé “Größe” 日本.\n");
+
+    testenv
+        .command()
+        .env("LC_ALL", "C")
+        .args(&["foo"])
         .assert()
         .success()
-        .stderr(contains("pager flag not available on Windows").not());
+        .stdout(contains("Comprime \"Grosse\" <U+65E5><U+672C>."))
+        .stderr(contains("does not use UTF-8"));
+    testenv
+        .command()
+        .env("LC_ALL", "en_US.UTF-8")
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stdout(contains("Comprimé “Größe” 日本."));
 
-    // But it should be shown if the pager flag is true
+    std::fs::write(
+        testenv.config_dir.path().join("config.toml"),
+        "[display]\noutput_encoding = \"utf-8\"\n",
+    )
+    .unwrap();
     testenv
         .command()
-        .args(&["tar", "-p"])
+        .env("LC_ALL", "C")
+        .args(&["foo"])
         .assert()
         .success()
-        .stderr(contains("pager flag not available on Windows"));
+        .stdout(contains("Comprimé “Größe” 日本."))
+        .stderr(is_empty());
+}
+
+#[test]
+fn test_pin_page() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Old foo.\n");
+
+    testenv
+        .command()
+        .args(&["--pin", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Pinned pages/common/foo.md"));
+    testenv
+        .command()
+        .args(&["--pin", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains("already pinned"));
+
+    // Updates don't change the pinned page
+    testenv.add_entry("foo", "# foo\n\n> New foo.\n");
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stdout(contains("Old foo."));
+    testenv
+        .command()
+        .args(&["--list"])
+        .assert()
+        .success()
+        .stdout("foo\n");
+
+    testenv
+        .command()
+        .args(&["--unpin", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Unpinned foo."));
+    testenv
+        .command()
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stdout(contains("New foo."));
+    testenv
+        .command()
+        .args(&["--unpin", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains("Page foo is not pinned."));
+    testenv
+        .command()
+        .args(&["--pin", "bar"])
+        .assert()
+        .failure()
+        .stderr(contains("Page bar not found in cache"));
+}
+
+#[test]
+fn test_export_import_profile() {
+    let laptop = TestEnv::new();
+    laptop.add_entry("foo", "# foo\n\n> Old foo.\n");
+    laptop.command().args(&["--pin", "foo"]).assert().success();
+
+    let profile = laptop.input_dir.path().join("tealdeer-profile.toml");
+    laptop
+        .command()
+        .args(&["--export-profile", profile.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Exported 1 pinned pages and 0 pages repositories"));
+
+    // Pins are added, but pages that are pinned already are kept
+    let desktop = TestEnv::new();
+    desktop.add_entry("foo", "# foo\n\n> New foo.\n");
+    desktop
+        .command()
+        .args(&["--import-profile", profile.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Imported 1 pinned pages"));
+    desktop
+        .command()
+        .args(&["foo"])
+        .assert()
+        .success()
+        .stdout(contains("Old foo."));
+
+    std::fs::write(
+        &profile,
+        "version = 1\n[[pin]]\npath = \"pages/common/foo.md\"\ncontents = \"# foo\\n\\n> Other foo.\\n\"\n",
+    )
+    .unwrap();
+    desktop
+        .command()
+        .args(&["--import-profile", profile.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Imported 0 pinned pages"))
+        .stderr(contains(
+            "pages/common/foo.md is pinned with other contents already",
+        ));
+
+    std::fs::write(&profile, "version = 99\n").unwrap();
+    desktop
+        .command()
+        .args(&["--import-profile", profile.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(contains("Upgrade tealdeer to import this profile."));
+}
+
+#[test]
+fn test_compare_lang() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo utility.\n");
+    testenv.add_translated_entry("foo", "# foo\n\n> Foo-Programm.\n", "de");
+
+    testenv
+        .command()
+        .env("COLUMNS", "80")
+        .args(&["--color", "never", "foo", "--compare-lang", "en,de"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "  Foo utility.                         |   Foo-Programm.",
+        ));
+
+    testenv
+        .command()
+        .args(&["foo", "--compare-lang", "en"])
+        .assert()
+        .failure()
+        .stderr(contains("expects two languages"));
+    testenv
+        .command()
+        .args(&["bar", "--compare-lang", "en,de"])
+        .assert()
+        .failure()
+        .stderr(contains("available in neither en nor de"));
+}
+
+#[test]
+fn test_translation_status() {
+    let testenv = TestEnv::new();
+    let tar = "# tar\n\n> Archiving utility.\n\n- Extract:\n\n`tar xf {{file}}`\n\n- List:\n\n`tar tf {{file}}`\n";
+    let ls = "# ls\n\n> List files.\n\n- List:\n\n`ls -l {{dir}}`\n";
+    testenv.add_entry("tar", tar);
+    testenv.add_entry("ls", ls);
+    testenv.add_entry("cp", "# cp\n\n> Copy files.\n");
+
+    // Translated placeholders don't make a translation outdated
+    testenv.add_translated_entry(
+        "ls",
+        "# ls\n\n> Dateien auflisten.\n\n- Auflisten:\n\n`ls -l {{verzeichnis}}`\n",
+        "de",
+    );
+    testenv
+        .command()
+        .args(&["--translation-status", "de"])
+        .assert()
+        .success()
+        .stdout("0 of 1 translated pages appear outdated (1 of 3 pages are translated).\n");
+
+    testenv.add_translated_entry(
+        "tar",
+        "# tar\n\n> Archivierungsprogramm.\n\n- Entpacken:\n\n`tar -xf {{datei}}`\n\n- Auflisten:\n\n`tar tf {{datei}}`\n",
+        "de",
+    );
+    testenv
+        .command()
+        .args(&["--translation-status", "de"])
+        .assert()
+        .failure()
+        .stdout(
+            "common/tar.md: the command of example 1 differs\n  \
+             en: tar xf {{file}}\n  \
+             de: tar -xf {{datei}}\n\
+             1 of 2 translated pages appear outdated (2 of 3 pages are translated).\n",
+        );
+
+    testenv
+        .command()
+        .args(&["--translation-status", "de", "--output", "json"])
+        .assert()
+        .failure()
+        .stdout(contains(r#""reason": "command""#))
+        .stdout(contains(r#""page": "common/tar.md""#));
+
+    testenv
+        .command()
+        .args(&["--translation-status", "fr"])
+        .assert()
+        .failure()
+        .stderr(contains("No pages translated into fr found in cache"));
 }